@@ -4,7 +4,7 @@ use axum::{
     extract::{Path, Path as AxumPath, Query},
     http::{StatusCode, header},
     response::{IntoResponse, Response},
-    routing::{delete as axum_delete, get, post},
+    routing::{delete as axum_delete, get, post, put},
 };
 use include_dir::{Dir, include_dir};
 use serde::{Deserialize, Serialize};
@@ -25,12 +25,17 @@ use crate::config::io::{
 };
 use crate::config::{ensure_data_dir, load_config};
 use crate::data::SearchIndex;
+use crate::data::flush;
+use crate::data::incognito;
 use crate::data::model::{EntryMetadata, SearchIndexRecord};
 use crate::data::store::{
-    copy_by_selector, copy_json_item, delete_entry, increment_copy_count, load_history_items,
-    load_index, load_metadata, refresh_index, store_json_item,
+    CompactReport, compact_store, copy_by_selector, copy_json_item, delete_entry,
+    increment_copy_count, index_generation, load_history_items, load_index, load_metadata,
+    record_paste, refresh_index, set_primary_plugin, set_summary_override, store_json_item,
 };
-use crate::search::SearchOptions;
+use crate::error::ClipboardError;
+use crate::search::{SearchOptions, SelectionFilter};
+use crate::service::permissions;
 use crate::util::paste;
 use crate::util::time::format_iso;
 
@@ -59,7 +64,7 @@ static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
 static API_START_TIME: OnceLock<u64> = OnceLock::new();
 static API_PORT: OnceLock<u16> = OnceLock::new();
 
-pub async fn serve(port: u16) -> Result<()> {
+pub async fn serve(port: u16, open_dashboard: bool) -> Result<()> {
     refresh_index()?;
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     println!("API listening on http://{}", addr);
@@ -73,17 +78,65 @@ pub async fn serve(port: u16) -> Result<()> {
     let _ = API_PORT.set(port);
 
     // Note: Watcher is now run separately via 'get_clipboard watch' command
+    spawn_idle_flush();
 
     let app = router();
     let listener = TcpListener::bind(addr).await?;
+    open_dashboard_if(open_dashboard, &format!("http://{addr}/dashboard"), open_in_browser);
     axum::serve(listener, app.into_make_service()).await?;
     Ok(())
 }
 
+/// Syncs entries written through the API to disk once requests pause,
+/// mirroring what the watcher does for captured copies.
+fn spawn_idle_flush() {
+    let Ok(config) = load_config() else {
+        return;
+    };
+    let (Some(delay), Ok(data_dir)) = (config.idle_flush_delay(), ensure_data_dir(&config)) else {
+        return;
+    };
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            if let Err(err) = flush::flush_if_idle(&data_dir, delay) {
+                eprintln!("Failed to flush clipboard history to disk: {err:#}");
+            }
+        }
+    });
+}
+
+/// Opens the dashboard once the listener is bound, so the browser never hits
+/// a closed port. Failing to launch a browser is not fatal for the server.
+fn open_dashboard_if(open: bool, url: &str, opener: impl FnOnce(&str) -> Result<()>) {
+    if !open {
+        return;
+    }
+    if let Err(err) = opener(url) {
+        eprintln!("Failed to open {url}: {err:#}");
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_in_browser(url: &str) -> Result<()> {
+    let status = std::process::Command::new("open").arg(url).status()?;
+    if !status.success() {
+        anyhow::bail!("open exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_in_browser(url: &str) -> Result<()> {
+    anyhow::bail!("Opening {url} in a browser is only supported on macOS")
+}
+
 fn router() -> Router {
     Router::new()
         .route("/", get(get_docs))
         .route("/version", get(get_version))
+        .route("/health", get(get_health))
+        .route("/incognito", get(get_incognito).post(update_incognito))
         .route("/dashboard", get(serve_dashboard_index))
         .route("/dashboard/", get(serve_dashboard_index))
         .route("/dashboard/*path", get(serve_dashboard))
@@ -94,19 +147,28 @@ fn router() -> Router {
             "/item/:selector",
             get(get_item).delete(axum_delete(delete_item)).put(put_item).patch(patch_item),
         )
+        .route("/item/:selector/primary", put(set_item_primary))
+        .route("/item/:selector/summary", put(set_item_summary))
         .route("/item/:selector/preview", get(preview_item))
+        .route("/previews", post(preview_items))
+        .route("/item/:selector/related", get(related_items))
         .route("/item/:selector/copy", post(copy_item))
         .route("/item/:selector/copy_plain", post(copy_item_plain))
         .route("/item/:selector/paste", post(paste_item))
         .route("/item/:selector/paste_plain", post(paste_item_plain))
+        .route("/resolve/:selector", get(resolve_item))
+        .route("/plugins", get(list_plugins))
         .route("/search", get(search_items))
+        .route("/suggest", get(suggest_items))
         .route("/stats", get(get_stats))
         .route("/mtime", get(get_mtime))
         .route("/dir", get(get_dir).post(update_dir))
         .route("/copy", post(copy_payload))
         .route("/save", post(save_payload))
         .route("/export", get(export_items))
+        .route("/export.ndjson", get(export_ndjson))
         .route("/import", post(import_items))
+        .route("/compact", post(compact_items))
 }
 
 async fn get_docs() -> impl IntoResponse {
@@ -169,7 +231,8 @@ async fn get_items(
     Query(params): Query<ItemsQuery>,
 ) -> Result<Json<Vec<plugins::ClipboardJsonItem>>, ApiError> {
     let index = load_index().map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
 
     if let Some(ids) = params.ids.as_ref() {
         let selectors: Vec<_> = ids
@@ -178,12 +241,14 @@ async fn get_items(
             .filter(|value| !value.is_empty())
             .map(Selector::parse)
             .collect();
-        return items_by_selectors(&index, &data_dir, selectors);
+        return items_by_selectors(&index, &data_dir, &config, selectors);
     }
 
     let mut options = SearchOptions::default();
     options.offset = params.offset.unwrap_or(0);
     options.limit = params.count;
+    options.filter =
+        SelectionFilter::from_query_params(params.formats.as_deref(), params.kinds.as_deref())?;
 
     if let Some(sort) = params.sort {
         options.sort = match sort.to_lowercase().as_str() {
@@ -202,16 +267,73 @@ async fn get_items(
         };
     }
 
-    let (items, _) = load_history_items(&index, &options).map_err(ApiError::from)?;
+    let max_pinned = if params.pinned_first {
+        params.pinned.unwrap_or_else(|| config.pinned_first_limit())
+    } else {
+        0
+    };
+    let items = if max_pinned > 0 {
+        // Pins can be anywhere in the history, so rank the whole list from
+        // the index and only load metadata for the requested page.
+        let offset = std::mem::take(&mut options.offset);
+        options.limit = None;
+        let hits = crate::search::search(&index, &options).hits;
+        let is_pinned = |hit: &crate::search::SearchHit| {
+            index.get(&hit.hash).is_some_and(|record| record.pinned)
+        };
+        pinned_first(hits, offset, params.count, max_pinned, is_pinned)
+            .into_iter()
+            .filter_map(|hit| match load_metadata(&hit.hash) {
+                Ok(metadata) => Some((metadata, hit.offset)),
+                Err(err) => {
+                    eprintln!("Warning: Failed to load metadata for {}: {err}", hit.hash);
+                    None
+                }
+            })
+            .collect()
+    } else {
+        let (items, _) = load_history_items(&index, &options).map_err(ApiError::from)?;
+        items
+            .into_iter()
+            .map(|item| (item.metadata, item.offset))
+            .collect::<Vec<_>>()
+    };
     let mut response = Vec::new();
-    for item in items {
-        response.push(
-            json_from_metadata(&item.metadata, item.offset, &data_dir).map_err(ApiError::from)?,
-        );
+    for (metadata, offset) in items {
+        let item =
+            json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+        response.push(item);
     }
     Ok(Json(response))
 }
 
+/// Moves up to `max_pinned` pinned items to the front, keeping their order,
+/// followed by the other items in their original order, and returns the
+/// `count` items starting at `offset` of that ranking.
+fn pinned_first<T>(
+    items: Vec<T>,
+    offset: usize,
+    count: Option<usize>,
+    max_pinned: usize,
+    is_pinned: impl Fn(&T) -> bool,
+) -> Vec<T> {
+    let mut pinned = Vec::new();
+    let mut rest = Vec::new();
+    for item in items {
+        if pinned.len() < max_pinned && is_pinned(&item) {
+            pinned.push(item);
+        } else {
+            rest.push(item);
+        }
+    }
+    pinned
+        .into_iter()
+        .chain(rest)
+        .skip(offset)
+        .take(count.unwrap_or(usize::MAX))
+        .collect()
+}
+
 #[derive(Clone)]
 enum Selector {
     Hash(String),
@@ -234,6 +356,7 @@ impl Selector {
 enum ApiError {
     NotFound(String),
     BadRequest(String),
+    Forbidden(String),
     Internal(anyhow::Error),
 }
 
@@ -245,35 +368,66 @@ impl ApiError {
     fn bad_request(message: impl Into<String>) -> Self {
         ApiError::BadRequest(message.into())
     }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        match self {
-            ApiError::NotFound(message) => {
-                (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
-            }
-            ApiError::BadRequest(message) => {
-                (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
-            }
-            ApiError::Internal(error) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": error.to_string() })),
-            )
-                .into_response(),
+        let status = self.status();
+        let message = match self {
+            ApiError::NotFound(message)
+            | ApiError::BadRequest(message)
+            | ApiError::Forbidden(message) => message,
+            ApiError::Internal(error) => error.to_string(),
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+impl From<ClipboardError> for ApiError {
+    fn from(error: ClipboardError) -> Self {
+        match error {
+            ClipboardError::NotFound(message) => ApiError::NotFound(message),
+            ClipboardError::InvalidInput(message) => ApiError::BadRequest(message),
+            ClipboardError::Permission(message) => ApiError::Forbidden(message),
+            other => ApiError::Internal(other.into()),
         }
     }
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(error: anyhow::Error) -> Self {
-        ApiError::Internal(error)
+        if paste::is_permission_denied(&error) {
+            return ApiError::from(ClipboardError::from(paste::PermissionDenied));
+        }
+        match error.downcast::<ClipboardError>() {
+            Ok(typed) => ApiError::from(typed),
+            Err(error) => ApiError::Internal(error),
+        }
     }
 }
 
 #[derive(Debug, Deserialize)]
 struct ItemQuery {
     formats: Option<String>,
+    kinds: Option<String>,
+}
+
+impl ItemQuery {
+    fn filter(&self) -> Result<SelectionFilter, ApiError> {
+        Ok(SelectionFilter::from_query_params(
+            self.formats.as_deref(),
+            self.kinds.as_deref(),
+        )?)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -281,8 +435,13 @@ struct ItemsQuery {
     offset: Option<usize>,
     count: Option<usize>,
     ids: Option<String>,
+    formats: Option<String>,
+    kinds: Option<String>,
     sort: Option<String>,
     order: Option<String>,
+    #[serde(default)]
+    pinned_first: bool,
+    pinned: Option<usize>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -291,8 +450,14 @@ struct SearchQuery {
     offset: Option<usize>,
     count: Option<usize>,
     formats: Option<String>,
+    kinds: Option<String>,
     sort: Option<String>,
     order: Option<String>,
+    #[serde(default)]
+    fold: bool,
+    flags: Option<String>,
+    #[serde(rename = "in")]
+    field: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -336,6 +501,8 @@ struct VersionResponse {
     version: String,
     api_start_time: Option<u64>,
     api_start_time_iso: Option<String>,
+    index_generation: u64,
+    index_size: usize,
 }
 
 async fn get_item(
@@ -343,25 +510,15 @@ async fn get_item(
     Query(params): Query<ItemQuery>,
 ) -> Result<Json<plugins::ClipboardJsonItem>, ApiError> {
     let index = load_index().map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
 
-    let mut filter = crate::search::SelectionFilter::default();
-    if let Some(formats) = params.formats {
-        for fmt in formats.split(',') {
-            let fmt = fmt.trim().to_lowercase();
-            match fmt.as_str() {
-                "text" => filter.include_text = true,
-                "image" => filter.include_image = true,
-                "file" | "files" => filter.include_file = true,
-                other => filter.include_formats.push(other.to_string()),
-            }
-        }
-    }
+    let filter = params.filter()?;
 
     let (ordered, offsets) = ordered_index_filtered(&index, &filter);
     let (hash, offset, real_index) = resolve_selector_filtered(&ordered, &offsets, &selector)?;
     let metadata = load_metadata(&hash).map_err(ApiError::from)?;
-    let item = json_from_metadata_with_index(&metadata, offset, real_index, &data_dir)
+    let item = json_from_metadata_with_index(&metadata, offset, real_index, &data_dir, &config)
         .map_err(ApiError::from)?;
     Ok(Json(item))
 }
@@ -373,18 +530,7 @@ async fn get_item_data(
     let index = load_index().map_err(ApiError::from)?;
     let data_dir = data_dir_path().map_err(ApiError::from)?;
 
-    let mut filter = crate::search::SelectionFilter::default();
-    if let Some(formats) = params.formats {
-        for fmt in formats.split(',') {
-            let fmt = fmt.trim().to_lowercase();
-            match fmt.as_str() {
-                "text" => filter.include_text = true,
-                "image" => filter.include_image = true,
-                "file" | "files" => filter.include_file = true,
-                other => filter.include_formats.push(other.to_string()),
-            }
-        }
-    }
+    let filter = params.filter()?;
 
     let (ordered, offsets) = ordered_index_filtered(&index, &filter);
     let (hash, offset, real_index) = resolve_selector_filtered(&ordered, &offsets, &selector)?;
@@ -401,6 +547,9 @@ struct PreviewResponse {
     kind: String,
     formats_order: Vec<String>,
     data: HashMap<String, PreviewData>,
+    /// Formats the item has but that were skipped by the format cap.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unrendered_formats: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -410,37 +559,175 @@ struct PreviewData {
     text: Option<String>,
 }
 
+impl PreviewResponse {
+    fn byte_len(&self) -> usize {
+        self.data
+            .values()
+            .map(|data| data.html.len() + data.text.as_ref().map_or(0, String::len))
+            .sum()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PreviewQuery {
     interactive: Option<String>, // Accept string "true"/"false" to be safe, or bool if axum handles it well. Axum handles bool.
+    /// Return only the body markup, without the injected `<style>`/`<script>`.
+    #[serde(default)]
+    raw: bool,
+    /// Render at most this many formats; overrides `max_preview_formats`.
+    max_formats: Option<usize>,
+    /// `data` returns structured JSON per format instead of rendered HTML.
+    format: Option<String>,
+}
+
+/// The request's format cap, falling back to the configured one.
+fn preview_format_limit(requested: Option<usize>) -> Option<usize> {
+    requested
+        .filter(|limit| *limit > 0)
+        .or_else(|| load_config().ok().and_then(|config| config.preview_format_limit()))
 }
 
 async fn preview_item(
     Path(selector): Path<String>,
     Query(params): Query<PreviewQuery>,
-) -> Result<Json<PreviewResponse>, ApiError> {
+) -> Result<Response, ApiError> {
     let index = load_index().map_err(ApiError::from)?;
     let data_dir = data_dir_path().map_err(ApiError::from)?;
     let (ordered, offsets) = ordered_index(&index);
     let (hash, _) = resolve_selector(&ordered, &offsets, &selector)?;
-    let metadata = load_metadata(&hash).map_err(ApiError::from)?;
-    let item_dir = data_dir.join(&metadata.relative_path);
-
     let interactive = params.interactive.as_deref().unwrap_or("true") == "true";
-
-    let preview_formats = plugins::build_preview_formats(&metadata, &item_dir)
+    let max_formats = preview_format_limit(params.max_formats);
+    match params.format.as_deref() {
+        None | Some("html") => {}
+        Some("data") => {
+            let preview =
+                structured_preview(&hash, &data_dir, max_formats).map_err(ApiError::from)?;
+            return Ok(Json(preview).into_response());
+        }
+        Some(other) => {
+            return Err(ApiError::bad_request(format!(
+                "Unknown preview format '{other}', expected html or data"
+            )));
+        }
+    }
+    let preview = render_preview(&hash, &data_dir, interactive, params.raw, max_formats)
         .map_err(ApiError::from)?;
+    Ok(Json(preview).into_response())
+}
+
+/// Upper bound on selectors per `POST /previews` request.
+const MAX_PREVIEW_BATCH: usize = 100;
+/// Rendered bytes after which the remaining selectors of a batch are
+/// deferred to a later request.
+const MAX_PREVIEW_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct PreviewsRequest {
+    selectors: Vec<String>,
+    #[serde(default)]
+    interactive: Option<bool>,
+    #[serde(default)]
+    raw: bool,
+    #[serde(default)]
+    max_formats: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct PreviewsResponse {
+    previews: HashMap<String, PreviewResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing: Vec<String>,
+    /// Selectors not rendered because the response reached its size cap.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deferred: Vec<String>,
+}
+
+/// Drops repeated selectors (keeping first-seen order) and enforces the
+/// batch cap.
+fn preview_batch(selectors: Vec<String>) -> Result<Vec<String>, ApiError> {
+    let mut seen = std::collections::HashSet::new();
+    let unique: Vec<String> = selectors
+        .into_iter()
+        .filter(|selector| seen.insert(selector.clone()))
+        .collect();
+    if unique.len() > MAX_PREVIEW_BATCH {
+        return Err(ApiError::bad_request(format!(
+            "At most {MAX_PREVIEW_BATCH} selectors per request, got {}",
+            unique.len()
+        )));
+    }
+    Ok(unique)
+}
+
+async fn preview_items(
+    Json(payload): Json<PreviewsRequest>,
+) -> Result<Json<PreviewsResponse>, ApiError> {
+    let selectors = preview_batch(payload.selectors)?;
+    let interactive = payload.interactive.unwrap_or(true);
+    let raw = payload.raw;
+    let max_formats = preview_format_limit(payload.max_formats);
+    let index = load_index().map_err(ApiError::from)?;
+    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    let (ordered, offsets) = ordered_index(&index);
+
+    let response = render_batch(selectors, MAX_PREVIEW_BATCH_BYTES, |selector| {
+        let (hash, _) = resolve_selector(&ordered, &offsets, selector).ok()?;
+        render_preview(&hash, &data_dir, interactive, raw, max_formats).ok()
+    });
+    Ok(Json(response))
+}
+
+/// Renders selectors in order until `max_bytes` of markup has been produced.
+/// The first preview is always rendered so an oversized item can still be
+/// fetched on its own.
+fn render_batch<F>(selectors: Vec<String>, max_bytes: usize, mut render: F) -> PreviewsResponse
+where
+    F: FnMut(&str) -> Option<PreviewResponse>,
+{
+    let mut response = PreviewsResponse {
+        previews: HashMap::new(),
+        missing: Vec::new(),
+        deferred: Vec::new(),
+    };
+    let mut remaining = max_bytes;
+    for selector in selectors {
+        if remaining == 0 {
+            response.deferred.push(selector);
+            continue;
+        }
+        match render(&selector) {
+            Some(preview) => {
+                remaining = remaining.saturating_sub(preview.byte_len());
+                response.previews.insert(selector, preview);
+            }
+            None => response.missing.push(selector),
+        }
+    }
+    response
+}
+
+fn render_preview(
+    hash: &str,
+    data_dir: &std::path::Path,
+    interactive: bool,
+    raw: bool,
+    max_formats: Option<usize>,
+) -> Result<PreviewResponse> {
+    let metadata = load_metadata(hash)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let preview_formats = plugins::build_preview_formats(&metadata, &item_dir, max_formats)?;
 
     let mut data = HashMap::new();
     let mut formats_order = Vec::new();
 
-    for format in preview_formats {
+    for format in preview_formats.rendered {
         let mut template_ctx = format.data.clone();
         if let Some(obj) = template_ctx.as_object_mut() {
             obj.insert("interactive".to_string(), json!(interactive));
         }
 
         if let Ok(html) = HANDLEBARS.render(&format.template_name, &template_ctx) {
+            let html = if raw { raw_fragment(&html) } else { html };
             formats_order.push(format.plugin_id.clone());
             data.insert(
                 format.plugin_id.clone(),
@@ -452,18 +739,97 @@ async fn preview_item(
         }
     }
 
-    let kind_str = match metadata.kind {
+    Ok(PreviewResponse {
+        kind: preview_kind(&metadata.kind).to_string(),
+        formats_order,
+        data,
+        unrendered_formats: preview_formats.unrendered,
+    })
+}
+
+fn preview_kind(kind: &crate::data::model::EntryKind) -> &'static str {
+    match kind {
         crate::data::model::EntryKind::Text => "text",
         crate::data::model::EntryKind::Image => "image",
         crate::data::model::EntryKind::File => "file",
         crate::data::model::EntryKind::Other => "other",
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StructuredPreviewResponse {
+    kind: String,
+    formats_order: Vec<String>,
+    data: HashMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unrendered_formats: Vec<String>,
+}
+
+/// The same formats as `render_preview`, described as JSON for clients that
+/// draw their own preview instead of embedding HTML.
+fn structured_preview(
+    hash: &str,
+    data_dir: &std::path::Path,
+    max_formats: Option<usize>,
+) -> Result<StructuredPreviewResponse> {
+    let metadata = load_metadata(hash)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let preview_formats = plugins::build_preview_formats(&metadata, &item_dir, max_formats)?;
+    Ok(structured_response(
+        &metadata.kind,
+        &preview_formats.rendered,
+        preview_formats.unrendered,
+    ))
+}
+
+fn structured_response(
+    kind: &crate::data::model::EntryKind,
+    formats: &[plugins::PreviewFormat],
+    unrendered_formats: Vec<String>,
+) -> StructuredPreviewResponse {
+    StructuredPreviewResponse {
+        kind: preview_kind(kind).to_string(),
+        formats_order: formats.iter().map(|f| f.plugin_id.clone()).collect(),
+        data: formats
+            .iter()
+            .map(|f| (f.plugin_id.clone(), plugins::structured_preview(f)))
+            .collect(),
+        unrendered_formats,
+    }
+}
+
+/// Reduces a rendered preview document to the markup inside `<body>`, minus
+/// every `<style>` and `<script>` block, so callers can embed it in their own
+/// page and style it themselves.
+fn raw_fragment(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let body = match (lower.find("<body"), lower.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => {
+            let open_end = lower[start..end].find('>').map_or(start, |i| start + i + 1);
+            open_end..end
+        }
+        _ => 0..html.len(),
     };
 
-    Ok(Json(PreviewResponse {
-        kind: kind_str.to_string(),
-        formats_order,
-        data,
-    }))
+    let mut fragment = String::new();
+    let mut cursor = body.start;
+    while cursor < body.end {
+        let next = ["<style", "<script"]
+            .iter()
+            .filter_map(|tag| lower[cursor..body.end].find(tag).map(|i| (cursor + i, *tag)))
+            .min_by_key(|(start, _)| *start);
+        let Some((start, tag)) = next else {
+            fragment.push_str(&html[cursor..body.end]);
+            break;
+        };
+        fragment.push_str(&html[cursor..start]);
+        let close = format!("</{}>", &tag[1..]);
+        cursor = lower[start..body.end]
+            .find(&close)
+            .map_or(body.end, |i| start + i + close.len());
+    }
+    fragment.trim().to_string()
 }
 
 #[derive(Debug, Serialize)]
@@ -510,59 +876,130 @@ async fn get_item_text(
     }))
 }
 
+const ACCESSIBILITY_REASON: &str = "accessibility permission not granted";
+
+fn accessibility_denied() -> ApiError {
+    ApiError::from(ClipboardError::from(paste::PermissionDenied))
+}
+
+/// Body returned by the copy/paste endpoints: the item plus whether a
+/// simulated paste ran, so clients can prompt for missing permissions.
+#[derive(Debug, Serialize)]
+struct ItemActionResponse {
+    #[serde(flatten)]
+    item: plugins::ClipboardJsonItem,
+    #[serde(flatten)]
+    outcome: PasteOutcome,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct PasteOutcome {
+    pasted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+impl PasteOutcome {
+    /// Copy endpoints never paste, but still report when a follow-up paste
+    /// by the caller would fail for lack of permissions.
+    fn copy_only(accessible: bool) -> Self {
+        Self {
+            pasted: false,
+            reason: (!accessible).then(|| ACCESSIBILITY_REASON.to_string()),
+        }
+    }
+}
+
+/// Runs `paste` when permitted. Missing accessibility permission is an error
+/// (403) since the client has to act on it; other failures are reported in
+/// the outcome because the item was still copied.
+fn attempt_paste(
+    accessible: bool,
+    paste: impl FnOnce() -> Result<()>,
+) -> Result<PasteOutcome, ApiError> {
+    if !accessible {
+        return Err(accessibility_denied());
+    }
+    match paste() {
+        Ok(()) => Ok(PasteOutcome {
+            pasted: true,
+            reason: None,
+        }),
+        Err(err) if paste::is_permission_denied(&err) => Err(accessibility_denied()),
+        Err(err) => Ok(PasteOutcome {
+            pasted: false,
+            reason: Some(format!("{err:#}")),
+        }),
+    }
+}
+
 async fn copy_item(
     Path(selector): Path<String>,
-) -> Result<(StatusCode, Json<plugins::ClipboardJsonItem>), ApiError> {
+) -> Result<(StatusCode, Json<ItemActionResponse>), ApiError> {
     let index = load_index().map_err(ApiError::from)?;
     let (ordered, offsets) = ordered_index(&index);
     let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
     copy_by_selector(&hash).map_err(ApiError::from)?;
+    let outcome = PasteOutcome::copy_only(permissions::check_accessibility());
     let metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
-    let item = json_from_metadata(&metadata, offset, &data_dir).map_err(ApiError::from)?;
-    Ok((StatusCode::OK, Json(item)))
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok((StatusCode::OK, Json(ItemActionResponse { item, outcome })))
 }
 
 async fn copy_item_plain(
     Path(selector): Path<String>,
-) -> Result<(StatusCode, Json<plugins::ClipboardJsonItem>), ApiError> {
+) -> Result<(StatusCode, Json<ItemActionResponse>), ApiError> {
     let index = load_index().map_err(ApiError::from)?;
     let (ordered, offsets) = ordered_index(&index);
     let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
     crate::data::store::copy_plain_by_selector(&hash).map_err(ApiError::from)?;
+    let outcome = PasteOutcome::copy_only(permissions::check_accessibility());
     let metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
-    let item = json_from_metadata(&metadata, offset, &data_dir).map_err(ApiError::from)?;
-    Ok((StatusCode::OK, Json(item)))
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok((StatusCode::OK, Json(ItemActionResponse { item, outcome })))
 }
 
 
 async fn paste_item(
     Path(selector): Path<String>,
-) -> Result<(StatusCode, Json<plugins::ClipboardJsonItem>), ApiError> {
+) -> Result<(StatusCode, Json<ItemActionResponse>), ApiError> {
     let index = load_index().map_err(ApiError::from)?;
     let (ordered, offsets) = ordered_index(&index);
     let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
     copy_by_selector(&hash).map_err(ApiError::from)?;
-    paste::simulate_paste().map_err(ApiError::from)?;
-    let metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
-    let item = json_from_metadata(&metadata, offset, &data_dir).map_err(ApiError::from)?;
-    Ok((StatusCode::OK, Json(item)))
+    let outcome = attempt_paste(permissions::check_accessibility(), paste::simulate_paste);
+    let mut metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
+    let outcome = outcome?;
+    if outcome.pasted {
+        metadata = record_paste(&hash).map_err(ApiError::from)?;
+    }
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok((StatusCode::OK, Json(ItemActionResponse { item, outcome })))
 }
 
 async fn paste_item_plain(
     Path(selector): Path<String>,
-) -> Result<(StatusCode, Json<plugins::ClipboardJsonItem>), ApiError> {
+) -> Result<(StatusCode, Json<ItemActionResponse>), ApiError> {
     let index = load_index().map_err(ApiError::from)?;
     let (ordered, offsets) = ordered_index(&index);
     let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
     crate::data::store::copy_plain_by_selector(&hash).map_err(ApiError::from)?;
-    paste::simulate_paste().map_err(ApiError::from)?;
-    let metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
-    let item = json_from_metadata(&metadata, offset, &data_dir).map_err(ApiError::from)?;
-    Ok((StatusCode::OK, Json(item)))
+    let outcome = attempt_paste(permissions::check_accessibility(), paste::simulate_paste);
+    let mut metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
+    let outcome = outcome?;
+    if outcome.pasted {
+        metadata = record_paste(&hash).map_err(ApiError::from)?;
+    }
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok((StatusCode::OK, Json(ItemActionResponse { item, outcome })))
 }
 
 async fn delete_item(Path(selector): Path<String>) -> Result<StatusCode, ApiError> {
@@ -580,8 +1017,54 @@ async fn put_item(
     let (ordered, offsets) = ordered_index(&index);
     let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
     let metadata = increment_copy_count(&hash).map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
-    let item = json_from_metadata(&metadata, offset, &data_dir).map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok(Json(item))
+}
+
+#[derive(Debug, Deserialize)]
+struct PrimaryRequest {
+    plugin: String,
+}
+
+async fn set_item_primary(
+    Path(selector): Path<String>,
+    Json(payload): Json<PrimaryRequest>,
+) -> Result<Json<plugins::ClipboardJsonItem>, ApiError> {
+    let index = load_index().map_err(ApiError::from)?;
+    let (ordered, offsets) = ordered_index(&index);
+    let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
+    if !set_primary_plugin(&hash, &payload.plugin).map_err(ApiError::from)? {
+        return Err(ApiError::bad_request(format!(
+            "Item has no '{}' format",
+            payload.plugin
+        )));
+    }
+    let metadata = load_metadata(&hash).map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+    Ok(Json(item))
+}
+
+#[derive(Debug, Deserialize)]
+struct SummaryRequest {
+    summary: Option<String>,
+}
+
+async fn set_item_summary(
+    Path(selector): Path<String>,
+    Json(payload): Json<SummaryRequest>,
+) -> Result<Json<plugins::ClipboardJsonItem>, ApiError> {
+    let index = load_index().map_err(ApiError::from)?;
+    let (ordered, offsets) = ordered_index(&index);
+    let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
+    let metadata =
+        set_summary_override(&hash, payload.summary.as_deref()).map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let item = json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
     Ok(Json(item))
 }
 
@@ -656,9 +1139,13 @@ async fn patch_item(
         date: Some(crate::util::time::format_iso(metadata.last_seen)),
         first_date: Some(crate::util::time::format_iso(metadata.first_seen)),
         summary: None,
+        summary_override: metadata.summary_override.clone(),
         item_type: Some(format!("{:?}", metadata.kind)),
         size: None,
         copy_count: Some(metadata.copy_count),
+        copy_events: metadata.copy_events.clone(),
+        paste_count: None,
+        last_pasted: None,
         detected_formats: metadata.detected_formats.clone(),
         sources: vec!["edit".to_string()],
         search_text: None,
@@ -688,17 +1175,28 @@ async fn search_items(
         ));
     }
     let index = load_index().map_err(ApiError::from)?;
-    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
 
+    let regex_flags = params
+        .flags
+        .as_deref()
+        .map(crate::search::RegexFlags::parse)
+        .transpose()?;
     let (parsed_query, is_regex, selection_filter) =
-        crate::search::parse_search_query(query, false);
+        crate::search::parse_search_query(query, regex_flags.is_some());
 
     let mut options = SearchOptions::default();
     if !parsed_query.is_empty() {
         options.query = Some(parsed_query);
     }
     options.regex = is_regex;
+    options.fold = params.fold;
+    options.regex_flags = regex_flags.unwrap_or_default();
     options.filter = selection_filter;
+    if let Some(field) = params.field.as_deref() {
+        options.field = crate::search::SearchField::parse(field)?;
+    }
 
     options.offset = params.offset.unwrap_or(0);
     options.limit = Some(params.count.unwrap_or(50));
@@ -721,18 +1219,10 @@ async fn search_items(
         };
     }
 
-    if let Some(formats) = params.formats {
-        for fmt in formats.split(',') {
-            let fmt = fmt.trim().to_lowercase();
-            match fmt.as_str() {
-                "text" => options.filter.include_text = true,
-                "image" => options.filter.include_image = true,
-                "file" | "files" => options.filter.include_file = true,
-                "html" => options.filter.include_html = true,
-                other => options.filter.include_formats.push(other.to_string()),
-            }
-        }
-    }
+    options.filter.extend(SelectionFilter::from_query_params(
+        params.formats.as_deref(),
+        params.kinds.as_deref(),
+    )?);
 
     let (items, _) = load_history_items(&index, &options).map_err(ApiError::from)?;
     let mut response = Vec::new();
@@ -743,6 +1233,7 @@ async fn search_items(
                 item.offset,
                 item.global_offset,
                 &data_dir,
+                &config,
             )
             .map_err(ApiError::from)?,
         );
@@ -776,11 +1267,7 @@ async fn get_stats() -> Result<Json<StatsResponse>, ApiError> {
         }
 
         // History grouping
-        let date = format_iso(record.last_seen)
-            .split('T')
-            .next()
-            .unwrap_or("unknown")
-            .to_string();
+        let date = crate::util::time::format_day(record.last_seen);
 
         let day_entry = history.entry(date).or_default();
         let type_entry = day_entry
@@ -831,13 +1318,143 @@ async fn get_version() -> Json<VersionResponse> {
         None => (None, None),
     };
 
+    let index_size = load_index().map(|index| index.len()).unwrap_or(0);
+
     Json(VersionResponse {
         version,
         api_start_time,
         api_start_time_iso,
+        index_generation: index_generation(),
+        index_size,
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IncognitoResponse {
+    enabled: bool,
+    since: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IncognitoRequest {
+    enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    incognito: IncognitoResponse,
+}
+
+fn incognito_response() -> Result<IncognitoResponse> {
+    let state = incognito::incognito_state(&data_dir_path()?);
+    Ok(IncognitoResponse {
+        enabled: state.is_some(),
+        since: state.map(|state| format_iso(state.since)),
     })
 }
 
+async fn get_health() -> Result<Json<HealthResponse>, ApiError> {
+    Ok(Json(HealthResponse {
+        status: "ok",
+        incognito: incognito_response().map_err(ApiError::from)?,
+    }))
+}
+
+async fn get_incognito() -> Result<Json<IncognitoResponse>, ApiError> {
+    Ok(Json(incognito_response().map_err(ApiError::from)?))
+}
+
+async fn update_incognito(
+    Json(payload): Json<IncognitoRequest>,
+) -> Result<Json<IncognitoResponse>, ApiError> {
+    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    incognito::set_incognito(&data_dir, payload.enabled).map_err(ApiError::from)?;
+    Ok(Json(incognito_response().map_err(ApiError::from)?))
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedQuery {
+    count: Option<usize>,
+}
+
+async fn related_items(
+    Path(selector): Path<String>,
+    Query(params): Query<RelatedQuery>,
+) -> Result<Json<Vec<plugins::ClipboardJsonItem>>, ApiError> {
+    let index = load_index().map_err(ApiError::from)?;
+    let config = load_config().map_err(ApiError::from)?;
+    let data_dir = config.data_dir();
+    let (ordered, offsets) = ordered_index(&index);
+    let (hash, _) = resolve_selector(&ordered, &offsets, &selector)?;
+
+    let hits = crate::search::related(&index, &hash, params.count.unwrap_or(10));
+    let mut response = Vec::new();
+    for hit in hits {
+        let offset = offsets.get(&hit.hash).copied().unwrap_or(0);
+        let metadata = load_metadata(&hit.hash).map_err(ApiError::from)?;
+        let item =
+            json_from_metadata(&metadata, offset, &data_dir, &config).map_err(ApiError::from)?;
+        response.push(item);
+    }
+    Ok(Json(response))
+}
+
+#[derive(Debug, Deserialize)]
+struct SuggestQuery {
+    prefix: Option<String>,
+    count: Option<usize>,
+}
+
+async fn suggest_items(Query(params): Query<SuggestQuery>) -> Result<Json<Vec<String>>, ApiError> {
+    let index = load_index().map_err(ApiError::from)?;
+    let prefix = params.prefix.unwrap_or_default();
+    Ok(Json(crate::search::suggest(
+        &index,
+        &prefix,
+        params.count.unwrap_or(10),
+    )))
+}
+
+#[derive(Debug, Serialize)]
+struct PluginInfo {
+    id: &'static str,
+    kind: &'static str,
+    priority: u8,
+    enabled: bool,
+}
+
+fn plugin_infos(config: &crate::config::AppConfig) -> Vec<PluginInfo> {
+    plugins::plugin_registry()
+        .iter()
+        .map(|plugin| PluginInfo {
+            id: plugin.id(),
+            kind: plugin.kind(),
+            priority: plugin.priority(),
+            enabled: config.is_plugin_enabled(plugin.id()),
+        })
+        .collect()
+}
+
+async fn list_plugins() -> Result<Json<Vec<PluginInfo>>, ApiError> {
+    let config = load_config().map_err(ApiError::from)?;
+    Ok(Json(plugin_infos(&config)))
+}
+
+#[derive(Debug, Serialize)]
+struct ResolveResponse {
+    hash: String,
+    offset: usize,
+}
+
+async fn resolve_item(Path(selector): Path<String>) -> Result<Json<ResolveResponse>, ApiError> {
+    let index = load_index().map_err(ApiError::from)?;
+    let (ordered, offsets) = ordered_index(&index);
+    let (hash, offset) = resolve_selector(&ordered, &offsets, &selector)?;
+    Ok(Json(ResolveResponse { hash, offset }))
+}
+
 async fn get_dir() -> Result<Json<DirResponse>, ApiError> {
     let config = load_config().map_err(ApiError::from)?;
     Ok(Json(DirResponse {
@@ -940,6 +1557,85 @@ async fn export_items() -> Result<Json<ExportResponse>, ApiError> {
     }))
 }
 
+#[derive(Debug, Deserialize)]
+struct ExportStreamQuery {
+    #[serde(default)]
+    offset: usize,
+    since: Option<String>,
+}
+
+/// Hashes to export, oldest first. Only items last seen after `since` are
+/// included. Copies made while an export runs always sort after everything
+/// already sent, so offsets into this order stay put for the items that
+/// haven't changed.
+fn export_order(index: &SearchIndex, since: Option<time::OffsetDateTime>) -> Vec<String> {
+    let (ordered, _) = ordered_index(index);
+    ordered
+        .into_iter()
+        .rev()
+        .filter(|record| since.is_none_or(|since| record.last_seen > since))
+        .map(|record| record.hash.clone())
+        .collect()
+}
+
+/// One JSON line per item, starting at `offset`. Each line carries its
+/// position in the export as `index`, so an interrupted client resumes with
+/// `offset` set to one past the last index it received. Items that fail to
+/// load are skipped without shifting the positions of the rest.
+fn ndjson_lines<F>(hashes: Vec<String>, offset: usize, mut load: F) -> impl Iterator<Item = String>
+where
+    F: FnMut(&str, usize) -> Result<plugins::ClipboardJsonFullItem>,
+{
+    hashes
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .filter_map(move |(idx, hash)| {
+            let line =
+                load(&hash, idx).and_then(|item| serde_json::to_string(&item).map_err(Into::into));
+            match line {
+                Ok(line) => Some(line + "\n"),
+                Err(err) => {
+                    eprintln!("Skipping {hash} in export: {err:#}");
+                    None
+                }
+            }
+        })
+}
+
+/// Streams the export as NDJSON, building each item only when the client is
+/// ready for it. `X-Total-Count` is the number of items matching `since`,
+/// before `offset` is applied.
+async fn export_ndjson(Query(params): Query<ExportStreamQuery>) -> Result<Response, ApiError> {
+    let since = params
+        .since
+        .as_deref()
+        .map(crate::util::time::parse_date)
+        .transpose()
+        .map_err(|err| ApiError::bad_request(format!("{err:#}")))?;
+    let index = load_fresh_index()?;
+    let data_dir = data_dir_path().map_err(ApiError::from)?;
+    let hashes = export_order(&index, since);
+    let total = hashes.len();
+    let lines = ndjson_lines(hashes, params.offset, move |hash, idx| {
+        let metadata = load_metadata(hash)?;
+        let item_dir = data_dir.join(&metadata.relative_path);
+        plugins::build_full_json_item(&metadata, &item_dir, Some(idx), None)
+    });
+    let stream = futures_util::stream::iter(lines.map(Ok::<_, std::convert::Infallible>));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .header("X-Total-Count", total)
+        .body(Body::from_stream(stream))
+        .map_err(|err| ApiError::Internal(err.into()))
+}
+
+async fn compact_items() -> Result<Json<CompactReport>, ApiError> {
+    let report = compact_store().map_err(ApiError::from)?;
+    Ok(Json(report))
+}
+
 async fn import_items(
     Json(payload): Json<ImportRequest>,
 ) -> Result<Json<ImportResponse>, ApiError> {
@@ -974,6 +1670,7 @@ async fn import_items(
 fn items_by_selectors(
     index: &SearchIndex,
     data_dir: &std::path::Path,
+    config: &crate::config::AppConfig,
     selectors: Vec<Selector>,
 ) -> Result<Json<Vec<plugins::ClipboardJsonItem>>, ApiError> {
     let (ordered, offsets) = ordered_index(index);
@@ -981,28 +1678,27 @@ fn items_by_selectors(
     for selector in selectors {
         let (hash, offset) = match selector {
             Selector::Hash(hash) => {
-                let offset = offsets
-                    .get(&hash)
-                    .copied()
-                    .ok_or_else(|| ApiError::not_found(format!("Unknown item {hash}")))?;
+                let offset = offsets.get(&hash).copied().ok_or_else(|| unknown_item(&hash))?;
                 (hash, offset)
             }
             Selector::Offset(index) => {
                 let record = ordered
                     .get(index)
-                    .ok_or_else(|| ApiError::not_found(format!("No item at offset {index}")))?;
+                    .ok_or_else(|| offset_out_of_range(index, ordered.len()))?;
                 (record.hash.clone(), index)
             }
         };
         let metadata = load_metadata(&hash).map_err(ApiError::from)?;
-        response.push(json_from_metadata(&metadata, offset, data_dir).map_err(ApiError::from)?);
+        let item =
+            json_from_metadata(&metadata, offset, data_dir, config).map_err(ApiError::from)?;
+        response.push(item);
     }
     Ok(Json(response))
 }
 
 fn ordered_index(index: &SearchIndex) -> (Vec<&SearchIndexRecord>, HashMap<String, usize>) {
     let mut ordered: Vec<_> = index.values().collect();
-    ordered.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    ordered.sort_by(|a, b| a.cmp_newest_first(b));
     let offsets = ordered
         .iter()
         .enumerate()
@@ -1013,10 +1709,10 @@ fn ordered_index(index: &SearchIndex) -> (Vec<&SearchIndexRecord>, HashMap<Strin
 
 fn ordered_index_filtered<'a>(
     index: &'a SearchIndex,
-    filter: &crate::search::SelectionFilter,
+    filter: &SelectionFilter,
 ) -> (Vec<(usize, &'a SearchIndexRecord)>, HashMap<String, usize>) {
     let mut all_ordered: Vec<_> = index.values().collect();
-    all_ordered.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    all_ordered.sort_by(|a, b| a.cmp_newest_first(b));
 
     let filtered: Vec<_> = all_ordered
         .iter()
@@ -1033,6 +1729,23 @@ fn ordered_index_filtered<'a>(
     (filtered, offsets)
 }
 
+/// A hash that matches nothing is a missing resource.
+fn unknown_item(hash: &str) -> ApiError {
+    ApiError::not_found(format!("Unknown item {hash}"))
+}
+
+/// An offset past the end is a malformed request; the message carries the
+/// valid range so clients can clamp.
+fn offset_out_of_range(index: usize, len: usize) -> ApiError {
+    match len {
+        0 => ApiError::bad_request(format!("Offset {index} is out of range: history is empty")),
+        len => ApiError::bad_request(format!(
+            "Offset {index} is out of range: valid offsets are 0-{}",
+            len - 1
+        )),
+    }
+}
+
 fn resolve_selector(
     ordered: &[&SearchIndexRecord],
     offsets: &HashMap<String, usize>,
@@ -1043,13 +1756,13 @@ fn resolve_selector(
             let offset = offsets
                 .get(&hash)
                 .copied()
-                .ok_or_else(|| ApiError::not_found(format!("Unknown item {hash}")))?;
+                .ok_or_else(|| unknown_item(&hash))?;
             Ok((hash, offset))
         }
         Selector::Offset(index) => {
             let record = ordered
                 .get(index)
-                .ok_or_else(|| ApiError::not_found(format!("No item at offset {index}")))?;
+                .ok_or_else(|| offset_out_of_range(index, ordered.len()))?;
             Ok((record.hash.clone(), index))
         }
     }
@@ -1065,16 +1778,16 @@ fn resolve_selector_filtered(
             let offset = offsets
                 .get(&hash)
                 .copied()
-                .ok_or_else(|| ApiError::not_found(format!("Unknown item {hash}")))?;
+                .ok_or_else(|| unknown_item(&hash))?;
             let (real_index, _) = ordered
                 .get(offset)
-                .ok_or_else(|| ApiError::not_found(format!("Unknown item {hash}")))?;
+                .ok_or_else(|| unknown_item(&hash))?;
             Ok((hash, offset, *real_index))
         }
         Selector::Offset(index) => {
             let (real_index, record) = ordered
                 .get(index)
-                .ok_or_else(|| ApiError::not_found(format!("No item at offset {index}")))?;
+                .ok_or_else(|| offset_out_of_range(index, ordered.len()))?;
             Ok((record.hash.clone(), index, *real_index))
         }
     }
@@ -1090,13 +1803,17 @@ fn load_fresh_index() -> Result<Arc<SearchIndex>, ApiError> {
     load_index().map_err(ApiError::from)
 }
 
+/// `config` is loaded once per request by the caller, so listing many items
+/// doesn't re-read it for each one.
 fn json_from_metadata(
     metadata: &EntryMetadata,
     offset: usize,
     data_dir: &std::path::Path,
+    config: &crate::config::AppConfig,
 ) -> Result<plugins::ClipboardJsonItem> {
     let item_dir = data_dir.join(&metadata.relative_path);
-    plugins::build_json_item_with_preference(metadata, &item_dir, offset, None, None)
+    let preferred = plugins::preferred_plugin(None, config, metadata);
+    plugins::build_json_item_with_preference(metadata, &item_dir, offset, preferred, None)
 }
 
 fn json_from_metadata_with_index(
@@ -1104,7 +1821,439 @@ fn json_from_metadata_with_index(
     offset: usize,
     real_index: usize,
     data_dir: &std::path::Path,
+    config: &crate::config::AppConfig,
 ) -> Result<plugins::ClipboardJsonItem> {
     let item_dir = data_dir.join(&metadata.relative_path);
-    plugins::build_json_item_with_preference(metadata, &item_dir, offset, None, Some(real_index))
+    let preferred = plugins::preferred_plugin(None, config, metadata);
+    plugins::build_json_item_with_preference(
+        metadata,
+        &item_dir,
+        offset,
+        preferred,
+        Some(real_index),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::model::EntryKind;
+    use time::{Duration, OffsetDateTime};
+
+    fn create_record(hash: &str, age_secs: i64) -> SearchIndexRecord {
+        let last_seen = OffsetDateTime::now_utc() - Duration::seconds(age_secs);
+        SearchIndexRecord {
+            hash: hash.to_string(),
+            first_seen: last_seen,
+            last_seen,
+            kind: EntryKind::Text,
+            copy_count: 1,
+            summary: None,
+            search_text: None,
+            detected_formats: vec![],
+            byte_size: 10,
+            relative_path: "".to_string(),
+            pinned: false,
+            source_app: None,
+        }
+    }
+
+    fn sample_index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        for (hash, age) in [("aaaaaaaa", 30), ("bbbbbbbb", 20), ("cccccccc", 10)] {
+            index.insert(hash.to_string(), create_record(hash, age));
+        }
+        index
+    }
+
+    fn export_from(hashes: Vec<String>, offset: usize) -> Vec<(Option<usize>, Option<String>)> {
+        ndjson_lines(hashes, offset, |hash, idx| {
+            if hash == "cccccccc" {
+                anyhow::bail!("metadata missing");
+            }
+            Ok(serde_json::from_value(json!({ "index": idx, "id": hash }))?)
+        })
+        .map(|line| {
+            assert!(line.ends_with('\n'));
+            let item: plugins::ClipboardJsonFullItem = serde_json::from_str(&line).unwrap();
+            (item.index, item.id)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_ndjson_export_resumes_at_offset() {
+        let mut index = sample_index();
+        index.insert("dddddddd".to_string(), create_record("dddddddd", 5));
+        let hashes = export_order(&index, None);
+        assert_eq!(hashes, vec!["aaaaaaaa", "bbbbbbbb", "cccccccc", "dddddddd"]);
+        let full = export_from(hashes.clone(), 0);
+        assert_eq!(
+            full,
+            vec![
+                (Some(0), Some("aaaaaaaa".to_string())),
+                (Some(1), Some("bbbbbbbb".to_string())),
+                (Some(3), Some("dddddddd".to_string())),
+            ]
+        );
+
+        // A client cut off after index 1 resumes at offset 2 and ends up with
+        // the same items as an uninterrupted export, even though a new item
+        // was copied in between.
+        let mut resumed = full[..2].to_vec();
+        index.insert("eeeeeeee".to_string(), create_record("eeeeeeee", 0));
+        resumed.extend(export_from(export_order(&index, None), 2));
+        assert_eq!(resumed[..3], full[..]);
+        assert_eq!(resumed[3], (Some(4), Some("eeeeeeee".to_string())));
+        assert!(export_from(hashes, 10).is_empty());
+    }
+
+    #[test]
+    fn test_export_since_filters_before_counting() {
+        let index = sample_index();
+        let since = index["aaaaaaaa"].last_seen;
+        let hashes = export_order(&index, Some(since));
+        // `X-Total-Count` is the length of this list.
+        assert_eq!(hashes, vec!["bbbbbbbb", "cccccccc"]);
+    }
+
+    #[test]
+    fn test_pinned_first_mixes_pins_and_recent() {
+        // (name, pinned), newest first.
+        let items = vec![
+            ("a", false),
+            ("b", true),
+            ("c", false),
+            ("d", true),
+            ("e", false),
+            ("f", true),
+        ];
+        let names = |items: Vec<(&'static str, bool)>| -> Vec<&'static str> {
+            items.into_iter().map(|(name, _)| name).collect()
+        };
+        let is_pinned = |item: &(&str, bool)| item.1;
+
+        assert_eq!(
+            names(pinned_first(items.clone(), 0, Some(4), 2, is_pinned)),
+            vec!["b", "d", "a", "c"]
+        );
+        // Pins past the limit stay in their recent position.
+        assert_eq!(
+            names(pinned_first(items.clone(), 0, Some(5), 1, is_pinned)),
+            vec!["b", "a", "c", "d", "e"]
+        );
+        // Pins never take more than the requested slots.
+        assert_eq!(
+            names(pinned_first(items.clone(), 0, Some(2), 5, is_pinned)),
+            vec!["b", "d"]
+        );
+        // Later pages continue the same ranking.
+        assert_eq!(
+            names(pinned_first(items.clone(), 2, Some(3), 2, is_pinned)),
+            vec!["a", "c", "e"]
+        );
+        assert_eq!(
+            names(pinned_first(items, 0, None, 5, is_pinned)),
+            vec!["b", "d", "f", "a", "c", "e"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_storing_an_item_bumps_version_generation() {
+        let _scratch = crate::data::store::scratch_store(|_| {});
+
+        let before = get_version().await.0;
+        let snapshot = serde_json::from_value(json!({
+            "kind": "Text",
+            "text": "generation test",
+            "html": null,
+            "rtf": null,
+            "image_bytes": null,
+            "image_mime": null,
+            "files": [],
+            "summary": null,
+            "detected_formats": ["public.utf8-plain-text"],
+            "extra": null,
+        }))
+        .unwrap();
+        crate::data::store::store_snapshot(snapshot).unwrap();
+        let after = get_version().await.0;
+
+        assert!(after.index_generation > before.index_generation);
+        assert_eq!(after.index_size, before.index_size + 1);
+    }
+
+    #[test]
+    fn test_attempt_paste_skips_without_permission() {
+        let mut called = false;
+        let outcome = attempt_paste(false, || {
+            called = true;
+            Ok(())
+        });
+        assert!(!called);
+        assert!(matches!(outcome, Err(ApiError::Forbidden(_))));
+    }
+
+    #[test]
+    fn test_attempt_paste_reports_result() {
+        let outcome = attempt_paste(true, || Ok(())).unwrap();
+        assert_eq!(
+            outcome,
+            PasteOutcome {
+                pasted: true,
+                reason: None
+            }
+        );
+
+        let failed = attempt_paste(true, || Err(anyhow::anyhow!("no event source"))).unwrap();
+        assert!(!failed.pasted);
+        assert_eq!(failed.reason.as_deref(), Some("no event source"));
+    }
+
+    #[test]
+    fn test_attempt_paste_maps_permission_error() {
+        let outcome = attempt_paste(true, || Err(paste::PermissionDenied.into()));
+        let err = outcome.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert!(matches!(err, ApiError::Forbidden(message) if message.contains("Accessibility")));
+    }
+
+    #[test]
+    fn test_permission_failure_is_forbidden_not_internal() {
+        let wrapped = anyhow::Error::from(paste::PermissionDenied).context("Failed to paste");
+        assert_eq!(ApiError::from(wrapped).status(), StatusCode::FORBIDDEN);
+        let other = ApiError::from(anyhow::anyhow!("no event source"));
+        assert_eq!(other.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_copy_only_outcome_flags_missing_permission() {
+        assert_eq!(PasteOutcome::copy_only(true).reason, None);
+        assert!(PasteOutcome::copy_only(false).reason.is_some());
+        assert!(!PasteOutcome::copy_only(true).pasted);
+    }
+
+    #[test]
+    fn test_plugin_infos_lists_builtins() {
+        let config = crate::config::AppConfig {
+            disabled_plugins: Some(vec!["rtf".to_string()]),
+            ..Default::default()
+        };
+        let listed: Vec<(&str, u8, bool)> = plugin_infos(&config)
+            .iter()
+            .map(|info| (info.id, info.priority, info.enabled))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("files", 0, true),
+                ("image", 1, true),
+                ("text", 2, true),
+                ("html", 3, true),
+                ("rtf", 4, false),
+                ("passthrough", 5, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_selector_by_offset() {
+        let index = sample_index();
+        let (ordered, offsets) = ordered_index(&index);
+        let (hash, offset) = resolve_selector(&ordered, &offsets, "1").unwrap();
+        assert_eq!(hash, "bbbbbbbb");
+        assert_eq!(offset, 1);
+        assert!(resolve_selector(&ordered, &offsets, "7").is_err());
+    }
+
+    #[test]
+    fn test_resolve_selector_out_of_range_offset_is_bad_request() {
+        let index = sample_index();
+        let (ordered, offsets) = ordered_index(&index);
+        let err = resolve_selector(&ordered, &offsets, "7").unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(matches!(
+            &err,
+            ApiError::BadRequest(message)
+                if message == "Offset 7 is out of range: valid offsets are 0-2"
+        ));
+
+        let empty = SearchIndex::new();
+        let (ordered, offsets) = ordered_index(&empty);
+        let err = resolve_selector(&ordered, &offsets, "0").unwrap_err();
+        assert!(matches!(
+            err,
+            ApiError::BadRequest(message) if message == "Offset 0 is out of range: history is empty"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_selector_unknown_hash_is_not_found() {
+        let index = sample_index();
+        let (ordered, offsets) = ordered_index(&index);
+        let err = resolve_selector(&ordered, &offsets, "ffffffff").unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert!(matches!(err, ApiError::NotFound(message) if message == "Unknown item ffffffff"));
+    }
+
+    #[test]
+    fn test_resolve_selector_filtered_distinguishes_errors() {
+        let index = sample_index();
+        let filter = SelectionFilter::default();
+        let (ordered, offsets) = ordered_index_filtered(&index, &filter);
+        let out_of_range = resolve_selector_filtered(&ordered, &offsets, "3").unwrap_err();
+        assert_eq!(out_of_range.status(), StatusCode::BAD_REQUEST);
+        let unknown = resolve_selector_filtered(&ordered, &offsets, "ffffffff").unwrap_err();
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_resolve_selector_by_hash() {
+        let index = sample_index();
+        let (ordered, offsets) = ordered_index(&index);
+        let (hash, offset) = resolve_selector(&ordered, &offsets, "aaaaaaaa").unwrap();
+        assert_eq!(hash, "aaaaaaaa");
+        assert_eq!(offset, 2);
+        assert!(resolve_selector(&ordered, &offsets, "ffffffff").is_err());
+    }
+
+    #[test]
+    fn test_open_flag_opens_dashboard_url() {
+        let mut opened = Vec::new();
+        open_dashboard_if(true, "http://127.0.0.1:3016/dashboard", |url| {
+            opened.push(url.to_string());
+            Ok(())
+        });
+        assert_eq!(opened, vec!["http://127.0.0.1:3016/dashboard"]);
+
+        open_dashboard_if(false, "http://127.0.0.1:3016/dashboard", |url| {
+            opened.push(url.to_string());
+            Ok(())
+        });
+        assert_eq!(opened.len(), 1);
+
+        open_dashboard_if(true, "http://127.0.0.1:3016/dashboard", |_| {
+            Err(anyhow::anyhow!("no browser"))
+        });
+    }
+
+    #[test]
+    fn test_preview_batch_dedupes_in_order() {
+        let selectors = vec!["0".into(), "abcdef12".into(), "0".into(), "3".into()];
+        assert_eq!(preview_batch(selectors).unwrap(), vec!["0", "abcdef12", "3"]);
+        assert!(preview_batch(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_structured_response_keeps_format_order() {
+        let formats = vec![
+            plugins::PreviewFormat {
+                plugin_id: "files".into(),
+                template_name: "files.hbs".into(),
+                priority: 0,
+                data: json!({ "files": [{ "name": "a", "size": "1 KB", "path": "/a" }] }),
+                text: None,
+            },
+            plugins::PreviewFormat {
+                plugin_id: "text".into(),
+                template_name: "text.hbs".into(),
+                priority: 1,
+                data: json!({ "raw_text": "/a" }),
+                text: Some("/a".into()),
+            },
+        ];
+        let response = structured_response(&EntryKind::File, &formats, vec!["html".into()]);
+        let body = serde_json::to_value(&response).unwrap();
+        assert_eq!(body["kind"], "file");
+        assert_eq!(body["formatsOrder"], json!(["files", "text"]));
+        assert_eq!(body["data"]["files"]["paths"], json!(["/a"]));
+        assert_eq!(body["data"]["text"]["type"], "text");
+        assert_eq!(body["unrenderedFormats"], json!(["html"]));
+    }
+
+    #[test]
+    fn test_preview_batch_enforces_cap() {
+        let at_cap: Vec<String> = (0..MAX_PREVIEW_BATCH).map(|i| i.to_string()).collect();
+        assert_eq!(preview_batch(at_cap).unwrap().len(), MAX_PREVIEW_BATCH);
+
+        let over_cap: Vec<String> = (0..=MAX_PREVIEW_BATCH).map(|i| i.to_string()).collect();
+        assert!(matches!(preview_batch(over_cap), Err(ApiError::BadRequest(_))));
+
+        let repeated = vec!["0".to_string(); MAX_PREVIEW_BATCH * 2];
+        assert_eq!(preview_batch(repeated).unwrap(), vec!["0"]);
+    }
+
+    fn preview_of_size(bytes: usize) -> PreviewResponse {
+        let data = PreviewData {
+            html: "x".repeat(bytes),
+            text: None,
+        };
+        PreviewResponse {
+            kind: "text".into(),
+            formats_order: vec!["text".into()],
+            data: HashMap::from([("text".to_string(), data)]),
+            unrendered_formats: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_preview_batch_defers_past_byte_cap() {
+        let selectors: Vec<String> = ["0", "gone", "1", "2", "3"].map(String::from).to_vec();
+        let response = render_batch(selectors, 100, |selector| {
+            (selector != "gone").then(|| preview_of_size(60))
+        });
+        assert_eq!(response.previews.len(), 2);
+        assert!(response.previews.contains_key("0") && response.previews.contains_key("1"));
+        assert_eq!(response.missing, vec!["gone"]);
+        assert_eq!(response.deferred, vec!["2", "3"]);
+
+        // A single oversized preview is still returned.
+        let selectors = vec!["0".to_string(), "1".to_string()];
+        let response = render_batch(selectors, 10, |_| Some(preview_of_size(50)));
+        assert_eq!(response.previews.len(), 1);
+        assert_eq!(response.deferred, vec!["1"]);
+    }
+
+    #[test]
+    fn test_clipboard_errors_map_to_api_errors() {
+        let missing = anyhow::Error::from(ClipboardError::NotFound("gone".into()));
+        assert!(matches!(ApiError::from(missing), ApiError::NotFound(message) if message == "gone"));
+
+        let invalid = ApiError::from(ClipboardError::InvalidInput("Invalid selector x".into()));
+        assert!(matches!(invalid, ApiError::BadRequest(_)));
+
+        // A stored item that fails to parse is the server's fault, not the
+        // caller's.
+        let corrupt = ApiError::from(ClipboardError::Parse("Failed to parse metadata".into()));
+        assert!(matches!(corrupt, ApiError::Internal(_)));
+
+        let clipboard = ApiError::from(ClipboardError::Clipboard("busy".into()));
+        assert!(matches!(clipboard, ApiError::Internal(_)));
+
+        let untyped = ApiError::from(anyhow::anyhow!("boom"));
+        assert!(matches!(untyped, ApiError::Internal(_)));
+    }
+
+    #[test]
+    fn test_raw_fragment_drops_injected_style_and_script() {
+        let doc = "<!DOCTYPE html><html><head><style>body{}</style></head>\
+                   <body class=\"x\"><div>hi</div><SCRIPT>run()</SCRIPT>\
+                   <p>there</p><style>.a{}</style></body></html>";
+        assert_eq!(raw_fragment(doc), "<div>hi</div><p>there</p>");
+        assert_eq!(raw_fragment("<b>bare</b>"), "<b>bare</b>");
+    }
+
+    #[test]
+    fn test_raw_preview_of_template_has_no_injection() {
+        let full = HANDLEBARS
+            .render("text.hbs", &json!({ "content": "hello raw" }))
+            .unwrap();
+        assert!(full.contains("<style>") && full.contains("<script>"));
+
+        let raw = raw_fragment(&full);
+        assert!(raw.contains("hello raw"));
+        assert!(!raw.contains("<style"));
+        assert!(!raw.contains("<script"));
+        assert!(!raw.contains("<body"));
+    }
 }
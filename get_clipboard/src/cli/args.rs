@@ -1,6 +1,9 @@
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::data::store::{DEFAULT_PHASH_THRESHOLD, MAX_PHASH_THRESHOLD};
+use crate::util::paste::MAX_PASTE_COUNT;
+
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about = "Minimal yet powerful clipboard history for macOS", long_about = None)]
 pub struct Cli {
@@ -30,10 +33,15 @@ impl FilterFlags {
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum Command {
-    #[command(about = "Interactive TUI for browsing and selecting clipboard items")]
+    #[command(
+        about = "Interactive TUI for browsing and selecting clipboard items",
+        visible_alias = "tui"
+    )]
     Interactive {
         #[arg(short, long, help = "Initial search query")]
         query: Option<String>,
+        #[arg(long, help = "Color theme: dark or light (default: tui.theme from config)")]
+        theme: Option<String>,
     },
     #[command(about = "Copy a clipboard item back to the clipboard")]
     Copy {
@@ -41,6 +49,33 @@ pub enum Command {
         selector: String,
         #[command(flatten)]
         filters: FilterFlags,
+        #[arg(long, help = "Append the item's text to the current clipboard text")]
+        append: bool,
+        #[arg(
+            long,
+            value_enum,
+            help = "Copy the item before or after the last copied one, wrapping around"
+        )]
+        relative: Option<RelativeDirection>,
+        #[arg(
+            long,
+            conflicts_with = "append",
+            help = "Copy the item's JSON metadata instead of its content"
+        )]
+        meta: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["append", "meta"],
+            help = "Copy an image item as a data:image/...;base64 URI text"
+        )]
+        data_uri: bool,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["append", "meta", "data_uri"],
+            help = "Only copy these formats of the item, e.g. text,html"
+        )]
+        formats: Option<Vec<String>>,
     },
     #[command(about = "Delete a clipboard item")]
     Delete {
@@ -49,6 +84,31 @@ pub enum Command {
         #[command(flatten)]
         filters: FilterFlags,
     },
+    #[command(about = "Re-read a file item's files and update their sizes and types")]
+    Replay {
+        #[arg(default_value = "0", help = "Item selector (index, hash, or search term)")]
+        selector: String,
+        #[command(flatten)]
+        filters: FilterFlags,
+    },
+    #[command(about = "Give a clipboard item a custom summary")]
+    Rename {
+        #[arg(help = "Item selector (index, hash, or search term)")]
+        selector: String,
+        #[arg(help = "New summary; an empty string restores the generated one")]
+        text: String,
+        #[command(flatten)]
+        filters: FilterFlags,
+    },
+    #[command(about = "Print where a clipboard item is stored on disk")]
+    Which {
+        #[arg(default_value = "0", help = "Item selector (index, hash, or search term)")]
+        selector: String,
+        #[command(flatten)]
+        filters: FilterFlags,
+        #[arg(long, help = "Reveal the item in Finder")]
+        open: bool,
+    },
     #[command(about = "Show details of a clipboard item")]
     Show {
         #[arg(default_value = "0", help = "Item selector (index, hash, or search term)")]
@@ -59,14 +119,21 @@ pub enum Command {
         json: bool,
     },
     #[command(about = "Watch for new clipboard items")]
-    Watch,
+    Watch {
+        #[arg(long, help = "Only store items when `capture-now` is run")]
+        manual: bool,
+    },
+    #[command(about = "Store the current clipboard contents now")]
+    CaptureNow,
+    #[command(about = "Print the formats on the clipboard right now as JSON")]
+    Formats,
     #[command(about = "Manage the background service")]
     Service(ServiceArgs),
     #[command(about = "Manage the data directory")]
     Dir(DirArgs),
     #[command(about = "Search clipboard history")]
     Search(SearchArgs),
-    #[command(about = "Start the HTTP API server")]
+    #[command(about = "Start the HTTP API server", visible_alias = "serve")]
     Api(ApiArgs),
     #[command(about = "List clipboard history")]
     History(HistoryArgs),
@@ -76,21 +143,113 @@ pub enum Command {
         selector: String,
         #[command(flatten)]
         filters: FilterFlags,
+        #[arg(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u32).range(1..=MAX_PASTE_COUNT as i64),
+            help = "Number of times to paste"
+        )]
+        count: u32,
+        #[arg(long, default_value_t = 100, help = "Delay between repeated pastes in milliseconds")]
+        delay: u64,
+        #[arg(
+            long,
+            value_name = "APP",
+            help = "Bundle id or app name to activate before pasting (macOS)"
+        )]
+        target_app: Option<String>,
     },
-    #[command(about = "Export clipboard history to a JSON file")]
-    Export {
-        #[arg(help = "Path to the export file")]
-        path: PathBuf,
+    #[command(about = "Copy a marker string and simulate a paste to check that pasting works")]
+    PasteTest {
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
     },
+    #[command(
+        about = "Export clipboard history to a JSON file",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
+    Export(ExportArgs),
     #[command(about = "Import clipboard history from a JSON file")]
     Import {
         #[arg(help = "Path to the import file")]
         path: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = ImportConflict::Skip,
+            help = "What to do when an imported item already exists"
+        )]
+        on_conflict: ImportConflict,
+    },
+    #[command(about = "List the most frequently copied items")]
+    Top {
+        #[arg(short = 'n', long, default_value_t = 10, help = "Number of items to show")]
+        limit: usize,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
     },
     #[command(about = "Show clipboard statistics")]
     Stats {
         #[arg(long, help = "Output in JSON format")]
         json: bool,
+        #[arg(long, help = "Count items per size range (<1KB up to >1MB)")]
+        size_buckets: bool,
+    },
+    #[command(about = "Show on-disk usage of the data directory")]
+    Du {
+        #[arg(long, help = "Also break usage down by item kind")]
+        by_kind: bool,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+    #[command(about = "Tag or pin every item matching a query")]
+    Tag(TagArgs),
+    #[command(about = "Transcode stored PNGs to lossless WebP where it saves space")]
+    OptimizeImages {
+        #[arg(long, help = "Report the savings without changing anything")]
+        dry_run: bool,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+    #[command(about = "Merge near-identical images, keeping the most recent copy")]
+    Dedup {
+        #[arg(
+            long,
+            required = true,
+            help = "Merge images whose perceptual hashes are close"
+        )]
+        images: bool,
+        #[arg(
+            long,
+            default_value_t = DEFAULT_PHASH_THRESHOLD,
+            value_parser = clap::value_parser!(u32).range(0..=MAX_PHASH_THRESHOLD as i64),
+            help = "Largest hash distance, in bits out of 64, that counts as a duplicate"
+        )]
+        threshold: u32,
+        #[arg(long, help = "Report the duplicates without changing anything")]
+        dry_run: bool,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+    #[command(about = "Rebuild the index from stored metadata")]
+    Reindex {
+        #[arg(long, help = "Only regenerate summaries with the current logic")]
+        summaries: bool,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+    #[command(about = "Delete items the configured pruning policy no longer keeps")]
+    Prune {
+        #[arg(long, help = "Report what would be deleted without changing anything")]
+        dry_run: bool,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+    #[command(about = "Compact the journal and remove empty store directories")]
+    Compact {
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
     },
     #[command(about = "Manage accessibility permissions")]
     Permissions {
@@ -99,10 +258,50 @@ pub enum Command {
     },
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct TagArgs {
+    #[arg(short, long, help = "Search query selecting the items (supports operators like @link)")]
+    pub query: String,
+    #[arg(long, help = "Treat query as a regular expression")]
+    pub regex: bool,
+    #[command(flatten)]
+    pub filters: FilterFlags,
+    #[arg(long, help = "List the matching items without changing them")]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub action: TagCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TagCommand {
+    #[command(about = "Add a tag")]
+    Add { tag: String },
+    #[command(about = "Remove a tag")]
+    Remove { tag: String },
+    #[command(about = "Pin the items")]
+    Pin,
+    #[command(about = "Unpin the items")]
+    Unpin,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExportAction {
+    #[command(about = "Check that an export file would import cleanly, without importing it")]
+    Verify {
+        #[arg(help = "Path to the export file")]
+        path: PathBuf,
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum PermissionsCmd {
     #[command(about = "Check if accessibility permissions are granted")]
-    Check,
+    Check {
+        #[arg(long, help = "Output in JSON format")]
+        json: bool,
+    },
     #[command(about = "Request accessibility permissions")]
     Request,
 }
@@ -116,7 +315,20 @@ pub struct ServiceArgs {
 #[derive(Subcommand, Debug, Clone)]
 pub enum ServiceAction {
     #[command(about = "Install the background service")]
-    Install,
+    Install {
+        #[arg(
+            long,
+            overrides_with = "no_autostart",
+            help = "Start the service automatically at login (default)"
+        )]
+        autostart: bool,
+        #[arg(
+            long,
+            overrides_with = "autostart",
+            help = "Only run the service when started with `service start`"
+        )]
+        no_autostart: bool,
+    },
     #[command(about = "Uninstall the background service")]
     Uninstall,
     #[command(about = "Start the background service")]
@@ -143,7 +355,10 @@ pub struct DirArgs {
 #[derive(Subcommand, Debug, Clone)]
 pub enum DirCommand {
     #[command(about = "Get the current data directory path")]
-    Get,
+    Get {
+        #[arg(long, help = "Output path, existence, writability and size as JSON")]
+        json: bool,
+    },
     #[command(about = "Set the data directory path (does not move existing data)")]
     Set {
         #[arg(help = "New data directory path")]
@@ -164,9 +379,17 @@ pub struct HistoryArgs {
     pub query: Option<String>,
     #[arg(long, value_enum, help = "Filter by item type")]
     pub kind: Option<EntryKind>,
-    #[arg(long, help = "Filter items from this date (YYYY-MM-DD or relative like '1d')")]
+    #[arg(
+        long,
+        visible_alias = "since",
+        help = "Filter items from this date (YYYY-MM-DD or relative like '2h', '3d', '1w')"
+    )]
     pub from: Option<String>,
-    #[arg(long, help = "Filter items until this date (YYYY-MM-DD or relative like '1d')")]
+    #[arg(
+        long,
+        visible_alias = "until",
+        help = "Filter items until this date (YYYY-MM-DD or relative like '2h', '3d', '1w')"
+    )]
     pub to: Option<String>,
     #[arg(long, help = "Include full content in JSON output")]
     pub full: bool,
@@ -178,6 +401,37 @@ pub struct HistoryArgs {
     pub filters: FilterFlags,
     #[arg(long, help = "Output in JSON format")]
     pub json: bool,
+    #[arg(
+        long,
+        conflicts_with = "json",
+        help = "Stable tab-separated output: hash, kind, last_seen, copy_count, byte_size"
+    )]
+    pub porcelain: bool,
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = ["json", "porcelain"],
+        help = "List items under a header for each day, source app or kind"
+    )]
+    pub group_by: Option<HistoryGroupBy>,
+    #[arg(
+        long,
+        conflicts_with_all = ["group_by", "json", "porcelain"],
+        help = "Shorthand for --group-by day"
+    )]
+    pub group_by_day: bool,
+    #[arg(
+        long,
+        overrides_with = "no_global",
+        help = "Show each item's global offset in parentheses"
+    )]
+    pub show_global: bool,
+    #[arg(
+        long,
+        overrides_with = "show_global",
+        help = "Hide global offsets (default)"
+    )]
+    pub no_global: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -190,18 +444,74 @@ pub struct SearchArgs {
     pub full: bool,
     #[arg(long, help = "Treat query as a regular expression")]
     pub regex: bool,
+    #[arg(long, help = "Ignore accents and other diacritics (\"cafe\" matches \"café\")")]
+    pub fold: bool,
+    #[arg(
+        long,
+        value_name = "FLAGS",
+        help = "Regex flags i, m, s, x (implies --regex; case-sensitive unless i is given)"
+    )]
+    pub regex_flags: Option<String>,
     #[arg(long, value_enum, help = "Sort order")]
     pub sort: Option<SearchSort>,
+    #[arg(
+        long = "in",
+        value_enum,
+        value_name = "FIELD",
+        help = "Only match the summary, the full text, or all fields (default)"
+    )]
+    pub field: Option<SearchIn>,
     #[command(flatten)]
     pub filters: FilterFlags,
     #[arg(long, help = "Output in JSON format")]
     pub json: bool,
+    #[arg(
+        long,
+        conflicts_with = "json",
+        help = "Show which field matched, how, and the relevance score"
+    )]
+    pub explain: bool,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    pub action: Option<ExportAction>,
+    #[arg(help = "Path to the export file", required = true)]
+    pub path: Option<PathBuf>,
+    #[arg(short, long, help = "Only export items matching this search query")]
+    pub query: Option<String>,
+    #[arg(long, value_enum, help = "Only export items of this type")]
+    pub kind: Option<EntryKind>,
+    #[arg(
+        long,
+        visible_alias = "since",
+        help = "Only export items from this date (YYYY-MM-DD or relative like '2h', '3d', '1w')"
+    )]
+    pub from: Option<String>,
+    #[arg(
+        long,
+        visible_alias = "until",
+        help = "Only export items until this date (YYYY-MM-DD or relative like '2h', '3d', '1w')"
+    )]
+    pub to: Option<String>,
+    #[arg(long, help = "Treat query as a regular expression")]
+    pub regex: bool,
+    #[arg(
+        long,
+        help = "Replace copied content with placeholders, keeping sizes, kinds, dates and formats"
+    )]
+    pub redacted: bool,
+    #[command(flatten)]
+    pub filters: FilterFlags,
 }
 
 #[derive(Args, Debug, Clone)]
 pub struct ApiArgs {
     #[arg(long, default_value_t = 3016, help = "Port to listen on")]
     pub port: u16,
+    #[arg(long, help = "Open the dashboard in the default browser once listening")]
+    pub open: bool,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -212,6 +522,26 @@ pub enum EntryKind {
     Other,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum RelativeDirection {
+    #[value(help = "One item older than the last copied item")]
+    Prev,
+    #[value(help = "One item newer than the last copied item")]
+    Next,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportConflict {
+    #[value(help = "Keep the existing item untouched")]
+    Skip,
+    #[value(help = "Add copy counts and keep the widest date range")]
+    Merge,
+    #[value(help = "Merge for syncing machines: count each distinct copy once")]
+    SyncMerge,
+    #[value(help = "Overwrite the existing item with the imported one")]
+    Replace,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum HistorySort {
     Date,
@@ -219,6 +549,13 @@ pub enum HistorySort {
     Type,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryGroupBy {
+    Day,
+    App,
+    Kind,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum SearchSort {
     Date,
@@ -226,3 +563,10 @@ pub enum SearchSort {
     Type,
     Relevance,
 }
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SearchIn {
+    Summary,
+    Text,
+    All,
+}
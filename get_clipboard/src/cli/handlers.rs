@@ -1,26 +1,33 @@
 use crate::api;
 use crate::cli::args::{
-    ApiArgs, Cli, Command, DirCommand, EntryKind as CliEntryKind, FilterFlags, HistoryArgs,
-    PermissionsCmd, SearchArgs, ServiceAction,
+    ApiArgs, Cli, Command, DirCommand, EntryKind as CliEntryKind, ExportAction, ExportArgs,
+    FilterFlags, HistoryArgs, HistoryGroupBy, ImportConflict as CliImportConflict, PermissionsCmd,
+    RelativeDirection, SearchArgs, ServiceAction, TagArgs, TagCommand,
 };
 use crate::clipboard::plugins::{self, DisplayContent, ImageDisplay};
 use crate::config::{self, ensure_data_dir, load_config};
-use crate::data::model::EntryMetadata;
+use crate::data::cursor::Direction;
+use crate::data::model::{EntryKind, EntryMetadata, SearchIndex, SearchIndexRecord};
 use crate::data::store::{
-    HistoryItem, SelectionFilter, copy_by_selector, delete_entry, human_size, load_history_items,
-    load_index, load_metadata, resolve_selector, stream_history_items,
+    HistoryItem, SelectionFilter, TagAction, compact_store, copy_appending_by_selector,
+    copy_data_uri_by_selector, copy_formats_by_selector, copy_metadata_by_selector,
+    copy_untracked_text, dedup_images, delete_entry, human_size, load_history_items, load_index,
+    load_metadata, optimize_images, prune_history, rebuild_index, record_paste, replay_entry,
+    resolve_relative, resolve_selector, resummarize_entries, set_summary_override,
+    stream_history_items, update_tags,
 };
-use crate::search::SearchOptions;
-use crate::service::{self, ServiceStatus, permissions, watch};
+use crate::search::{RegexFlags, SearchOptions};
+use crate::service::watch::{self, CaptureMode};
+use crate::service::{self, ServiceStatus, permissions};
 use crate::tui;
 use crate::util::paste;
-use crate::util::time::{OffsetDateTime, format_iso, parse_date};
+use crate::util::time::{OffsetDateTime, format_day, format_iso, parse_date};
 use anyhow::{Context, Result, bail};
 use serde_json::to_string_pretty;
 use std::{
     env,
     io::{self, ErrorKind, IsTerminal, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 use viuer::Config as ViuerConfig;
 
@@ -29,6 +36,7 @@ enum OutputMode {
     Text,
     JsonSimple,
     JsonFull,
+    Porcelain,
 }
 
 pub fn dispatch(cli: Cli) -> Result<()> {
@@ -36,9 +44,37 @@ pub fn dispatch(cli: Cli) -> Result<()> {
         .command
         .unwrap_or(Command::History(HistoryArgs::default()));
     match command {
-        Command::Interactive { query } => run_interactive(query),
-        Command::Copy { selector, filters } => copy_entry(&selector, &filters),
+        Command::Interactive { query, theme } => run_interactive(query, theme),
+        Command::Copy {
+            selector,
+            filters,
+            append,
+            relative,
+            meta,
+            data_uri,
+            formats,
+        } => copy_entry(
+            &selector,
+            &filters,
+            append,
+            relative,
+            meta,
+            data_uri,
+            formats.as_deref(),
+        )
+        .map(|_| ()),
         Command::Delete { selector, filters } => delete_item(&selector, &filters),
+        Command::Replay { selector, filters } => replay_item(&selector, &filters),
+        Command::Rename {
+            selector,
+            text,
+            filters,
+        } => rename_item(&selector, &text, &filters),
+        Command::Which {
+            selector,
+            filters,
+            open,
+        } => which_item(&selector, &filters, open),
         Command::Show {
             selector,
             filters,
@@ -51,7 +87,28 @@ pub fn dispatch(cli: Cli) -> Result<()> {
             };
             show_item(&selector, &filters, mode)
         }
-        Command::Watch => watch::run_watch(None),
+        Command::Watch { manual } => {
+            let mode = if manual {
+                CaptureMode::Manual
+            } else {
+                CaptureMode::Auto
+            };
+            watch::run_watch(None, mode)
+        }
+        Command::CaptureNow => {
+            let metadata = watch::capture_now()?;
+            let summary = metadata
+                .summary
+                .clone()
+                .unwrap_or_else(|| "(no summary)".into());
+            eprintln!("Stored clipboard item: {summary} [{} copies]", metadata.copy_count);
+            Ok(())
+        }
+        Command::Formats => {
+            let report = watch::inspect_formats()?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
         Command::Service(args) => run_service(args.action),
         Command::Dir(args) => run_dir(args.command),
         Command::Search(args) => {
@@ -68,7 +125,9 @@ pub fn dispatch(cli: Cli) -> Result<()> {
         }
         Command::Api(args) => run_api(args),
         Command::History(args) => {
-            let mode = if args.json {
+            let mode = if args.porcelain {
+                OutputMode::Porcelain
+            } else if args.json {
                 if args.full {
                     OutputMode::JsonFull
                 } else {
@@ -79,22 +138,50 @@ pub fn dispatch(cli: Cli) -> Result<()> {
             };
             print_history(args, mode)
         }
-        Command::Paste { selector, filters } => {
-            copy_entry(&selector, &filters)?;
-            paste::simulate_paste()?;
+        Command::Paste {
+            selector,
+            filters,
+            count,
+            delay,
+            target_app,
+        } => {
+            let target = target_app
+                .as_deref()
+                .map(paste::PasteTarget::parse)
+                .transpose()?;
+            let metadata = copy_entry(&selector, &filters, false, None, false, false, None)?;
+            let delay = std::time::Duration::from_millis(delay);
+            match target {
+                Some(target) => paste::paste_into(&target, count, delay)?,
+                None => paste::simulate_paste_repeated(count, delay)?,
+            }
+            record_paste(&metadata.hash)?;
             Ok(())
         }
-        Command::Export { path } => export_command(&path),
-        Command::Import { path } => import_command(&path),
-        Command::Stats { json } => run_stats(&json),
+        Command::PasteTest { json } => run_paste_test(json),
+        Command::Export(args) => export_command(args),
+        Command::Import { path, on_conflict } => import_command(&path, on_conflict),
+        Command::Top { limit, json } => run_top(limit, json),
+        Command::Stats { json, size_buckets } => run_stats(&json, size_buckets),
+        Command::Du { by_kind, json } => run_du(by_kind, json),
+        Command::Tag(args) => run_tag(args),
+        Command::Reindex { summaries, json } => run_reindex(summaries, json),
+        Command::OptimizeImages { dry_run, json } => run_optimize_images(dry_run, json),
+        Command::Dedup {
+            images: _,
+            threshold,
+            dry_run,
+            json,
+        } => run_dedup_images(threshold, dry_run, json),
+        Command::Prune { dry_run, json } => run_prune(dry_run, json),
+        Command::Compact { json } => run_compact(json),
         Command::Permissions { subcommand } => match subcommand {
-            PermissionsCmd::Check => {
-                if permissions::check_accessibility() {
-                    println!("Accessibility permissions granted");
-                    Ok(())
-                } else {
-                    bail!("Accessibility permissions NOT granted");
-                }
+            PermissionsCmd::Check { json } => {
+                println!(
+                    "{}",
+                    permissions_check(permissions::check_accessibility, json)?
+                );
+                Ok(())
             }
             PermissionsCmd::Request => {
                 permissions::request_accessibility();
@@ -105,25 +192,144 @@ pub fn dispatch(cli: Cli) -> Result<()> {
     }
 }
 
-fn run_interactive(query: Option<String>) -> Result<()> {
-    tui::start(query)
+#[derive(Debug, serde::Serialize)]
+struct PermissionsReport {
+    accessibility: bool,
+}
+
+/// Text mode fails when permission is missing so scripts can rely on the exit
+/// code; JSON mode always succeeds and reports the state in the output.
+fn permissions_check(check: impl FnOnce() -> bool, json: bool) -> Result<String> {
+    let accessibility = check();
+    if json {
+        return Ok(serde_json::to_string(&PermissionsReport { accessibility })?);
+    }
+    if !accessibility {
+        bail!("Accessibility permissions NOT granted");
+    }
+    Ok("Accessibility permissions granted".to_string())
 }
 
-fn copy_entry(selector: &str, filters: &FilterFlags) -> Result<()> {
+fn run_interactive(query: Option<String>, theme: Option<String>) -> Result<()> {
+    tui::start(query, theme)
+}
+
+/// Number of fuzzy matches offered when a selector is ambiguous.
+const FUZZY_CHOICES: usize = 9;
+
+/// Resolves a hash or offset selector. In an interactive terminal, anything
+/// else is matched fuzzily against summaries, prompting when several match.
+fn resolve_target(index: &SearchIndex, selector: &str, filter: &SelectionFilter) -> Result<String> {
+    resolve_fuzzy_target(index, selector, filter, false)
+}
+
+/// Like [`resolve_target`], but a lone fuzzy match still has to be picked
+/// from the prompt, for commands that change or remove the item.
+fn resolve_target_confirmed(
+    index: &SearchIndex,
+    selector: &str,
+    filter: &SelectionFilter,
+) -> Result<String> {
+    resolve_fuzzy_target(index, selector, filter, true)
+}
+
+fn resolve_fuzzy_target(
+    index: &SearchIndex,
+    selector: &str,
+    filter: &SelectionFilter,
+    confirm_single: bool,
+) -> Result<String> {
+    let err = match resolve_selector(index, selector, filter) {
+        Ok(hash) => return Ok(hash),
+        Err(err) => err,
+    };
+    let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+    if !interactive || selector.parse::<usize>().is_ok() {
+        return Err(err)
+            .with_context(|| format!("No clipboard item found for selector {selector}"));
+    }
+
+    let candidates = crate::search::fuzzy_candidates(index, selector, filter, FUZZY_CHOICES);
+    match candidates.as_slice() {
+        [] => Err(err).with_context(|| format!("No clipboard item found for selector {selector}")),
+        [only] if !confirm_single => Ok(only.hash.clone()),
+        _ => prompt_for_candidate(&candidates),
+    }
+}
+
+fn prompt_for_candidate(candidates: &[crate::search::FuzzyCandidate]) -> Result<String> {
+    let mut stderr = io::stderr();
+    if candidates.len() == 1 {
+        writeln!(stderr, "Only a fuzzy match was found:")?;
+    } else {
+        writeln!(stderr, "Several items match:")?;
+    }
+    for (idx, candidate) in candidates.iter().enumerate() {
+        let summary: String = candidate.summary.chars().take(70).collect();
+        writeln!(stderr, "  {}) {summary}", idx + 1)?;
+    }
+    write!(stderr, "Pick one [1-{}]: ", candidates.len())?;
+    stderr.flush()?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read selection")?;
+    let choice = parse_choice(&input, candidates.len())
+        .with_context(|| format!("Invalid selection {:?}", input.trim()))?;
+    Ok(candidates[choice].hash.clone())
+}
+
+/// Parses a 1-based menu choice into an index below `count`.
+fn parse_choice(input: &str, count: usize) -> Option<usize> {
+    let choice: usize = input.trim().parse().ok()?;
+    (1..=count).contains(&choice).then(|| choice - 1)
+}
+
+fn copy_entry(
+    selector: &str,
+    filters: &FilterFlags,
+    append: bool,
+    relative: Option<RelativeDirection>,
+    meta: bool,
+    data_uri: bool,
+    formats: Option<&[String]>,
+) -> Result<EntryMetadata> {
     let index = load_index()?;
     let selection_filter = build_selection_filter(filters, None);
-    let target = resolve_selector(&index, selector, &selection_filter)
-        .with_context(|| format!("No clipboard item found for selector {selector}"))?;
-    let metadata = copy_by_selector(&target)?;
+    let target = match relative {
+        Some(direction) => {
+            let direction = match direction {
+                RelativeDirection::Prev => Direction::Prev,
+                RelativeDirection::Next => Direction::Next,
+            };
+            resolve_relative(&index, direction, &selection_filter)?
+        }
+        None => resolve_target(&index, selector, &selection_filter)?,
+    };
+    if meta {
+        let metadata = copy_metadata_by_selector(&target)?;
+        eprintln!("Copied metadata for {}", metadata.hash);
+        return Ok(metadata);
+    }
+    if data_uri {
+        let metadata = copy_data_uri_by_selector(&target)?;
+        eprintln!("Copied data URI for {}", metadata.hash);
+        return Ok(metadata);
+    }
+    let metadata = if append {
+        copy_appending_by_selector(&target)?
+    } else {
+        copy_formats_by_selector(&target, formats)?
+    };
     log_copy(&metadata);
-    Ok(())
+    Ok(metadata)
 }
 
 fn delete_item(selector: &str, filters: &FilterFlags) -> Result<()> {
     let index = load_index()?;
     let selection_filter = build_selection_filter(filters, None);
-    let target = resolve_selector(&index, selector, &selection_filter)
-        .with_context(|| format!("No clipboard item found for selector {selector}"))?;
+    let target = resolve_target_confirmed(&index, selector, &selection_filter)?;
     let metadata = load_metadata(&target)?;
     delete_entry(&target)?;
     let summary = metadata
@@ -134,17 +340,98 @@ fn delete_item(selector: &str, filters: &FilterFlags) -> Result<()> {
     Ok(())
 }
 
+fn replay_item(selector: &str, filters: &FilterFlags) -> Result<()> {
+    let index = load_index()?;
+    let selection_filter = build_selection_filter(filters, None);
+    let target = resolve_target(&index, selector, &selection_filter)?;
+    let (metadata, report) = replay_entry(&target)?;
+    let summary = metadata
+        .summary
+        .clone()
+        .unwrap_or_else(|| target.chars().take(12).collect());
+    println!(
+        "Replayed {}: {} file(s), {} changed, {} missing",
+        summary, report.files, report.changed, report.missing
+    );
+    Ok(())
+}
+
+fn rename_item(selector: &str, text: &str, filters: &FilterFlags) -> Result<()> {
+    let index = load_index()?;
+    let selection_filter = build_selection_filter(filters, None);
+    let target = resolve_target_confirmed(&index, selector, &selection_filter)?;
+    let metadata = set_summary_override(&target, Some(text))?;
+    let short_hash = &target[..target.len().min(12)];
+    let summary = metadata.summary.unwrap_or_default();
+    if metadata.summary_override.is_some() {
+        println!("Renamed {short_hash} to {summary}");
+    } else {
+        println!("Restored generated summary of {short_hash}: {summary}");
+    }
+    Ok(())
+}
+
+fn which_item(selector: &str, filters: &FilterFlags, open: bool) -> Result<()> {
+    let index = load_index()?;
+    let selection_filter = build_selection_filter(filters, None);
+    let target = resolve_target(&index, selector, &selection_filter)?;
+    let metadata = load_metadata(&target)?;
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let (item_dir, content) = item_paths(&data_dir, &metadata);
+    println!("{}", item_dir.display());
+    if let Some(content) = &content {
+        println!("{}", content.display());
+    }
+    if open {
+        reveal_in_finder(&item_dir, content.as_deref())?;
+    }
+    Ok(())
+}
+
+/// The absolute item directory and its primary content file, if the entry
+/// records one.
+fn item_paths(data_dir: &Path, metadata: &EntryMetadata) -> (PathBuf, Option<PathBuf>) {
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let item_dir = std::path::absolute(&item_dir).unwrap_or(item_dir);
+    let content = Some(metadata.content_filename.as_str())
+        .filter(|name| !name.is_empty())
+        .map(|name| item_dir.join(name));
+    (item_dir, content)
+}
+
+/// Selects the content file in Finder, or opens the directory when there
+/// is none.
+#[cfg(target_os = "macos")]
+fn reveal_in_finder(item_dir: &Path, content: Option<&Path>) -> Result<()> {
+    let mut command = std::process::Command::new("open");
+    match content.filter(|path| path.exists()) {
+        Some(path) => command.arg("-R").arg(path),
+        None => command.arg(item_dir),
+    };
+    let status = command.status().context("Failed to run open")?;
+    if !status.success() {
+        bail!("open exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn reveal_in_finder(_item_dir: &Path, _content: Option<&Path>) -> Result<()> {
+    bail!("Revealing items in a file browser is only supported on macOS")
+}
+
 fn show_item(selector: &str, filters: &FilterFlags, mode: OutputMode) -> Result<()> {
     let index = load_index()?;
     let selection_filter = build_selection_filter(filters, None);
-    let target = resolve_selector(&index, selector, &selection_filter)
-        .with_context(|| format!("No clipboard item found for selector {selector}"))?;
+    let target = resolve_target(&index, selector, &selection_filter)?;
     let metadata = load_metadata(&target)?;
     let selector_index = selector.parse::<usize>().ok();
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
     let item_dir = data_dir.join(&metadata.relative_path);
-    let preferred_plugin = preferred_display_plugin(filters);
+    let preferred_plugin =
+        plugins::preferred_plugin(preferred_display_plugin(filters), &config, &metadata);
     let is_tty = io::stdout().is_terminal();
 
     match mode {
@@ -169,6 +456,9 @@ fn show_item(selector: &str, filters: &FilterFlags, mode: OutputMode) -> Result<
                 return Ok(());
             }
         }
+        OutputMode::Porcelain => {
+            write_line(&porcelain_line(&metadata))?;
+        }
         OutputMode::Text => {
             let content = plugins::build_display_content_with_preference(
                 &metadata,
@@ -185,8 +475,8 @@ fn show_item(selector: &str, filters: &FilterFlags, mode: OutputMode) -> Result<
 
 fn run_service(action: ServiceAction) -> Result<()> {
     match action {
-        ServiceAction::Install => {
-            service::install_agent()?;
+        ServiceAction::Install { no_autostart, .. } => {
+            service::install_agent(!no_autostart)?;
             if let Ok(status) = service::service_status() {
                 print_service_status(&status);
             }
@@ -215,15 +505,20 @@ fn run_api(args: ApiArgs) -> Result<()> {
         .enable_all()
         .build()
         .context("Failed to initialize async runtime")?;
-    runtime.block_on(api::serve(args.port))?;
+    runtime.block_on(api::serve(args.port, args.open))?;
     Ok(())
 }
 
 fn run_dir(command: DirCommand) -> Result<()> {
     let mut config = load_config().unwrap_or_default();
     match command {
-        DirCommand::Get => {
-            println!("{}", config.data_dir().display());
+        DirCommand::Get { json } => {
+            let data_dir = config.data_dir();
+            if json {
+                println!("{}", to_string_pretty(&dir_info(&data_dir))?);
+            } else {
+                println!("{}", data_dir.display());
+            }
             Ok(())
         }
         DirCommand::Set { path } => {
@@ -239,7 +534,28 @@ fn run_dir(command: DirCommand) -> Result<()> {
     }
 }
 
-fn export_command(path: &Path) -> Result<()> {
+#[derive(Debug, serde::Serialize)]
+struct DirInfo {
+    path: String,
+    exists: bool,
+    writable: bool,
+    size: u64,
+}
+
+fn dir_info(path: &Path) -> DirInfo {
+    let exists = path.is_dir();
+    DirInfo {
+        path: path.display().to_string(),
+        exists,
+        writable: exists && tempfile::tempfile_in(path).is_ok(),
+        size: compute_dir_storage(path),
+    }
+}
+
+fn export_command(args: ExportArgs) -> Result<()> {
+    if let Some(ExportAction::Verify { path, json }) = &args.action {
+        return verify_export_command(path, *json);
+    }
     use serde::Serialize;
     use std::fs::File;
     use std::io::Write;
@@ -254,8 +570,11 @@ fn export_command(path: &Path) -> Result<()> {
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
 
-    let mut options = SearchOptions::default();
-    options.limit = None;
+    let options = export_search_options(&args)?;
+    let path = args
+        .path
+        .as_deref()
+        .context("Missing path to the export file")?;
 
     let (items, _) = load_history_items(&index, &options)?;
     let mut export_items = Vec::new();
@@ -266,7 +585,11 @@ fn export_command(path: &Path) -> Result<()> {
         let item_dir = data_dir.join(&item.metadata.relative_path);
         match plugins::build_full_json_item(&item.metadata, &item_dir, Some(item.offset), None) {
             Ok(full_item) => {
-                export_items.push(full_item);
+                export_items.push(if args.redacted {
+                    plugins::redact_full_item(&full_item)
+                } else {
+                    full_item
+                });
                 if (i + 1) % 100 == 0 {
                     println!("  Processed {}/{} items", i + 1, items.len());
                 }
@@ -286,12 +609,363 @@ fn export_command(path: &Path) -> Result<()> {
     let mut file = File::create(path)?;
     file.write_all(json.as_bytes())?;
 
-    println!("Exported {} items to {}", export_data.items.len(), path.display());
+    println!(
+        "Exported {} {}items to {}",
+        export_data.items.len(),
+        if args.redacted { "redacted " } else { "" },
+        path.display()
+    );
     Ok(())
 }
 
-fn import_command(path: &Path) -> Result<()> {
-    use crate::data::store::store_json_item;
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportCheck {
+    version: Option<String>,
+    items: usize,
+    valid: usize,
+    /// Items sharing an id with an earlier one; importing merges them.
+    duplicates: usize,
+    total_bytes: u64,
+    by_kind: std::collections::BTreeMap<String, usize>,
+    oldest: Option<String>,
+    newest: Option<String>,
+    problems: Vec<ExportProblem>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ExportProblem {
+    index: usize,
+    id: Option<String>,
+    error: String,
+}
+
+/// Checks every item of an export the way `import` would read it, without
+/// touching the store. Fails only if the file itself isn't an export.
+fn verify_export(content: &str) -> Result<ExportCheck> {
+    #[derive(serde::Deserialize)]
+    struct RawExport {
+        version: Option<String>,
+        items: Vec<serde_json::Value>,
+    }
+
+    let raw: RawExport =
+        serde_json::from_str(content).context("Not a get_clipboard export file")?;
+    let mut check = ExportCheck {
+        version: raw.version,
+        items: raw.items.len(),
+        ..ExportCheck::default()
+    };
+    let mut seen = std::collections::HashSet::new();
+    let mut range: Option<(OffsetDateTime, OffsetDateTime)> = None;
+
+    for (index, value) in raw.items.into_iter().enumerate() {
+        let id = value
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(str::to_string);
+        let item = match verify_export_item(value) {
+            Ok(item) => item,
+            Err(err) => {
+                check.problems.push(ExportProblem {
+                    index,
+                    id,
+                    error: format!("{err:#}"),
+                });
+                continue;
+            }
+        };
+        check.valid += 1;
+        if !seen.insert(item.hash) {
+            check.duplicates += 1;
+        }
+        check.total_bytes += item.size;
+        *check.by_kind.entry(item.kind).or_default() += 1;
+        for date in item.dates {
+            range = Some(match range {
+                Some((oldest, newest)) => (oldest.min(date), newest.max(date)),
+                None => (date, date),
+            });
+        }
+    }
+
+    if let Some((oldest, newest)) = range {
+        check.oldest = Some(format_iso(oldest));
+        check.newest = Some(format_iso(newest));
+    }
+    Ok(check)
+}
+
+struct VerifiedItem {
+    hash: String,
+    kind: String,
+    size: u64,
+    dates: Vec<OffsetDateTime>,
+}
+
+fn verify_export_item(value: serde_json::Value) -> Result<VerifiedItem> {
+    let item: plugins::ClipboardJsonFullItem =
+        serde_json::from_value(value).context("Malformed item")?;
+    plugins::prepare_import(&item)?;
+    let computed = plugins::compute_json_item_hash(&item)?;
+    let hash = item
+        .id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map_or(computed, str::to_string);
+    let (date, first_date) = crate::data::store::json_item_dates(&item)?;
+    let dates = [date, first_date].into_iter().flatten().collect();
+    Ok(VerifiedItem {
+        hash,
+        kind: item.item_type.unwrap_or_else(|| "unknown".into()),
+        size: item.size.unwrap_or(0),
+        dates,
+    })
+}
+
+fn verify_export_command(path: &Path, json: bool) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let check = verify_export(&content)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&check)?);
+    } else {
+        println!(
+            "{}: {} items from version {}",
+            path.display(),
+            check.items,
+            check.version.as_deref().unwrap_or("unknown")
+        );
+        println!("{:<12} {}", "Valid", check.valid);
+        println!("{:<12} {}", "Invalid", check.problems.len());
+        println!("{:<12} {}", "Duplicates", check.duplicates);
+        println!("{:<12} {}", "Size", human_size(check.total_bytes));
+        if let (Some(oldest), Some(newest)) = (&check.oldest, &check.newest) {
+            println!("{:<12} {} to {}", "Dates", oldest, newest);
+        }
+        for (kind, count) in &check.by_kind {
+            println!("  {:<10} {}", kind, count);
+        }
+        for problem in &check.problems {
+            println!(
+                "  item {} ({}): {}",
+                problem.index,
+                problem.id.as_deref().unwrap_or("no id"),
+                problem.error
+            );
+        }
+    }
+
+    if !check.problems.is_empty() {
+        bail!(
+            "{} of {} items failed verification",
+            check.problems.len(),
+            check.items
+        );
+    }
+    Ok(())
+}
+
+fn export_search_options(args: &ExportArgs) -> Result<SearchOptions> {
+    let mut options = SearchOptions::default();
+    options.limit = None;
+    options.query = args.query.clone().filter(|query| !query.trim().is_empty());
+    options.regex = args.regex;
+    options.filter = build_selection_filter(&args.filters, args.kind.clone());
+    options.from = args.from.as_deref().map(parse_date).transpose()?;
+    options.to = args.to.as_deref().map(parse_date).transpose()?;
+    Ok(options)
+}
+
+fn tag_search_options(args: &TagArgs) -> SearchOptions {
+    let (query, regex, mut filter) = crate::search::parse_search_query(&args.query, args.regex);
+    let extra = build_selection_filter(&args.filters, None);
+    filter.include_text |= extra.include_text;
+    filter.include_image |= extra.include_image;
+    filter.include_file |= extra.include_file;
+    filter.include_other |= extra.include_other;
+    filter.include_html |= extra.include_html;
+    filter.include_formats.extend(extra.include_formats);
+    SearchOptions {
+        limit: None,
+        query: Some(query).filter(|query| !query.trim().is_empty()),
+        regex,
+        filter,
+        ..SearchOptions::default()
+    }
+}
+
+fn run_reindex(summaries: bool, json: bool) -> Result<()> {
+    let report = if summaries {
+        resummarize_entries()?
+    } else {
+        rebuild_index()?
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if summaries {
+        println!(
+            "Updated {} of {} summaries ({} failed)",
+            report.updated, report.scanned, report.failed
+        );
+    } else {
+        println!(
+            "Indexed {} of {} entries ({} unreadable)",
+            report.updated, report.scanned, report.failed
+        );
+    }
+    Ok(())
+}
+
+fn run_optimize_images(dry_run: bool, json: bool) -> Result<()> {
+    let report = optimize_images(dry_run)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let verb = if dry_run { "Would optimize" } else { "Optimized" };
+        println!(
+            "{verb} {} of {} images, saving {}",
+            report.optimized,
+            report.scanned,
+            human_size(report.bytes_saved)
+        );
+        if report.failed > 0 {
+            println!("{} images could not be optimized", report.failed);
+        }
+    }
+    Ok(())
+}
+
+fn run_dedup_images(threshold: u32, dry_run: bool, json: bool) -> Result<()> {
+    let report = dedup_images(threshold, dry_run)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let verb = if dry_run { "Would merge" } else { "Merged" };
+        println!(
+            "{verb} {} of {} images into near-identical copies ({} newly hashed)",
+            report.merged, report.scanned, report.hashed
+        );
+        if report.failed > 0 {
+            println!("{} images could not be read", report.failed);
+        }
+    }
+    Ok(())
+}
+
+fn run_prune(dry_run: bool, json: bool) -> Result<()> {
+    let report = prune_history(dry_run)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let verb = if dry_run { "Would prune" } else { "Pruned" };
+        println!(
+            "{verb} {} of {} items ({} pinned or kept per app)",
+            report.pruned, report.scanned, report.protected
+        );
+    }
+    Ok(())
+}
+
+fn run_compact(json: bool) -> Result<()> {
+    let report = compact_store()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "Removed {} empty directories and {} unused blobs, reclaimed {}",
+            report.removed_dirs,
+            report.removed_blobs,
+            human_size(report.reclaimed_bytes)
+        );
+    }
+    Ok(())
+}
+
+/// Time the focused app gets to handle the test paste before the previous
+/// clipboard is put back.
+const PASTE_TEST_RESTORE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Diagnoses "paste from history doesn't work" by running each step of a
+/// paste with a throwaway marker instead of a stored item.
+fn run_paste_test(json: bool) -> Result<()> {
+    let marker = paste::paste_test_marker(crate::util::time::unix_millis(crate::util::time::now()));
+    let saved = watch::SavedClipboard::save()?;
+    let report = paste::run_paste_test(
+        permissions::check_accessibility(),
+        &marker,
+        copy_untracked_text,
+        crate::clipboard::mac::get_current_text,
+        paste::simulate_paste,
+        |after_paste| {
+            // The keystroke is handled asynchronously; restoring right away
+            // could paste the old contents instead of the marker.
+            if after_paste {
+                std::thread::sleep(PASTE_TEST_RESTORE_DELAY);
+            }
+            saved.restore();
+        },
+    );
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let status = |ok: bool| if ok { "ok" } else { "FAILED" };
+        println!("Accessibility permission: {}", status(report.accessible));
+        if report.accessible {
+            println!(
+                "Copy marker {}: {}",
+                report.marker,
+                status(report.marker_copied)
+            );
+        }
+        if report.marker_copied {
+            println!("Paste keystroke: {}", status(report.keystroke_sent));
+        }
+        if report.passed() {
+            println!("The marker should now appear in the focused app");
+        }
+    }
+    match report.error {
+        Some(error) if !report.passed() => bail!("Paste test failed: {error}"),
+        _ => Ok(()),
+    }
+}
+
+fn run_tag(args: TagArgs) -> Result<()> {
+    let index = load_index()?;
+    let hits = crate::search::search(&index, &tag_search_options(&args)).hits;
+    let action = match &args.action {
+        TagCommand::Add { tag } => TagAction::Add(tag.clone()),
+        TagCommand::Remove { tag } => TagAction::Remove(tag.clone()),
+        TagCommand::Pin => TagAction::Pin,
+        TagCommand::Unpin => TagAction::Unpin,
+    };
+
+    let mut changed = 0;
+    for hit in &hits {
+        let summary = hit.summary.clone().unwrap_or_else(|| hit.hash.clone());
+        if args.dry_run {
+            let short_hash = &hit.hash[..hit.hash.len().min(12)];
+            println!("Would update {short_hash}  {}", clean_summary(&summary));
+            continue;
+        }
+        if update_tags(&hit.hash, &action)? {
+            changed += 1;
+        }
+    }
+
+    if args.dry_run {
+        println!("{} items match", hits.len());
+    } else {
+        println!("Updated {changed} of {} matching items", hits.len());
+    }
+    Ok(())
+}
+
+fn import_command(path: &Path, on_conflict: CliImportConflict) -> Result<()> {
+    use crate::data::store::{ImportConflict, ImportOutcome, import_json_item};
     use serde::Deserialize;
     use std::fs;
 
@@ -309,6 +983,13 @@ fn import_command(path: &Path) -> Result<()> {
 
     println!("Importing from version {} ({} items)...", import_data.version, import_data.items.len());
 
+    let policy = match on_conflict {
+        CliImportConflict::Skip => ImportConflict::Skip,
+        CliImportConflict::Merge => ImportConflict::Merge,
+        CliImportConflict::SyncMerge => ImportConflict::SyncMerge,
+        CliImportConflict::Replace => ImportConflict::Replace,
+    };
+
     let mut success_count = 0;
     let mut skip_count = 0;
     let mut error_count = 0;
@@ -325,20 +1006,18 @@ fn import_command(path: &Path) -> Result<()> {
             }
         }.replace('\n', " ");
 
-        match store_json_item(item) {
-            Ok(_metadata) => {
+        match import_json_item(item, policy) {
+            Ok(ImportOutcome::Stored(_metadata)) => {
                 success_count += 1;
                 println!("  [{}/{}] Imported: {}", i + 1, import_data.items.len(), truncated);
             }
+            Ok(ImportOutcome::Skipped(_hash)) => {
+                skip_count += 1;
+                println!("  [{}/{}] Skipped (exists): {}", i + 1, import_data.items.len(), truncated);
+            }
             Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("already exists") || err_str.contains("duplicate") {
-                    skip_count += 1;
-                    println!("  [{}/{}] Skipped (exists): {}", i + 1, import_data.items.len(), truncated);
-                } else {
-                    error_count += 1;
-                    eprintln!("  [{}/{}] Failed: {} - {}", i + 1, import_data.items.len(), truncated, e);
-                }
+                error_count += 1;
+                eprintln!("  [{}/{}] Failed: {} - {}", i + 1, import_data.items.len(), truncated, e);
             }
         }
     }
@@ -347,7 +1026,7 @@ fn import_command(path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn run_stats(json: &bool) -> Result<()> {
+fn run_stats(json: &bool, size_buckets: bool) -> Result<()> {
     use std::collections::HashMap;
     use serde::Serialize;
 
@@ -358,6 +1037,11 @@ fn run_stats(json: &bool) -> Result<()> {
         actual_storage_size: u64,
         type_counts: HashMap<String, usize>,
         largest_items: Vec<LargeItem>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size_buckets: Option<Vec<SizeBucket>>,
+        oldest: Option<String>,
+        newest: Option<String>,
+        span_days: Option<i64>,
     }
 
     #[derive(Serialize, Clone)]
@@ -374,6 +1058,8 @@ fn run_stats(json: &bool) -> Result<()> {
 
     let total_items = index.len();
     let total_size: u64 = index.values().map(|r| r.byte_size).sum();
+    let buckets = size_buckets.then(|| size_histogram(index.values().map(|r| r.byte_size)));
+    let range = history_range(index.values().map(|r| (r.first_seen, r.last_seen)));
 
     let mut type_counts: HashMap<String, usize> = HashMap::new();
     let mut items_with_storage: Vec<(String, String, u64, Option<String>, usize)> = Vec::new();
@@ -381,7 +1067,7 @@ fn run_stats(json: &bool) -> Result<()> {
 
     // Build ordered index to get offsets
     let mut ordered: Vec<_> = index.values().collect();
-    ordered.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    ordered.sort_by(|a, b| a.cmp_newest_first(b));
     let offsets: HashMap<String, usize> = ordered
         .iter()
         .enumerate()
@@ -433,6 +1119,10 @@ fn run_stats(json: &bool) -> Result<()> {
             actual_storage_size,
             type_counts,
             largest_items: largest.iter().map(|(item, _)| item.clone()).collect(),
+            size_buckets: buckets,
+            oldest: range.map(|(oldest, _)| format_iso(oldest)),
+            newest: range.map(|(_, newest)| format_iso(newest)),
+            span_days: range.map(|(oldest, newest)| (newest - oldest).whole_days()),
         };
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
@@ -441,12 +1131,29 @@ fn run_stats(json: &bool) -> Result<()> {
         println!("Total items:    {}", total_items);
         println!("Reported size:  {}", human_size(total_size));
         println!("Storage size:   {}", human_size(actual_storage_size));
+        if let Some((oldest, newest)) = range {
+            println!("Oldest item:    {}", format_iso(oldest));
+            println!("Newest item:    {}", format_iso(newest));
+            println!("Span:           {} days", (newest - oldest).whole_days());
+        }
         println!();
         println!("By type:");
         for (type_name, count) in &type_counts {
             println!("  {:10} {}", type_name, count);
         }
         println!();
+        if let Some(buckets) = &buckets {
+            println!("By size:");
+            for bucket in buckets {
+                println!(
+                    "  {:10} {:>6}  {}",
+                    bucket.label,
+                    bucket.count,
+                    human_size(bucket.bytes)
+                );
+            }
+            println!();
+        }
         println!("Top 20 Largest Items (by storage):");
         println!("{:<8} {:<10} {:<12} {}", "Index", "Type", "Size", "Summary");
         println!("{}", "-".repeat(70));
@@ -499,6 +1206,122 @@ fn compute_dir_storage(path: &Path) -> u64 {
     total
 }
 
+/// Exclusive upper bound and label of each `stats --size-buckets` range.
+const SIZE_BUCKETS: &[(u64, &str)] = &[
+    (1024, "<1KB"),
+    (10 * 1024, "1-10KB"),
+    (100 * 1024, "10-100KB"),
+    (1024 * 1024, "100KB-1MB"),
+    (u64::MAX, ">1MB"),
+];
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct SizeBucket {
+    label: &'static str,
+    count: usize,
+    bytes: u64,
+}
+
+/// Counts items, and their total bytes, per `SIZE_BUCKETS` range. Every
+/// range is listed, including empty ones.
+fn size_histogram(sizes: impl IntoIterator<Item = u64>) -> Vec<SizeBucket> {
+    let mut buckets: Vec<SizeBucket> = SIZE_BUCKETS
+        .iter()
+        .map(|(_, label)| SizeBucket {
+            label,
+            count: 0,
+            bytes: 0,
+        })
+        .collect();
+    for size in sizes {
+        let slot = SIZE_BUCKETS
+            .iter()
+            .position(|(limit, _)| size < *limit)
+            .unwrap_or(SIZE_BUCKETS.len() - 1);
+        buckets[slot].count += 1;
+        buckets[slot].bytes += size;
+    }
+    buckets
+}
+
+/// How far back history goes: the earliest first copy and the latest copy
+/// among `(first_seen, last_seen)` pairs.
+fn history_range(
+    seen: impl IntoIterator<Item = (OffsetDateTime, OffsetDateTime)>,
+) -> Option<(OffsetDateTime, OffsetDateTime)> {
+    seen.into_iter().fold(None, |range, (first, last)| {
+        Some(match range {
+            Some((oldest, newest)) => (oldest.min(first), newest.max(last)),
+            None => (first, last),
+        })
+    })
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskUsage {
+    total: u64,
+    items: u64,
+    other: u64,
+    by_kind: std::collections::BTreeMap<String, u64>,
+    by_year: std::collections::BTreeMap<i32, u64>,
+}
+
+/// Adds up per-item storage by kind and by the year an item was last seen.
+fn tally_usage(index: &SearchIndex, storage: impl Fn(&SearchIndexRecord) -> u64) -> DiskUsage {
+    let mut usage = DiskUsage::default();
+    for record in index.values() {
+        let bytes = storage(record);
+        let kind_str = match record.kind {
+            EntryKind::Text => "text",
+            EntryKind::Image => "image",
+            EntryKind::File => "file",
+            EntryKind::Other => "other",
+        };
+        usage.items += bytes;
+        *usage.by_kind.entry(kind_str.to_string()).or_insert(0) += bytes;
+        *usage.by_year.entry(record.last_seen.year()).or_insert(0) += bytes;
+    }
+    usage
+}
+
+fn run_du(by_kind: bool, json: bool) -> Result<()> {
+    let index = load_index()?;
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+
+    let mut usage = tally_usage(&index, |record| {
+        compute_dir_storage(&data_dir.join(&record.relative_path))
+    });
+    usage.total = compute_dir_storage(&data_dir);
+    usage.other = usage.total.saturating_sub(usage.items);
+    if !by_kind {
+        usage.by_kind.clear();
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&usage)?);
+        return Ok(());
+    }
+
+    println!("{:<12} {}", "Total", human_size(usage.total));
+    println!("{:<12} {}", "Items", human_size(usage.items));
+    println!("{:<12} {}", "Journal etc", human_size(usage.other));
+    if by_kind {
+        println!();
+        println!("By kind:");
+        for (kind, bytes) in &usage.by_kind {
+            println!("  {:<10} {}", kind, human_size(*bytes));
+        }
+    }
+    println!();
+    println!("By year:");
+    for (year, bytes) in usage.by_year.iter().rev() {
+        println!("  {:<10} {}", year, human_size(*bytes));
+    }
+    Ok(())
+}
+
 fn print_history(args: HistoryArgs, mode: OutputMode) -> Result<()> {
     let index = load_index()?;
     let HistoryArgs {
@@ -509,15 +1332,19 @@ fn print_history(args: HistoryArgs, mode: OutputMode) -> Result<()> {
         to: to_str,
         sort,
         filters,
+        show_global,
+        group_by,
+        group_by_day,
         ..
     } = args;
+    let group_by = group_by.or(group_by_day.then_some(HistoryGroupBy::Day));
 
     let from = from_str.map(|value| parse_date(&value)).transpose()?;
     let to = to_str.map(|value| parse_date(&value)).transpose()?;
     let selection_filter = build_selection_filter(&filters, kind.clone());
 
     let mut options = SearchOptions::default();
-    let is_interactive = io::stdout().is_terminal();
+    let is_interactive = io::stdout().is_terminal() && !matches!(mode, OutputMode::Porcelain);
     options.limit = limit.or_else(|| if is_interactive { Some(100) } else { None });
     options.query = query;
     options.filter = selection_filter;
@@ -531,12 +1358,19 @@ fn print_history(args: HistoryArgs, mode: OutputMode) -> Result<()> {
     };
 
     match mode {
-        OutputMode::Text => {
-            stream_history_items(&index, &options, |item| output_single_item(item, mode))
+        OutputMode::Text if group_by.is_some() => {
+            let (items, _) = load_history_items(&index, &options)?;
+            output_grouped_history(&items, group_by.unwrap_or(HistoryGroupBy::Day), show_global)
         }
+        OutputMode::Text => stream_history_items(&index, &options, |item| {
+            output_single_item(item, mode, show_global)
+        }),
+        OutputMode::Porcelain => stream_history_items(&index, &options, |item| {
+            write_line(&porcelain_line(&item.metadata))
+        }),
         _ => {
             let (items, _) = load_history_items(&index, &options)?;
-            output_history(&items, mode)
+            output_history(&items, mode, show_global)
         }
     }
 }
@@ -548,11 +1382,17 @@ fn run_search(args: SearchArgs, mode: OutputMode) -> Result<()> {
         limit,
         sort,
         regex,
+        fold,
+        regex_flags,
+        field,
         filters,
+        explain,
         ..
     } = args;
 
-    let (query, is_regex, mut selection_filter) = crate::search::parse_search_query(&query, regex);
+    let regex_flags = regex_flags.as_deref().map(RegexFlags::parse).transpose()?;
+    let (query, is_regex, mut selection_filter) =
+        crate::search::parse_search_query(&query, regex || regex_flags.is_some());
     let extra_filter = build_selection_filter(&filters, None);
 
     if extra_filter.include_text {
@@ -576,9 +1416,11 @@ fn run_search(args: SearchArgs, mode: OutputMode) -> Result<()> {
 
     let mut options = SearchOptions::default();
     options.limit = limit;
-    options.query = Some(query);
+    options.query = Some(query.clone());
     options.filter = selection_filter;
     options.regex = is_regex;
+    options.fold = fold;
+    options.regex_flags = regex_flags.unwrap_or_default();
     options.sort = match sort {
         Some(crate::cli::args::SearchSort::Date) => crate::search::SortOrder::Date,
         Some(crate::cli::args::SearchSort::Copies) => crate::search::SortOrder::Copies,
@@ -586,19 +1428,98 @@ fn run_search(args: SearchArgs, mode: OutputMode) -> Result<()> {
         Some(crate::cli::args::SearchSort::Relevance) => crate::search::SortOrder::Relevance,
         None => crate::search::SortOrder::Date,
     };
+    options.field = match field {
+        Some(crate::cli::args::SearchIn::Summary) => crate::search::SearchField::Summary,
+        Some(crate::cli::args::SearchIn::Text) => crate::search::SearchField::Text,
+        Some(crate::cli::args::SearchIn::All) | None => crate::search::SearchField::All,
+    };
+    let matcher = is_regex.then_some(options.regex_flags);
 
     match mode {
-        OutputMode::Text => {
-            stream_history_items(&index, &options, |item| output_single_item(item, mode))
-        }
+        OutputMode::Text if explain => stream_history_items(&index, &options, |item| {
+            if !output_single_item(item, mode, true)? {
+                return Ok(false);
+            }
+            let explanation = index.get(&item.metadata.hash).and_then(|record| {
+                crate::search::explain_match(record, &query, matcher, fold, options.field)
+            });
+            match explanation {
+                Some(explanation) => write_line(&format_explanation(&explanation)),
+                None => Ok(true),
+            }
+        }),
+        OutputMode::Text => stream_history_items(&index, &options, |item| {
+            output_single_item(item, mode, true)
+        }),
         _ => {
             let (items, _) = load_history_items(&index, &options)?;
-            output_history(&items, mode)
+            output_history(&items, mode, true)
         }
     }
 }
 
-fn output_single_item(item: &HistoryItem, mode: OutputMode) -> Result<bool> {
+fn format_explanation(explanation: &crate::search::MatchExplanation) -> String {
+    format!(
+        "      matched {} ({}), score {}",
+        explanation.field.label(),
+        explanation.match_type.label(),
+        explanation.score
+    )
+}
+
+fn top_search_options(limit: usize) -> SearchOptions {
+    SearchOptions {
+        limit: Some(limit),
+        sort: crate::search::SortOrder::Copies,
+        ..SearchOptions::default()
+    }
+}
+
+fn run_top(limit: usize, json: bool) -> Result<()> {
+    let index = load_index()?;
+    let (items, _) = load_history_items(&index, &top_search_options(limit))?;
+
+    if json {
+        return output_history(&items, OutputMode::JsonSimple, false);
+    }
+
+    let is_interactive = io::stdout().is_terminal();
+    let terminal_width = if is_interactive {
+        crossterm::terminal::size()
+            .map(|(width, _)| width as usize)
+            .unwrap_or(80)
+    } else {
+        usize::MAX
+    };
+
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+
+    if !write_line(&format!(" {:>4}  {:>6}  {:<6}  {}", "#", "Copies", "Type", "Summary"))? {
+        return Ok(());
+    }
+    for item in &items {
+        let item_dir = data_dir.join(&item.metadata.relative_path);
+        let raw_summary = plugins::build_summary(&item.metadata, &item_dir, is_interactive)
+            .unwrap_or_else(|| item.summary.clone());
+        let prefix = format!(
+            " {:>4}  {:>6}  {:<6}  ",
+            item.global_offset, item.metadata.copy_count, item.kind
+        );
+        let available = terminal_width.saturating_sub(prefix.chars().count());
+        let mut summary = clean_summary(&raw_summary);
+        if summary.chars().count() > available {
+            summary = summary.chars().take(available.saturating_sub(3)).collect();
+            summary.push_str("...");
+        }
+        if !write_line(&format!("{prefix}{summary}"))? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn output_single_item(item: &HistoryItem, mode: OutputMode, show_global: bool) -> Result<bool> {
     match mode {
         OutputMode::Text => {
             let is_interactive = io::stdout().is_terminal();
@@ -620,29 +1541,79 @@ fn output_single_item(item: &HistoryItem, mode: OutputMode) -> Result<bool> {
             let raw_summary = plugins::build_summary(&item.metadata, &item_dir, is_interactive)
                 .unwrap_or_else(|| item.summary.clone());
             
+            let prefix = history_line_prefix(
+                item.offset,
+                show_global.then_some(item.global_offset),
+                &timestamp,
+                copies,
+            );
             let summary = if is_interactive {
-                clip_summary_to_width(
-                    &raw_summary,
-                    terminal_width,
-                    item.offset,
-                    &timestamp,
-                    copies,
-                    item.global_offset,
-                )
+                clip_summary_to_width(&raw_summary, terminal_width, &prefix)
             } else {
                 clean_summary(&raw_summary)
             };
-            let line = format!(
-                " {:>3} ({:>3}) [{} x{}]   {}",
-                item.offset, item.global_offset, timestamp, copies, summary
-            );
+            let summary = if is_interactive && terminal_supports_hyperlinks() {
+                linkify_summary(&item.metadata, &raw_summary, &summary)
+            } else {
+                summary
+            };
+            let line = format!("{prefix}{summary}");
             write_line(&line)
         }
         _ => Ok(true),
     }
 }
 
-fn output_history(items: &[HistoryItem], mode: OutputMode) -> Result<()> {
+/// Header an item is listed under by `history --group-by`.
+fn history_group_key(metadata: &EntryMetadata, group_by: HistoryGroupBy) -> String {
+    match group_by {
+        HistoryGroupBy::Day => format_day(metadata.last_seen),
+        HistoryGroupBy::App => metadata
+            .source_app
+            .clone()
+            .unwrap_or_else(|| "Unknown app".into()),
+        HistoryGroupBy::Kind => kind_label(&metadata.kind).into(),
+    }
+}
+
+/// Buckets items under their headers. Headers keep the order they first
+/// appear in and items keep theirs, so a date-sorted history stays newest
+/// first within and across groups.
+fn group_history_items(
+    items: &[HistoryItem],
+    group_by: HistoryGroupBy,
+) -> Vec<(String, Vec<&HistoryItem>)> {
+    let mut groups: Vec<(String, Vec<&HistoryItem>)> = Vec::new();
+    for item in items {
+        let key = history_group_key(&item.metadata, group_by);
+        match groups.iter_mut().find(|(header, _)| *header == key) {
+            Some((_, members)) => members.push(item),
+            None => groups.push((key, vec![item])),
+        }
+    }
+    groups
+}
+
+fn output_grouped_history(
+    items: &[HistoryItem],
+    group_by: HistoryGroupBy,
+    show_global: bool,
+) -> Result<()> {
+    for (idx, (header, members)) in group_history_items(items, group_by).iter().enumerate() {
+        let separator = if idx == 0 { "" } else { "\n" };
+        if !write_line(&format!("{separator}{header} ({})", members.len()))? {
+            return Ok(());
+        }
+        for item in members {
+            if !output_single_item(item, OutputMode::Text, show_global)? {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn output_history(items: &[HistoryItem], mode: OutputMode, show_global: bool) -> Result<()> {
     match mode {
         OutputMode::JsonFull => {
             let config = load_config()?;
@@ -669,10 +1640,13 @@ fn output_history(items: &[HistoryItem], mode: OutputMode) -> Result<()> {
             let mut json_items = Vec::new();
             for item in items {
                 let item_dir = data_dir.join(&item.metadata.relative_path);
-                json_items.push(plugins::build_json_item(
+                let preferred = plugins::preferred_plugin(None, &config, &item.metadata);
+                json_items.push(plugins::build_json_item_with_preference(
                     &item.metadata,
                     &item_dir,
                     item.offset,
+                    preferred,
+                    None,
                 )?);
             }
             let output = to_string_pretty(&json_items)?;
@@ -681,6 +1655,14 @@ fn output_history(items: &[HistoryItem], mode: OutputMode) -> Result<()> {
             }
             return Ok(());
         }
+        OutputMode::Porcelain => {
+            for item in items {
+                if !write_line(&porcelain_line(&item.metadata))? {
+                    break;
+                }
+            }
+            return Ok(());
+        }
         OutputMode::Text => {
             let is_interactive = io::stdout().is_terminal();
             let terminal_width = if is_interactive {
@@ -693,6 +1675,7 @@ fn output_history(items: &[HistoryItem], mode: OutputMode) -> Result<()> {
 
             let config = load_config()?;
             let data_dir = ensure_data_dir(&config)?;
+            let hyperlinks = is_interactive && terminal_supports_hyperlinks();
 
             for item in items {
                 let item_dir = data_dir.join(&item.metadata.relative_path);
@@ -702,22 +1685,23 @@ fn output_history(items: &[HistoryItem], mode: OutputMode) -> Result<()> {
                 let raw_summary = plugins::build_summary(&item.metadata, &item_dir, is_interactive)
                     .unwrap_or_else(|| item.summary.clone());
                 
+                let prefix = history_line_prefix(
+                    item.offset,
+                    show_global.then_some(item.global_offset),
+                    &timestamp,
+                    copies,
+                );
                 let summary = if is_interactive {
-                    clip_summary_to_width(
-                        &raw_summary,
-                        terminal_width,
-                        item.offset,
-                        &timestamp,
-                        copies,
-                        item.global_offset,
-                    )
+                    clip_summary_to_width(&raw_summary, terminal_width, &prefix)
                 } else {
                     clean_summary(&raw_summary)
                 };
-                let line = format!(
-                    " {:>3} ({:>3}) [{} x{}]   {}",
-                    item.offset, item.global_offset, timestamp, copies, summary
-                );
+                let summary = if hyperlinks {
+                    linkify_summary(&item.metadata, &raw_summary, &summary)
+                } else {
+                    summary
+                };
+                let line = format!("{prefix}{summary}");
                 if !write_line(&line)? {
                     break;
                 }
@@ -792,6 +1776,63 @@ fn terminal_supports_images() -> bool {
         || env::var("WEZTERM_PANE").is_ok()
 }
 
+fn terminal_supports_hyperlinks() -> bool {
+    if env::var("NO_HYPERLINKS").is_ok() {
+        return false;
+    }
+    env::var("TERM_PROGRAM")
+        .map(|value| {
+            ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"]
+                .iter()
+                .any(|known| value.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+        || env::var("TERM")
+            .map(|term| {
+                term.contains("kitty") || term.contains("wezterm") || term.contains("ghostty")
+            })
+            .unwrap_or(false)
+        || env::var("VTE_VERSION")
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+            .map(|version| version >= 5000)
+            .unwrap_or(false)
+        || env::var("WEZTERM_PANE").is_ok()
+}
+
+/// Wraps `text` in an OSC 8 escape so supporting terminals render it as a
+/// clickable link to `target`.
+fn osc8_link(target: &str, text: &str) -> String {
+    format!("\x1b]8;;{target}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+/// File entries link to their first path; text entries link when the whole
+/// summary is a URL that wasn't shortened.
+fn hyperlink_target(metadata: &EntryMetadata, raw_summary: &str) -> Option<String> {
+    if metadata.kind == crate::data::model::EntryKind::File {
+        return metadata
+            .sources
+            .first()
+            .and_then(|path| url::Url::from_file_path(path).ok())
+            .map(|url| url.to_string());
+    }
+    let trimmed = raw_summary.trim();
+    if (trimmed.starts_with("http://") || trimmed.starts_with("https://"))
+        && !trimmed.contains(char::is_whitespace)
+        && !trimmed.ends_with("...")
+    {
+        return url::Url::parse(trimmed).ok().map(|url| url.to_string());
+    }
+    None
+}
+
+fn linkify_summary(metadata: &EntryMetadata, raw_summary: &str, summary: &str) -> String {
+    match hyperlink_target(metadata, raw_summary) {
+        Some(target) => osc8_link(&target, summary),
+        None => summary.to_string(),
+    }
+}
+
 fn build_selection_filter(filters: &FilterFlags, kind: Option<CliEntryKind>) -> SelectionFilter {
     let mut selection = SelectionFilter::default();
     if filters.text || matches!(kind, Some(CliEntryKind::Text)) {
@@ -861,10 +1902,20 @@ fn log_item_details(metadata: &EntryMetadata, item_dir: &Path) -> Result<()> {
     let mut details = Vec::new();
     details.push(("date".to_string(), format_iso(metadata.last_seen)));
     details.push(("copies".to_string(), metadata.copy_count.to_string()));
+    if let Some(last_pasted) = metadata.last_pasted {
+        details.push(("pastes".to_string(), metadata.paste_count.to_string()));
+        details.push(("last pasted".to_string(), format_iso(last_pasted)));
+    }
     details.push(("hash".to_string(), metadata.hash.clone()));
     details.push(("size".to_string(), human_size(metadata.byte_size)));
     details.push(("path".to_string(), item_dir.to_string_lossy().to_string()));
     details.push(("kind".to_string(), format!("{:?}", metadata.kind)));
+    if !metadata.tags.is_empty() {
+        details.push(("tags".to_string(), metadata.tags.join(", ")));
+    }
+    if metadata.pinned {
+        details.push(("pinned".to_string(), "yes".to_string()));
+    }
     if let Some(summary) = &metadata.summary {
         details.push(("summary".to_string(), clean_summary(summary)));
     }
@@ -896,19 +1947,25 @@ fn clean_summary(input: &str) -> String {
     }
 }
 
-fn clip_summary_to_width(
-    input: &str,
-    terminal_width: usize,
-    index: usize,
+/// The ` offset (global) [timestamp xcopies]` column block that precedes each
+/// summary. The global offset only differs from the offset when results are
+/// filtered, so history leaves it out unless asked.
+fn history_line_prefix(
+    offset: usize,
+    global_offset: Option<usize>,
     timestamp: &str,
     copies: u64,
-    global_index: usize,
 ) -> String {
+    match global_offset {
+        Some(global_offset) => {
+            format!(" {offset:>3} ({global_offset:>3}) [{timestamp} x{copies}]   ")
+        }
+        None => format!(" {offset:>3} [{timestamp} x{copies}]   "),
+    }
+}
+
+fn clip_summary_to_width(input: &str, terminal_width: usize, prefix: &str) -> String {
     let clean = clean_summary(input);
-    let prefix = format!(
-        "{} ({}) [{} x{}]   ",
-        index, global_index, timestamp, copies
-    );
     let prefix_len = prefix.chars().count();
 
     if terminal_width <= prefix_len {
@@ -933,6 +1990,28 @@ fn clip_summary_to_width(
     format!("{}...", truncated)
 }
 
+/// One tab-separated line per item. The columns and their order are a stable
+/// interface for scripts; add new columns only at the end.
+fn porcelain_line(metadata: &EntryMetadata) -> String {
+    let kind = kind_label(&metadata.kind);
+    format!(
+        "{}\t{kind}\t{}\t{}\t{}",
+        metadata.hash,
+        metadata.last_seen.unix_timestamp(),
+        metadata.copy_count,
+        metadata.byte_size
+    )
+}
+
+fn kind_label(kind: &EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Text => "text",
+        EntryKind::Image => "image",
+        EntryKind::File => "file",
+        EntryKind::Other => "other",
+    }
+}
+
 fn write_line(line: &str) -> Result<bool> {
     let mut stdout = io::stdout();
     match writeln!(stdout, "{}", line) {
@@ -950,3 +2029,512 @@ fn write_text_block(text: &str) -> Result<bool> {
         Err(err) => Err(err.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::model::{EntryKind, SearchIndex, SearchIndexRecord};
+    use crate::search::search;
+    use time::macros::datetime;
+
+    fn create_record(
+        hash: &str,
+        kind: EntryKind,
+        text: &str,
+        last_seen: OffsetDateTime,
+    ) -> SearchIndexRecord {
+        SearchIndexRecord {
+            hash: hash.to_string(),
+            first_seen: last_seen,
+            last_seen,
+            kind,
+            copy_count: 1,
+            summary: Some(text.to_string()),
+            search_text: Some(text.to_string()),
+            detected_formats: vec![],
+            byte_size: text.len() as u64,
+            relative_path: "".to_string(),
+            pinned: false,
+            source_app: None,
+        }
+    }
+
+    #[test]
+    fn test_export_honors_filters() {
+        let mut index = SearchIndex::new();
+        let records = [
+            ("work-new", EntryKind::Text, "work snippet", datetime!(2024-02-01 0:00 UTC)),
+            ("work-old", EntryKind::Text, "work notes", datetime!(2023-06-01 0:00 UTC)),
+            ("home-new", EntryKind::Text, "grocery list", datetime!(2024-02-02 0:00 UTC)),
+            ("work-img", EntryKind::Image, "work diagram", datetime!(2024-02-03 0:00 UTC)),
+        ];
+        for (hash, kind, text, last_seen) in records {
+            index.insert(hash.to_string(), create_record(hash, kind, text, last_seen));
+        }
+
+        let args = ExportArgs {
+            query: Some("work".into()),
+            kind: Some(CliEntryKind::Text),
+            from: Some("2024-01-01".into()),
+            ..ExportArgs::default()
+        };
+        let options = export_search_options(&args).unwrap();
+        let hashes: Vec<_> = search(&index, &options)
+            .hits
+            .into_iter()
+            .map(|hit| hit.hash)
+            .collect();
+        assert_eq!(hashes, vec!["work-new"]);
+
+        let everything = export_search_options(&ExportArgs::default()).unwrap();
+        assert_eq!(search(&index, &everything).hits.len(), 4);
+    }
+
+    #[test]
+    fn test_tag_targets_follow_search_pipeline() {
+        let mut index = SearchIndex::new();
+        let records = [
+            ("link-a", EntryKind::Text, "https://example.com/a", datetime!(2024-02-01 0:00 UTC)),
+            ("link-b", EntryKind::Text, "http://example.org", datetime!(2024-02-02 0:00 UTC)),
+            ("prose", EntryKind::Text, "see https://example.com", datetime!(2024-02-03 0:00 UTC)),
+            ("note", EntryKind::Text, "grocery list", datetime!(2024-02-04 0:00 UTC)),
+        ];
+        for (hash, kind, text, last_seen) in records {
+            index.insert(hash.to_string(), create_record(hash, kind, text, last_seen));
+        }
+
+        let args = TagArgs {
+            query: "@link".into(),
+            regex: false,
+            filters: FilterFlags::default(),
+            dry_run: true,
+            action: TagCommand::Add { tag: "web".into() },
+        };
+        let mut hashes: Vec<_> = search(&index, &tag_search_options(&args))
+            .hits
+            .into_iter()
+            .map(|hit| hit.hash)
+            .collect();
+        hashes.sort();
+        assert_eq!(hashes, vec!["link-a", "link-b"]);
+
+        let plain = TagArgs {
+            query: "grocery".into(),
+            ..args
+        };
+        let hashes: Vec<_> = search(&index, &tag_search_options(&plain))
+            .hits
+            .into_iter()
+            .map(|hit| hit.hash)
+            .collect();
+        assert_eq!(hashes, vec!["note"]);
+    }
+
+    #[test]
+    fn test_top_orders_by_copy_count() {
+        let mut index = SearchIndex::new();
+        let counts = [("a", 3), ("b", 12), ("c", 1), ("d", 7)];
+        for (offset, (hash, copies)) in counts.into_iter().enumerate() {
+            let last_seen = datetime!(2024-01-01 0:00 UTC) + time::Duration::hours(offset as i64);
+            let mut record = create_record(hash, EntryKind::Text, hash, last_seen);
+            record.copy_count = copies;
+            index.insert(hash.to_string(), record);
+        }
+
+        let hashes: Vec<_> = search(&index, &top_search_options(3))
+            .hits
+            .into_iter()
+            .map(|hit| hit.hash)
+            .collect();
+        assert_eq!(hashes, vec!["b", "d", "a"]);
+    }
+
+    fn metadata_with(kind: EntryKind, sources: Vec<String>) -> EntryMetadata {
+        EntryMetadata {
+            hash: "abc123".into(),
+            kind,
+            detected_formats: vec![],
+            copy_count: 1,
+            first_seen: datetime!(2024-01-01 0:00 UTC),
+            last_seen: datetime!(2024-01-01 0:00 UTC),
+            byte_size: 0,
+            sources,
+            summary: None,
+            summary_override: None,
+            primary_plugin: None,
+            search_text: None,
+            version: "test".into(),
+            relative_path: String::new(),
+            content_filename: String::new(),
+            files: vec![],
+            extra: serde_json::Value::Null,
+            tags: vec![],
+            pinned: false,
+            paste_count: 0,
+            last_pasted: None,
+            source_app: None,
+            copy_events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_item_paths_are_absolute() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut metadata = metadata_with(EntryKind::Text, vec![]);
+        metadata.relative_path = "objects/ab/c1/abc123".into();
+        metadata.content_filename = "text__content.txt".into();
+
+        let (item_dir, content) = item_paths(data_dir.path(), &metadata);
+        assert_eq!(item_dir, data_dir.path().join("objects/ab/c1/abc123"));
+        assert_eq!(content, Some(item_dir.join("text__content.txt")));
+
+        let (relative_dir, _) = item_paths(Path::new("data"), &metadata);
+        assert!(relative_dir.is_absolute());
+        assert!(relative_dir.ends_with("data/objects/ab/c1/abc123"));
+
+        metadata.content_filename.clear();
+        assert_eq!(item_paths(data_dir.path(), &metadata).1, None);
+    }
+
+    fn history_item(
+        offset: usize,
+        kind: EntryKind,
+        app: Option<&str>,
+        last_seen: OffsetDateTime,
+    ) -> HistoryItem {
+        let mut metadata = metadata_with(kind, vec![]);
+        metadata.source_app = app.map(str::to_string);
+        metadata.last_seen = last_seen;
+        HistoryItem {
+            summary: format!("item {offset}"),
+            kind: String::new(),
+            metadata,
+            offset,
+            global_offset: offset,
+        }
+    }
+
+    fn grouped(items: &[HistoryItem], group_by: HistoryGroupBy) -> Vec<(String, Vec<usize>)> {
+        group_history_items(items, group_by)
+            .into_iter()
+            .map(|(header, members)| (header, members.iter().map(|item| item.offset).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_history_groups_by_day_newest_first() {
+        let items = [
+            history_item(0, EntryKind::Text, None, datetime!(2024-03-10 18:00 UTC)),
+            history_item(1, EntryKind::Image, None, datetime!(2024-03-10 09:00 UTC)),
+            history_item(2, EntryKind::Text, None, datetime!(2024-03-09 23:59 UTC)),
+            history_item(3, EntryKind::File, None, datetime!(2024-03-01 12:00 UTC)),
+        ];
+        assert_eq!(
+            grouped(&items, HistoryGroupBy::Day),
+            vec![
+                ("2024-03-10".to_string(), vec![0, 1]),
+                ("2024-03-09".to_string(), vec![2]),
+                ("2024-03-01".to_string(), vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_groups_by_app_and_kind_collect_scattered_items() {
+        let day = datetime!(2024-03-10 12:00 UTC);
+        let items = [
+            history_item(0, EntryKind::Text, Some("Safari"), day),
+            history_item(1, EntryKind::Image, None, day),
+            history_item(2, EntryKind::Text, Some("Terminal"), day),
+            history_item(3, EntryKind::Image, Some("Safari"), day),
+        ];
+        assert_eq!(
+            grouped(&items, HistoryGroupBy::App),
+            vec![
+                ("Safari".to_string(), vec![0, 3]),
+                ("Unknown app".to_string(), vec![1]),
+                ("Terminal".to_string(), vec![2]),
+            ]
+        );
+        assert_eq!(
+            grouped(&items, HistoryGroupBy::Kind),
+            vec![
+                ("text".to_string(), vec![0, 2]),
+                ("image".to_string(), vec![1, 3]),
+            ]
+        );
+        assert!(grouped(&[], HistoryGroupBy::Day).is_empty());
+    }
+
+    #[test]
+    fn test_format_explanation() {
+        let explanation = crate::search::MatchExplanation {
+            field: crate::search::MatchField::SearchText,
+            match_type: crate::search::MatchType::Regex,
+            score: 40,
+        };
+        assert_eq!(
+            format_explanation(&explanation),
+            "      matched search_text (regex), score 40"
+        );
+    }
+
+    #[test]
+    fn test_parse_choice_bounds() {
+        assert_eq!(parse_choice("1\n", 3), Some(0));
+        assert_eq!(parse_choice(" 3 ", 3), Some(2));
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("two", 3), None);
+    }
+
+    #[test]
+    fn test_porcelain_line_columns() {
+        let mut metadata = metadata_with(EntryKind::Image, vec![]);
+        metadata.copy_count = 4;
+        metadata.byte_size = 2048;
+        metadata.summary = Some("a very long summary that would normally be truncated".into());
+        assert_eq!(porcelain_line(&metadata), "abc123\timage\t1704067200\t4\t2048");
+    }
+
+    #[test]
+    fn test_history_line_prefix_global_offset() {
+        assert_eq!(
+            history_line_prefix(2, None, "Jan 01 10:00", 3),
+            "   2 [Jan 01 10:00 x3]   "
+        );
+        assert_eq!(
+            history_line_prefix(2, Some(17), "Jan 01 10:00", 3),
+            "   2 ( 17) [Jan 01 10:00 x3]   "
+        );
+        assert_eq!(
+            clip_summary_to_width(
+                "hello world",
+                33,
+                &history_line_prefix(2, None, "Jan 01 10:00", 3)
+            ),
+            "hello..."
+        );
+    }
+
+    #[test]
+    fn test_osc8_link_escape() {
+        assert_eq!(
+            osc8_link("https://example.com", "example"),
+            "\x1b]8;;https://example.com\x1b\\example\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_hyperlink_target_for_paths_and_urls() {
+        let file = metadata_with(EntryKind::File, vec!["/tmp/my notes.txt".into()]);
+        assert_eq!(
+            hyperlink_target(&file, "my notes.txt").as_deref(),
+            Some("file:///tmp/my%20notes.txt")
+        );
+
+        let text = metadata_with(EntryKind::Text, vec![]);
+        assert_eq!(
+            hyperlink_target(&text, " https://example.com/a?b=1 ").as_deref(),
+            Some("https://example.com/a?b=1")
+        );
+        assert_eq!(hyperlink_target(&text, "see https://example.com"), None);
+    }
+
+    #[test]
+    fn test_dir_info_json_structure() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("journal.log"), b"12345").unwrap();
+        std::fs::create_dir(dir.path().join("2024")).unwrap();
+        std::fs::write(dir.path().join("2024").join("item.txt"), b"abc").unwrap();
+
+        let value = serde_json::to_value(dir_info(dir.path())).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "path": dir.path().display().to_string(),
+                "exists": true,
+                "writable": true,
+                "size": 8,
+            })
+        );
+
+        let missing = dir_info(&dir.path().join("missing"));
+        assert!(!missing.exists);
+        assert!(!missing.writable);
+        assert_eq!(missing.size, 0);
+    }
+
+    #[test]
+    fn test_verify_export_reports_stats_for_valid_file() {
+        let export = serde_json::json!({
+            "version": "0.1.0",
+            "items": [
+                {
+                    "id": "aaa",
+                    "date": "2024-03-01T10:00:00.000Z",
+                    "firstDate": "2024-01-01T10:00:00.000Z",
+                    "type": "Text",
+                    "size": 5,
+                    "formats": [{"pluginId": "text", "data": "hello"}]
+                },
+                {
+                    "date": "2024-02-01",
+                    "type": "Text",
+                    "size": 3,
+                    "formats": [{"pluginId": "text", "data": "bye"}]
+                },
+                {
+                    "id": "aaa",
+                    "type": "Text",
+                    "size": 5,
+                    "formats": [{"pluginId": "text", "data": "hello"}]
+                }
+            ]
+        });
+        let check = verify_export(&export.to_string()).unwrap();
+        assert_eq!(check.version.as_deref(), Some("0.1.0"));
+        assert_eq!(check.items, 3);
+        assert_eq!(check.valid, 3);
+        assert_eq!(check.duplicates, 1);
+        assert_eq!(check.total_bytes, 13);
+        assert_eq!(check.by_kind.get("Text"), Some(&3));
+        assert_eq!(check.oldest.as_deref(), Some("2024-01-01T10:00:00.000Z"));
+        assert_eq!(check.newest.as_deref(), Some("2024-03-01T10:00:00.000Z"));
+        assert!(check.problems.is_empty());
+    }
+
+    #[test]
+    fn test_verify_export_flags_corrupt_items() {
+        let export = serde_json::json!({
+            "version": "0.1.0",
+            "items": [
+                {"id": "ok", "formats": [{"pluginId": "text", "data": "fine"}]},
+                {"id": "empty", "formats": []},
+                {"id": "unknown", "formats": [{"pluginId": "nope", "data": "x"}]},
+                {"id": "wrong-data", "formats": [{"pluginId": "text", "data": 42}]},
+                {"id": "bad-date", "date": "yesterday-ish", "formats": [{"pluginId": "text", "data": "x"}]},
+                {"formats": "not a list"}
+            ]
+        });
+        let check = verify_export(&export.to_string()).unwrap();
+        assert_eq!(check.valid, 1);
+        let flagged: Vec<(usize, Option<&str>)> = check
+            .problems
+            .iter()
+            .map(|problem| (problem.index, problem.id.as_deref()))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                (1, Some("empty")),
+                (2, Some("unknown")),
+                (3, Some("wrong-data")),
+                (4, Some("bad-date")),
+                (5, None),
+            ]
+        );
+
+        assert!(verify_export("{\"items\": 3}").is_err());
+        assert!(verify_export("not json").is_err());
+    }
+
+    #[test]
+    fn test_tally_usage_groups_by_kind_and_year() {
+        let mut index = SearchIndex::new();
+        let records = [
+            ("text-a", EntryKind::Text, datetime!(2024-02-01 0:00 UTC), 100),
+            ("text-b", EntryKind::Text, datetime!(2023-06-01 0:00 UTC), 50),
+            ("image-a", EntryKind::Image, datetime!(2024-03-01 0:00 UTC), 4000),
+            ("file-a", EntryKind::File, datetime!(2023-01-01 0:00 UTC), 7),
+        ];
+        let mut sizes = std::collections::HashMap::new();
+        for (hash, kind, last_seen, bytes) in records {
+            index.insert(hash.to_string(), create_record(hash, kind, hash, last_seen));
+            sizes.insert(hash.to_string(), bytes);
+        }
+
+        let usage = tally_usage(&index, |record| sizes[&record.hash]);
+        assert_eq!(usage.items, 4157);
+        assert_eq!(usage.by_kind["text"], 150);
+        assert_eq!(usage.by_kind["image"], 4000);
+        assert_eq!(usage.by_kind["file"], 7);
+        assert!(!usage.by_kind.contains_key("other"));
+        assert_eq!(usage.by_year[&2024], 4100);
+        assert_eq!(usage.by_year[&2023], 57);
+    }
+
+    #[test]
+    fn test_size_histogram_bucket_edges() {
+        let sizes = [
+            0,
+            1023,
+            1024,
+            10 * 1024 - 1,
+            10 * 1024,
+            512 * 1024,
+            1024 * 1024,
+            50 * 1024 * 1024,
+        ];
+        let buckets = size_histogram(sizes);
+        let counts: Vec<(&str, usize)> = buckets.iter().map(|b| (b.label, b.count)).collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("<1KB", 2),
+                ("1-10KB", 2),
+                ("10-100KB", 1),
+                ("100KB-1MB", 1),
+                (">1MB", 2),
+            ]
+        );
+        assert_eq!(buckets[0].bytes, 1023);
+        assert_eq!(buckets[1].bytes, 1024 + 10 * 1024 - 1);
+
+        let empty = size_histogram([]);
+        assert_eq!(empty.len(), SIZE_BUCKETS.len());
+        assert!(empty.iter().all(|bucket| bucket.count == 0));
+    }
+
+    #[test]
+    fn test_history_range_finds_oldest_and_newest() {
+        let dates = [
+            datetime!(2024-02-01 0:00 UTC),
+            datetime!(2023-06-01 12:30 UTC),
+            datetime!(2024-03-15 8:00 UTC),
+            datetime!(2024-01-01 0:00 UTC),
+        ];
+        let seen = dates.map(|date| (date, date));
+        let (oldest, newest) = history_range(seen).unwrap();
+        assert_eq!(oldest, datetime!(2023-06-01 12:30 UTC));
+        assert_eq!(newest, datetime!(2024-03-15 8:00 UTC));
+        assert_eq!((newest - oldest).whole_days(), 287);
+
+        // An item copied again recently still counts from its first copy.
+        let recopied = (
+            datetime!(2022-05-01 0:00 UTC),
+            datetime!(2024-03-20 0:00 UTC),
+        );
+        let (oldest, newest) = history_range(seen.into_iter().chain([recopied])).unwrap();
+        assert_eq!(oldest, datetime!(2022-05-01 0:00 UTC));
+        assert_eq!(newest, datetime!(2024-03-20 0:00 UTC));
+
+        let single = datetime!(2024-02-01 0:00 UTC);
+        assert_eq!(history_range([(single, single)]), Some((single, single)));
+        assert_eq!(history_range([]), None);
+    }
+
+    #[test]
+    fn test_permissions_check_json_reports_both_states() {
+        assert_eq!(
+            permissions_check(|| true, true).unwrap(),
+            r#"{"accessibility":true}"#
+        );
+        assert_eq!(
+            permissions_check(|| false, true).unwrap(),
+            r#"{"accessibility":false}"#
+        );
+        assert!(permissions_check(|| true, false).is_ok());
+        assert!(permissions_check(|| false, false).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Maps clipboard format identifiers between platforms. Stored items use
+//! macOS UTIs and MIME types interchangeably; when setting the clipboard we
+//! translate to whatever the running platform expects.
+
+use clipboard_rs::common::ClipboardContent;
+
+/// Windows formats with fixed ids. Registering their names instead would
+/// create private formats that no other app reads.
+#[cfg(any(test, target_os = "windows"))]
+const CF_UNICODETEXT: u32 = 13;
+#[cfg(any(test, target_os = "windows"))]
+const CF_HDROP: u32 = 15;
+
+struct FormatIds {
+    mime: &'static str,
+    macos: &'static str,
+    windows: &'static str,
+    /// Set when `windows` names a predefined format rather than a registered one.
+    #[cfg(any(test, target_os = "windows"))]
+    windows_predefined: Option<u32>,
+    linux: &'static str,
+}
+
+const FORMAT_IDS: &[FormatIds] = &[
+    FormatIds {
+        mime: "text/plain",
+        macos: "public.utf8-plain-text",
+        windows: "CF_UNICODETEXT",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: Some(CF_UNICODETEXT),
+        linux: "text/plain;charset=utf-8",
+    },
+    FormatIds {
+        mime: "text/html",
+        macos: "public.html",
+        windows: "HTML Format",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: None,
+        linux: "text/html",
+    },
+    FormatIds {
+        mime: "text/rtf",
+        macos: "public.rtf",
+        windows: "Rich Text Format",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: None,
+        linux: "text/rtf",
+    },
+    FormatIds {
+        mime: "image/png",
+        macos: "public.png",
+        windows: "PNG",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: None,
+        linux: "image/png",
+    },
+    FormatIds {
+        mime: "image/tiff",
+        macos: "public.tiff",
+        windows: "TIFF",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: None,
+        linux: "image/tiff",
+    },
+    FormatIds {
+        mime: "text/uri-list",
+        macos: "public.file-url",
+        windows: "CF_HDROP",
+        #[cfg(any(test, target_os = "windows"))]
+        windows_predefined: Some(CF_HDROP),
+        linux: "text/uri-list",
+    },
+];
+
+#[cfg(target_os = "macos")]
+mod platform {
+    pub fn pick(ids: &super::FormatIds) -> &'static str {
+        ids.macos
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    pub fn pick(ids: &super::FormatIds) -> &'static str {
+        ids.windows
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    pub fn pick(ids: &super::FormatIds) -> &'static str {
+        ids.linux
+    }
+}
+
+fn lookup(id: &str) -> Option<&'static FormatIds> {
+    FORMAT_IDS.iter().find(|ids| {
+        [ids.mime, ids.macos, ids.windows, ids.linux]
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(id))
+    })
+}
+
+/// Returns the identifier the current platform uses for `id`, which may be a
+/// MIME type or any platform's native name. Unknown ids pass through as-is.
+pub fn platform_format_id(id: &str) -> String {
+    lookup(id)
+        .map(|ids| platform::pick(ids).to_string())
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// The clipboard content for `bytes` stored under format `id`, in the form
+/// the current platform expects. clipboard-rs registers every `Other` format
+/// by name, so on Windows the predefined formats go through the typed
+/// content it sets with their fixed ids.
+pub fn platform_content(id: &str, bytes: Vec<u8>) -> ClipboardContent {
+    #[cfg(target_os = "windows")]
+    if let Some(code) = lookup(id).and_then(|ids| ids.windows_predefined) {
+        return predefined_content(code, bytes);
+    }
+    ClipboardContent::Other(platform_format_id(id), bytes)
+}
+
+#[cfg(any(test, target_os = "windows"))]
+fn predefined_content(code: u32, bytes: Vec<u8>) -> ClipboardContent {
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    if code != CF_HDROP {
+        return ClipboardContent::Text(text);
+    }
+    let paths = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            url::Url::parse(line)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .map_or_else(
+                    || line.to_string(),
+                    |path| path.to_string_lossy().into_owned(),
+                )
+        })
+        .collect();
+    ClipboardContent::Files(paths)
+}
+
+/// Whether `id` names one of the formats the built-in plugins store.
+pub fn is_known_format(id: &str) -> bool {
+    lookup(id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_matches_any_platform_name() {
+        assert_eq!(lookup("public.png").map(|ids| ids.mime), Some("image/png"));
+        assert_eq!(lookup("HTML Format").map(|ids| ids.mime), Some("text/html"));
+        assert_eq!(lookup("TEXT/RTF").map(|ids| ids.mime), Some("text/rtf"));
+        assert!(lookup("com.example.custom").is_none());
+    }
+
+    #[test]
+    fn test_predefined_windows_formats_use_typed_content() {
+        let text = lookup("public.utf8-plain-text").unwrap();
+        assert_eq!(text.windows_predefined, Some(CF_UNICODETEXT));
+        assert_eq!(lookup("PNG").unwrap().windows_predefined, None);
+        assert!(matches!(
+            predefined_content(CF_UNICODETEXT, b"hello".to_vec()),
+            ClipboardContent::Text(text) if text == "hello"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_predefined_file_list_holds_paths() {
+        let files = predefined_content(CF_HDROP, b"# copied\nfile:///tmp/a%20b.txt\n".to_vec());
+        assert!(matches!(
+            files,
+            ClipboardContent::Files(paths) if paths == vec!["/tmp/a b.txt".to_string()]
+        ));
+    }
+
+    #[test]
+    fn test_unknown_ids_pass_through() {
+        assert_eq!(platform_format_id("com.example.custom"), "com.example.custom");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_platform_format_ids_macos() {
+        assert_eq!(platform_format_id("image/png"), "public.png");
+        assert_eq!(platform_format_id("HTML Format"), "public.html");
+        assert_eq!(platform_format_id("text/plain"), "public.utf8-plain-text");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_platform_format_ids_windows() {
+        assert_eq!(platform_format_id("public.png"), "PNG");
+        assert_eq!(platform_format_id("text/html"), "HTML Format");
+        assert_eq!(platform_format_id("public.rtf"), "Rich Text Format");
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[test]
+    fn test_platform_format_ids_linux() {
+        assert_eq!(platform_format_id("public.png"), "image/png");
+        assert_eq!(platform_format_id("public.html"), "text/html");
+        assert_eq!(platform_format_id("CF_UNICODETEXT"), "text/plain;charset=utf-8");
+    }
+}
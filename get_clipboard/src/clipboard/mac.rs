@@ -1,6 +1,6 @@
 use anyhow::{Result, anyhow};
 use objc2::rc::autoreleasepool;
-use objc2_app_kit::{NSPasteboard, NSPasteboardTypeString, NSPasteboardTypeTIFF};
+use objc2_app_kit::{NSPasteboard, NSPasteboardTypeString, NSPasteboardTypeTIFF, NSWorkspace};
 use objc2_foundation::{NSData, NSString};
 
 pub fn assert_macos() -> Result<()> {
@@ -11,6 +11,17 @@ pub fn assert_macos() -> Result<()> {
     }
 }
 
+/// Bundle id (or, failing that, name) of the app in the foreground, which is
+/// normally the one that just wrote to the clipboard.
+pub fn frontmost_app() -> Option<String> {
+    autoreleasepool(|_| {
+        let app = NSWorkspace::sharedWorkspace().frontmostApplication()?;
+        app.bundleIdentifier()
+            .or_else(|| app.localizedName())
+            .map(|name| name.to_string())
+    })
+}
+
 pub fn set_clipboard_from_bytes(bytes: &[u8], formats: &[String]) -> Result<()> {
     unsafe {
         autoreleasepool(|_| {
@@ -30,6 +41,36 @@ pub fn set_clipboard_from_bytes(bytes: &[u8], formats: &[String]) -> Result<()>
     Ok(())
 }
 
+/// Every type on the general pasteboard with its data, for putting back with
+/// `write_pasteboard_types`.
+pub fn read_pasteboard_types() -> Vec<(String, Vec<u8>)> {
+    autoreleasepool(|_| {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        let Some(types) = pasteboard.types() else {
+            return Vec::new();
+        };
+        types
+            .iter()
+            .filter_map(|pasteboard_type| {
+                let data = pasteboard.dataForType(&pasteboard_type)?;
+                Some((pasteboard_type.to_string(), data.to_vec()))
+            })
+            .collect()
+    })
+}
+
+/// Replaces the general pasteboard's contents with `types`.
+pub fn write_pasteboard_types(types: &[(String, Vec<u8>)]) {
+    autoreleasepool(|_| {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        pasteboard.clearContents();
+        for (pasteboard_type, bytes) in types {
+            let data = NSData::with_bytes(bytes);
+            pasteboard.setData_forType(Some(&data), &NSString::from_str(pasteboard_type));
+        }
+    });
+}
+
 pub fn get_current_text() -> Result<Option<String>> {
     unsafe {
         Ok(autoreleasepool(|_| {
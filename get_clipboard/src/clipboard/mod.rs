@@ -1,5 +1,8 @@
+pub mod formats;
 pub mod mac;
+pub mod noise;
 pub mod plugins;
 pub mod snapshot;
+pub mod transform;
 
 pub use snapshot::ClipboardSnapshot;
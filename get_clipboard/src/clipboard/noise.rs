@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use crate::config::model::NoiseFilterConfig;
+use crate::data::model::EntryKind;
+use crate::util::time::{Duration, OffsetDateTime};
+
+use super::ClipboardSnapshot;
+
+const DEFAULT_WINDOW_SECS: u64 = 5;
+const DEFAULT_MAX_LENGTH: usize = 40;
+const DEFAULT_REPEATS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoiseSettings {
+    pub window: Duration,
+    pub max_length: usize,
+    pub repeats: usize,
+    pub min_length: usize,
+}
+
+impl From<&NoiseFilterConfig> for NoiseSettings {
+    fn from(config: &NoiseFilterConfig) -> Self {
+        NoiseSettings {
+            window: Duration::seconds(config.window_secs.unwrap_or(DEFAULT_WINDOW_SECS) as i64),
+            max_length: config.max_length.unwrap_or(DEFAULT_MAX_LENGTH),
+            repeats: config.repeats.unwrap_or(DEFAULT_REPEATS).max(2),
+            min_length: config.min_length.unwrap_or(0),
+        }
+    }
+}
+
+/// A short text capture as the filter remembers it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentCapture {
+    pub app: Option<String>,
+    pub shape: String,
+    pub at: OffsetDateTime,
+}
+
+/// The text with every run of digits collapsed to `#`, so `12:01:09` and
+/// `12:01:10`, or `99` and `100`, look the same.
+pub fn text_shape(text: &str) -> String {
+    let mut shape = String::with_capacity(text.len());
+    for ch in text.trim().chars() {
+        if ch.is_ascii_digit() {
+            if !shape.ends_with('#') {
+                shape.push('#');
+            }
+        } else {
+            shape.push(ch);
+        }
+    }
+    shape
+}
+
+/// Whether `candidate` continues a burst: the same app has already produced
+/// `repeats - 1` captures of the same shape, each within `window` of the
+/// next. The first captures of a burst are kept so one-off copies of a
+/// timestamp or number still make it into history.
+pub fn is_noise(
+    recent: &[RecentCapture],
+    candidate: &RecentCapture,
+    settings: &NoiseSettings,
+) -> bool {
+    let mut previous_at = candidate.at;
+    let mut run = 1;
+    for capture in recent.iter().rev() {
+        if previous_at - capture.at > settings.window
+            || capture.app != candidate.app
+            || capture.shape != candidate.shape
+        {
+            break;
+        }
+        run += 1;
+        previous_at = capture.at;
+    }
+    run >= settings.repeats
+}
+
+/// Drops auto-generated clipboard noise, such as clocks or counters an app
+/// keeps copying. Only plain text captures are considered; anything longer
+/// than `max_length` is always kept.
+pub struct NoiseFilter {
+    settings: NoiseSettings,
+    recent: VecDeque<RecentCapture>,
+}
+
+impl NoiseFilter {
+    pub fn new(settings: NoiseSettings) -> Self {
+        NoiseFilter {
+            settings,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Records the snapshot and reports whether it should be skipped.
+    pub fn check(&mut self, snapshot: &ClipboardSnapshot, at: OffsetDateTime) -> bool {
+        if snapshot.kind != EntryKind::Text || snapshot.html.is_some() {
+            return false;
+        }
+        let Some(text) = snapshot.text.as_deref() else {
+            return false;
+        };
+        let length = text.trim().chars().count();
+        if length < self.settings.min_length {
+            return true;
+        }
+        if length > self.settings.max_length {
+            return false;
+        }
+        let candidate = RecentCapture {
+            app: snapshot.source_app.clone(),
+            shape: text_shape(text),
+            at,
+        };
+        let noise = is_noise(self.recent.make_contiguous(), &candidate, &self.settings);
+        self.recent.push_back(candidate);
+        while self.recent.len() > self.settings.repeats {
+            self.recent.pop_front();
+        }
+        noise
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn settings() -> NoiseSettings {
+        NoiseSettings::from(&NoiseFilterConfig::default())
+    }
+
+    fn text_snapshot(text: &str, app: Option<&str>) -> ClipboardSnapshot {
+        serde_json::from_value(serde_json::json!({
+            "kind": "Text",
+            "text": text,
+            "html": null,
+            "rtf": null,
+            "image_bytes": null,
+            "image_mime": null,
+            "files": [],
+            "summary": null,
+            "detected_formats": [],
+            "extra": null,
+            "source_app": app,
+        }))
+        .unwrap()
+    }
+
+    fn capture(app: &str, text: &str, secs: i64) -> RecentCapture {
+        RecentCapture {
+            app: Some(app.to_string()),
+            shape: text_shape(text),
+            at: datetime!(2024-03-10 12:00:00 UTC) + Duration::seconds(secs),
+        }
+    }
+
+    #[test]
+    fn test_text_shape_collapses_digit_runs() {
+        assert_eq!(text_shape("12:01:09"), "#:#:#");
+        assert_eq!(text_shape("99"), text_shape("100"));
+        assert_eq!(text_shape(" v1.2 build 345 "), "v#.# build #");
+        assert_eq!(text_shape("hello"), "hello");
+    }
+
+    #[test]
+    fn test_rapid_counter_from_one_app_is_noise_after_repeats() {
+        let recent = vec![capture("Timer", "98", 0), capture("Timer", "99", 1)];
+        assert!(is_noise(&recent, &capture("Timer", "100", 2), &settings()));
+        // The start of a burst is kept.
+        assert!(!is_noise(
+            &recent[..1],
+            &capture("Timer", "99", 1),
+            &settings()
+        ));
+    }
+
+    #[test]
+    fn test_slow_or_mixed_captures_are_kept() {
+        let recent = vec![capture("Timer", "98", 0), capture("Timer", "99", 1)];
+        // Too long after the previous capture.
+        assert!(!is_noise(
+            &recent,
+            &capture("Timer", "100", 30),
+            &settings()
+        ));
+        // Same shape, different app.
+        assert!(!is_noise(&recent, &capture("Notes", "100", 2), &settings()));
+        // Same app, different shape.
+        assert!(!is_noise(
+            &recent,
+            &capture("Timer", "done", 2),
+            &settings()
+        ));
+
+        let interrupted = vec![
+            capture("Timer", "97", 0),
+            capture("Notes", "hello", 1),
+            capture("Timer", "98", 2),
+        ];
+        assert!(!is_noise(
+            &interrupted,
+            &capture("Timer", "99", 3),
+            &settings()
+        ));
+    }
+
+    #[test]
+    fn test_filter_ignores_long_and_non_text_captures() {
+        let mut filter = NoiseFilter::new(settings());
+        let at = datetime!(2024-03-10 12:00:00 UTC);
+        let snapshot = text_snapshot("1", Some("Timer"));
+        for secs in 0..DEFAULT_REPEATS as i64 - 1 {
+            assert!(!filter.check(&snapshot, at + Duration::seconds(secs)));
+        }
+        assert!(filter.check(&snapshot, at + Duration::seconds(2)));
+
+        let long = text_snapshot(&"1".repeat(DEFAULT_MAX_LENGTH + 1), Some("Timer"));
+        assert!(!filter.check(&long, at + Duration::seconds(3)));
+
+        let mut image = text_snapshot("1", Some("Timer"));
+        image.kind = EntryKind::Image;
+        assert!(!filter.check(&image, at + Duration::seconds(4)));
+
+        let mut min_length = NoiseFilter::new(NoiseSettings {
+            min_length: 3,
+            ..settings()
+        });
+        assert!(min_length.check(&text_snapshot(" a ", None), at));
+        assert!(!min_length.check(&text_snapshot("abc", None), at));
+    }
+}
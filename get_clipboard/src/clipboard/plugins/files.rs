@@ -1,21 +1,35 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Result, anyhow};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Serialize;
 use serde_json::json;
+use url::Url;
 
 use crate::clipboard::snapshot::{
-    ClipboardSnapshot, FileOutput, FileRecord, format_file_summary, human_kb,
+    ClipboardSnapshot, FileOutput, FileRecord, decode_path_bytes, format_file_summary, human_kb,
 };
+use crate::config::load_config;
 use crate::data::model::EntryKind;
 
 use super::{
-    ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture, PluginContext,
-    PluginImport,
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture,
+    PluginContext, PluginImport,
 };
 
 pub static FILES_PLUGIN: &FilesPlugin = &FilesPlugin;
 
+/// Directory inside an entry that stashed copies of the files are kept in.
+const STASH_DIR: &str = "files__stash";
+/// Most images embedded in a single files preview.
+const GALLERY_MAX_IMAGES: usize = 12;
+/// Images larger than this are listed but not embedded in the preview.
+const GALLERY_MAX_IMAGE_BYTES: u64 = 4 * 1024 * 1024;
+/// Images that would take the gallery past this many bytes are left out.
+const GALLERY_MAX_TOTAL_BYTES: u64 = 12 * 1024 * 1024;
+
 pub struct FilesPlugin;
 
 impl ClipboardPlugin for FilesPlugin {
@@ -39,7 +53,11 @@ impl ClipboardPlugin for FilesPlugin {
         !snapshot.files.is_empty()
     }
 
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture> {
+    fn capture(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        _ctx: &CaptureContext,
+    ) -> Option<PluginCapture> {
         if snapshot.files.is_empty() {
             return None;
         }
@@ -60,11 +78,21 @@ impl ClipboardPlugin for FilesPlugin {
             .collect();
         let joined = lines.join("\n");
 
-        let files = vec![FileOutput {
+        let mut files = vec![FileOutput {
             filename: "files__paths.txt".to_string(),
             bytes: joined.as_bytes().to_vec(),
         }];
 
+        let stash_limit = load_config().ok().and_then(|config| config.file_stash_limit());
+        let stash = match stash_limit {
+            Some(limit) => {
+                let (outputs, stash) = stash_files(&snapshot.files, limit);
+                files.extend(outputs);
+                stash
+            }
+            None => Vec::new(),
+        };
+
         let byte_size = files.iter().map(|f| f.bytes.len() as u64).sum();
         let sources = snapshot.sources();
 
@@ -78,6 +106,7 @@ impl ClipboardPlugin for FilesPlugin {
             files,
             metadata: json!({
                 "entries": snapshot.files.clone(),
+                "stash": stash,
             }),
             byte_size,
             sources,
@@ -111,18 +140,11 @@ impl ClipboardPlugin for FilesPlugin {
         let mut paths = Vec::new();
         for value in array {
             if let Some(path) = value.as_str() {
-                paths.push(path.to_string());
+                paths.push(PathBuf::from(path));
                 continue;
             }
-            if let Some(object) = value.as_object() {
-                if let Some(path) = object
-                    .get("source_path")
-                    .or_else(|| object.get("sourcePath"))
-                    .or_else(|| object.get("path"))
-                    .and_then(serde_json::Value::as_str)
-                {
-                    paths.push(path.to_string());
-                }
+            if let Some(path) = entry_path(value) {
+                paths.push(path);
             }
         }
 
@@ -133,36 +155,19 @@ impl ClipboardPlugin for FilesPlugin {
         let mut records = Vec::new();
         let mut lines = Vec::new();
 
-        for raw_path in &paths {
-            let path_buf = PathBuf::from(raw_path);
-            let metadata = fs::metadata(&path_buf).ok();
+        for path_buf in &paths {
+            let metadata = fs::metadata(path_buf).ok();
             let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-            let name = path_buf
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| raw_path.clone());
-            let extension = path_buf
-                .extension()
-                .map(|ext| ext.to_string_lossy().to_string());
-            let mime = mime_guess::from_path(&path_buf)
-                .first_raw()
-                .map(String::from);
-
-            records.push(FileRecord {
-                name,
-                extension,
-                size,
-                source_path: path_buf.clone(),
-                mime: mime.clone(),
-            });
-
-            let mime_label = mime.unwrap_or_else(|| "file".into());
+            let record = FileRecord::from_path(path_buf, size);
+
+            let mime_label = record.mime.clone().unwrap_or_else(|| "file".into());
             lines.push(format!(
                 "{} ({} - {})",
                 path_buf.display(),
                 human_kb(size),
                 mime_label
             ));
+            records.push(record);
         }
 
         let summary = Some(format_file_summary(&records));
@@ -186,14 +191,14 @@ impl ClipboardPlugin for FilesPlugin {
                 "entries": records,
             }),
             byte_size,
-            sources: paths.clone(),
+            sources: paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
         };
         capture.finalize_metadata();
 
-        let urls = paths
-            .iter()
-            .map(|path| format!("file://{}", path))
-            .collect::<Vec<_>>();
+        let urls = paths.iter().map(|path| file_url(path)).collect::<Vec<_>>();
 
         Ok(PluginImport {
             capture,
@@ -212,7 +217,7 @@ impl ClipboardPlugin for FilesPlugin {
     fn get_preview_data(&self, ctx: &PluginContext<'_>) -> Result<serde_json::Value> {
         let entries = collect_entries(ctx)?;
         let mut file_items = Vec::new();
-        for entry in entries {
+        for entry in &entries {
             let name = entry.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
             let size_bytes = entry.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
             let source_path = entry.get("source_path").and_then(|v| v.as_str()).unwrap_or_default().to_string();
@@ -223,7 +228,14 @@ impl ClipboardPlugin for FilesPlugin {
                 "path": source_path
             }));
         }
-        Ok(json!({ "files": file_items }))
+        let stash = ctx
+            .plugin_meta
+            .get("stash")
+            .and_then(serde_json::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let images = gallery_images(&entries, stash, ctx.item_dir);
+        Ok(json!({ "files": file_items, "images": images }))
     }
 
     fn is_editable(&self) -> bool {
@@ -254,13 +266,164 @@ fn collect_paths(ctx: &PluginContext<'_>) -> Result<Vec<String>> {
     Ok(paths)
 }
 
+/// What `replay` found when it re-read an entry's files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReplayReport {
+    pub files: usize,
+    pub changed: usize,
+    pub missing: usize,
+}
+
+/// Re-reads size and type for every file in a stored files capture. Files
+/// that no longer exist keep what was recorded when they were copied.
+pub fn refresh_records(plugin_meta: &serde_json::Value) -> (Vec<FileRecord>, ReplayReport) {
+    let mut report = ReplayReport::default();
+    let records = plugin_meta
+        .get("entries")
+        .and_then(serde_json::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry_path(entry)?;
+            let stored: Option<FileRecord> = serde_json::from_value(entry.clone()).ok();
+            report.files += 1;
+            let Ok(meta) = fs::metadata(&path) else {
+                report.missing += 1;
+                return Some(stored.unwrap_or_else(|| FileRecord::from_path(&path, 0)));
+            };
+            let fresh = FileRecord::from_path(&path, meta.len());
+            let unchanged = stored
+                .as_ref()
+                .is_some_and(|old| old.size == fresh.size && old.mime == fresh.mime);
+            if !unchanged {
+                report.changed += 1;
+            }
+            Some(fresh)
+        })
+        .collect();
+    (records, report)
+}
+
+/// The exact path of a file entry. Non-UTF-8 paths are rebuilt from their
+/// raw bytes because the `source_path` string is only a lossy copy.
+fn entry_path(entry: &serde_json::Value) -> Option<PathBuf> {
+    if let Some(path) = entry
+        .get("source_path_bytes")
+        .and_then(serde_json::Value::as_str)
+        .and_then(decode_path_bytes)
+    {
+        return Some(path);
+    }
+    entry
+        .get("source_path")
+        .or_else(|| entry.get("sourcePath"))
+        .or_else(|| entry.get("path"))
+        .and_then(serde_json::Value::as_str)
+        .map(PathBuf::from)
+}
+
+/// `file://` reference for the clipboard. UTF-8 paths are passed through as
+/// before; other paths are percent-encoded so no bytes are lost.
+fn file_url(path: &Path) -> String {
+    match path.to_str() {
+        Some(path) => format!("file://{path}"),
+        None => Url::from_file_path(path)
+            .map(String::from)
+            .unwrap_or_else(|_| format!("file://{}", path.display())),
+    }
+}
+
 fn collect_urls(ctx: &PluginContext<'_>) -> Result<Vec<String>> {
-    collect_paths(ctx).map(|paths| {
-        paths
-            .into_iter()
-            .map(|path| format!("file://{}", path))
-            .collect()
-    })
+    let stash = ctx
+        .plugin_meta
+        .get("stash")
+        .and_then(serde_json::Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(collect_entries(ctx)?
+        .iter()
+        .filter_map(entry_path)
+        .map(|path| file_url(&resolve_stashed(&path, stash, ctx.item_dir)))
+        .collect())
+}
+
+/// Reads each file no larger than `max_bytes` so it can be stored with the
+/// entry under its original name. Returns the outputs to persist and the
+/// metadata linking each stashed copy back to its source path.
+fn stash_files(records: &[FileRecord], max_bytes: u64) -> (Vec<FileOutput>, Vec<serde_json::Value>) {
+    let mut outputs = Vec::new();
+    let mut stash = Vec::new();
+    for (idx, record) in records.iter().enumerate() {
+        if record.size > max_bytes || record.link_target.is_some() || !record.source_path.is_file()
+        {
+            continue;
+        }
+        let Ok(bytes) = fs::read(&record.source_path) else {
+            continue;
+        };
+        let filename = format!("{STASH_DIR}/{idx}/{}", record.name);
+        stash.push(json!({
+            "source_path": record.source_path.to_string_lossy(),
+            "filename": filename,
+        }));
+        outputs.push(FileOutput { filename, bytes });
+    }
+    (outputs, stash)
+}
+
+/// Whether `filename` is one of the stashed file copies.
+pub fn is_stashed_file(filename: &str) -> bool {
+    Path::new(filename).starts_with(STASH_DIR)
+}
+
+/// Falls back to the stashed copy when the original file no longer exists.
+fn resolve_stashed(path: &Path, stash: &[serde_json::Value], item_dir: &Path) -> PathBuf {
+    if path.exists() {
+        return path.to_path_buf();
+    }
+    let key = path.to_string_lossy();
+    stash
+        .iter()
+        .filter(|entry| entry.get("source_path").and_then(|v| v.as_str()) == Some(&*key))
+        .filter_map(|entry| entry.get("filename").and_then(|v| v.as_str()))
+        .map(|filename| item_dir.join(filename))
+        .find(|candidate| candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Embeds the image entries as data URLs for the preview gallery, reading
+/// stashed copies when the originals are gone. Missing or oversized images
+/// are skipped, as are images that would push the gallery past its total
+/// size cap.
+fn gallery_images(
+    entries: &[serde_json::Value],
+    stash: &[serde_json::Value],
+    item_dir: &Path,
+) -> Vec<serde_json::Value> {
+    let mut total = 0;
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let mime = entry.get("mime").and_then(|v| v.as_str())?;
+            if !mime.starts_with("image/") {
+                return None;
+            }
+            let source_path = entry_path(entry)?;
+            let path = resolve_stashed(&source_path, stash, item_dir);
+            let size = fs::metadata(&path).ok()?.len();
+            if size > GALLERY_MAX_IMAGE_BYTES || total + size > GALLERY_MAX_TOTAL_BYTES {
+                return None;
+            }
+            let bytes = fs::read(&path).ok()?;
+            total += size;
+            let name = entry.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+            Some(json!({
+                "name": name,
+                "src": format!("data:{mime};base64,{}", BASE64.encode(bytes)),
+            }))
+        })
+        .take(GALLERY_MAX_IMAGES)
+        .collect()
 }
 
 fn entry_count(ctx: &PluginContext<'_>) -> usize {
@@ -270,3 +433,153 @@ fn entry_count(ctx: &PluginContext<'_>) -> usize {
         .map(|arr| arr.len())
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_for(path: &Path) -> FileRecord {
+        FileRecord {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            extension: None,
+            size: fs::metadata(path).unwrap().len(),
+            source_path: path.to_path_buf(),
+            source_path_bytes: None,
+            mime: None,
+            link_target: None,
+        }
+    }
+
+    #[test]
+    fn test_stash_files_respects_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.txt");
+        let large = dir.path().join("large.txt");
+        fs::write(&small, b"hi").unwrap();
+        fs::write(&large, vec![b'x'; 64]).unwrap();
+
+        let (outputs, stash) = stash_files(&[record_for(&small), record_for(&large)], 16);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].filename, "files__stash/0/small.txt");
+        assert_eq!(outputs[0].bytes, b"hi");
+        assert_eq!(stash.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_stashed_after_original_deleted() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let item_dir = tempfile::tempdir().unwrap();
+        let original = source_dir.path().join("notes.txt");
+        fs::write(&original, b"keep me").unwrap();
+
+        let (outputs, stash) = stash_files(&[record_for(&original)], 1024);
+        for output in &outputs {
+            let dest = item_dir.path().join(&output.filename);
+            fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            fs::write(dest, &output.bytes).unwrap();
+        }
+
+        assert_eq!(
+            resolve_stashed(&original, &stash, item_dir.path()),
+            original
+        );
+
+        fs::remove_file(&original).unwrap();
+        let resolved = resolve_stashed(&original, &stash, item_dir.path());
+        assert_ne!(resolved, original);
+        assert_eq!(fs::read(resolved).unwrap(), b"keep me");
+    }
+
+    fn entry_for(path: &Path, mime: &str) -> serde_json::Value {
+        let mut record = record_for(path);
+        record.mime = Some(mime.to_string());
+        serde_json::to_value(record).unwrap()
+    }
+
+    #[test]
+    fn test_gallery_embeds_each_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.png");
+        let second = dir.path().join("second.jpg");
+        let notes = dir.path().join("notes.txt");
+        fs::write(&first, b"png bytes").unwrap();
+        fs::write(&second, b"jpeg bytes").unwrap();
+        fs::write(&notes, b"text").unwrap();
+
+        let entries = vec![
+            entry_for(&first, "image/png"),
+            entry_for(&notes, "text/plain"),
+            entry_for(&second, "image/jpeg"),
+        ];
+        let images = gallery_images(&entries, &[], dir.path());
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0]["name"], "first.png");
+        assert_eq!(
+            images[0]["src"],
+            format!("data:image/png;base64,{}", BASE64.encode(b"png bytes"))
+        );
+        assert_eq!(images[1]["name"], "second.jpg");
+        assert!(images[1]["src"].as_str().unwrap().starts_with("data:image/jpeg;base64,"));
+    }
+
+    #[test]
+    fn test_gallery_skips_missing_images_and_caps_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let gone_path = dir.path().join("gone.png");
+        fs::write(&gone_path, b"img").unwrap();
+        let gone = entry_for(&gone_path, "image/png");
+        fs::remove_file(&gone_path).unwrap();
+        assert!(gallery_images(&[gone], &[], dir.path()).is_empty());
+
+        let entries: Vec<_> = (0..GALLERY_MAX_IMAGES + 3)
+            .map(|idx| {
+                let path = dir.path().join(format!("{idx}.png"));
+                fs::write(&path, b"img").unwrap();
+                entry_for(&path, "image/png")
+            })
+            .collect();
+        assert_eq!(gallery_images(&entries, &[], dir.path()).len(), GALLERY_MAX_IMAGES);
+    }
+
+    #[test]
+    fn test_gallery_respects_total_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let per_image = GALLERY_MAX_IMAGE_BYTES as usize;
+        let entries: Vec<_> = (0..4)
+            .map(|idx| {
+                let path = dir.path().join(format!("{idx}.png"));
+                fs::write(&path, vec![0u8; per_image]).unwrap();
+                entry_for(&path, "image/png")
+            })
+            .collect();
+        let expected = (GALLERY_MAX_TOTAL_BYTES / GALLERY_MAX_IMAGE_BYTES) as usize;
+        assert_eq!(gallery_images(&entries, &[], dir.path()).len(), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_survives_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(OsStr::from_bytes(b"caf\xe9.txt"));
+        fs::write(&path, b"bytes").unwrap();
+
+        let record = FileRecord::from_path(&path, 5);
+        assert!(record.source_path_bytes.is_some());
+        let entry = serde_json::to_value(&record).unwrap();
+        assert!(entry["source_path"].as_str().unwrap().ends_with("caf\u{FFFD}.txt"));
+        assert_eq!(entry_path(&entry).unwrap(), path);
+
+        let url = file_url(&path);
+        assert!(url.ends_with("caf%E9.txt"));
+        assert_eq!(Url::parse(&url).unwrap().to_file_path().unwrap(), path);
+
+        let stored: FileRecord = serde_json::from_value(entry).unwrap();
+        assert_eq!(
+            decode_path_bytes(stored.source_path_bytes.as_deref().unwrap()).unwrap(),
+            path
+        );
+    }
+}
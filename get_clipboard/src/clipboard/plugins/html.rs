@@ -7,8 +7,8 @@ use crate::clipboard::snapshot::{ClipboardSnapshot, FileOutput, truncate_summary
 use crate::data::model::EntryKind;
 
 use super::{
-    ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture, PluginContext,
-    PluginImport,
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture,
+    PluginContext, PluginImport,
 };
 
 pub static HTML_PLUGIN: &HtmlPlugin = &HtmlPlugin;
@@ -44,7 +44,11 @@ impl ClipboardPlugin for HtmlPlugin {
             .unwrap_or(false)
     }
 
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture> {
+    fn capture(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        _ctx: &CaptureContext,
+    ) -> Option<PluginCapture> {
         let html = snapshot.html.as_ref()?;
         if html.is_empty() {
             return None;
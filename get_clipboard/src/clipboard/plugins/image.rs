@@ -1,5 +1,6 @@
 use std::fs;
 use std::io::{Cursor, Write};
+use std::path::Path;
 
 use anyhow::{Context, Result, anyhow, bail};
 use base64::Engine as _;
@@ -7,12 +8,15 @@ use base64::engine::general_purpose::STANDARD as BASE64;
 use image::ImageFormat;
 use serde_json::json;
 
+use crate::clipboard::formats::platform_format_id;
 use crate::clipboard::snapshot::{ClipboardSnapshot, FileOutput, human_kb, mime_for_extension};
+use crate::config::load_config;
 use crate::data::model::EntryKind;
+use crate::fs::layout;
 
 use super::{
-    ClipboardJsonFormat, ClipboardPlugin, DisplayContent, ImageDisplay, PluginCapture,
-    PluginContext, PluginImport, StoredFile,
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, ImageDisplay,
+    PluginCapture, PluginContext, PluginImport, StoredFile,
 };
 use clipboard_rs::common::{ClipboardContent, RustImage, RustImageData};
 use tempfile::NamedTempFile;
@@ -46,7 +50,7 @@ impl ClipboardPlugin for ImagePlugin {
             .unwrap_or(false)
     }
 
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture> {
+    fn capture(&self, snapshot: &ClipboardSnapshot, ctx: &CaptureContext) -> Option<PluginCapture> {
         let bytes = snapshot.image_bytes.as_ref()?.clone();
         if bytes.is_empty() {
             return None;
@@ -70,6 +74,19 @@ impl ClipboardPlugin for ImagePlugin {
             bytes: bytes.clone(),
         }];
 
+        let mut metadata = json!({
+            "width": width,
+            "height": height,
+            "mime": mime,
+            "byteSize": bytes.len(),
+        });
+        if ctx.image_phash
+            && let Ok(fingerprint) = ImageFingerprint::from_bytes(&bytes)
+        {
+            metadata["phash"] = json!(format_phash(fingerprint.phash));
+            metadata["meanColor"] = json!(format_mean_color(fingerprint.mean_color));
+        }
+
         Some(PluginCapture {
             plugin_id: self.id(),
             kind: self.kind(),
@@ -84,12 +101,7 @@ impl ClipboardPlugin for ImagePlugin {
             )),
             search_text: None,
             files,
-            metadata: json!({
-                "width": width,
-                "height": height,
-                "mime": mime,
-                "byteSize": bytes.len(),
-            }),
+            metadata,
             byte_size: bytes.len() as u64,
             sources: Vec::new(),
         })
@@ -124,8 +136,7 @@ impl ClipboardPlugin for ImagePlugin {
             .get("mime")
             .and_then(serde_json::Value::as_str)
             .unwrap_or("image/png");
-        let data_url = format!("data:{};base64,{}", mime, BASE64.encode(bytes));
-        Ok(serde_json::Value::String(data_url))
+        Ok(serde_json::Value::String(encode_data_url(mime, &bytes)))
     }
 
     fn import_json(&self, format: &ClipboardJsonFormat) -> Result<PluginImport> {
@@ -206,7 +217,7 @@ impl ClipboardPlugin for ImagePlugin {
 
         let mut clipboard_contents = Vec::new();
         clipboard_contents.push(ClipboardContent::Image(image_data));
-        clipboard_contents.push(ClipboardContent::Other("public.png".into(), png_bytes));
+        clipboard_contents.push(ClipboardContent::Other(platform_format_id("image/png"), png_bytes));
 
         Ok(PluginImport {
             capture,
@@ -255,19 +266,197 @@ impl ClipboardPlugin for ImagePlugin {
             String::new()
         };
 
+        let config = load_config().unwrap_or_default();
+        let max_height = config.thumbnail_height();
+        let cache_dir = layout::thumbnails_dir(&config.data_dir(), &ctx.metadata.hash);
+        let file = primary_file(ctx)?;
+        let thumbnail = (height == 0 || height > u64::from(max_height))
+            .then(|| cached_thumbnail(&cache_dir, &file.path, max_height))
+            .flatten()
+            .map(|png| format!("data:image/png;base64,{}", BASE64.encode(png)));
+
         Ok(json!({
             "content": src,
-            "dimensions": dimensions
+            "thumbnail": thumbnail,
+            "dimensions": dimensions,
+            "width": width,
+            "height": height
         }))
     }
 }
 
+/// Returns the thumbnail of `source` at `max_height`, reading it from
+/// `cache_dir` when present and filling the cache otherwise. The cache holds
+/// one scaled copy per height, so previews only decode the full image once.
+fn cached_thumbnail(cache_dir: &Path, source: &Path, max_height: u32) -> Option<Vec<u8>> {
+    let cache_path = cache_dir.join(format!("{max_height}.png"));
+    if let Ok(png) = fs::read(&cache_path) {
+        return Some(png);
+    }
+    let png = thumbnail_png(&fs::read(source).ok()?, max_height).ok()??;
+    let cached = fs::create_dir_all(cache_dir)
+        .and_then(|()| NamedTempFile::new_in(cache_dir))
+        .and_then(|mut temp| {
+            temp.write_all(&png)?;
+            temp.persist(&cache_path).map_err(|err| err.error)
+        });
+    if let Err(err) = cached {
+        eprintln!("Failed to cache thumbnail: {err}");
+    }
+    Some(png)
+}
+
+/// Scales an image down to `max_height`, keeping its aspect ratio. Returns
+/// `None` when the image is already short enough to show as-is.
+fn thumbnail_png(bytes: &[u8], max_height: u32) -> Result<Option<Vec<u8>>> {
+    let decoded = image::load_from_memory(bytes).context("Failed to decode image")?;
+    if decoded.height() <= max_height {
+        return Ok(None);
+    }
+    let width = (u64::from(decoded.width()) * u64::from(max_height) / u64::from(decoded.height()))
+        .max(1) as u32;
+    let scaled = decoded.resize_exact(width, max_height, image::imageops::FilterType::Triangle);
+    let mut out = Cursor::new(Vec::new());
+    scaled
+        .write_to(&mut out, ImageFormat::Png)
+        .context("Failed to encode thumbnail")?;
+    Ok(Some(out.into_inner()))
+}
+
+/// Re-encodes a stored image as lossless WebP. Returns `None` when that would
+/// not make it smaller, or when the image has more precision per channel than
+/// the WebP encoder keeps.
+pub fn lossless_webp(bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+    let decoded = image::load_from_memory(bytes).context("Failed to decode image")?;
+    let converted = match decoded.color() {
+        image::ColorType::Rgb8 | image::ColorType::Rgba8 => decoded,
+        image::ColorType::L8 => image::DynamicImage::ImageRgb8(decoded.to_rgb8()),
+        image::ColorType::La8 => image::DynamicImage::ImageRgba8(decoded.to_rgba8()),
+        _ => return Ok(None),
+    };
+    let mut out = Cursor::new(Vec::new());
+    converted
+        .write_to(&mut out, ImageFormat::WebP)
+        .context("Failed to encode WebP")?;
+    let encoded = out.into_inner();
+    Ok((encoded.len() < bytes.len()).then_some(encoded))
+}
+
+/// Largest relative difference in aspect ratio between two images that can
+/// still be the same picture, allowing for a rescaled copy rounding a pixel.
+const ASPECT_TOLERANCE: f64 = 0.02;
+/// Largest per-channel difference in average colour between two images that
+/// can still be the same picture.
+const MEAN_COLOR_TOLERANCE: u8 = 12;
+
+/// What `dedup --images` compares. The perceptual hash only sees gradients,
+/// so a flat or smoothly shaded image hashes to (nearly) 0 whatever its
+/// colour or shape; the shape and average colour rule those matches out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageFingerprint {
+    pub phash: u64,
+    pub width: u32,
+    pub height: u32,
+    pub mean_color: [u8; 3],
+}
+
+impl ImageFingerprint {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let decoded = image::load_from_memory(bytes).context("Failed to decode image")?;
+        let small = decoded.resize_exact(9, 8, image::imageops::FilterType::Triangle);
+        Ok(ImageFingerprint {
+            phash: difference_hash(&small.to_luma8()),
+            width: decoded.width(),
+            height: decoded.height(),
+            mean_color: mean_color(&small.to_rgb8()),
+        })
+    }
+
+    /// True when both images look the same: hashes within `threshold` bits,
+    /// the same aspect ratio and a similar average colour.
+    pub fn matches(&self, other: &ImageFingerprint, threshold: u32) -> bool {
+        phash_distance(self.phash, other.phash) <= threshold
+            && self.same_aspect(other)
+            && self
+                .mean_color
+                .iter()
+                .zip(other.mean_color)
+                .all(|(a, b)| a.abs_diff(b) <= MEAN_COLOR_TOLERANCE)
+    }
+
+    fn same_aspect(&self, other: &ImageFingerprint) -> bool {
+        if self.height == 0 || other.height == 0 {
+            return self.width == other.width && self.height == other.height;
+        }
+        let ours = f64::from(self.width) / f64::from(self.height);
+        let theirs = f64::from(other.width) / f64::from(other.height);
+        (ours - theirs).abs() <= ASPECT_TOLERANCE * ours.max(theirs)
+    }
+}
+
+/// A 64-bit difference hash: the image is shrunk to 9x8 grey pixels and
+/// each bit records whether a pixel is darker than its right neighbour.
+/// Re-encodes and small edits barely move it, so near-identical screenshots
+/// end up a few bits apart.
+fn difference_hash(small: &image::GrayImage) -> u64 {
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn mean_color(small: &image::RgbImage) -> [u8; 3] {
+    let mut sums = [0u32; 3];
+    for pixel in small.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += u32::from(channel);
+        }
+    }
+    let count = (small.width() * small.height()).max(1);
+    sums.map(|sum| (sum / count) as u8)
+}
+
+/// Number of differing bits between two perceptual hashes.
+pub fn phash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Stored as hex so JSON readers that parse numbers as doubles keep every bit.
+pub fn format_phash(phash: u64) -> String {
+    format!("{phash:016x}")
+}
+
+pub fn parse_phash(value: &str) -> Option<u64> {
+    u64::from_str_radix(value, 16).ok()
+}
+
+/// Stored as a CSS-style `#rrggbb` string.
+pub fn format_mean_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+pub fn parse_mean_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
 fn primary_file<'a>(ctx: &'a PluginContext<'a>) -> Result<&'a StoredFile> {
     ctx.stored_files
         .first()
         .ok_or_else(|| anyhow!("image plugin missing stored file"))
 }
 
+pub(crate) fn encode_data_url(mime: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime, BASE64.encode(bytes))
+}
+
 fn decode_data_url(input: &str) -> Result<(String, Vec<u8>)> {
     let trimmed = input.trim();
     let Some(rest) = trimmed.strip_prefix("data:") else {
@@ -302,3 +491,176 @@ fn decode_data_url(input: &str) -> Result<(String, Vec<u8>)> {
         .map_err(|err| anyhow!("Failed to decode image payload: {err}"))?;
     Ok((mime.to_string(), bytes))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageEncoder;
+
+    fn png_of_size(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbaImage::from_pixel(width, height, image::Rgba([200, 40, 40, 255]));
+        let mut out = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut out, ImageFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_thumbnail_respects_configured_height() {
+        let thumb = thumbnail_png(&png_of_size(400, 300), 120).unwrap().unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (160, 120));
+
+        let taller = thumbnail_png(&png_of_size(400, 300), 60).unwrap().unwrap();
+        let decoded = image::load_from_memory(&taller).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (80, 60));
+    }
+
+    #[test]
+    fn test_thumbnail_is_cached_per_height() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("image.png");
+        fs::write(&source, png_of_size(400, 300)).unwrap();
+        let cache_dir = layout::thumbnails_dir(dir.path(), "abc123");
+
+        let first = cached_thumbnail(&cache_dir, &source, 120).unwrap();
+        assert!(cache_dir.join("120.png").exists());
+
+        // Later previews never go back to the full image.
+        fs::remove_file(&source).unwrap();
+        assert_eq!(cached_thumbnail(&cache_dir, &source, 120).unwrap(), first);
+        assert!(cached_thumbnail(&cache_dir, &source, 60).is_none());
+    }
+
+    fn gradient_png() -> Vec<u8> {
+        let img = image::RgbImage::from_fn(256, 256, |x, y| image::Rgb([x as u8, y as u8, 0]));
+        let mut out = Cursor::new(Vec::new());
+        image::codecs::png::PngEncoder::new_with_quality(
+            &mut out,
+            image::codecs::png::CompressionType::Fast,
+            image::codecs::png::FilterType::NoFilter,
+        )
+        .write_image(img.as_raw(), 256, 256, image::ExtendedColorType::Rgb8)
+        .unwrap();
+        out.into_inner()
+    }
+
+    fn encode_png(img: &image::RgbImage) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_perceptual_hash_matches_near_identical_images() {
+        let scene = |x: u32, y: u32| {
+            let shade = if (x / 40 + y / 30).is_multiple_of(2) {
+                220
+            } else {
+                30
+            };
+            image::Rgb([shade, (x / 2) as u8, (y / 2) as u8])
+        };
+        let original = image::RgbImage::from_fn(320, 200, scene);
+        // Same screenshot with a moved cursor: a handful of pixels differ, so
+        // the PNG bytes differ too.
+        let mut touched = original.clone();
+        for x in 150..154 {
+            for y in 90..96 {
+                touched.put_pixel(x, y, image::Rgb([255, 255, 255]));
+            }
+        }
+        let original_png = encode_png(&original);
+        let touched_png = encode_png(&touched);
+        assert_ne!(original_png, touched_png);
+
+        let a = ImageFingerprint::from_bytes(&original_png).unwrap().phash;
+        let b = ImageFingerprint::from_bytes(&touched_png).unwrap().phash;
+        assert!(phash_distance(a, b) <= 2);
+
+        let different = image::RgbImage::from_fn(320, 200, |x, y| scene(319 - x, y));
+        let c = ImageFingerprint::from_bytes(&encode_png(&different))
+            .unwrap()
+            .phash;
+        assert!(phash_distance(a, c) > 10);
+    }
+
+    #[test]
+    fn test_flat_images_of_different_colours_do_not_match() {
+        let flat = |colour: [u8; 3]| {
+            let png = encode_png(&image::RgbImage::from_pixel(200, 120, image::Rgb(colour)));
+            ImageFingerprint::from_bytes(&png).unwrap()
+        };
+        let white = flat([255, 255, 255]);
+        let black = flat([0, 0, 0]);
+        // No gradients means no bits set, so the hashes alone are identical.
+        assert_eq!(white.phash, 0);
+        assert_eq!(white.phash, black.phash);
+        assert!(!white.matches(&black, 0));
+        assert!(white.matches(&flat([250, 252, 255]), 0));
+    }
+
+    #[test]
+    fn test_fingerprints_of_different_shapes_do_not_match() {
+        let flat = |width: u32, height: u32| {
+            let png = encode_png(&image::RgbImage::from_pixel(
+                width,
+                height,
+                image::Rgb([90; 3]),
+            ));
+            ImageFingerprint::from_bytes(&png).unwrap()
+        };
+        assert!(!flat(200, 120).matches(&flat(120, 200), 0));
+        // A retina copy of the same picture keeps its aspect ratio.
+        assert!(flat(200, 120).matches(&flat(400, 240), 0));
+        assert!(flat(200, 120).matches(&flat(401, 240), 0));
+    }
+
+    #[test]
+    fn test_mean_color_round_trips_as_hex() {
+        assert_eq!(format_mean_color([255, 8, 170]), "#ff08aa");
+        assert_eq!(parse_mean_color("#ff08aa"), Some([255, 8, 170]));
+        assert_eq!(parse_mean_color("ff08aa"), None);
+        assert_eq!(parse_mean_color("#ff08"), None);
+    }
+
+    #[test]
+    fn test_phash_round_trips_as_hex() {
+        let phash = 0x00ff_0000_1234_abcd;
+        assert_eq!(format_phash(phash), "00ff00001234abcd");
+        assert_eq!(parse_phash(&format_phash(phash)), Some(phash));
+        assert_eq!(parse_phash("not hex"), None);
+    }
+
+    #[test]
+    fn test_lossless_webp_only_when_smaller() {
+        let png = gradient_png();
+        let webp = lossless_webp(&png).unwrap().unwrap();
+        assert!(webp.len() < png.len());
+        let original = image::load_from_memory(&png).unwrap().to_rgb8();
+        let round_trip = image::load_from_memory(&webp).unwrap().to_rgb8();
+        assert_eq!(original, round_trip);
+
+        assert!(lossless_webp(&webp).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_thumbnail_skips_short_images() {
+        assert!(thumbnail_png(&png_of_size(50, 40), 120).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_data_url_is_valid_and_round_trips() {
+        let png = png_of_size(4, 3);
+        let uri = encode_data_url("image/png", &png);
+        let payload = uri.strip_prefix("data:image/png;base64,").unwrap();
+        assert!(!payload.contains(char::is_whitespace));
+
+        let (mime, bytes) = decode_data_url(&uri).unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(bytes, png);
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+    }
+}
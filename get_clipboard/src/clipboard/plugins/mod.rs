@@ -1,25 +1,36 @@
 mod files;
 mod html;
-mod image;
+pub(crate) mod image;
+mod passthrough;
 mod rtf;
 mod text;
 
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::fs;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::Once;
 
 use anyhow::{anyhow, bail, Context, Result};
 use clipboard_rs::common::ClipboardContent;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
 
+use crate::clipboard::formats::platform_content;
 use crate::clipboard::snapshot::{ClipboardSnapshot, FileOutput};
+use crate::config::model::{AppConfig, MixedCapturePrimary};
 use crate::data::model::EntryMetadata;
+use crate::fs::layout;
 use crate::util::hash::sha256_bytes;
 
-pub use files::FILES_PLUGIN;
+pub use files::{FILES_PLUGIN, ReplayReport, is_stashed_file, refresh_records};
 pub use html::HTML_PLUGIN;
 pub use image::IMAGE_PLUGIN;
+pub use passthrough::PASSTHROUGH_PLUGIN;
 pub use rtf::RTF_PLUGIN;
 pub use text::TEXT_PLUGIN;
 
@@ -29,7 +40,7 @@ pub trait ClipboardPlugin: Sync + Send {
     fn priority(&self) -> u8;
     fn entry_kind(&self) -> crate::data::model::EntryKind;
     fn matches(&self, snapshot: &ClipboardSnapshot) -> bool;
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture>;
+    fn capture(&self, snapshot: &ClipboardSnapshot, ctx: &CaptureContext) -> Option<PluginCapture>;
     fn to_clipboard_items(&self, ctx: &PluginContext<'_>) -> Result<Vec<ClipboardContent>>;
     fn display_content(&self, ctx: &PluginContext<'_>) -> Result<DisplayContent>;
     fn export_json(&self, ctx: &PluginContext<'_>) -> Result<Value>;
@@ -146,6 +157,29 @@ impl StoredFile {
     }
 }
 
+/// Settings that shape what is read from the pasteboard and what plugins
+/// capture from it, read from the config once by whoever takes the snapshot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptureContext {
+    /// Compute a perceptual hash for images, see `image_phash`.
+    pub image_phash: bool,
+    /// Capture copied symlinks as their targets, see `follow_symlinks`.
+    pub follow_symlinks: bool,
+    /// Keep unknown pasteboard formats up to this size, see
+    /// `custom_format_max_bytes`.
+    pub custom_format_limit: Option<usize>,
+}
+
+impl CaptureContext {
+    pub fn from_config(config: &AppConfig) -> Self {
+        CaptureContext {
+            image_phash: config.computes_image_phash(),
+            follow_symlinks: config.follows_symlinks(),
+            custom_format_limit: config.custom_format_limit(),
+        }
+    }
+}
+
 pub struct PluginContext<'a> {
     pub metadata: &'a EntryMetadata,
     pub plugin_meta: &'a Value,
@@ -187,6 +221,10 @@ pub struct ClipboardJsonItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub copyCount: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub pasteCount: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastPasted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub detectedFormats: Option<Vec<String>>,
 }
 
@@ -221,12 +259,20 @@ pub struct ClipboardJsonFullItem {
     pub first_date: Option<String>,
     #[serde(default)]
     pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary_override: Option<String>,
     #[serde(rename = "type", default)]
     pub item_type: Option<String>,
     #[serde(default)]
     pub size: Option<u64>,
     #[serde(default)]
     pub copy_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub copy_events: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paste_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_pasted: Option<String>,
     #[serde(default)]
     pub detected_formats: Vec<String>,
     #[serde(default)]
@@ -265,6 +311,7 @@ static REGISTRY: Lazy<Vec<&'static dyn ClipboardPlugin>> = Lazy::new(|| {
         TEXT_PLUGIN as &'static dyn ClipboardPlugin,
         HTML_PLUGIN as &'static dyn ClipboardPlugin,
         RTF_PLUGIN as &'static dyn ClipboardPlugin,
+        PASSTHROUGH_PLUGIN as &'static dyn ClipboardPlugin,
     ]
 });
 
@@ -279,26 +326,213 @@ pub fn plugin_by_id(id: &str) -> Option<&'static dyn ClipboardPlugin> {
         .find(|plugin| plugin.id() == id)
 }
 
-pub fn capture_plugins(snapshot: &ClipboardSnapshot) -> Vec<PluginCapture> {
+/// Plugins that panicked while capturing. They stay off until the process
+/// restarts so one bad plugin can't take the watcher down on every copy.
+static DISABLED_PLUGINS: Lazy<Mutex<HashSet<&'static str>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Data directory the panic hook leaves a crashing plugin's id in, set by
+/// [`start_safe_mode`].
+static CRASH_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+thread_local! {
+    /// The plugin this thread is running `matches`/`capture` for.
+    static CAPTURING: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Release builds abort on panic, so `catch_unwind` in [`capture_with`] only
+/// saves the watcher in debug builds. For the rest, a panic hook records
+/// which plugin was capturing before the process dies, and the restarted
+/// watcher calls this to start with that plugin disabled. Nothing is left
+/// half-written by the abort: captures are only stored after every plugin
+/// has returned.
+pub fn start_safe_mode(data_dir: &Path) {
+    if let Some(plugin) = take_crashed_plugin(data_dir) {
+        eprintln!(
+            "Plugin '{}' crashed the last run; starting with it disabled",
+            plugin.id()
+        );
+        DISABLED_PLUGINS.lock().insert(plugin.id());
+    }
+    if CRASH_DIR.set(data_dir.to_path_buf()).is_err() {
+        return;
+    }
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Some(data_dir) = CRASH_DIR.get() {
+                record_crashed_plugin(data_dir);
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Leaves the id of the plugin this thread is capturing with, if any.
+fn record_crashed_plugin(data_dir: &Path) {
+    if let Some(id) = CAPTURING.get() {
+        let _ = fs::write(layout::crashed_plugin_path(data_dir), id);
+    }
+}
+
+/// The plugin a previous run crashed in. The marker is removed, so the
+/// plugin is only skipped for one run.
+fn take_crashed_plugin(data_dir: &Path) -> Option<&'static dyn ClipboardPlugin> {
+    let marker = layout::crashed_plugin_path(data_dir);
+    let id = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+    plugin_by_id(id.trim())
+}
+
+pub fn capture_plugins(snapshot: &ClipboardSnapshot, ctx: &CaptureContext) -> Vec<PluginCapture> {
+    capture_with(plugin_registry(), snapshot, ctx, &DISABLED_PLUGINS)
+}
+
+fn capture_with(
+    plugins: &[&dyn ClipboardPlugin],
+    snapshot: &ClipboardSnapshot,
+    ctx: &CaptureContext,
+    disabled: &Mutex<HashSet<&'static str>>,
+) -> Vec<PluginCapture> {
     let mut captures = Vec::new();
-    for plugin in plugin_registry() {
-        if plugin.matches(snapshot) {
-            if let Some(mut capture) = plugin.capture(snapshot) {
-                capture.finalize_metadata();
-                if capture.search_text.is_none() {
-                    capture.search_text = plugin.searchable_text(snapshot, &capture);
-                }
-                captures.push(capture);
+    for plugin in plugins {
+        if disabled.lock().contains(plugin.id()) {
+            continue;
+        }
+        CAPTURING.set(Some(plugin.id()));
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            if !plugin.matches(snapshot) {
+                return None;
+            }
+            let mut capture = plugin.capture(snapshot, ctx)?;
+            capture.finalize_metadata();
+            if capture.search_text.is_none() {
+                capture.search_text = plugin.searchable_text(snapshot, &capture);
+            }
+            Some(capture)
+        }));
+        CAPTURING.set(None);
+        match outcome {
+            Ok(Some(capture)) => captures.push(capture),
+            Ok(None) => {}
+            Err(payload) => {
+                eprintln!(
+                    "Plugin '{}' panicked during capture ({}); disabling it until restart",
+                    plugin.id(),
+                    panic_message(payload.as_ref())
+                );
+                disabled.lock().insert(plugin.id());
             }
         }
     }
     captures
 }
 
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Keeps at most `max` captures, dropping the lowest-priority ones first while
+/// preserving the original order. The primary capture is always retained.
+pub fn limit_captures(captures: Vec<PluginCapture>, max: usize) -> Vec<PluginCapture> {
+    let max = max.max(1);
+    if captures.len() <= max {
+        return captures;
+    }
+    let mut by_priority: Vec<usize> = (0..captures.len()).collect();
+    by_priority.sort_by_key(|&idx| captures[idx].priority);
+    let keep: HashSet<usize> = by_priority.into_iter().take(max).collect();
+    captures
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| keep.contains(idx))
+        .map(|(_, capture)| capture)
+        .collect()
+}
+
+/// Tags that only wrap or lay out text. HTML made of nothing else adds no
+/// formatting a plain-text paste would lose.
+const TRIVIAL_HTML_TAGS: &[&str] = &["html", "head", "body", "meta", "div", "span", "p", "br"];
+
+/// Drops the snapshot's HTML when it is redundant with its plain text, see
+/// [`html_is_redundant`]. Run before hashing, so the copy is keyed as if the
+/// HTML had never been there.
+pub fn drop_redundant_html(snapshot: &mut ClipboardSnapshot) {
+    let (Some(html), Some(text)) = (snapshot.html.as_deref(), snapshot.text.as_deref()) else {
+        return;
+    };
+    if html_is_redundant(html, text) {
+        snapshot.html = None;
+    }
+}
+
+/// True when `html` only uses trivial wrapper tags, none of them styled,
+/// and its visible text equals `text` once entities are decoded and
+/// whitespace is collapsed.
+pub fn html_is_redundant(html: &str, text: &str) -> bool {
+    let only_trivial_tags = html_tags(html).all(|(name, attributes)| {
+        name.is_empty()
+            || name.starts_with('!')
+            || (TRIVIAL_HTML_TAGS.contains(&name.as_str()) && !has_styling_attribute(attributes))
+    });
+    if !only_trivial_tags {
+        return false;
+    }
+    let visible = html_escape::decode_html_entities(&strip_tags(html)).into_owned();
+    collapse_whitespace(&visible) == collapse_whitespace(text)
+}
+
+/// Lowercased names of the tags in `html`, closing tags included, each with
+/// the rest of the tag up to `>`. Comments and doctypes keep their leading `!`.
+fn html_tags(html: &str) -> impl Iterator<Item = (String, &str)> + '_ {
+    html.split('<').skip(1).map(|tag| {
+        let tag = tag.trim_start_matches('/');
+        let end = tag
+            .char_indices()
+            .find(|(idx, ch)| !(ch.is_ascii_alphanumeric() || (*idx == 0 && *ch == '!')))
+            .map_or(tag.len(), |(idx, _)| idx);
+        let rest = &tag[end..];
+        let attributes = rest.split('>').next().unwrap_or_default();
+        (tag[..end].to_ascii_lowercase(), attributes)
+    })
+}
+
+/// A `style` or `class` attribute means the tag formats its text, e.g. the
+/// colored `<span>`s editors copy, so it is no longer a plain wrapper.
+fn has_styling_attribute(attributes: &str) -> bool {
+    attributes.split_whitespace().any(|attribute| {
+        let name = attribute.split('=').next().unwrap_or_default();
+        name.eq_ignore_ascii_case("style") || name.eq_ignore_ascii_case("class")
+    })
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 pub fn prioritized_capture<'a>(captures: &'a [PluginCapture]) -> Option<&'a PluginCapture> {
     captures.iter().min_by_key(|capture| capture.priority)
 }
 
+/// Like [`prioritized_capture`], except that a copy carrying both a file
+/// list and text is led by the text when `mixed` asks for it.
+pub fn primary_capture(
+    captures: &[PluginCapture],
+    mixed: MixedCapturePrimary,
+) -> Option<&PluginCapture> {
+    let has_files = captures.iter().any(|capture| capture.plugin_id == "files");
+    let text = captures.iter().find(|capture| capture.plugin_id == "text");
+    match text {
+        Some(text) if has_files && mixed == MixedCapturePrimary::Text => Some(text),
+        _ => prioritized_capture(captures),
+    }
+}
+
 pub fn plugin_order(captures: &[PluginCapture]) -> Vec<String> {
     captures
         .iter()
@@ -306,12 +540,44 @@ pub fn plugin_order(captures: &[PluginCapture]) -> Vec<String> {
         .collect()
 }
 
+/// [`plugin_order`] with `primary` moved to the front, so display and copy
+/// fall back to the same format the entry is summarized by.
+pub fn plugin_order_led_by(captures: &[PluginCapture], primary: &str) -> Vec<String> {
+    let mut order = plugin_order(captures);
+    if let Some(pos) = order.iter().position(|id| id == primary) {
+        let lead = order.remove(pos);
+        order.insert(0, lead);
+    }
+    order
+}
+
 pub fn rebuild_clipboard_contents(
     metadata: &EntryMetadata,
     item_dir: &Path,
 ) -> Result<Vec<ClipboardContent>> {
-    let (order, map) = extract_plugin_meta(metadata)?
+    rebuild_clipboard_formats(metadata, item_dir, None)
+}
+
+/// [`rebuild_clipboard_contents`] limited to the plugins named in `formats`.
+/// The entry's own plugin order is kept whatever order they are listed in,
+/// and a format the entry doesn't have is an error rather than a silent gap.
+pub fn rebuild_clipboard_formats(
+    metadata: &EntryMetadata,
+    item_dir: &Path,
+    formats: Option<&[String]>,
+) -> Result<Vec<ClipboardContent>> {
+    let (mut order, map) = extract_plugin_meta(metadata)?
         .ok_or_else(|| anyhow!("Missing plugin metadata for {}", metadata.hash))?;
+    if let Some(formats) = formats {
+        if let Some(missing) = formats.iter().find(|id| !order.contains(id)) {
+            bail!(
+                "Item {} has no '{missing}' format, available: {}",
+                metadata.hash,
+                order.join(", ")
+            );
+        }
+        order.retain(|id| formats.contains(id));
+    }
 
     let mut results = Vec::new();
     for plugin_id in order {
@@ -321,11 +587,35 @@ pub fn rebuild_clipboard_contents(
         let plugin = plugin_by_id(&plugin_id)
             .ok_or_else(|| anyhow!("Unknown clipboard plugin {plugin_id}"))?;
         let instance = PluginInstance::new(plugin, metadata, item_dir, plugin_meta)?;
-        results.extend(plugin.to_clipboard_items(&instance.context())?);
+        results.extend(
+            plugin
+                .to_clipboard_items(&instance.context())?
+                .into_iter()
+                .map(to_platform_content),
+        );
     }
     Ok(results)
 }
 
+fn to_platform_content(content: ClipboardContent) -> ClipboardContent {
+    match content {
+        ClipboardContent::Other(id, bytes) => platform_content(&id, bytes),
+        other => other,
+    }
+}
+
+/// The plugin `build_display_content_with_preference` and
+/// `build_json_item_with_preference` try first: an explicit choice such as
+/// `show --html` wins, otherwise the configured `preferred_plugins` entry for
+/// the item's kind.
+pub fn preferred_plugin<'a>(
+    explicit: Option<&'a str>,
+    config: &'a AppConfig,
+    metadata: &EntryMetadata,
+) -> Option<&'a str> {
+    explicit.or_else(|| config.preferred_plugin(&metadata.kind))
+}
+
 pub fn build_display_content(metadata: &EntryMetadata, item_dir: &Path) -> Result<DisplayContent> {
     build_display_content_with_preference(metadata, item_dir, None)
 }
@@ -354,6 +644,9 @@ pub fn build_display_content_with_preference(
 }
 
 pub fn build_summary(metadata: &EntryMetadata, item_dir: &Path, is_tty: bool) -> Option<String> {
+    if let Some(custom) = &metadata.summary_override {
+        return Some(custom.clone());
+    }
     let (order, map) = match extract_plugin_meta(metadata) {
         Ok(Some(result)) => result,
         _ => return metadata.summary.clone(),
@@ -426,6 +719,8 @@ fn json_with_plugin(
         data,
         summary: metadata.summary.clone(),
         copyCount: Some(metadata.copy_count),
+        pasteCount: Some(metadata.paste_count),
+        lastPasted: metadata.last_pasted.map(crate::util::time::format_iso),
         detectedFormats: Some(metadata.detected_formats.clone()),
     }))
 }
@@ -530,9 +825,13 @@ pub fn build_full_json_item(
         date: Some(crate::util::time::format_iso(metadata.last_seen)),
         first_date: Some(crate::util::time::format_iso(metadata.first_seen)),
         summary: metadata.summary.clone(),
+        summary_override: metadata.summary_override.clone(),
         item_type: Some(format!("{:?}", metadata.kind)),
         size: Some(metadata.byte_size),
         copy_count: Some(metadata.copy_count),
+        copy_events: metadata.copy_events.clone(),
+        paste_count: Some(metadata.paste_count),
+        last_pasted: metadata.last_pasted.map(crate::util::time::format_iso),
         detected_formats: metadata.detected_formats.clone(),
         sources: metadata.sources.clone(),
         search_text: metadata.search_text.clone(),
@@ -541,14 +840,23 @@ pub fn build_full_json_item(
     })
 }
 
+/// Preview formats for an entry, plus the ids of formats left unrendered
+/// because they fell outside `max_formats`.
+pub struct PreviewFormats {
+    pub rendered: Vec<PreviewFormat>,
+    pub unrendered: Vec<String>,
+}
+
 pub fn build_preview_formats(
     metadata: &EntryMetadata,
     item_dir: &Path,
-) -> Result<Vec<PreviewFormat>> {
+    max_formats: Option<usize>,
+) -> Result<PreviewFormats> {
     let mut previews = Vec::new();
 
     let (order, map) = extract_plugin_meta(metadata)?
         .ok_or_else(|| anyhow!("Missing plugin metadata for {}", metadata.hash))?;
+    let (order, unrendered) = split_preview_order(order, max_formats);
 
     for plugin_id in order {
         let Some(plugin_meta) = map.get(&plugin_id) else {
@@ -587,7 +895,172 @@ pub fn build_preview_formats(
     }
 
     previews.sort_by_key(|p| p.priority);
-    Ok(previews)
+    prefer_rendered_preview(&mut previews);
+    Ok(PreviewFormats {
+        rendered: previews,
+        unrendered,
+    })
+}
+
+/// A preview format as plain JSON for clients that draw their own UI
+/// instead of embedding the rendered template. Every value carries a `type`
+/// naming what the client is looking at.
+pub fn structured_preview(format: &PreviewFormat) -> Value {
+    let data = &format.data;
+    let str_field = |key: &str| data.get(key).and_then(Value::as_str).unwrap_or_default();
+    let flag = |key: &str| data.get(key).and_then(Value::as_bool).unwrap_or(false);
+    match format.plugin_id.as_str() {
+        "text" => {
+            let text = str_field("raw_text");
+            if flag("is_color") {
+                let hex = str_field("color_value");
+                json!({ "type": "color", "hex": hex, "rgb": color_rgb(hex) })
+            } else if flag("is_url") {
+                json!({
+                    "type": "url",
+                    "url": str_field("url"),
+                    "linkPreview": data.get("link_preview"),
+                })
+            } else if flag("is_svg") {
+                json!({ "type": "svg", "markup": text })
+            } else if flag("is_json") {
+                json!({ "type": "json", "text": text, "pretty": str_field("json_pretty") })
+            } else {
+                json!({ "type": "text", "text": text })
+            }
+        }
+        "html" => json!({ "type": "html", "html": str_field("content"), "text": format.text }),
+        "rtf" => json!({
+            "type": "rtf",
+            "rtf": html_escape::decode_html_entities(str_field("content")),
+        }),
+        "image" => json!({
+            "type": "image",
+            "src": str_field("content"),
+            "thumbnail": data.get("thumbnail"),
+            "width": data.get("width"),
+            "height": data.get("height"),
+        }),
+        "files" => {
+            let files = data.get("files").and_then(Value::as_array);
+            let paths: Vec<&str> = files
+                .into_iter()
+                .flatten()
+                .filter_map(|file| file.get("path").and_then(Value::as_str))
+                .collect();
+            json!({
+                "type": "files",
+                "paths": paths,
+                "files": files,
+                "images": data.get("images"),
+            })
+        }
+        "passthrough" => json!({ "type": "formats", "formats": data.get("formats") }),
+        other => json!({ "type": other, "data": data }),
+    }
+}
+
+/// `[r, g, b]` for `#rgb`, `#rrggbb` and `rgb()`/`rgba()` colors; `None`
+/// for anything else, such as `hsl()`.
+fn color_rgb(color: &str) -> Option<[u8; 3]> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+        return match hex.len() {
+            3 => {
+                let mut rgb = [0; 3];
+                for (slot, digit) in rgb.iter_mut().zip(hex.chars()) {
+                    *slot = channel(&digit.to_string())? * 17;
+                }
+                Some(rgb)
+            }
+            6 => Some([
+                channel(hex.get(0..2)?)?,
+                channel(hex.get(2..4)?)?,
+                channel(hex.get(4..6)?)?,
+            ]),
+            _ => None,
+        };
+    }
+    let lower = color.to_ascii_lowercase();
+    let args = lower
+        .strip_prefix("rgba")
+        .or_else(|| lower.strip_prefix("rgb"))?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?;
+    let mut channels = args
+        .split([',', ' ', '/'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<f64>()
+                .ok()
+                .map(|v| v.clamp(0.0, 255.0).round() as u8)
+        });
+    Some([channels.next()??, channels.next()??, channels.next()??])
+}
+
+/// Splits plugin ids into the `max` with the best preview order, which get
+/// rendered, and the rest. Unknown plugins sort last.
+fn split_preview_order(mut order: Vec<String>, max: Option<usize>) -> (Vec<String>, Vec<String>) {
+    let Some(max) = max else {
+        return (order, Vec::new());
+    };
+    order.sort_by_key(|id| {
+        plugin_by_id(id).map_or(u8::MAX, |plugin| plugin.get_preview_format_order())
+    });
+    let rest = order.split_off(max.max(1).min(order.len()));
+    (order, rest)
+}
+
+/// Plugins whose preview renders the content instead of showing its source.
+const RENDERED_PREVIEW_PLUGINS: &[&str] = &["html"];
+
+/// Leads with the rendered format when an item also has plain text, unless
+/// the rendering shows much less than the text does (an empty wrapper or a
+/// lone image tag), in which case the plain text is the better default.
+fn prefer_rendered_preview(previews: &mut Vec<PreviewFormat>) {
+    let Some(text_idx) = previews.iter().position(|p| p.plugin_id == "text") else {
+        return;
+    };
+    let Some(rendered_idx) = previews
+        .iter()
+        .position(|p| RENDERED_PREVIEW_PLUGINS.contains(&p.plugin_id.as_str()))
+    else {
+        return;
+    };
+    let text_len = previews[text_idx].text.as_deref().map_or(0, readable_len);
+    let rendered_len = previews[rendered_idx]
+        .text
+        .as_deref()
+        .map_or(0, |html| readable_len(&strip_tags(html)));
+    let rendered_is_richer = rendered_len * 2 >= text_len;
+
+    let (from, to) = match (rendered_is_richer, rendered_idx > text_idx) {
+        (true, true) => (rendered_idx, text_idx),
+        (false, false) => (text_idx, rendered_idx),
+        _ => return,
+    };
+    let moved = previews.remove(from);
+    previews.insert(to, moved);
+}
+
+fn readable_len(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
 }
 
 pub fn prepare_import(item: &ClipboardJsonFullItem) -> Result<ClipboardJsonImport> {
@@ -612,6 +1085,21 @@ pub fn prepare_import(item: &ClipboardJsonFullItem) -> Result<ClipboardJsonImpor
     })
 }
 
+/// Recomputes an entry's summary from its stored formats using the current
+/// capture logic, as if it had just been copied.
+pub fn regenerate_summary(metadata: &EntryMetadata, item_dir: &Path) -> Result<Option<String>> {
+    let item = build_full_json_item(metadata, item_dir, None, None)?;
+    let import = prepare_import(&item)?;
+    Ok(prioritized_capture(&import.captures)
+        .and_then(|capture| capture.summary.clone())
+        .or_else(|| {
+            import
+                .captures
+                .iter()
+                .find_map(|capture| capture.summary.clone())
+        }))
+}
+
 pub fn compute_json_item_hash(item: &ClipboardJsonFullItem) -> Result<String> {
     let mut normalized = Vec::new();
     for format in &item.formats {
@@ -627,6 +1115,63 @@ pub fn compute_json_item_hash(item: &ClipboardJsonFullItem) -> Result<String> {
     Ok(sha256_bytes(&bytes))
 }
 
+/// String fields that describe a format rather than hold copied content, so
+/// a redacted export keeps them as-is.
+const REDACTION_KEPT_KEYS: &[&str] = &["mime", "uti", "extension", "kind"];
+
+/// A copy of `item` with everything copied replaced by placeholders: text,
+/// image and file data, summaries, search text and file names or paths,
+/// including the source paths of copied files.
+/// Ids, kinds, sizes, timestamps, counts and format lists are kept, so the
+/// result shows the shape of a history without its contents.
+pub fn redact_full_item(item: &ClipboardJsonFullItem) -> ClipboardJsonFullItem {
+    ClipboardJsonFullItem {
+        summary: item.summary.as_deref().map(redacted),
+        summary_override: item.summary_override.as_deref().map(redacted),
+        search_text: item.search_text.as_deref().map(redacted),
+        data_path: item.data_path.as_deref().map(redacted),
+        sources: item.sources.iter().map(|source| redacted(source)).collect(),
+        formats: item
+            .formats
+            .iter()
+            .map(|format| ClipboardJsonFormat {
+                data: redact_value(&format.data),
+                metadata: redact_value(&format.metadata),
+                ..format.clone()
+            })
+            .collect(),
+        ..item.clone()
+    }
+}
+
+fn redacted(value: &str) -> String {
+    format!("[redacted {} bytes]", value.len())
+}
+
+/// Replaces every string in `value` with a placeholder giving its length,
+/// except for the fields in `REDACTION_KEPT_KEYS`. Numbers, booleans and the
+/// shape of arrays and objects are kept.
+fn redact_value(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redacted(text)),
+        Value::Array(values) => Value::Array(values.iter().map(redact_value).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let kept = REDACTION_KEPT_KEYS.contains(&key.as_str()) && value.is_string();
+                    let value = if kept {
+                        value.clone()
+                    } else {
+                        redact_value(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
 pub fn extract_plugin_meta(
     metadata: &EntryMetadata,
 ) -> Result<Option<(Vec<String>, Map<String, Value>)>> {
@@ -703,3 +1248,728 @@ pub fn load_plugin_files(item_dir: &Path, plugin_meta: &Value) -> Result<Vec<Sto
     };
     Ok(stored_files)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::snapshot::CustomFormat;
+    use crate::data::model::EntryKind;
+
+    fn capture(plugin_id: &'static str, priority: u8) -> PluginCapture {
+        PluginCapture {
+            plugin_id,
+            kind: plugin_id,
+            entry_kind: EntryKind::Text,
+            priority,
+            summary: None,
+            search_text: None,
+            files: Vec::new(),
+            metadata: Value::Null,
+            byte_size: 0,
+            sources: Vec::new(),
+        }
+    }
+
+    fn preview(plugin_id: &str, priority: u8, text: &str) -> PreviewFormat {
+        PreviewFormat {
+            plugin_id: plugin_id.to_string(),
+            template_name: plugin_id.to_string(),
+            priority,
+            data: Value::Null,
+            text: Some(text.to_string()),
+        }
+    }
+
+    fn preview_ids(previews: &[PreviewFormat]) -> Vec<&str> {
+        previews.iter().map(|p| p.plugin_id.as_str()).collect()
+    }
+
+    fn data_preview(plugin_id: &str, data: Value) -> PreviewFormat {
+        PreviewFormat {
+            data,
+            text: None,
+            ..preview(plugin_id, 1, "")
+        }
+    }
+
+    #[test]
+    fn test_structured_preview_for_text_kinds() {
+        let color = data_preview(
+            "text",
+            json!({ "raw_text": "#ff8000", "is_color": true, "color_value": "#ff8000" }),
+        );
+        assert_eq!(
+            structured_preview(&color),
+            json!({ "type": "color", "hex": "#ff8000", "rgb": [255, 128, 0] })
+        );
+
+        let url = data_preview(
+            "text",
+            json!({ "raw_text": "https://example.com", "is_url": true, "url": "https://example.com" }),
+        );
+        let structured = structured_preview(&url);
+        assert_eq!(structured["type"], "url");
+        assert_eq!(structured["url"], "https://example.com");
+
+        let plain = data_preview("text", json!({ "raw_text": "hello", "is_color": false }));
+        assert_eq!(
+            structured_preview(&plain),
+            json!({ "type": "text", "text": "hello" })
+        );
+
+        let pretty = data_preview(
+            "text",
+            json!({ "raw_text": "{\"a\":1}", "is_json": true, "json_pretty": "{\n  \"a\": 1\n}" }),
+        );
+        assert_eq!(structured_preview(&pretty)["type"], "json");
+    }
+
+    #[test]
+    fn test_structured_preview_for_files_images_and_rtf() {
+        let files = data_preview(
+            "files",
+            json!({
+                "files": [
+                    { "name": "a.txt", "size": "1 KB", "path": "/tmp/a.txt" },
+                    { "name": "b.png", "size": "2 KB", "path": "/tmp/b.png" },
+                ],
+                "images": [],
+            }),
+        );
+        let structured = structured_preview(&files);
+        assert_eq!(structured["type"], "files");
+        assert_eq!(structured["paths"], json!(["/tmp/a.txt", "/tmp/b.png"]));
+
+        let image = data_preview(
+            "image",
+            json!({ "content": "data:image/png;base64,AA==", "width": 4, "height": 2 }),
+        );
+        let structured = structured_preview(&image);
+        assert_eq!(structured["type"], "image");
+        assert_eq!(structured["src"], "data:image/png;base64,AA==");
+        assert_eq!(
+            (structured["width"].as_u64(), structured["height"].as_u64()),
+            (Some(4), Some(2))
+        );
+
+        let rtf = data_preview("rtf", json!({ "content": "{\\rtf1 a &amp; b}" }));
+        assert_eq!(structured_preview(&rtf)["rtf"], "{\\rtf1 a & b}");
+
+        let passthrough = data_preview("passthrough", json!({ "formats": [] }));
+        assert_eq!(structured_preview(&passthrough)["type"], "formats");
+    }
+
+    #[test]
+    fn test_color_rgb_formats() {
+        assert_eq!(color_rgb("#fff"), Some([255, 255, 255]));
+        assert_eq!(color_rgb("#1A2b3C"), Some([26, 43, 60]));
+        assert_eq!(color_rgb("rgb(10, 20, 30)"), Some([10, 20, 30]));
+        assert_eq!(color_rgb("rgba(10 20 300 / 0.5)"), Some([10, 20, 255]));
+        assert_eq!(color_rgb("hsl(10, 20%, 30%)"), None);
+        assert_eq!(color_rgb("#12"), None);
+    }
+
+    #[test]
+    fn test_rendered_html_leads_text() {
+        let mut previews = vec![
+            preview("text", 1, "Quarterly report"),
+            preview("html", 2, "<p><b>Quarterly</b> report</p>"),
+            preview("rtf", 4, "{\\rtf1 Quarterly report}"),
+        ];
+        prefer_rendered_preview(&mut previews);
+        assert_eq!(preview_ids(&previews), vec!["html", "text", "rtf"]);
+    }
+
+    #[test]
+    fn test_sparse_html_falls_back_to_text() {
+        let mut previews = vec![
+            preview("html", 2, "<div><img src=\"logo.png\"></div>"),
+            preview("text", 3, "A long paragraph of copied prose"),
+        ];
+        prefer_rendered_preview(&mut previews);
+        assert_eq!(preview_ids(&previews), vec!["text", "html"]);
+
+        let mut text_only = vec![preview("text", 3, "plain")];
+        prefer_rendered_preview(&mut text_only);
+        assert_eq!(preview_ids(&text_only), vec!["text"]);
+    }
+
+    #[test]
+    fn test_limit_captures_drops_lowest_priority() {
+        let captures = vec![
+            capture("image", 1),
+            capture("text", 2),
+            capture("html", 3),
+            capture("rtf", 4),
+        ];
+        let kept = limit_captures(captures, 2);
+        let ids: Vec<_> = kept.iter().map(|c| c.plugin_id).collect();
+        assert_eq!(ids, vec!["image", "text"]);
+    }
+
+    #[test]
+    fn test_redundant_html_is_detected() {
+        let text = "Hello world & friends";
+        assert!(html_is_redundant(
+            "<meta charset='utf-8'><span>Hello world &amp; friends</span>",
+            text
+        ));
+        assert!(html_is_redundant(
+            "<!--StartFragment--><div><p>Hello\n  world</p> &amp; friends</div><!--EndFragment-->",
+            text
+        ));
+        assert!(!html_is_redundant("<b>Hello</b> world &amp; friends", text));
+        assert!(!html_is_redundant(
+            "<a href=\"https://example.com\">Hello world &amp; friends</a>",
+            text
+        ));
+        assert!(!html_is_redundant("<p>Hello there</p>", text));
+        assert!(!html_is_redundant(
+            "<span style=\"color: red\">Hello world &amp; friends</span>",
+            text
+        ));
+        assert!(!html_is_redundant(
+            "<div class=\"highlight\"><p>Hello world &amp; friends</p></div>",
+            text
+        ));
+        assert!(!html_is_redundant(
+            "<table><tr><td>Hello world &amp; friends</td></tr></table>",
+            text
+        ));
+    }
+
+    #[test]
+    fn test_drop_redundant_html_keeps_text_only() {
+        let mut plain = custom_snapshot(Vec::new());
+        plain.text = Some("plain words".into());
+        let mut snapshot = custom_snapshot(Vec::new());
+        snapshot.text = Some("plain words".into());
+        snapshot.html = Some("<span>plain words</span>".into());
+        drop_redundant_html(&mut snapshot);
+        assert!(snapshot.html.is_none());
+        assert_eq!(snapshot.compute_hash(), plain.compute_hash());
+        let ids: Vec<_> = capture_plugins(&snapshot, &CaptureContext::default())
+            .iter()
+            .map(|c| c.plugin_id)
+            .collect();
+        assert_eq!(ids, vec!["text"]);
+
+        snapshot.html = Some("<em>plain</em> words".into());
+        drop_redundant_html(&mut snapshot);
+        assert!(snapshot.html.is_some());
+    }
+
+    #[test]
+    fn test_limit_captures_keeps_primary() {
+        let captures = vec![capture("html", 3), capture("files", 0), capture("text", 2)];
+        let kept = limit_captures(captures.clone(), 0);
+        let ids: Vec<_> = kept.iter().map(|c| c.plugin_id).collect();
+        assert_eq!(ids, vec!["files"]);
+        assert_eq!(limit_captures(captures, 10).len(), 3);
+    }
+
+    /// Writes captures the way `persist_entry` does and returns the metadata
+    /// that would be saved alongside them.
+    fn persist_captures(captures: &[PluginCapture], item_dir: &Path) -> EntryMetadata {
+        for capture in captures {
+            for file in &capture.files {
+                fs::write(item_dir.join(&file.filename), &file.bytes).unwrap();
+            }
+        }
+        let plugins: Map<String, Value> = captures
+            .iter()
+            .map(|capture| (capture.plugin_id.to_string(), capture.metadata.clone()))
+            .collect();
+        let now = crate::util::time::now();
+        EntryMetadata {
+            hash: "custom".into(),
+            kind: EntryKind::Other,
+            detected_formats: vec![],
+            copy_count: 1,
+            first_seen: now,
+            last_seen: now,
+            byte_size: 0,
+            sources: vec![],
+            summary: None,
+            summary_override: None,
+            primary_plugin: None,
+            search_text: None,
+            version: "test".into(),
+            relative_path: String::new(),
+            content_filename: String::new(),
+            files: vec![],
+            extra: serde_json::json!({
+                "plugins": plugins,
+                "pluginOrder": plugin_order(captures),
+            }),
+            tags: vec![],
+            pinned: false,
+            paste_count: 0,
+            last_pasted: None,
+            source_app: None,
+            copy_events: Vec::new(),
+        }
+    }
+
+    #[derive(Default)]
+    struct PanickingPlugin {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ClipboardPlugin for PanickingPlugin {
+        fn id(&self) -> &'static str {
+            "panicking"
+        }
+        fn kind(&self) -> &'static str {
+            "panicking"
+        }
+        fn priority(&self) -> u8 {
+            0
+        }
+        fn entry_kind(&self) -> EntryKind {
+            EntryKind::Other
+        }
+        fn matches(&self, _snapshot: &ClipboardSnapshot) -> bool {
+            true
+        }
+        fn capture(
+            &self,
+            _snapshot: &ClipboardSnapshot,
+            _ctx: &CaptureContext,
+        ) -> Option<PluginCapture> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            panic!("deliberate test panic");
+        }
+        fn to_clipboard_items(&self, _ctx: &PluginContext<'_>) -> Result<Vec<ClipboardContent>> {
+            Ok(Vec::new())
+        }
+        fn display_content(&self, _ctx: &PluginContext<'_>) -> Result<DisplayContent> {
+            Ok(DisplayContent::Empty)
+        }
+        fn export_json(&self, _ctx: &PluginContext<'_>) -> Result<Value> {
+            Ok(Value::Null)
+        }
+        fn import_json(&self, _format: &ClipboardJsonFormat) -> Result<PluginImport> {
+            bail!("not importable")
+        }
+        fn detail_log(&self, _ctx: &PluginContext<'_>) -> Result<Vec<(String, String)>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_panicking_plugin_is_disabled_and_others_still_run() {
+        let mut snapshot = custom_snapshot(Vec::new());
+        snapshot.kind = EntryKind::Text;
+        snapshot.text = Some("still captured".into());
+        let panicking = PanickingPlugin::default();
+        let plugins: [&dyn ClipboardPlugin; 2] = [&panicking, TEXT_PLUGIN];
+        let disabled = Mutex::new(HashSet::new());
+
+        let captures = capture_with(&plugins, &snapshot, &CaptureContext::default(), &disabled);
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].plugin_id, "text");
+        assert!(disabled.lock().contains("panicking"));
+
+        // Skipped from then on instead of panicking again.
+        let captures = capture_with(&plugins, &snapshot, &CaptureContext::default(), &disabled);
+        assert_eq!(captures.len(), 1);
+        assert_eq!(panicking.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(CAPTURING.get(), None);
+    }
+
+    #[test]
+    fn test_plugin_that_crashed_the_last_run_is_taken_once() {
+        let dir = tempfile::tempdir().unwrap();
+        // Outside a capture, a panic names no plugin.
+        record_crashed_plugin(dir.path());
+        assert!(take_crashed_plugin(dir.path()).is_none());
+
+        CAPTURING.set(Some("html"));
+        record_crashed_plugin(dir.path());
+        CAPTURING.set(None);
+        assert_eq!(
+            take_crashed_plugin(dir.path()).map(|plugin| plugin.id()),
+            Some("html")
+        );
+        assert!(take_crashed_plugin(dir.path()).is_none());
+    }
+
+    fn custom_snapshot(formats: Vec<CustomFormat>) -> ClipboardSnapshot {
+        let mut snapshot: ClipboardSnapshot = serde_json::from_value(serde_json::json!({
+            "kind": "Other",
+            "text": null,
+            "html": null,
+            "rtf": null,
+            "image_bytes": null,
+            "image_mime": null,
+            "files": [],
+            "summary": null,
+            "detected_formats": [],
+            "extra": null,
+        }))
+        .unwrap();
+        snapshot.custom_formats = formats;
+        snapshot
+    }
+
+    fn other_contents(contents: Vec<ClipboardContent>) -> Vec<(String, Vec<u8>)> {
+        contents
+            .into_iter()
+            .filter_map(|content| match content {
+                ClipboardContent::Other(uti, bytes) => Some((uti, bytes)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_configured_preference_selects_plugin() {
+        let mut snapshot = custom_snapshot(Vec::new());
+        snapshot.text = Some("Quarterly report".into());
+        snapshot.html = Some("<p><b>Quarterly</b> report</p>".into());
+        let captures = capture_plugins(&snapshot, &CaptureContext::default());
+        let dir = tempfile::tempdir().unwrap();
+        let mut metadata = persist_captures(&captures, dir.path());
+        metadata.kind = EntryKind::Text;
+
+        let item_type = |preferred| {
+            build_json_item_with_preference(&metadata, dir.path(), 0, preferred, None)
+                .unwrap()
+                .item_type
+        };
+        let unconfigured = AppConfig::default();
+        assert_eq!(
+            item_type(preferred_plugin(None, &unconfigured, &metadata)),
+            "text"
+        );
+
+        let config = AppConfig {
+            preferred_plugins: Some(
+                [("Text".to_string(), "html".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..AppConfig::default()
+        };
+        assert_eq!(preferred_plugin(None, &config, &metadata), Some("html"));
+        assert_eq!(
+            item_type(preferred_plugin(None, &config, &metadata)),
+            "html"
+        );
+        let display = build_display_content_with_preference(
+            &metadata,
+            dir.path(),
+            preferred_plugin(None, &config, &metadata),
+        )
+        .unwrap();
+        assert!(matches!(display, DisplayContent::Text(text) if text.contains("<b>")));
+
+        // An explicit flag still wins over the configured default.
+        assert_eq!(
+            item_type(preferred_plugin(Some("text"), &config, &metadata)),
+            "text"
+        );
+
+        // Kinds without a configured plugin keep the usual order.
+        metadata.kind = EntryKind::Image;
+        assert_eq!(preferred_plugin(None, &config, &metadata), None);
+    }
+
+    #[test]
+    fn test_mixed_files_and_text_keep_both_and_honor_primary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, b"%PDF").unwrap();
+        let mut snapshot = custom_snapshot(Vec::new());
+        snapshot.files = vec![crate::clipboard::snapshot::FileRecord::from_path(&path, 4)];
+        snapshot.text = Some("report.pdf".into());
+
+        let captures = capture_plugins(&snapshot, &CaptureContext::default());
+        assert_eq!(plugin_order(&captures), vec!["files", "text"]);
+        let primary = primary_capture(&captures, MixedCapturePrimary::Files).unwrap();
+        assert_eq!(primary.plugin_id, "files");
+        assert_eq!(
+            plugin_order_led_by(&captures, primary.plugin_id),
+            vec!["files", "text"]
+        );
+
+        let primary = primary_capture(&captures, MixedCapturePrimary::Text).unwrap();
+        assert_eq!(primary.plugin_id, "text");
+        assert_eq!(primary.entry_kind, EntryKind::Text);
+        assert_eq!(
+            plugin_order_led_by(&captures, primary.plugin_id),
+            vec!["text", "files"]
+        );
+
+        // Either way both formats are stored and put back on copy.
+        let item_dir = tempfile::tempdir().unwrap();
+        let metadata = persist_captures(&captures, item_dir.path());
+        let contents = rebuild_clipboard_contents(&metadata, item_dir.path()).unwrap();
+        let has_files = contents
+            .iter()
+            .any(|content| matches!(content, ClipboardContent::Files(urls) if urls.len() == 1));
+        let has_text = contents
+            .iter()
+            .any(|content| matches!(content, ClipboardContent::Text(text) if text == "report.pdf"));
+        assert!(has_files && has_text);
+    }
+
+    #[test]
+    fn test_text_primary_needs_a_file_list() {
+        let captures = vec![capture("image", 1), capture("text", 2)];
+        assert_eq!(
+            primary_capture(&captures, MixedCapturePrimary::Text)
+                .unwrap()
+                .plugin_id,
+            "image"
+        );
+    }
+
+    #[test]
+    fn test_custom_uti_bytes_survive_capture_store_copy() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let snapshot = custom_snapshot(vec![
+            CustomFormat {
+                uti: "com.example.app-state".into(),
+                bytes: bytes.clone(),
+            },
+            CustomFormat {
+                uti: "public.url".into(),
+                bytes: b"https://example.com".to_vec(),
+            },
+        ]);
+        let captures = capture_plugins(&snapshot, &CaptureContext::default());
+        assert_eq!(plugin_order(&captures), vec!["passthrough"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = persist_captures(&captures, dir.path());
+        let contents = rebuild_clipboard_contents(&metadata, dir.path()).unwrap();
+        assert_eq!(
+            other_contents(contents),
+            vec![
+                ("com.example.app-state".to_string(), bytes),
+                ("public.url".to_string(), b"https://example.com".to_vec()),
+            ]
+        );
+    }
+
+    fn content_kind(content: &ClipboardContent) -> &'static str {
+        match content {
+            ClipboardContent::Text(_) => "text",
+            ClipboardContent::Html(_) => "html",
+            ClipboardContent::Rtf(_) => "rtf",
+            _ => "other",
+        }
+    }
+
+    fn report_snapshot() -> ClipboardSnapshot {
+        let mut snapshot = custom_snapshot(Vec::new());
+        snapshot.text = Some("Quarterly report".into());
+        snapshot.html = Some("<p><b>Quarterly</b> report</p>".into());
+        snapshot.rtf = Some(r"{\rtf1 Quarterly report}".into());
+        snapshot
+    }
+
+    #[test]
+    fn test_rebuild_selected_formats_keeps_entry_order() {
+        let captures = capture_plugins(&report_snapshot(), &CaptureContext::default());
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = persist_captures(&captures, dir.path());
+
+        let all = rebuild_clipboard_contents(&metadata, dir.path()).unwrap();
+        assert_eq!(all.len(), 3);
+        let expected: Vec<_> = all
+            .iter()
+            .map(content_kind)
+            .filter(|kind| *kind != "html")
+            .collect();
+
+        // Listed backwards, but copied in the entry's own order.
+        let formats = ids(&["text", "rtf"]);
+        let selected = rebuild_clipboard_formats(&metadata, dir.path(), Some(&formats)).unwrap();
+        let reversed = ids(&["rtf", "text"]);
+        let reordered = rebuild_clipboard_formats(&metadata, dir.path(), Some(&reversed)).unwrap();
+        assert_eq!(
+            selected.iter().map(content_kind).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            reordered.iter().map(content_kind).collect::<Vec<_>>(),
+            expected
+        );
+
+        let missing = ids(&["text", "image"]);
+        assert!(rebuild_clipboard_formats(&metadata, dir.path(), Some(&missing)).is_err());
+    }
+
+    #[test]
+    fn test_selected_formats_read_back_under_the_subset_hash() {
+        let snapshot = report_snapshot();
+        let captures = capture_plugins(&snapshot, &CaptureContext::default());
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = persist_captures(&captures, dir.path());
+
+        let formats = ids(&["text", "html"]);
+        let selected = rebuild_clipboard_formats(&metadata, dir.path(), Some(&formats)).unwrap();
+        let copied = ClipboardSnapshot::from_contents(&selected, true);
+        let mut subset = report_snapshot();
+        subset.rtf = None;
+        assert_eq!(copied.storage_hash(false), subset.compute_hash());
+        assert_ne!(copied.storage_hash(false), snapshot.compute_hash());
+    }
+
+    #[test]
+    fn test_custom_uti_round_trips_through_json_export() {
+        let snapshot = custom_snapshot(vec![CustomFormat {
+            uti: "com.example.app-state".into(),
+            bytes: vec![0, 159, 146, 150],
+        }]);
+        let captures = capture_plugins(&snapshot, &CaptureContext::default());
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = persist_captures(&captures, dir.path());
+
+        let item = build_full_json_item(&metadata, dir.path(), None, None).unwrap();
+        let import = prepare_import(&item).unwrap();
+        assert_eq!(
+            other_contents(import.clipboard_contents),
+            vec![("com.example.app-state".to_string(), vec![0, 159, 146, 150])]
+        );
+        assert_eq!(import.captures[0].files[0].bytes, vec![0, 159, 146, 150]);
+    }
+
+    fn exported_item() -> ClipboardJsonFullItem {
+        serde_json::from_value(json!({
+            "id": "abc123",
+            "date": "2024-03-10T12:00:00Z",
+            "firstDate": "2024-03-09T08:30:00Z",
+            "summary": "meet at the secret spot",
+            "type": "File",
+            "size": 4096,
+            "copyCount": 3,
+            "copyEvents": [1710000000000i64],
+            "detectedFormats": ["public.file-url", "public.utf8-plain-text"],
+            "sources": ["/Users/jane/taxes.pdf"],
+            "searchText": "meet at the secret spot",
+            "dataPath": "/Users/jane/Library/Application Support/clipboard/data/abc123",
+            "formats": [
+                {
+                    "pluginId": "text",
+                    "kind": "text",
+                    "priority": 1,
+                    "data": "meet at the secret spot",
+                    "metadata": { "length": 23, "counts": { "lines": 1, "words": 5 } },
+                },
+                {
+                    "pluginId": "image",
+                    "kind": "image",
+                    "data": "data:image/png;base64,iVBORw0KGgoSECRETPIXELS",
+                    "metadata": {
+                        "mime": "image/png",
+                        "width": 640,
+                        "height": 480,
+                        "phash": "f0f0e1e1c3c38787",
+                    },
+                },
+                {
+                    "pluginId": "files",
+                    "kind": "files",
+                    "data": [{
+                        "name": "taxes.pdf",
+                        "extension": "pdf",
+                        "size": 4096,
+                        "source_path": "/Users/jane/taxes.pdf",
+                        "mime": "application/pdf",
+                    }],
+                    "metadata": {
+                        "entries": [{ "name": "taxes.pdf", "source_path": "/Users/jane/taxes.pdf" }],
+                    },
+                },
+                {
+                    "pluginId": "passthrough",
+                    "kind": "passthrough",
+                    "data": [{ "uti": "com.example.app-state", "data": "AJ+SlsecretBASE64" }],
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_redacted_item_drops_content() {
+        let redacted = serde_json::to_string(&redact_full_item(&exported_item())).unwrap();
+        for secret in [
+            "secret",
+            "/Users/",
+            "jane",
+            "taxes",
+            "iVBORw0KGgo",
+            "AJ+Sls",
+            "f0f0e1e1",
+        ] {
+            assert!(!redacted.contains(secret), "{secret} leaked");
+        }
+        assert!(redacted.contains("[redacted 23 bytes]"));
+    }
+
+    #[test]
+    fn test_redacted_item_keeps_structure() {
+        let item = exported_item();
+        let redacted = redact_full_item(&item);
+        assert_eq!(redacted.id, item.id);
+        assert_eq!(redacted.date, item.date);
+        assert_eq!(redacted.first_date, item.first_date);
+        assert_eq!(redacted.item_type, item.item_type);
+        assert_eq!(redacted.size, Some(4096));
+        assert_eq!(redacted.copy_count, Some(3));
+        assert_eq!(redacted.copy_events, item.copy_events);
+        assert_eq!(redacted.detected_formats, item.detected_formats);
+        assert_eq!(redacted.sources, ["[redacted 21 bytes]"]);
+
+        let ids: Vec<_> = redacted
+            .formats
+            .iter()
+            .map(|format| format.plugin_id.as_str())
+            .collect();
+        assert_eq!(ids, ["text", "image", "files", "passthrough"]);
+        assert_eq!(redacted.formats[0].kind.as_deref(), Some("text"));
+        assert_eq!(redacted.formats[0].priority, Some(1));
+        assert_eq!(redacted.formats[0].metadata, item.formats[0].metadata);
+        let image = &redacted.formats[1].metadata;
+        assert_eq!(image["mime"], "image/png");
+        assert_eq!(image["width"], 640);
+        assert_eq!(image["height"], 480);
+        let file = &redacted.formats[2].data[0];
+        assert_eq!(file["name"], "[redacted 9 bytes]");
+        assert_eq!(file["size"], 4096);
+        assert_eq!(file["mime"], "application/pdf");
+        assert_eq!(redacted.formats[3].data[0]["uti"], "com.example.app-state");
+    }
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_preview_order_caps_rendered_formats() {
+        let order = ids(&["rtf", "text", "passthrough", "html", "image"]);
+        let (rendered, rest) = split_preview_order(order.clone(), Some(2));
+        assert_eq!(rendered, ids(&["image", "html"]));
+        assert_eq!(rest, ids(&["text", "rtf", "passthrough"]));
+
+        let (rendered, rest) = split_preview_order(order.clone(), None);
+        assert_eq!(rendered, order);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_split_preview_order_renders_at_least_one() {
+        let (rendered, rest) = split_preview_order(ids(&["text", "html"]), Some(0));
+        assert_eq!(rendered, ids(&["html"]));
+        assert_eq!(rest, ids(&["text"]));
+
+        let (rendered, rest) = split_preview_order(ids(&["text", "mystery"]), Some(5));
+        assert_eq!(rendered, ids(&["text", "mystery"]));
+        assert!(rest.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs;
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clipboard_rs::common::ClipboardContent;
+use serde_json::{Value, json};
+
+use crate::clipboard::snapshot::{ClipboardSnapshot, CustomFormat, FileOutput, human_kb};
+use crate::data::model::EntryKind;
+
+use super::{
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture,
+    PluginContext, PluginImport,
+};
+
+pub static PASSTHROUGH_PLUGIN: &PassthroughPlugin = &PassthroughPlugin;
+
+/// Stores the raw bytes of every pasteboard format the other plugins skip
+/// (app-private UTIs, `public.url`, ...) and puts them back unchanged on copy.
+pub struct PassthroughPlugin;
+
+impl ClipboardPlugin for PassthroughPlugin {
+    fn id(&self) -> &'static str {
+        "passthrough"
+    }
+
+    fn kind(&self) -> &'static str {
+        "custom"
+    }
+
+    fn priority(&self) -> u8 {
+        5
+    }
+
+    fn entry_kind(&self) -> EntryKind {
+        EntryKind::Other
+    }
+
+    fn matches(&self, snapshot: &ClipboardSnapshot) -> bool {
+        !snapshot.custom_formats.is_empty()
+    }
+
+    fn capture(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        _ctx: &CaptureContext,
+    ) -> Option<PluginCapture> {
+        if snapshot.custom_formats.is_empty() {
+            return None;
+        }
+        Some(self.build_capture(&snapshot.custom_formats))
+    }
+
+    fn to_clipboard_items(&self, ctx: &PluginContext<'_>) -> Result<Vec<ClipboardContent>> {
+        Ok(read_formats(ctx)?
+            .into_iter()
+            .map(|format| ClipboardContent::Other(format.uti, format.bytes))
+            .collect())
+    }
+
+    fn display_content(&self, ctx: &PluginContext<'_>) -> Result<DisplayContent> {
+        Ok(DisplayContent::Lines(
+            stored_entries(ctx)
+                .map(|(uti, _, size)| format!("{uti} [{}]", human_kb(size)))
+                .collect(),
+        ))
+    }
+
+    fn export_json(&self, ctx: &PluginContext<'_>) -> Result<Value> {
+        Ok(Value::Array(
+            read_formats(ctx)?
+                .into_iter()
+                .map(|format| json!({ "uti": format.uti, "data": BASE64.encode(format.bytes) }))
+                .collect(),
+        ))
+    }
+
+    fn import_json(&self, format: &ClipboardJsonFormat) -> Result<PluginImport> {
+        let entries = format
+            .data
+            .as_array()
+            .ok_or_else(|| anyhow!("passthrough plugin expects an array of formats"))?;
+        let formats = entries
+            .iter()
+            .map(|entry| {
+                let uti = entry
+                    .get("uti")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("passthrough format is missing its uti"))?;
+                let data = entry
+                    .get("data")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let bytes = BASE64
+                    .decode(data)
+                    .with_context(|| format!("Invalid base64 data for {uti}"))?;
+                Ok(CustomFormat {
+                    uti: uti.to_string(),
+                    bytes,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut capture = self.build_capture(&formats);
+        capture.finalize_metadata();
+        Ok(PluginImport {
+            capture,
+            clipboard_contents: formats
+                .into_iter()
+                .map(|format| ClipboardContent::Other(format.uti, format.bytes))
+                .collect(),
+        })
+    }
+
+    fn detail_log(&self, ctx: &PluginContext<'_>) -> Result<Vec<(String, String)>> {
+        let mut details = vec![("kind".into(), self.kind().into())];
+        details.extend(
+            stored_entries(ctx).map(|(uti, _, size)| (uti.to_string(), format!("{size} bytes"))),
+        );
+        Ok(details)
+    }
+
+    fn is_editable(&self) -> bool {
+        false
+    }
+
+    fn get_preview_data(&self, ctx: &PluginContext<'_>) -> Result<Value> {
+        let formats: Vec<Value> = stored_entries(ctx)
+            .map(|(uti, _, size)| json!({ "uti": uti, "size": human_kb(size) }))
+            .collect();
+        Ok(json!({ "formats": formats }))
+    }
+}
+
+impl PassthroughPlugin {
+    fn build_capture(&self, formats: &[CustomFormat]) -> PluginCapture {
+        let files: Vec<FileOutput> = formats
+            .iter()
+            .enumerate()
+            .map(|(idx, format)| FileOutput {
+                filename: format!("passthrough__{idx}.bin"),
+                bytes: format.bytes.clone(),
+            })
+            .collect();
+        let entries: Vec<Value> = formats
+            .iter()
+            .zip(&files)
+            .map(|(format, file)| {
+                json!({
+                    "uti": format.uti,
+                    "file": file.filename,
+                    "byteSize": format.bytes.len(),
+                })
+            })
+            .collect();
+        let byte_size: u64 = formats.iter().map(|format| format.bytes.len() as u64).sum();
+        let utis: Vec<&str> = formats.iter().map(|format| format.uti.as_str()).collect();
+
+        PluginCapture {
+            plugin_id: self.id(),
+            kind: self.kind(),
+            entry_kind: self.entry_kind(),
+            priority: self.priority(),
+            summary: Some(format!(
+                "Custom data: {} [{}]",
+                utis.join(", "),
+                human_kb(byte_size)
+            )),
+            search_text: Some(utis.join(" ")),
+            files,
+            metadata: json!({
+                "formats": entries,
+                "byteSize": byte_size,
+            }),
+            byte_size,
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// `(uti, filename, byte size)` for each stored format, in capture order.
+fn stored_entries<'a>(ctx: &'a PluginContext<'_>) -> impl Iterator<Item = (&'a str, &'a str, u64)> {
+    ctx.plugin_meta
+        .get("formats")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let uti = entry.get("uti").and_then(Value::as_str)?;
+            let file = entry.get("file").and_then(Value::as_str)?;
+            let size = entry.get("byteSize").and_then(Value::as_u64).unwrap_or(0);
+            Some((uti, file, size))
+        })
+}
+
+fn read_formats(ctx: &PluginContext<'_>) -> Result<Vec<CustomFormat>> {
+    stored_entries(ctx)
+        .map(|(uti, file, _)| {
+            let path = ctx.item_dir.join(file);
+            let bytes =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok(CustomFormat {
+                uti: uti.to_string(),
+                bytes,
+            })
+        })
+        .collect()
+}
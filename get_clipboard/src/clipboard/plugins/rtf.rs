@@ -7,8 +7,8 @@ use crate::clipboard::snapshot::{ClipboardSnapshot, FileOutput, human_kb};
 use crate::data::model::EntryKind;
 
 use super::{
-    ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture, PluginContext,
-    PluginImport,
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture,
+    PluginContext, PluginImport,
 };
 
 pub static RTF_PLUGIN: &RtfPlugin = &RtfPlugin;
@@ -40,7 +40,11 @@ impl ClipboardPlugin for RtfPlugin {
             .unwrap_or(false)
     }
 
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture> {
+    fn capture(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        _ctx: &CaptureContext,
+    ) -> Option<PluginCapture> {
         let rtf = snapshot.rtf.as_ref()?;
         if rtf.is_empty() {
             return None;
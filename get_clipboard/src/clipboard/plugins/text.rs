@@ -3,6 +3,7 @@ use std::fs;
 use anyhow::{Result, anyhow};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
 use serde_json::json;
 
 static COLOR_RE: Lazy<Regex> = Lazy::new(|| {
@@ -15,8 +16,8 @@ use crate::clipboard::snapshot::{FileOutput, truncate_summary};
 use crate::data::model::EntryKind;
 
 use super::{
-    ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture, PluginContext,
-    PluginImport,
+    CaptureContext, ClipboardJsonFormat, ClipboardPlugin, DisplayContent, PluginCapture,
+    PluginContext, PluginImport,
 };
 
 pub static TEXT_PLUGIN: &TextPlugin = &TextPlugin;
@@ -52,7 +53,11 @@ impl ClipboardPlugin for TextPlugin {
             .unwrap_or(false)
     }
 
-    fn capture(&self, snapshot: &ClipboardSnapshot) -> Option<PluginCapture> {
+    fn capture(
+        &self,
+        snapshot: &ClipboardSnapshot,
+        _ctx: &CaptureContext,
+    ) -> Option<PluginCapture> {
         let text = snapshot.text.as_ref()?;
         if text.is_empty() {
             return None;
@@ -73,6 +78,7 @@ impl ClipboardPlugin for TextPlugin {
             files,
             metadata: json!({
                 "length": text.chars().count(),
+                "counts": TextCounts::of(text),
             }),
             byte_size: text.len() as u64,
             sources: Vec::new(),
@@ -117,6 +123,7 @@ impl ClipboardPlugin for TextPlugin {
             files,
             metadata: json!({
                 "length": text.chars().count(),
+                "counts": TextCounts::of(&text),
             }),
             byte_size: text.len() as u64,
             sources: Vec::new(),
@@ -130,10 +137,13 @@ impl ClipboardPlugin for TextPlugin {
     }
 
     fn detail_log(&self, ctx: &PluginContext<'_>) -> Result<Vec<(String, String)>> {
-        let length = read_text(ctx)?.chars().count();
+        let counts = TextCounts::of(&read_text(ctx)?);
         Ok(vec![
             ("kind".into(), self.kind().into()),
-            ("length".into(), length.to_string()),
+            ("length".into(), counts.chars.to_string()),
+            ("lines".into(), counts.lines.to_string()),
+            ("words".into(), counts.words.to_string()),
+            ("bytes".into(), counts.bytes.to_string()),
         ])
     }
 
@@ -163,6 +173,13 @@ impl ClipboardPlugin for TextPlugin {
 
         let is_url = !is_svg && !is_color && (trimmed.starts_with("http://") || trimmed.starts_with("https://"));
 
+        let json_pretty = if !is_svg && !is_color && !is_url {
+            pretty_json(trimmed)
+        } else {
+            None
+        };
+        let is_json = json_pretty.is_some();
+
         let mut result = json!({
             "content": content,
             "raw_text": text_content,
@@ -170,7 +187,9 @@ impl ClipboardPlugin for TextPlugin {
             "is_color": is_color,
             "color_value": if is_color { trimmed } else { "" },
             "is_url": is_url,
-            "url": if is_url { trimmed } else { "" }
+            "url": if is_url { trimmed } else { "" },
+            "is_json": is_json,
+            "json_pretty": json_pretty.unwrap_or_default()
         });
 
         if is_url {
@@ -193,6 +212,90 @@ impl ClipboardPlugin for TextPlugin {
     }
 }
 
+/// How long a piece of copied text is, for answering "how long is this"
+/// straight from `show`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextCounts {
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+}
+
+impl TextCounts {
+    /// Lines don't count a trailing newline, words are whitespace-separated
+    /// runs and chars are Unicode scalar values.
+    pub fn of(text: &str) -> Self {
+        TextCounts {
+            lines: text.lines().count(),
+            words: text.split_whitespace().count(),
+            chars: text.chars().count(),
+            bytes: text.len(),
+        }
+    }
+}
+
+/// Pretty-prints text that parses as a JSON object or array using 2-space
+/// indentation. Scalars are left alone since plain words and numbers parse too.
+fn pretty_json(text: &str) -> Option<String> {
+    if !(text.starts_with('{') || text.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+    Some(reindent_json(text))
+}
+
+/// Re-indents already validated JSON token by token. Going through
+/// `serde_json::Value` would sort object keys and reorder the user's JSON.
+fn reindent_json(text: &str) -> String {
+    fn newline(out: &mut String, depth: usize) {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth));
+    }
+
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                out.push(ch);
+                while let Some(ch) = chars.next() {
+                    out.push(ch);
+                    match ch {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                out.push(ch);
+                while chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+                if let Some(close) = chars.next_if(|next| matches!(next, '}' | ']')) {
+                    out.push(close);
+                } else {
+                    depth += 1;
+                    newline(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut out, depth);
+                out.push(ch);
+            }
+            ',' => {
+                out.push(ch);
+                newline(&mut out, depth);
+            }
+            ':' => out.push_str(": "),
+            ch if ch.is_whitespace() => {}
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
 fn read_text(ctx: &PluginContext<'_>) -> Result<String> {
     if let Some(file) = ctx.stored_files.first() {
         return file.read_string();
@@ -204,3 +307,69 @@ fn read_text(ctx: &PluginContext<'_>) -> Result<String> {
     }
     Err(anyhow!("text content not available"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_counts() {
+        assert_eq!(
+            TextCounts::of("one two\nthree\n"),
+            TextCounts {
+                lines: 2,
+                words: 3,
+                chars: 14,
+                bytes: 14,
+            }
+        );
+        assert_eq!(
+            TextCounts::of(""),
+            TextCounts {
+                lines: 0,
+                words: 0,
+                chars: 0,
+                bytes: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_text_counts_multibyte() {
+        let counts = TextCounts::of("héllo wörld 👋\r\n日本語");
+        assert_eq!(counts.lines, 2);
+        assert_eq!(counts.words, 4);
+        assert_eq!(counts.chars, 18);
+        assert_eq!(counts.bytes, 29);
+    }
+
+    #[test]
+    fn test_pretty_json_detects_valid_json() {
+        let pretty = pretty_json(r#"{"a":1,"b":[true,null]}"#);
+        assert_eq!(
+            pretty.as_deref(),
+            Some("{\n  \"a\": 1,\n  \"b\": [\n    true,\n    null\n  ]\n}")
+        );
+        assert!(pretty_json("[1, 2, 3]").is_some());
+    }
+
+    #[test]
+    fn test_pretty_json_keeps_key_order_and_strings() {
+        let pretty =
+            pretty_json(r#"{"zeta": "a, b: {c}", "alpha": {}, "mid": [ ], "q": "say \"hi\""}"#);
+        assert_eq!(
+            pretty.as_deref(),
+            Some(
+                "{\n  \"zeta\": \"a, b: {c}\",\n  \"alpha\": {},\n  \"mid\": [],\n  \"q\": \"say \\\"hi\\\"\"\n}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_pretty_json_falls_back_for_invalid_json() {
+        assert!(pretty_json("{not json}").is_none());
+        assert!(pretty_json("[1, 2").is_none());
+        assert!(pretty_json("42").is_none());
+        assert!(pretty_json("hello world").is_none());
+    }
+}
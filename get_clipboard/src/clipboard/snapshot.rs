@@ -1,15 +1,19 @@
+use crate::clipboard::plugins::CaptureContext;
 use crate::data::model::EntryKind;
 use crate::util::hash::sha256_bytes;
 use anyhow::{Result, anyhow};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use clipboard_rs::{Clipboard, ClipboardContext, ContentFormat};
-use clipboard_rs::common::RustImage;
+use clipboard_rs::common::{ClipboardContent, RustImage};
 use objc2_app_kit::NSPasteboard;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
 use url::Url;
 
 #[derive(Debug, Clone)]
@@ -19,19 +23,167 @@ enum FormatPreview {
     Empty,
 }
 
+/// One pasteboard format as printed by `get_clipboard formats`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FormatReport {
+    pub format: String,
+    /// `text`, `binary` or `empty`.
+    pub kind: &'static str,
+    pub bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+}
+
+/// Everything the pasteboard currently offers, and whether Clippy would
+/// store it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteboardReport {
+    pub captured: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<EntryKind>,
+    pub formats: Vec<FormatReport>,
+}
+
+const REPORT_PREVIEW_CHARS: usize = 120;
+
 #[derive(Debug, Clone)]
 pub struct FileOutput {
     pub filename: String,
     pub bytes: Vec<u8>,
 }
 
+/// A pasteboard format none of the built-in plugins understand, kept byte for
+/// byte so it can be put back on copy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomFormat {
+    pub uti: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Alternate encodings of text that macOS offers next to the plain-text UTI.
+const REDUNDANT_FORMATS: &[&str] = &[
+    "public.plain-text",
+    "public.text",
+    "public.utf16-plain-text",
+    "public.utf16-external-plain-text",
+    "com.apple.traditional-mac-plain-text",
+];
+
+/// Other encodings of content the built-in plugins already keep, such as the
+/// JPEG or HEIC next to a copied image's PNG, which would otherwise be stored
+/// a second time.
+const COVERED_FORMATS: &[&str] = &[
+    "public.jpeg",
+    "public.heic",
+    "public.heif",
+    "com.microsoft.bmp",
+    "org.webmproject.webp",
+    "com.apple.webarchive",
+    "com.apple.flat-rtfd",
+];
+
+/// Legacy pasteboard type names the system synthesizes from modern UTIs.
+const SYNTHESIZED_FORMAT_PREFIXES: &[&str] = &[
+    "dyn.",
+    "CorePasteboardFlavorType",
+    "NeXT ",
+    "Apple ",
+    "NS",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileRecord {
     pub name: String,
     pub extension: Option<String>,
     pub size: u64,
+    #[serde(serialize_with = "serialize_lossy_path")]
     pub source_path: PathBuf,
+    /// Base64 of the raw path bytes when `source_path` is not valid UTF-8,
+    /// because JSON can only hold a lossy copy of such a path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_path_bytes: Option<String>,
     pub mime: Option<String>,
+    /// Where the copied symlink points, set when it was recorded as a link
+    /// rather than captured as its target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+
+impl FileRecord {
+    pub fn from_path(path: &Path, size: u64) -> Self {
+        let name = path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        FileRecord {
+            name,
+            extension: path.extension().map(|s| s.to_string_lossy().to_string()),
+            size,
+            source_path: path.to_path_buf(),
+            source_path_bytes: encode_path_bytes(path),
+            mime: mime_guess::from_path(path).first_raw().map(String::from),
+            link_target: None,
+        }
+    }
+
+    /// The record for a copied path. Symlinks are captured as their target
+    /// when `follow_symlinks` is set, and otherwise (or when the target is
+    /// gone) recorded as the link itself. A symlinked directory is recorded
+    /// with size 0 instead of being walked.
+    pub fn capture(path: &Path, follow_symlinks: bool) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        if !metadata.file_type().is_symlink() {
+            return Some(FileRecord::from_path(path, metadata.len()));
+        }
+        if follow_symlinks && let Ok(target) = fs::metadata(path) {
+            let size = if target.is_dir() { 0 } else { target.len() };
+            return Some(FileRecord::from_path(path, size));
+        }
+        Some(FileRecord {
+            mime: None,
+            link_target: fs::read_link(path).ok(),
+            ..FileRecord::from_path(path, metadata.len())
+        })
+    }
+}
+
+fn serialize_lossy_path<P: AsRef<Path>, S: Serializer>(
+    path: &P,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.as_ref().to_string_lossy())
+}
+
+/// Base64 of the path's raw bytes, or `None` when the path is valid UTF-8 and
+/// survives JSON unchanged.
+pub(crate) fn encode_path_bytes(path: &Path) -> Option<String> {
+    if path.to_str().is_some() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(BASE64.encode(path.as_os_str().as_bytes()))
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+pub(crate) fn decode_path_bytes(encoded: &str) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let bytes = BASE64.decode(encoded).ok()?;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(&bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = encoded;
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,12 +198,110 @@ pub struct ClipboardSnapshot {
     pub summary: Option<String>,
     pub detected_formats: Vec<String>,
     pub extra: Value,
+    /// App that was frontmost when the snapshot was taken.
+    #[serde(default)]
+    pub source_app: Option<String>,
+    /// `NSPasteboard.changeCount` at capture time.
+    #[serde(default)]
+    pub change_count: Option<isize>,
+    /// When the copy was made, if it is stored later than that, e.g. an
+    /// image copy deferred until the Mac is plugged in.
+    #[serde(skip)]
+    pub copied_at: Option<OffsetDateTime>,
+    /// Formats left over after the built-in plugins, for the passthrough plugin.
+    #[serde(default)]
+    pub custom_formats: Vec<CustomFormat>,
     #[serde(skip)]
     format_previews: Vec<(String, FormatPreview)>,
 }
 
+/// Whether the clipboard holds an image, without reading or converting it.
+pub fn clipboard_has_image() -> bool {
+    ClipboardContext::new().is_ok_and(|ctx| ctx.has(ContentFormat::Image))
+}
+
 impl ClipboardSnapshot {
-    pub fn from_pasteboard(_pasteboard: &NSPasteboard) -> Result<Option<Self>> {
+    pub fn from_pasteboard(
+        pasteboard: &NSPasteboard,
+        ctx: &CaptureContext,
+    ) -> Result<Option<Self>> {
+        Self::read_pasteboard(pasteboard, ctx, true)
+    }
+
+    /// Like `from_pasteboard`, but leaves any image alone so the PNG
+    /// conversion is skipped. The rest of the copy is still read.
+    pub fn from_pasteboard_without_images(
+        pasteboard: &NSPasteboard,
+        ctx: &CaptureContext,
+    ) -> Result<Option<Self>> {
+        Self::read_pasteboard(pasteboard, ctx, false)
+    }
+
+    /// A snapshot holding only a file list, for re-running the files plugin
+    /// on a stored entry.
+    pub fn from_files(files: Vec<FileRecord>) -> Self {
+        ClipboardSnapshot {
+            kind: EntryKind::File,
+            text: None,
+            html: None,
+            rtf: None,
+            image_bytes: None,
+            image_mime: None,
+            files,
+            summary: None,
+            detected_formats: Vec::new(),
+            extra: Value::Null,
+            source_app: None,
+            change_count: None,
+            copied_at: None,
+            custom_formats: Vec::new(),
+            format_previews: Vec::new(),
+        }
+    }
+
+    /// The snapshot the watcher would read back after Clippy puts `contents`
+    /// on the pasteboard, so a partial copy can be recognised by its hash.
+    pub fn from_contents(contents: &[ClipboardContent], follow_symlinks: bool) -> Self {
+        let mut snapshot = ClipboardSnapshot::from_files(Vec::new());
+        for content in contents {
+            match content {
+                ClipboardContent::Text(text) => snapshot.text = Some(text.clone()),
+                ClipboardContent::Html(html) => snapshot.html = Some(html.clone()),
+                ClipboardContent::Rtf(rtf) => snapshot.rtf = Some(rtf.clone().into_bytes()),
+                ClipboardContent::Image(image) => {
+                    snapshot.image_bytes = image.to_png().ok().map(|png| png.get_bytes().to_vec());
+                    snapshot.image_mime = Some("image/png".to_string());
+                }
+                ClipboardContent::Files(references) => {
+                    for path in references
+                        .iter()
+                        .filter_map(|reference| parse_clipboard_file_reference(reference))
+                    {
+                        process_file_path(&path, follow_symlinks, &mut snapshot.files);
+                    }
+                }
+                ClipboardContent::Other(uti, bytes) => snapshot.custom_formats.push(CustomFormat {
+                    uti: uti.clone(),
+                    bytes: bytes.clone(),
+                }),
+            }
+        }
+        snapshot
+            .files
+            .sort_by(|a, b| a.source_path.cmp(&b.source_path));
+        snapshot.kind = entry_kind(
+            &snapshot.files,
+            snapshot.image_bytes.is_some(),
+            snapshot.text.is_some() || snapshot.html.is_some(),
+        );
+        snapshot
+    }
+
+    fn read_pasteboard(
+        pasteboard: &NSPasteboard,
+        capture_ctx: &CaptureContext,
+        include_images: bool,
+    ) -> Result<Option<Self>> {
         let ctx =
             ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
         let available_formats = ctx
@@ -62,7 +312,6 @@ impl ClipboardSnapshot {
         }
 
         let mut detected = Vec::new();
-        let mut format_previews: Vec<(String, FormatPreview)> = Vec::new();
         let mut text = None;
         let mut html = None;
         let mut rtf = None;
@@ -75,21 +324,12 @@ impl ClipboardSnapshot {
             if !detected.contains(format) {
                 detected.push(format.clone());
             }
-
-            let preview = match ctx.get_buffer(format) {
-                Ok(buffer) if buffer.is_empty() => FormatPreview::Empty,
-                Ok(buffer) => {
-                    if let Ok(string_data) = String::from_utf8(buffer.clone()) {
-                        FormatPreview::Text(string_data)
-                    } else {
-                        FormatPreview::Binary(buffer)
-                    }
-                }
-                Err(_) => FormatPreview::Empty,
-            };
-
-            format_previews.push((format.clone(), preview));
         }
+        let format_previews = read_format_previews(&ctx, &available_formats);
+        let custom_formats = capture_ctx
+            .custom_format_limit
+            .map(|max_bytes| collect_custom_formats(&format_previews, max_bytes))
+            .unwrap_or_default();
 
         if ctx.has(ContentFormat::Text) {
             if let Ok(value) = ctx.get_text() {
@@ -115,7 +355,7 @@ impl ClipboardSnapshot {
             }
         }
 
-        if ctx.has(ContentFormat::Image) {
+        if include_images && ctx.has(ContentFormat::Image) {
             if let Ok(image_data) = ctx.get_image() {
                 match image_data.to_png() {
                     Ok(png) => {
@@ -142,7 +382,7 @@ impl ClipboardSnapshot {
                     {
                         if let Some(path) = parse_clipboard_file_reference(entry) {
                             if seen_paths.insert(path.clone()) {
-                                process_file_path(&path, &mut files);
+                                process_file_path(&path, capture_ctx.follow_symlinks, &mut files);
                             }
                         }
                     }
@@ -155,20 +395,17 @@ impl ClipboardSnapshot {
             && html.as_ref().map_or(true, |s| s.is_empty())
             && rtf.as_ref().map_or(true, |s| s.is_empty())
             && image_bytes.as_ref().map_or(true, |s| s.is_empty())
+            && custom_formats.is_empty()
         {
             return Ok(None);
         }
 
         files.sort_by(|a, b| a.source_path.cmp(&b.source_path));
-        let kind = if !files.is_empty() {
-            EntryKind::File
-        } else if image_bytes.is_some() {
-            EntryKind::Image
-        } else if text.is_some() || html.is_some() {
-            EntryKind::Text
-        } else {
-            EntryKind::Other
-        };
+        let kind = entry_kind(
+            &files,
+            image_bytes.is_some(),
+            text.is_some() || html.is_some(),
+        );
 
         let summary = None;
 
@@ -183,6 +420,10 @@ impl ClipboardSnapshot {
             summary,
             detected_formats: detected,
             extra: Value::Null,
+            source_app: crate::clipboard::mac::frontmost_app(),
+            change_count: Some(pasteboard.changeCount()),
+            copied_at: None,
+            custom_formats,
             format_previews,
         }))
     }
@@ -202,15 +443,54 @@ impl ClipboardSnapshot {
             hasher.update(bytes);
         }
         for record in &self.files {
-            hasher.update(record.source_path.to_string_lossy().as_bytes());
+            hasher.update(record.source_path.as_os_str().as_encoded_bytes());
             hasher.update(record.size.to_le_bytes());
             if let Some(mime) = &record.mime {
                 hasher.update(mime.as_bytes());
             }
         }
+        // Custom formats only key an entry when nothing else is present, so
+        // app-private data riding along with text does not split duplicates.
+        if !self.has_standard_content() {
+            for format in &self.custom_formats {
+                hasher.update(format.uti.as_bytes());
+                hasher.update(&format.bytes);
+            }
+        }
         sha256_bytes(&hasher.finalize())
     }
 
+    fn has_standard_content(&self) -> bool {
+        !self.files.is_empty()
+            || self.text.as_ref().is_some_and(|s| !s.is_empty())
+            || self.html.as_ref().is_some_and(|s| !s.is_empty())
+            || self.rtf.as_ref().is_some_and(|s| !s.is_empty())
+            || self.image_bytes.as_ref().is_some_and(|s| !s.is_empty())
+    }
+
+    /// Hash a snapshot would get if the pasteboard held only `text`.
+    pub fn text_only_hash(text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(text.as_bytes());
+        sha256_bytes(&hasher.finalize())
+    }
+
+    /// Hash used as the entry id. With `dedupe_by_text`, text-only content
+    /// (optionally with HTML/RTF renderings) is keyed by its plain text alone.
+    pub fn storage_hash(&self, dedupe_by_text: bool) -> String {
+        match &self.text {
+            Some(text)
+                if dedupe_by_text
+                    && !text.is_empty()
+                    && self.files.is_empty()
+                    && self.image_bytes.is_none() =>
+            {
+                Self::text_only_hash(text)
+            }
+            _ => self.compute_hash(),
+        }
+    }
+
     pub fn sources(&self) -> Vec<String> {
         self.files
             .iter()
@@ -236,6 +516,35 @@ impl ClipboardSnapshot {
         total
     }
 
+    /// Builds the `get_clipboard formats` output for the current pasteboard,
+    /// including formats Clippy does not know how to store.
+    pub fn inspect_pasteboard(
+        pasteboard: &NSPasteboard,
+        ctx: &CaptureContext,
+    ) -> Result<PasteboardReport> {
+        if let Some(snapshot) = Self::from_pasteboard(pasteboard, ctx)? {
+            return Ok(snapshot.pasteboard_report());
+        }
+        let ctx =
+            ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+        let available_formats = ctx
+            .available_formats()
+            .map_err(|e| anyhow!("Failed to list clipboard formats: {e}"))?;
+        Ok(PasteboardReport {
+            captured: false,
+            kind: None,
+            formats: describe_previews(&read_format_previews(&ctx, &available_formats)),
+        })
+    }
+
+    pub fn pasteboard_report(&self) -> PasteboardReport {
+        PasteboardReport {
+            captured: true,
+            kind: Some(self.kind.clone()),
+            formats: describe_previews(&self.format_previews),
+        }
+    }
+
     pub fn log_format_details(&self) {
         eprintln!("\n=== Clipboard Change Detected ===");
         eprintln!("All formats ({} total):", self.format_previews.len());
@@ -243,13 +552,13 @@ impl ClipboardSnapshot {
         for (format_name, preview) in &self.format_previews {
             match preview {
                 FormatPreview::Text(text) => {
-                    let preview = self.truncate_preview(text, 120);
+                    let preview = truncate_preview(text, REPORT_PREVIEW_CHARS);
                     eprintln!("  • {}: \"{}\"", format_name, preview);
                 }
                 FormatPreview::Binary(bytes) => {
                     if is_likely_text_binary(bytes) {
                         let text = String::from_utf8_lossy(bytes);
-                        let preview = self.truncate_preview(&text, 120);
+                        let preview = truncate_preview(&text, REPORT_PREVIEW_CHARS);
                         eprintln!(
                             "  • {}: \"{}\" ({} bytes)",
                             format_name,
@@ -268,23 +577,121 @@ impl ClipboardSnapshot {
 
         eprintln!("================================\n");
     }
+}
 
-    fn truncate_preview(&self, content: &str, max_len: usize) -> String {
-        let normalized = content.trim().replace('\n', " ").replace('\r', " ");
-        let mut chars = normalized.chars();
-        let char_count = normalized.chars().count();
+fn read_format_previews(
+    ctx: &ClipboardContext,
+    formats: &[String],
+) -> Vec<(String, FormatPreview)> {
+    formats
+        .iter()
+        .map(|format| {
+            let preview = match ctx.get_buffer(format) {
+                Ok(buffer) if buffer.is_empty() => FormatPreview::Empty,
+                Ok(buffer) => match String::from_utf8(buffer) {
+                    Ok(string_data) => FormatPreview::Text(string_data),
+                    Err(err) => FormatPreview::Binary(err.into_bytes()),
+                },
+                Err(_) => FormatPreview::Empty,
+            };
+            (format.clone(), preview)
+        })
+        .collect()
+}
 
-        if char_count > max_len {
-            let take_len = max_len.saturating_sub(3);
-            let mut truncated = String::new();
-            for ch in chars.by_ref().take(take_len) {
-                truncated.push(ch);
+/// Custom formats larger than `max_bytes` are dropped.
+fn collect_custom_formats(
+    previews: &[(String, FormatPreview)],
+    max_bytes: usize,
+) -> Vec<CustomFormat> {
+    previews
+        .iter()
+        .filter(|(format, _)| is_custom_format(format))
+        .filter_map(|(format, preview)| {
+            let bytes = match preview {
+                FormatPreview::Text(text) => text.clone().into_bytes(),
+                FormatPreview::Binary(bytes) => bytes.clone(),
+                FormatPreview::Empty => return None,
+            };
+            if bytes.len() > max_bytes {
+                return None;
             }
-            truncated.push_str("...");
-            truncated
-        } else {
-            normalized
+            Some(CustomFormat {
+                uti: format.clone(),
+                bytes,
+            })
+        })
+        .collect()
+}
+
+/// Whether `format` is left for the passthrough plugin: not something the
+/// built-in plugins store, and not a system alias or other encoding of one
+/// of those.
+fn is_custom_format(format: &str) -> bool {
+    !crate::clipboard::formats::is_known_format(format)
+        && !REDUNDANT_FORMATS.contains(&format)
+        && !COVERED_FORMATS.contains(&format)
+        && !SYNTHESIZED_FORMAT_PREFIXES
+            .iter()
+            .any(|prefix| format.starts_with(prefix))
+}
+
+fn describe_previews(previews: &[(String, FormatPreview)]) -> Vec<FormatReport> {
+    previews
+        .iter()
+        .map(|(format, preview)| {
+            let (kind, bytes, preview) = match preview {
+                FormatPreview::Text(text) => (
+                    "text",
+                    text.len(),
+                    Some(truncate_preview(text, REPORT_PREVIEW_CHARS)),
+                ),
+                FormatPreview::Binary(bytes) => (
+                    "binary",
+                    bytes.len(),
+                    is_likely_text_binary(bytes).then(|| {
+                        truncate_preview(&String::from_utf8_lossy(bytes), REPORT_PREVIEW_CHARS)
+                    }),
+                ),
+                FormatPreview::Empty => ("empty", 0, None),
+            };
+            FormatReport {
+                format: format.clone(),
+                kind,
+                bytes,
+                preview,
+            }
+        })
+        .collect()
+}
+
+fn truncate_preview(content: &str, max_len: usize) -> String {
+    let normalized = content.trim().replace('\n', " ").replace('\r', " ");
+    let mut chars = normalized.chars();
+    let char_count = normalized.chars().count();
+
+    if char_count > max_len {
+        let take_len = max_len.saturating_sub(3);
+        let mut truncated = String::new();
+        for ch in chars.by_ref().take(take_len) {
+            truncated.push(ch);
         }
+        truncated.push_str("...");
+        truncated
+    } else {
+        normalized
+    }
+}
+
+fn entry_kind(files: &[FileRecord], has_image: bool, has_text: bool) -> EntryKind {
+    if !files.is_empty() {
+        EntryKind::File
+    } else if has_image {
+        EntryKind::Image
+    } else if has_text {
+        EntryKind::Text
+    } else {
+        EntryKind::Other
     }
 }
 
@@ -303,24 +710,12 @@ fn parse_clipboard_file_reference(raw: &str) -> Option<PathBuf> {
     Some(PathBuf::from(trimmed))
 }
 
-fn process_file_path(path: &Path, files: &mut Vec<FileRecord>) {
+fn process_file_path(path: &Path, follow_symlinks: bool, files: &mut Vec<FileRecord>) {
     if is_temporary_file(path) {
         return;
     }
-    if let Ok(metadata) = fs::metadata(path) {
-        let name = path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| path.display().to_string());
-        let extension = path.extension().map(|s| s.to_string_lossy().to_string());
-        let mime = mime_guess::from_path(path).first_raw().map(String::from);
-        files.push(FileRecord {
-            name,
-            extension,
-            size: metadata.len(),
-            source_path: path.to_path_buf(),
-            mime,
-        });
+    if let Some(record) = FileRecord::capture(path, follow_symlinks) {
+        files.push(record);
     }
 }
 
@@ -344,9 +739,14 @@ pub(crate) fn truncate_summary(input: &str) -> String {
     }
 }
 
+/// Number of files named in a summary before the rest are collapsed into a
+/// count. The full list stays in the files plugin metadata.
+const SUMMARY_FILE_LIMIT: usize = 10;
+
 pub(crate) fn format_file_summary(files: &[FileRecord]) -> String {
-    files
+    let listed = files
         .iter()
+        .take(SUMMARY_FILE_LIMIT)
         .map(|f| {
             let mime = f.mime.clone().unwrap_or_else(|| "file".into());
             format!(
@@ -357,7 +757,11 @@ pub(crate) fn format_file_summary(files: &[FileRecord]) -> String {
             )
         })
         .collect::<Vec<_>>()
-        .join(", ")
+        .join(", ");
+    match files.len().saturating_sub(SUMMARY_FILE_LIMIT) {
+        0 => listed,
+        rest => format!("{listed} … +{rest} more"),
+    }
 }
 
 pub(crate) fn mime_for_extension(ext: &str) -> Option<String> {
@@ -376,3 +780,219 @@ fn is_likely_text_binary(bytes: &[u8]) -> bool {
         .count();
     non_printable < sample_size / 10
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_record(name: &str) -> FileRecord {
+        FileRecord {
+            name: name.to_string(),
+            extension: Some("txt".to_string()),
+            size: 2048,
+            source_path: PathBuf::from(format!("/tmp/{name}")),
+            source_path_bytes: None,
+            mime: Some("text/plain".to_string()),
+            link_target: None,
+        }
+    }
+
+    fn text_snapshot(text: &str, html: Option<&str>) -> ClipboardSnapshot {
+        ClipboardSnapshot {
+            kind: EntryKind::Text,
+            text: Some(text.to_string()),
+            html: html.map(String::from),
+            rtf: None,
+            image_bytes: None,
+            image_mime: None,
+            files: Vec::new(),
+            summary: None,
+            detected_formats: Vec::new(),
+            extra: Value::Null,
+            source_app: None,
+            change_count: None,
+            copied_at: None,
+            custom_formats: Vec::new(),
+            format_previews: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_pasteboard_report_lists_every_format() {
+        let mut snapshot = text_snapshot("hello", None);
+        snapshot.format_previews = vec![
+            ("public.utf8-plain-text".into(), FormatPreview::Text("hello\nworld".into())),
+            ("public.png".into(), FormatPreview::Binary(vec![0x89, 0x50, 0x00, 0xff])),
+            ("com.apple.webarchive".into(), FormatPreview::Empty),
+        ];
+
+        let report = serde_json::to_value(snapshot.pasteboard_report()).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "captured": true,
+                "kind": "Text",
+                "formats": [
+                    {
+                        "format": "public.utf8-plain-text",
+                        "kind": "text",
+                        "bytes": 11,
+                        "preview": "hello world"
+                    },
+                    { "format": "public.png", "kind": "binary", "bytes": 4 },
+                    { "format": "com.apple.webarchive", "kind": "empty", "bytes": 0 }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_format_report_truncates_long_previews() {
+        let mut snapshot = text_snapshot("x", None);
+        let long = "a".repeat(REPORT_PREVIEW_CHARS * 2);
+        snapshot.format_previews = vec![("public.utf8-plain-text".into(), FormatPreview::Text(long))];
+
+        let formats = snapshot.pasteboard_report().formats;
+        let preview = formats[0].preview.as_deref().unwrap();
+        assert_eq!(preview.chars().count(), REPORT_PREVIEW_CHARS);
+        assert!(preview.ends_with("..."));
+        assert_eq!(formats[0].bytes, REPORT_PREVIEW_CHARS * 2);
+    }
+
+    #[test]
+    fn test_storage_hash_dedupes_rich_text_by_plain_text() {
+        let plain = text_snapshot("hello world", None);
+        let rich = text_snapshot("hello world", Some("<b>hello</b> world"));
+        assert_eq!(plain.storage_hash(true), rich.storage_hash(true));
+        assert_eq!(plain.storage_hash(true), ClipboardSnapshot::text_only_hash("hello world"));
+        assert_ne!(plain.storage_hash(false), rich.storage_hash(false));
+        assert_ne!(
+            plain.storage_hash(true),
+            text_snapshot("hello there", None).storage_hash(true)
+        );
+    }
+
+    #[test]
+    fn test_format_file_summary_lists_small_sets() {
+        let files = vec![file_record("a.txt"), file_record("b.txt")];
+        assert_eq!(
+            format_file_summary(&files),
+            "/tmp/a.txt (2.0 KB - text/plain), /tmp/b.txt (2.0 KB - text/plain)"
+        );
+    }
+
+    #[test]
+    fn test_format_file_summary_truncates_long_lists() {
+        let files: Vec<FileRecord> = (0..2500)
+            .map(|i| file_record(&format!("{i}.txt")))
+            .collect();
+        let summary = format_file_summary(&files);
+        assert!(summary.starts_with("/tmp/0.txt (2.0 KB - text/plain), /tmp/1.txt"));
+        assert!(summary.ends_with("/tmp/9.txt (2.0 KB - text/plain) … +2490 more"));
+        assert!(!summary.contains("/tmp/10.txt"));
+    }
+
+    #[test]
+    fn test_custom_formats_skip_builtin_and_synthesized_types() {
+        let previews = vec![
+            ("public.utf8-plain-text".into(), FormatPreview::Text("hi".into())),
+            ("public.utf16-external-plain-text".into(), FormatPreview::Binary(vec![0xff, 0xfe])),
+            ("dyn.ah62d4rv4gu8y6y4grf0gn5xbrzw1gydcr7u1e3cytf2gn".into(), FormatPreview::Binary(vec![1])),
+            ("NSStringPboardType".into(), FormatPreview::Text("hi".into())),
+            ("com.example.app-state".into(), FormatPreview::Binary(vec![0, 159, 146, 150])),
+            ("public.url".into(), FormatPreview::Text("https://example.com".into())),
+            ("com.example.empty".into(), FormatPreview::Empty),
+            ("public.jpeg".into(), FormatPreview::Binary(vec![0xff, 0xd8])),
+            ("com.apple.webarchive".into(), FormatPreview::Binary(vec![1, 2])),
+        ];
+        assert_eq!(
+            collect_custom_formats(&previews, 1024),
+            vec![
+                CustomFormat {
+                    uti: "com.example.app-state".into(),
+                    bytes: vec![0, 159, 146, 150],
+                },
+                CustomFormat {
+                    uti: "public.url".into(),
+                    bytes: b"https://example.com".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_formats_over_size_cap_are_dropped() {
+        let binary = |uti: &str, len| (uti.to_string(), FormatPreview::Binary(vec![0; len]));
+        let previews = vec![binary("com.example.fits", 4), binary("com.example.big", 5)];
+        let kept: Vec<String> = collect_custom_formats(&previews, 4)
+            .into_iter()
+            .map(|format| format.uti)
+            .collect();
+        assert_eq!(kept, vec!["com.example.fits"]);
+    }
+
+    #[test]
+    fn test_custom_formats_only_key_otherwise_empty_snapshots() {
+        let custom = CustomFormat {
+            uti: "com.example.app-state".into(),
+            bytes: vec![1, 2, 3],
+        };
+        let plain = text_snapshot("hello", None);
+        let mut with_custom = text_snapshot("hello", None);
+        with_custom.custom_formats = vec![custom.clone()];
+        assert_eq!(plain.compute_hash(), with_custom.compute_hash());
+
+        let mut custom_only = text_snapshot("", None);
+        custom_only.text = None;
+        let empty_hash = custom_only.compute_hash();
+        custom_only.custom_formats = vec![custom];
+        assert_ne!(custom_only.compute_hash(), empty_hash);
+    }
+
+    #[test]
+    fn test_capture_symlinks_follows_or_records_link() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("report.pdf");
+        fs::write(&target, vec![0u8; 300]).unwrap();
+        let link = dir.path().join("latest.pdf");
+        symlink(&target, &link).unwrap();
+
+        let followed = FileRecord::capture(&link, true).unwrap();
+        assert_eq!(followed.size, 300);
+        assert_eq!(followed.source_path, link);
+        assert_eq!(followed.mime.as_deref(), Some("application/pdf"));
+        assert!(followed.link_target.is_none());
+
+        let recorded = FileRecord::capture(&link, false).unwrap();
+        assert_eq!(recorded.link_target.as_deref(), Some(target.as_path()));
+        assert_eq!(recorded.size, fs::symlink_metadata(&link).unwrap().len());
+        assert!(recorded.mime.is_none());
+
+        // Regular files are unaffected by the setting.
+        assert_eq!(FileRecord::capture(&target, false).unwrap().size, 300);
+    }
+
+    #[test]
+    fn test_capture_symlinked_directory_or_dangling_link() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("big.bin"), vec![0u8; 4096]).unwrap();
+        let folder_link = dir.path().join("folder-link");
+        symlink(&folder, &folder_link).unwrap();
+
+        let followed = FileRecord::capture(&folder_link, true).unwrap();
+        assert_eq!(followed.size, 0);
+        assert!(followed.link_target.is_none());
+
+        let dangling = dir.path().join("dangling");
+        symlink(dir.path().join("missing"), &dangling).unwrap();
+        let recorded = FileRecord::capture(&dangling, true).unwrap();
+        assert_eq!(recorded.link_target, Some(dir.path().join("missing")));
+        assert!(FileRecord::capture(&dir.path().join("missing"), true).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use url::Url;
+
+use super::ClipboardSnapshot;
+
+pub const BUILTIN_TRANSFORMS: &[&str] = &["strip-url-params", "trim"];
+
+/// Unknown transform names already reported, so the watcher warns about a
+/// typo once instead of on every capture.
+static REPORTED_UNKNOWN: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Rewrites the plain text of a snapshot before it is hashed and stored.
+/// Configured through `capture_transforms` and applied in list order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureTransform {
+    /// Removes `utm_*` and `fbclid` query parameters from copied URLs.
+    StripUrlParams,
+    /// Drops leading and trailing whitespace.
+    Trim,
+}
+
+impl CaptureTransform {
+    pub fn named(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "strip-url-params" => Some(CaptureTransform::StripUrlParams),
+            "trim" => Some(CaptureTransform::Trim),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, text: &str) -> Option<String> {
+        match self {
+            CaptureTransform::StripUrlParams => strip_url_params(text),
+            CaptureTransform::Trim => {
+                let trimmed = text.trim();
+                (trimmed.len() != text.len() && !trimmed.is_empty()).then(|| trimmed.to_string())
+            }
+        }
+    }
+}
+
+/// Runs the named transforms over the snapshot's text. Unknown names are
+/// reported once and skipped so a typo in the config never blocks capturing.
+pub fn apply_transforms(snapshot: &mut ClipboardSnapshot, names: &[String]) {
+    let Some(text) = snapshot.text.as_mut() else {
+        return;
+    };
+    for name in names {
+        let Some(transform) = CaptureTransform::named(name) else {
+            if REPORTED_UNKNOWN.lock().insert(name.clone()) {
+                eprintln!(
+                    "Unknown capture transform '{name}', expected one of: {}",
+                    BUILTIN_TRANSFORMS.join(", ")
+                );
+            }
+            continue;
+        };
+        if let Some(updated) = transform.apply(text) {
+            *text = updated;
+        }
+    }
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || key == "fbclid"
+}
+
+/// Punctuation that surrounds a URL in prose rather than belonging to it.
+fn is_wrapping_punctuation(ch: char) -> bool {
+    matches!(
+        ch,
+        '.' | ',' | ';' | ':' | '!' | '?' | '(' | ')' | '[' | ']' | '<' | '>' | '"' | '\''
+    )
+}
+
+/// Strips tracking parameters from every http(s) URL in `text`. Returns
+/// `None` when nothing changed so untouched text keeps its exact bytes.
+fn strip_url_params(text: &str) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut changed = false;
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let token = piece.trim_end_matches(char::is_whitespace);
+        let start = token.len() - token.trim_start_matches(is_wrapping_punctuation).len();
+        let kept = token.trim_end_matches(is_wrapping_punctuation);
+        let end = kept.len().max(start);
+        match strip_tracking_params(&token[start..end]) {
+            Some(cleaned) => {
+                result.push_str(&token[..start]);
+                result.push_str(&cleaned);
+                result.push_str(&piece[end..]);
+                changed = true;
+            }
+            None => result.push_str(piece),
+        }
+    }
+    changed.then_some(result)
+}
+
+/// Drops the tracking `key=value` segments from the URL's raw query. Every
+/// other byte, including how kept parameters are encoded, is left as typed.
+fn strip_tracking_params(candidate: &str) -> Option<String> {
+    let url = Url::parse(candidate).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let (base, rest) = candidate.split_once('?')?;
+    let (query, fragment) = match rest.find('#') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, ""),
+    };
+    let is_tracking_segment = |segment: &str| {
+        url::form_urlencoded::parse(segment.as_bytes())
+            .next()
+            .is_some_and(|(key, _)| is_tracking_param(&key))
+    };
+    let segments: Vec<&str> = query.split('&').collect();
+    if !segments.iter().any(|segment| is_tracking_segment(segment)) {
+        return None;
+    }
+    let kept: Vec<&str> = segments
+        .into_iter()
+        .filter(|segment| !is_tracking_segment(segment))
+        .collect();
+    let mut cleaned = base.to_string();
+    if !kept.is_empty() {
+        cleaned.push('?');
+        cleaned.push_str(&kept.join("&"));
+    }
+    cleaned.push_str(fragment);
+    Some(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_url_params_removes_tracking_only() {
+        assert_eq!(
+            strip_url_params("https://example.com/post?id=4&utm_source=news&utm_medium=email")
+                .as_deref(),
+            Some("https://example.com/post?id=4")
+        );
+        assert_eq!(
+            strip_url_params("https://example.com/post?fbclid=abc123#comments").as_deref(),
+            Some("https://example.com/post#comments")
+        );
+    }
+
+    #[test]
+    fn test_strip_url_params_keeps_other_params_byte_for_byte() {
+        assert_eq!(
+            strip_url_params(
+                "https://Example.com/a%7Eb?q=caf%C3%A9+bar&utm_source=x&tag=a%20b&flag"
+            )
+            .as_deref(),
+            Some("https://Example.com/a%7Eb?q=caf%C3%A9+bar&tag=a%20b&flag")
+        );
+        assert_eq!(
+            strip_url_params("https://example.com/?utm_source=x&b=%2F#top").as_deref(),
+            Some("https://example.com/?b=%2F#top")
+        );
+    }
+
+    #[test]
+    fn test_strip_url_params_leaves_other_text_alone() {
+        assert_eq!(strip_url_params("https://example.com/post?id=4"), None);
+        assert_eq!(strip_url_params("utm_source=news is not a url"), None);
+        assert_eq!(strip_url_params("ftp://example.com/?utm_source=x"), None);
+    }
+
+    #[test]
+    fn test_strip_url_params_inside_text() {
+        assert_eq!(
+            strip_url_params("read this: https://example.com/a?utm_campaign=x later").as_deref(),
+            Some("read this: https://example.com/a later")
+        );
+    }
+
+    #[test]
+    fn test_strip_url_params_keeps_surrounding_punctuation() {
+        assert_eq!(
+            strip_url_params("See (https://example.com/a?utm_source=x), then.").as_deref(),
+            Some("See (https://example.com/a), then.")
+        );
+        assert_eq!(
+            strip_url_params("https://example.com/a?id=1&utm_source=x.").as_deref(),
+            Some("https://example.com/a?id=1.")
+        );
+    }
+
+    #[test]
+    fn test_strip_url_params_rewrites_each_occurrence_in_place() {
+        let text = "https://example.com/?utm_source=x\thttps://example.com/?utm_source=x&id=2";
+        assert_eq!(
+            strip_url_params(text).as_deref(),
+            Some("https://example.com/\thttps://example.com/?id=2")
+        );
+    }
+
+    #[test]
+    fn test_transform_names() {
+        assert_eq!(
+            CaptureTransform::named("Strip-URL-Params"),
+            Some(CaptureTransform::StripUrlParams)
+        );
+        assert_eq!(
+            CaptureTransform::named("trim"),
+            Some(CaptureTransform::Trim)
+        );
+        assert_eq!(CaptureTransform::named("lowercase"), None);
+        assert_eq!(
+            CaptureTransform::Trim.apply("  hi \n").as_deref(),
+            Some("hi")
+        );
+        assert_eq!(CaptureTransform::Trim.apply("   "), None);
+    }
+}
@@ -11,6 +11,14 @@ pub struct ConfigPaths {
 }
 
 pub fn resolve_paths() -> ConfigPaths {
+    #[cfg(test)]
+    if let Some(config_dir) = SCRATCH_CONFIG_DIR.lock().clone() {
+        return ConfigPaths {
+            data_dir: config_dir.join("data"),
+            config_file: config_dir.join("config.json"),
+            config_dir,
+        };
+    }
     let dirs = default_project_dirs();
     let config_dir = dirs.config_dir().to_path_buf();
     ConfigPaths {
@@ -88,3 +96,44 @@ pub fn ensure_data_dir(config: &AppConfig) -> Result<PathBuf> {
     fs::create_dir_all(&path)?;
     Ok(path)
 }
+
+/// Config directory `resolve_paths` returns while a test holds a
+/// `ScratchConfig`.
+#[cfg(test)]
+static SCRATCH_CONFIG_DIR: parking_lot::Mutex<Option<PathBuf>> = parking_lot::Mutex::new(None);
+
+/// A throwaway config and data directory for tests that go through the real
+/// config and store. Tests holding one run one at a time, and the real paths
+/// come back once it is dropped.
+#[cfg(test)]
+pub struct ScratchConfig {
+    _dir: tempfile::TempDir,
+    _serial: parking_lot::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl Drop for ScratchConfig {
+    fn drop(&mut self) {
+        *SCRATCH_CONFIG_DIR.lock() = None;
+    }
+}
+
+/// Switches config and data to a fresh temp directory, with `configure`
+/// applied to the default config.
+#[cfg(test)]
+pub fn scratch_config(configure: impl FnOnce(&mut AppConfig)) -> ScratchConfig {
+    static SERIAL: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+    let serial = SERIAL.lock();
+    let dir = tempfile::tempdir().expect("create scratch config dir");
+    *SCRATCH_CONFIG_DIR.lock() = Some(dir.path().to_path_buf());
+    let mut config = AppConfig {
+        override_data_dir: Some(dir.path().join("data")),
+        ..AppConfig::default()
+    };
+    configure(&mut config);
+    save_config(&config).expect("write scratch config");
+    ScratchConfig {
+        _dir: dir,
+        _serial: serial,
+    }
+}
@@ -1,5 +1,7 @@
+use crate::data::model::EntryKind;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use time::{Duration, OffsetDateTime};
 
@@ -12,8 +14,123 @@ pub struct AppConfig {
     pub override_data_dir: Option<PathBuf>,
     pub pruning: Option<PrunePolicy>,
     pub version: Option<String>,
+    /// Seconds during which a clipboard change matching an item Clippy just
+    /// copied is ignored by the watcher. `0` disables the guard.
+    pub self_copy_window_secs: Option<u64>,
+    /// When set, copied files up to this many bytes are stashed alongside the
+    /// entry so it can still be re-copied after the originals are deleted.
+    pub stash_files_max_bytes: Option<u64>,
+    /// When enabled, stashed file bytes are kept once under `blobs/` by
+    /// content hash and each entry links to that copy, so stashing the same
+    /// file from several entries only takes its size once.
+    pub share_stashed_files: Option<bool>,
+    /// Maximum number of plugin formats persisted per entry. The
+    /// highest-priority formats are kept.
+    pub max_formats: Option<usize>,
+    /// Maximum number of formats the preview endpoints render per entry. The
+    /// rest are listed as available but not rendered.
+    pub max_preview_formats: Option<usize>,
+    /// Plugin ids skipped when capturing new clipboard entries.
+    pub disabled_plugins: Option<Vec<String>>,
+    /// Height in pixels of the image thumbnails shown in compact previews.
+    pub thumbnail_height: Option<u32>,
+    /// When enabled, text copies are keyed by their plain text so a later
+    /// rich copy of the same text merges into the existing entry.
+    pub dedupe_by_text: Option<bool>,
+    /// When enabled, an HTML copy whose visible text equals the plain text and
+    /// that only uses wrapper tags like `<span>` or `<p>` is stored as text only.
+    pub drop_redundant_html: Option<bool>,
+    /// Built-in transforms (`strip-url-params`, `trim`) run over copied text,
+    /// in order, before it is hashed and stored.
+    pub capture_transforms: Option<Vec<String>>,
+    /// Seconds within which copying a stored item again only bumps its
+    /// copy count and timestamp, skipping plugin processing. `0` disables.
+    pub collapse_window_secs: Option<u64>,
+    /// Pruning never removes the newest item from each of this many most
+    /// recently active source apps.
+    pub keep_latest_per_app: Option<usize>,
+    /// Apply `pruning` after every capture. Off by default, in which case
+    /// history is only pruned by the `prune` command.
+    pub prune_on_capture: Option<bool>,
+    /// Pinned items listed ahead of the most recent ones by
+    /// `/items?pinned_first=true`, which the tray menu uses. `0` keeps the
+    /// list purely recent.
+    pub pinned_first_limit: Option<usize>,
+    /// Another Clippy API (base URL or its `/save` endpoint) that the watcher
+    /// forwards each captured item to. Undelivered items are retried later.
+    pub mirror_url: Option<String>,
+    /// Plugin shown first for each entry kind (`text`, `image`, `file`,
+    /// `other`) when no format flag is given, e.g. `{"text": "html"}`. Items
+    /// without that plugin fall back to their usual order.
+    pub preferred_plugins: Option<HashMap<String, String>>,
+    /// Which representation leads an entry when a copy carries both a file
+    /// list and text (as Finder copies do). Both are stored either way.
+    pub mixed_capture_primary: Option<MixedCapturePrimary>,
+    /// What the watcher does with image copies while the Mac is on battery
+    /// or in Low Power Mode: `capture` as usual, `skip` the image and store
+    /// the rest of the copy, or `defer` the copy until power is back.
+    pub battery_images: Option<BatteryImagePolicy>,
+    /// When enabled, each entry directory also gets a `content` link to its
+    /// primary file, so scripts can find it without reading `metadata.json`.
+    pub stable_content_file: Option<bool>,
+    /// When set, the watcher drops rapid, short, similar-looking text copies
+    /// from one app, such as clocks or counters some apps keep copying.
+    pub noise_filter: Option<NoiseFilterConfig>,
+    /// When enabled, images get a perceptual hash on capture so `dedup
+    /// --images` can find near-identical screenshots. Off by default since
+    /// it decodes every pixel of each copied image.
+    pub image_phash: Option<bool>,
+    /// Whether copied symlinks are captured as the file they point to (the
+    /// default) or recorded as links, without reading or stashing the
+    /// target. Symlinked directories are never descended into either way.
+    pub follow_symlinks: Option<bool>,
+    /// Seconds without a write after which the watcher and API sync newly
+    /// written entries and the journal to disk. `0` leaves flushing to the OS.
+    pub idle_flush_secs: Option<u64>,
+    /// Milliseconds the watcher keeps re-reading the clipboard when a change
+    /// leaves it empty right after a real copy, for apps that clear it before
+    /// setting the new contents. `0` disables the re-check.
+    pub empty_retry_ms: Option<u64>,
+    /// Largest pasteboard format, in bytes, that the passthrough plugin keeps
+    /// when no built-in plugin handles it (app-private UTIs, `public.url`,
+    /// ...). Defaults to 1 MB; `0` turns custom formats off entirely.
+    pub custom_format_max_bytes: Option<usize>,
+    /// Theme and color overrides for the interactive TUI.
+    pub tui: Option<TuiConfig>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NoiseFilterConfig {
+    /// Seconds between copies for them to count as one burst (default 5).
+    pub window_secs: Option<u64>,
+    /// Copies longer than this many characters are never noise (default 40).
+    pub max_length: Option<usize>,
+    /// Same-looking copies from one app in a burst before the rest are
+    /// dropped (default 3).
+    pub repeats: Option<usize>,
+    /// Text copies shorter than this many characters are always dropped
+    /// (default 0, off).
+    pub min_length: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TuiConfig {
+    /// Built-in theme name (`dark` or `light`); `--theme` takes precedence.
+    pub theme: Option<String>,
+    /// Background of the selected row, as a color name or `#rrggbb`.
+    pub selection_color: Option<String>,
+    /// Color of search matches in item summaries.
+    pub highlight_color: Option<String>,
+    /// `plain`, `rounded`, `double` or `thick`.
+    pub border_style: Option<String>,
+}
+
+const DEFAULT_SELF_COPY_WINDOW_SECS: u64 = 3;
+const DEFAULT_THUMBNAIL_HEIGHT: u32 = 120;
+const DEFAULT_IDLE_FLUSH_SECS: u64 = 2;
+const DEFAULT_EMPTY_RETRY_MS: u64 = 250;
+const DEFAULT_CUSTOM_FORMAT_MAX_BYTES: usize = 1024 * 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "mode", rename_all = "snake_case")]
 pub enum PrunePolicy {
@@ -21,6 +138,29 @@ pub enum PrunePolicy {
     MaxCount { count: usize },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixedCapturePrimary {
+    /// The entry is a file entry; its text is kept as a secondary format.
+    #[default]
+    Files,
+    /// The entry is a text entry; its file list is kept as a secondary format.
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryImagePolicy {
+    /// Images are converted and stored regardless of power.
+    #[default]
+    Capture,
+    /// The image is left out; any text or files in the same copy are stored.
+    Skip,
+    /// The copy is stored once the Mac is plugged in again, if it is still
+    /// on the clipboard by then.
+    Defer,
+}
+
 impl Default for PrunePolicy {
     fn default() -> Self {
         PrunePolicy::MaxCount { count: usize::MAX }
@@ -39,6 +179,124 @@ impl AppConfig {
         std::fs::create_dir_all(self.data_dir())
     }
 
+    pub fn self_copy_window(&self) -> Duration {
+        Duration::seconds(
+            self.self_copy_window_secs
+                .unwrap_or(DEFAULT_SELF_COPY_WINDOW_SECS) as i64,
+        )
+    }
+
+    pub fn idle_flush_delay(&self) -> Option<std::time::Duration> {
+        let secs = self.idle_flush_secs.unwrap_or(DEFAULT_IDLE_FLUSH_SECS);
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+
+    pub fn empty_retry_budget(&self) -> Option<std::time::Duration> {
+        let millis = self.empty_retry_ms.unwrap_or(DEFAULT_EMPTY_RETRY_MS);
+        (millis > 0).then(|| std::time::Duration::from_millis(millis))
+    }
+
+    pub fn custom_format_limit(&self) -> Option<usize> {
+        let limit = self
+            .custom_format_max_bytes
+            .unwrap_or(DEFAULT_CUSTOM_FORMAT_MAX_BYTES);
+        (limit > 0).then_some(limit)
+    }
+
+    pub fn file_stash_limit(&self) -> Option<u64> {
+        self.stash_files_max_bytes.filter(|limit| *limit > 0)
+    }
+
+    pub fn shares_stashed_files(&self) -> bool {
+        self.share_stashed_files.unwrap_or(false)
+    }
+
+    pub fn thumbnail_height(&self) -> u32 {
+        self.thumbnail_height
+            .unwrap_or(DEFAULT_THUMBNAIL_HEIGHT)
+            .max(1)
+    }
+
+    pub fn preview_format_limit(&self) -> Option<usize> {
+        self.max_preview_formats.filter(|limit| *limit > 0)
+    }
+
+    pub fn mirror_url(&self) -> Option<&str> {
+        self.mirror_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+    }
+
+    pub fn dedupes_by_text(&self) -> bool {
+        self.dedupe_by_text.unwrap_or(false)
+    }
+
+    pub fn drops_redundant_html(&self) -> bool {
+        self.drop_redundant_html.unwrap_or(false)
+    }
+
+    pub fn writes_stable_content_file(&self) -> bool {
+        self.stable_content_file.unwrap_or(false)
+    }
+
+    pub fn computes_image_phash(&self) -> bool {
+        self.image_phash.unwrap_or(false)
+    }
+
+    pub fn follows_symlinks(&self) -> bool {
+        self.follow_symlinks.unwrap_or(true)
+    }
+
+    pub fn capture_transforms(&self) -> &[String] {
+        self.capture_transforms.as_deref().unwrap_or_default()
+    }
+
+    pub fn collapse_window(&self) -> Duration {
+        Duration::seconds(self.collapse_window_secs.unwrap_or(0) as i64)
+    }
+
+    pub fn mixed_capture_primary(&self) -> MixedCapturePrimary {
+        self.mixed_capture_primary.unwrap_or_default()
+    }
+
+    pub fn battery_images(&self) -> BatteryImagePolicy {
+        self.battery_images.unwrap_or_default()
+    }
+
+    pub fn pinned_first_limit(&self) -> usize {
+        self.pinned_first_limit.unwrap_or(0)
+    }
+
+    pub fn keep_latest_per_app(&self) -> usize {
+        self.keep_latest_per_app.unwrap_or(0)
+    }
+
+    pub fn prunes_on_capture(&self) -> bool {
+        self.prune_on_capture.unwrap_or(false)
+    }
+
+    pub fn preferred_plugin(&self, kind: &EntryKind) -> Option<&str> {
+        let kind = match kind {
+            EntryKind::Text => "text",
+            EntryKind::Image => "image",
+            EntryKind::File => "file",
+            EntryKind::Other => "other",
+        };
+        self.preferred_plugins
+            .as_ref()?
+            .iter()
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(kind))
+            .map(|(_, plugin)| plugin.trim())
+            .filter(|plugin| !plugin.is_empty())
+    }
+
+    pub fn is_plugin_enabled(&self, id: &str) -> bool {
+        self.disabled_plugins
+            .as_ref()
+            .is_none_or(|disabled| !disabled.iter().any(|name| name == id))
+    }
+
     pub fn should_prune(&self, total_items: usize) -> Option<PruneDirective> {
         match self.pruning.clone().unwrap_or_default() {
             PrunePolicy::MaxCount { count } if total_items > count => {
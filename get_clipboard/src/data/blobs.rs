@@ -0,0 +1,173 @@
+use crate::fs::layout;
+use crate::util::hash::sha256_bytes;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Writes `bytes` to `dest` through the shared blob store. The bytes are kept
+/// once under `blobs/` by their SHA-256 and `dest` becomes a hard link to that
+/// copy, so identical files stashed by several entries share their storage.
+/// Falls back to a plain write when the link can't be made.
+pub fn write_shared(data_dir: &Path, dest: &Path, bytes: &[u8]) -> Result<()> {
+    let blob = layout::blob_path(data_dir, &sha256_bytes(bytes));
+    // A blob is only reused when it holds exactly these bytes; one damaged
+    // on disk, even without changing length, is written again.
+    let intact = fs::metadata(&blob).is_ok_and(|meta| meta.len() == bytes.len() as u64)
+        && fs::read(&blob).is_ok_and(|existing| existing == bytes);
+    if !intact {
+        write_blob(&blob, bytes)?;
+    }
+    match fs::remove_file(dest) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            return Err(err).with_context(|| format!("Failed to replace {}", dest.display()));
+        }
+        _ => {}
+    }
+    if fs::hard_link(&blob, dest).is_err() {
+        fs::write(dest, bytes).with_context(|| format!("Failed to write {}", dest.display()))?;
+    }
+    Ok(())
+}
+
+/// Writes the blob through a temp file in its shard, so a crash mid-write
+/// can't leave a truncated blob for later entries to link to. A damaged blob
+/// is replaced by a new file; entries already linked to it still point at
+/// the damaged one.
+fn write_blob(blob: &Path, bytes: &[u8]) -> Result<()> {
+    let shard = blob.parent().context("Blob path has no parent")?;
+    layout::ensure_dir(shard)?;
+    NamedTempFile::new_in(shard)
+        .and_then(|mut temp| {
+            temp.write_all(bytes)?;
+            temp.persist(blob).map_err(|err| err.error)
+        })
+        .with_context(|| format!("Failed to write blob {}", blob.display()))?;
+    Ok(())
+}
+
+/// Removes blobs no entry links to any more, once the entries that stashed
+/// them were deleted. Returns how many blobs were removed and their size.
+pub fn prune_blobs(data_dir: &Path) -> Result<(usize, u64)> {
+    let mut removed = 0;
+    let mut reclaimed = 0;
+    let Ok(shards) = fs::read_dir(layout::blobs_dir(data_dir)) else {
+        return Ok((removed, reclaimed));
+    };
+    for shard in shards.flatten() {
+        let Ok(blobs) = fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for blob in blobs.flatten() {
+            let Ok(meta) = blob.metadata() else {
+                continue;
+            };
+            if meta.is_file() && link_count(&meta) <= 1 {
+                let path = blob.path();
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove blob {}", path.display()))?;
+                removed += 1;
+                reclaimed += meta.len();
+            }
+        }
+    }
+    Ok((removed, reclaimed))
+}
+
+#[cfg(unix)]
+fn link_count(meta: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(meta)
+}
+
+/// Without link counts there is no telling whether a blob is still used, so
+/// nothing is pruned.
+#[cfg(not(unix))]
+fn link_count(_meta: &fs::Metadata) -> u64 {
+    u64::MAX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob_count(data_dir: &Path) -> usize {
+        fs::read_dir(layout::blobs_dir(data_dir))
+            .unwrap()
+            .flatten()
+            .map(|shard| fs::read_dir(shard.path()).unwrap().count())
+            .sum()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_identical_content_shares_one_blob() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let first = data_dir
+            .path()
+            .join("objects/aa/first/files__stash/0/report.pdf");
+        let second = data_dir
+            .path()
+            .join("objects/bb/second/files__stash/0/copy.pdf");
+        for dest in [&first, &second] {
+            fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            write_shared(data_dir.path(), dest, b"%PDF same bytes").unwrap();
+        }
+
+        assert_eq!(blob_count(data_dir.path()), 1);
+        let blob = layout::blob_path(data_dir.path(), &sha256_bytes(b"%PDF same bytes"));
+        assert_eq!(link_count(&fs::metadata(&blob).unwrap()), 3);
+        assert_eq!(link_count(&fs::metadata(&first).unwrap()), 3);
+        assert_eq!(fs::read(&second).unwrap(), b"%PDF same bytes");
+
+        let other = data_dir.path().join("objects/cc/third/other.txt");
+        fs::create_dir_all(other.parent().unwrap()).unwrap();
+        write_shared(data_dir.path(), &other, b"different").unwrap();
+        assert_eq!(blob_count(data_dir.path()), 2);
+    }
+
+    #[test]
+    fn test_truncated_blob_is_rewritten() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let blob = layout::blob_path(data_dir.path(), &sha256_bytes(b"full contents"));
+        fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        fs::write(&blob, b"full").unwrap();
+
+        let dest = data_dir.path().join("copy.txt");
+        write_shared(data_dir.path(), &dest, b"full contents").unwrap();
+        assert_eq!(fs::read(&blob).unwrap(), b"full contents");
+        assert_eq!(fs::read(&dest).unwrap(), b"full contents");
+        assert_eq!(blob_count(data_dir.path()), 1);
+    }
+
+    #[test]
+    fn test_corrupt_blob_of_the_same_length_is_rewritten() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let blob = layout::blob_path(data_dir.path(), &sha256_bytes(b"full contents"));
+        fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        fs::write(&blob, b"full c\0ntents").unwrap();
+
+        let dest = data_dir.path().join("copy.txt");
+        write_shared(data_dir.path(), &dest, b"full contents").unwrap();
+        assert_eq!(fs::read(&blob).unwrap(), b"full contents");
+        assert_eq!(fs::read(&dest).unwrap(), b"full contents");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prune_keeps_blobs_still_linked() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let first = data_dir.path().join("first.txt");
+        let second = data_dir.path().join("second.txt");
+        write_shared(data_dir.path(), &first, b"shared").unwrap();
+        write_shared(data_dir.path(), &second, b"shared").unwrap();
+
+        fs::remove_file(&first).unwrap();
+        assert_eq!(prune_blobs(data_dir.path()).unwrap(), (0, 0));
+        assert_eq!(fs::read(&second).unwrap(), b"shared");
+
+        fs::remove_file(&second).unwrap();
+        assert_eq!(prune_blobs(data_dir.path()).unwrap(), (1, 6));
+        assert_eq!(blob_count(data_dir.path()), 0);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::fs::layout;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The item Clippy last put on the clipboard. `copy --relative` steps from
+/// here, so repeated invocations walk through history one item at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastCopied {
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// One step older in history.
+    Prev,
+    /// One step newer in history.
+    Next,
+}
+
+pub fn record_last_copied(data_dir: &Path, hash: &str) -> Result<()> {
+    let state = LastCopied {
+        hash: hash.to_string(),
+    };
+    let path = layout::last_copied_path(data_dir);
+    let payload = serde_json::to_vec(&state)?;
+    fs::write(&path, payload).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn load_last_copied(data_dir: &Path) -> Option<String> {
+    let raw = fs::read(layout::last_copied_path(data_dir)).ok()?;
+    serde_json::from_slice::<LastCopied>(&raw)
+        .ok()
+        .map(|state| state.hash)
+}
+
+/// Picks the neighbour of `current` in `ordered` (newest first), wrapping
+/// around at either end. When `current` is unknown the newest item is used
+/// as the starting point.
+pub fn step(ordered: &[String], current: Option<&str>, direction: Direction) -> Option<String> {
+    if ordered.is_empty() {
+        return None;
+    }
+    let len = ordered.len();
+    let position = current
+        .and_then(|hash| ordered.iter().position(|candidate| candidate == hash))
+        .unwrap_or(0);
+    let next = match direction {
+        Direction::Prev => (position + 1) % len,
+        Direction::Next => (position + len - 1) % len,
+    };
+    ordered.get(next).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ordered() -> Vec<String> {
+        vec!["aaa".into(), "bbb".into(), "ccc".into()]
+    }
+
+    #[test]
+    fn test_step_wraps_at_both_ends() {
+        let ordered = ordered();
+        assert_eq!(step(&ordered, Some("bbb"), Direction::Prev).as_deref(), Some("ccc"));
+        assert_eq!(step(&ordered, Some("ccc"), Direction::Prev).as_deref(), Some("aaa"));
+        assert_eq!(step(&ordered, Some("bbb"), Direction::Next).as_deref(), Some("aaa"));
+        assert_eq!(step(&ordered, Some("aaa"), Direction::Next).as_deref(), Some("ccc"));
+    }
+
+    #[test]
+    fn test_step_starts_from_newest_when_unknown() {
+        let ordered = ordered();
+        assert_eq!(step(&ordered, None, Direction::Prev).as_deref(), Some("bbb"));
+        assert_eq!(step(&ordered, Some("gone"), Direction::Next).as_deref(), Some("ccc"));
+        assert_eq!(step(&[], None, Direction::Prev), None);
+    }
+
+    #[test]
+    fn test_last_copied_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_last_copied(dir.path()), None);
+        record_last_copied(dir.path(), "abc123").unwrap();
+        assert_eq!(load_last_copied(dir.path()).as_deref(), Some("abc123"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::fs::layout;
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How many idle delays a steady stream of writes may postpone a flush.
+const MAX_DEFERRALS: u32 = 10;
+
+/// Entries written since the last flush. The store writes files right away
+/// but leaves them in the OS cache; once writes pause for a while they are
+/// synced to disk, so a power cut only loses the last few seconds.
+#[derive(Debug, Default)]
+pub struct IdleFlush {
+    first_write: Option<Instant>,
+    last_write: Option<Instant>,
+    hashes: BTreeSet<String>,
+}
+
+impl IdleFlush {
+    pub const fn new() -> Self {
+        IdleFlush {
+            first_write: None,
+            last_write: None,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    pub fn note_write(&mut self, hash: &str, at: Instant) {
+        self.first_write.get_or_insert(at);
+        self.last_write = Some(at);
+        self.hashes.insert(hash.to_string());
+    }
+
+    /// Whether pending writes should be flushed: nothing was written for
+    /// `delay`, or writes kept coming for `MAX_DEFERRALS` delays in a row.
+    pub fn due(&self, now: Instant, delay: Duration) -> bool {
+        let (Some(first), Some(last)) = (self.first_write, self.last_write) else {
+            return false;
+        };
+        now.saturating_duration_since(last) >= delay
+            || now.saturating_duration_since(first) >= delay * MAX_DEFERRALS
+    }
+
+    /// The hashes written since the last flush, leaving nothing pending.
+    pub fn take(&mut self) -> Vec<String> {
+        self.first_write = None;
+        self.last_write = None;
+        std::mem::take(&mut self.hashes).into_iter().collect()
+    }
+}
+
+static PENDING: Mutex<IdleFlush> = Mutex::new(IdleFlush::new());
+
+/// Records that `hash` was just written so the next idle flush syncs it.
+pub fn note_write(hash: &str) {
+    PENDING.lock().note_write(hash, Instant::now());
+}
+
+/// Syncs pending entries, the journal and the index snapshot once no write
+/// happened for `delay`, then the directories that list them so new entries
+/// and renamed files survive a power cut too. Returns how many entries were
+/// flushed.
+pub fn flush_if_idle(data_dir: &Path, delay: Duration) -> Result<usize> {
+    let hashes = {
+        let mut pending = PENDING.lock();
+        if !pending.due(Instant::now(), delay) {
+            return Ok(0);
+        }
+        pending.take()
+    };
+    for hash in &hashes {
+        sync_dir(&layout::item_dir(data_dir, hash))?;
+    }
+    sync_file(&layout::journal_path(data_dir))?;
+    sync_file(&layout::snapshot_path(data_dir))?;
+    for dir in parent_dirs(data_dir, &hashes) {
+        sync_file(&dir)?;
+    }
+    Ok(hashes.len())
+}
+
+/// The shard directories holding the entries' directories, innermost first,
+/// followed by the data directory that holds the journal and snapshot.
+fn parent_dirs(data_dir: &Path, hashes: &[String]) -> Vec<PathBuf> {
+    let mut shards = BTreeSet::new();
+    for hash in hashes {
+        let mut dir = layout::item_dir(data_dir, hash);
+        while dir.pop() && dir.starts_with(data_dir) && dir != data_dir {
+            shards.insert(dir.clone());
+        }
+    }
+    // Deeper paths sort after their parents; sync children first.
+    let mut dirs: Vec<PathBuf> = shards.into_iter().rev().collect();
+    dirs.push(data_dir.to_path_buf());
+    dirs
+}
+
+/// Syncs every file in `dir` and then the directory itself. A directory that
+/// is gone was deleted after the write and has nothing left to sync.
+fn sync_dir(dir: &Path) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|kind| kind.is_file()) {
+            sync_file(&entry.path())?;
+        }
+    }
+    sync_file(dir)
+}
+
+fn sync_file(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    File::open(path)
+        .and_then(|file| file.sync_all())
+        .with_context(|| format!("Failed to sync {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn test_flush_waits_for_idle_period() {
+        let start = Instant::now();
+        let mut flush = IdleFlush::new();
+        assert!(!flush.due(start + DELAY * 100, DELAY));
+
+        flush.note_write("aaa", start);
+        assert!(!flush.due(start + Duration::from_secs(1), DELAY));
+        flush.note_write("bbb", start + Duration::from_secs(1));
+        assert!(!flush.due(start + Duration::from_secs(2), DELAY));
+        assert!(flush.due(start + Duration::from_secs(3), DELAY));
+
+        flush.note_write("aaa", start + Duration::from_secs(2));
+        assert_eq!(flush.take(), vec!["aaa".to_string(), "bbb".to_string()]);
+        assert!(!flush.due(start + DELAY * 100, DELAY));
+    }
+
+    #[test]
+    fn test_steady_writes_only_defer_flush_so_long() {
+        let start = Instant::now();
+        let mut flush = IdleFlush::new();
+        let mut now = start;
+        while now < start + DELAY * MAX_DEFERRALS {
+            flush.note_write("aaa", now);
+            assert!(!flush.due(now, DELAY));
+            now += Duration::from_secs(1);
+        }
+        flush.note_write("aaa", now);
+        assert!(flush.due(now, DELAY));
+    }
+
+    #[test]
+    fn test_parent_dirs_cover_shards_and_data_dir() {
+        let data_dir = Path::new("/data");
+        let hashes = vec!["abcdef".to_string(), "abffff".to_string()];
+        assert_eq!(
+            parent_dirs(data_dir, &hashes),
+            vec![
+                PathBuf::from("/data/objects/ab/ff"),
+                PathBuf::from("/data/objects/ab/cd"),
+                PathBuf::from("/data/objects/ab"),
+                PathBuf::from("/data/objects"),
+                PathBuf::from("/data"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_dir_tolerates_deleted_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("metadata.json"), b"{}").unwrap();
+        sync_dir(dir.path()).unwrap();
+        sync_dir(&dir.path().join("gone")).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use crate::fs::layout;
+use crate::util::time::{self, OffsetDateTime};
+use ::time::Duration;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Marker left behind when Clippy itself writes to the clipboard, so the
+/// watcher (which runs in a separate process) can tell its own writes apart
+/// from real user copies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfCopyMarker {
+    pub hash: String,
+    #[serde(with = "::time::serde::timestamp")]
+    pub at: OffsetDateTime,
+}
+
+impl SelfCopyMarker {
+    pub fn matches(&self, hash: &str, now: OffsetDateTime, window: Duration) -> bool {
+        self.hash == hash && now - self.at <= window
+    }
+}
+
+pub fn record_self_copy(data_dir: &Path, hash: &str) -> Result<()> {
+    let marker = SelfCopyMarker {
+        hash: hash.to_string(),
+        at: time::now(),
+    };
+    let path = layout::self_copy_path(data_dir);
+    let payload = serde_json::to_vec(&marker)?;
+    fs::write(&path, payload).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Returns true when `hash` is the item Clippy just set. The marker is
+/// consumed so only the next capture is suppressed.
+pub fn take_self_copy(data_dir: &Path, hash: &str, window: Duration) -> bool {
+    if window <= Duration::ZERO {
+        return false;
+    }
+    let path = layout::self_copy_path(data_dir);
+    let Ok(raw) = fs::read(&path) else {
+        return false;
+    };
+    let Ok(marker) = serde_json::from_slice::<SelfCopyMarker>(&raw) else {
+        let _ = fs::remove_file(&path);
+        return false;
+    };
+    let now = time::now();
+    let expired = now - marker.at > window;
+    let matched = marker.matches(hash, now, window);
+    if expired || matched {
+        let _ = fs::remove_file(&path);
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_copy_is_suppressed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let window = Duration::seconds(3);
+        record_self_copy(dir.path(), "abc123").unwrap();
+
+        assert!(!take_self_copy(dir.path(), "other", window));
+        assert!(take_self_copy(dir.path(), "abc123", window));
+        assert!(!take_self_copy(dir.path(), "abc123", window));
+    }
+
+    #[test]
+    fn test_self_copy_marker_expires() {
+        let now = time::now();
+        let marker = SelfCopyMarker {
+            hash: "abc123".into(),
+            at: now - Duration::seconds(10),
+        };
+        assert!(!marker.matches("abc123", now, Duration::seconds(3)));
+        assert!(marker.matches("abc123", now, Duration::seconds(30)));
+    }
+
+    #[test]
+    fn test_self_copy_guard_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        record_self_copy(dir.path(), "abc123").unwrap();
+        assert!(!take_self_copy(dir.path(), "abc123", Duration::ZERO));
+    }
+}
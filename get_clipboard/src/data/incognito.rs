@@ -0,0 +1,79 @@
+use crate::fs::layout;
+use crate::util::time::{self, OffsetDateTime};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// While this file exists the watcher drops every clipboard change. It lives
+/// in the data directory so a restarted or crashed watcher stays private
+/// instead of silently resuming recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncognitoState {
+    #[serde(with = "crate::util::time::timestamp_millis")]
+    pub since: OffsetDateTime,
+}
+
+pub fn set_incognito(data_dir: &Path, enabled: bool) -> Result<()> {
+    let path = layout::incognito_path(data_dir);
+    if !enabled {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    if path.exists() {
+        return Ok(());
+    }
+    let payload = serde_json::to_vec(&IncognitoState { since: time::now() })?;
+    fs::write(&path, payload).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The active session, if any. A marker that can't be parsed still counts as
+/// enabled, so a damaged file never turns recording back on.
+pub fn incognito_state(data_dir: &Path) -> Option<IncognitoState> {
+    let raw = fs::read(layout::incognito_path(data_dir)).ok()?;
+    Some(
+        serde_json::from_slice(&raw).unwrap_or(IncognitoState {
+            since: OffsetDateTime::UNIX_EPOCH,
+        }),
+    )
+}
+
+pub fn is_incognito(data_dir: &Path) -> bool {
+    layout::incognito_path(data_dir).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_persists_across_reads() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_incognito(dir.path()));
+        assert!(incognito_state(dir.path()).is_none());
+
+        set_incognito(dir.path(), true).unwrap();
+        assert!(is_incognito(dir.path()));
+        let since = incognito_state(dir.path()).unwrap().since;
+
+        // Enabling again keeps the original start time.
+        set_incognito(dir.path(), true).unwrap();
+        assert_eq!(incognito_state(dir.path()).unwrap().since, since);
+
+        set_incognito(dir.path(), false).unwrap();
+        assert!(!is_incognito(dir.path()));
+        set_incognito(dir.path(), false).unwrap();
+    }
+
+    #[test]
+    fn test_damaged_marker_stays_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(layout::incognito_path(dir.path()), b"not json").unwrap();
+        assert!(is_incognito(dir.path()));
+        assert!(incognito_state(dir.path()).is_some());
+    }
+}
@@ -1,3 +1,8 @@
+pub mod blobs;
+pub mod cursor;
+pub mod flush;
+pub mod guard;
+pub mod incognito;
 pub mod model;
 pub mod store;
 
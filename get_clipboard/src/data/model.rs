@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use time::OffsetDateTime;
-use time::serde::timestamp;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntryMetadata {
@@ -10,13 +10,22 @@ pub struct EntryMetadata {
     pub kind: EntryKind,
     pub detected_formats: Vec<String>,
     pub copy_count: u64,
-    #[serde(with = "timestamp")]
+    #[serde(with = "crate::util::time::timestamp_millis")]
     pub first_seen: OffsetDateTime,
-    #[serde(with = "timestamp")]
+    #[serde(with = "crate::util::time::timestamp_millis")]
     pub last_seen: OffsetDateTime,
     pub byte_size: u64,
     pub sources: Vec<String>,
     pub summary: Option<String>,
+    /// Summary set by the user with `rename`. It is copied into `summary`
+    /// and kept there when the entry is copied again or reindexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary_override: Option<String>,
+    /// Format chosen with `PUT /item/:selector/primary`. While the entry holds
+    /// it, it leads `pluginOrder` and supplies `content_filename`, also after
+    /// the entry is copied again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_plugin: Option<String>,
     #[serde(default)]
     pub search_text: Option<String>,
     pub version: String,
@@ -26,6 +35,24 @@ pub struct EntryMetadata {
     pub files: Vec<String>,
     #[serde(default)]
     pub extra: Value,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(default)]
+    pub paste_count: u64,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::util::time::option_timestamp_millis"
+    )]
+    pub last_pasted: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_app: Option<String>,
+    /// Unix-millisecond times of the most recent copies, so a sync merge can
+    /// tell events both machines already share from new ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub copy_events: Vec<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -65,7 +92,9 @@ impl EntryKind {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchIndexRecord {
     pub hash: String,
-    #[serde(with = "timestamp")]
+    #[serde(with = "crate::util::time::timestamp_millis")]
+    pub first_seen: OffsetDateTime,
+    #[serde(with = "crate::util::time::timestamp_millis")]
     pub last_seen: OffsetDateTime,
     pub kind: EntryKind,
     pub copy_count: u64,
@@ -75,6 +104,21 @@ pub struct SearchIndexRecord {
     pub detected_formats: Vec<String>,
     pub byte_size: u64,
     pub relative_path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_app: Option<String>,
+}
+
+impl SearchIndexRecord {
+    /// Newest first, with the hash breaking ties so that selector offsets
+    /// stay the same between calls when timestamps collide.
+    pub fn cmp_newest_first(&self, other: &Self) -> Ordering {
+        other
+            .last_seen
+            .cmp(&self.last_seen)
+            .then_with(|| self.hash.cmp(&other.hash))
+    }
 }
 
 pub type SearchIndex = HashMap<String, SearchIndexRecord>;
@@ -85,7 +129,15 @@ pub enum JournalEntry {
     #[serde(rename = "add")]
     Add {
         hash: String,
-        #[serde(with = "timestamp")]
+        /// Missing from entries journaled before it was tracked, which then
+        /// fall back to `last_seen`.
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "crate::util::time::option_timestamp_millis"
+        )]
+        first_seen: Option<OffsetDateTime>,
+        #[serde(with = "crate::util::time::timestamp_millis")]
         last_seen: OffsetDateTime,
         kind: EntryKind,
         copy_count: u64,
@@ -95,6 +147,10 @@ pub enum JournalEntry {
         search_text: Option<String>,
         detected_formats: Vec<String>,
         byte_size: u64,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pinned: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source_app: Option<String>,
     },
     #[serde(rename = "del")]
     Delete {
@@ -106,6 +162,7 @@ impl JournalEntry {
     pub fn from_record(record: &SearchIndexRecord) -> Self {
         JournalEntry::Add {
             hash: record.hash.clone(),
+            first_seen: Some(record.first_seen),
             last_seen: record.last_seen,
             kind: record.kind.clone(),
             copy_count: record.copy_count,
@@ -113,6 +170,8 @@ impl JournalEntry {
             search_text: record.search_text.clone(),
             detected_formats: record.detected_formats.clone(),
             byte_size: record.byte_size,
+            pinned: record.pinned,
+            source_app: record.source_app.clone(),
         }
     }
 
@@ -126,6 +185,7 @@ impl JournalEntry {
         match self {
             JournalEntry::Add {
                 hash,
+                first_seen,
                 last_seen,
                 kind,
                 copy_count,
@@ -133,8 +193,11 @@ impl JournalEntry {
                 search_text,
                 detected_formats,
                 byte_size,
+                pinned,
+                source_app,
             } => Some(SearchIndexRecord {
                 hash: hash.clone(),
+                first_seen: first_seen.unwrap_or(*last_seen),
                 last_seen: *last_seen,
                 kind: kind.clone(),
                 copy_count: *copy_count,
@@ -143,6 +206,8 @@ impl JournalEntry {
                 detected_formats: detected_formats.clone(),
                 byte_size: *byte_size,
                 relative_path: crate::fs::layout::relative_path_for_hash(hash),
+                pinned: *pinned,
+                source_app: source_app.clone(),
             }),
             JournalEntry::Delete { .. } => None,
         }
@@ -1,39 +1,110 @@
-use crate::clipboard::{plugins, ClipboardSnapshot};
-use crate::clipboard::plugins::PluginCapture;
-use crate::config::{ensure_data_dir, load_config};
+use crate::clipboard::{plugins, transform, ClipboardSnapshot};
+use crate::clipboard::plugins::{ClipboardPlugin, PluginCapture};
+use crate::clipboard::snapshot::FileOutput;
+use crate::config::model::PruneDirective;
+use crate::config::{AppConfig, ensure_data_dir, load_config};
+use crate::data::blobs;
+use crate::data::cursor::{self, Direction};
+use crate::data::flush;
+use crate::data::guard;
 use crate::data::model::{EntryKind, EntryMetadata, JournalEntry, SearchIndex, SearchIndexRecord};
+use crate::error::ClipboardError;
 use crate::fs::layout;
 pub use crate::search::SelectionFilter;
 use crate::search::{SearchOptions, search};
 use crate::util::time::{self, OffsetDateTime};
 use anyhow::{Context, Result, anyhow};
+#[cfg(not(test))]
 use clipboard_rs::{Clipboard, ClipboardContext};
 use image::ImageReader;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use serde_json::{self, Map, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::io::{BufRead, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 struct SharedState {
     index: Arc<SearchIndex>,
     sorted_hashes: Vec<String>,
     sorted_valid: bool,
     journal_len: u64,
+    generation: u64,
+}
+
+/// On-disk form of the index snapshot.
+#[derive(Serialize, Deserialize)]
+struct IndexSnapshot<T> {
+    format: u32,
+    records: T,
 }
 
 static STATE: parking_lot::Once = parking_lot::Once::new();
 static STATE_LOCK: RwLock<Option<SharedState>> = RwLock::new(None);
 
 const COMPACT_THRESHOLD: u64 = 500;
+/// Bumped when `SearchIndexRecord` gains a field that older snapshots lack,
+/// so those are rebuilt from `metadata.json` rather than read with defaults.
+const INDEX_FORMAT: u32 = 3;
 const MAX_SEARCH_TEXT_CHARS: usize = 65536;
 const MAX_SEARCH_TEXT_SEGMENTS: usize = 4;
+/// Copy event timestamps kept per entry; older events only live on in the count.
+const MAX_COPY_EVENTS: usize = 200;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CopyCountMode {
     Increment,
     Override(u64),
+    /// Adds to the existing count and keeps the widest first/last seen range.
+    Merge(u64),
+    /// Like `Merge`, but counts only copy events the entry doesn't know yet,
+    /// so syncing the same item again never inflates its count.
+    Sync(u64),
+}
+
+/// Where a live capture came from, stamped onto the entry in the same write
+/// that persists it.
+#[derive(Debug, Clone, Default)]
+struct CaptureOrigin {
+    source_app: Option<String>,
+    change_count: Option<isize>,
+}
+
+/// Everything `persist_entry` needs besides the hash, timestamp and captures.
+#[derive(Debug, Clone)]
+struct PersistOptions<'a> {
+    summary_hint: Option<String>,
+    detected_formats: Vec<String>,
+    base_sources: Vec<String>,
+    copy_mode: CopyCountMode,
+    copy_events: &'a [i64],
+    search_override: Option<String>,
+    first_seen_override: Option<OffsetDateTime>,
+    /// Merges the new formats into those already stored instead of replacing them.
+    keep_existing_formats: bool,
+    /// A custom summary carried by an imported item.
+    summary_override: Option<String>,
+    origin: CaptureOrigin,
+}
+
+/// What `import_json_item` does when an item with the same hash already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportConflict {
+    #[default]
+    Skip,
+    Merge,
+    /// Conflict-free merge for syncing machines: widest date range and each
+    /// distinct copy event counted once.
+    SyncMerge,
+    Replace,
+}
+
+pub enum ImportOutcome {
+    Stored(Box<EntryMetadata>),
+    Skipped(String),
 }
 
 fn init_state() {
@@ -51,6 +122,7 @@ fn init_state() {
             sorted_hashes: Vec::new(),
             sorted_valid: false,
             journal_len: 0,
+            generation: 0,
         });
     });
 }
@@ -77,6 +149,12 @@ pub fn load_index() -> Result<Arc<SearchIndex>> {
     Ok(with_state(|s| Arc::clone(&s.index)))
 }
 
+/// Counter bumped every time the in-memory index is replaced, so callers can
+/// cheaply tell whether anything changed since they last looked.
+pub fn index_generation() -> u64 {
+    with_state(|s| s.generation)
+}
+
 pub fn ensure_index() -> Result<Arc<SearchIndex>> {
     load_index()
 }
@@ -89,7 +167,7 @@ pub fn sorted_hashes() -> Vec<String> {
             hashes.sort_by(|a, b| {
                 let ra = idx.get(a).unwrap();
                 let rb = idx.get(b).unwrap();
-                rb.last_seen.cmp(&ra.last_seen)
+                ra.cmp_newest_first(rb)
             });
             state.sorted_hashes = hashes;
             state.sorted_valid = true;
@@ -98,11 +176,23 @@ pub fn sorted_hashes() -> Vec<String> {
     })
 }
 
+/// Points the config and store at a scratch directory for the length of a
+/// test, see `config::io::scratch_config`.
+#[cfg(test)]
+pub(crate) fn scratch_store(
+    configure: impl FnOnce(&mut AppConfig),
+) -> crate::config::io::ScratchConfig {
+    let scratch = crate::config::io::scratch_config(configure);
+    refresh_index().expect("load scratch index");
+    scratch
+}
+
 pub fn refresh_index() -> Result<()> {
     let new_index = load_from_journal()?;
     with_state_mut(|state| {
         state.index = Arc::new(new_index);
         state.sorted_valid = false;
+        state.generation += 1;
     });
     Ok(())
 }
@@ -116,6 +206,7 @@ where
         f(&mut new_map);
         state.index = Arc::new(new_map);
         state.sorted_valid = false;
+        state.generation += 1;
     });
 }
 
@@ -126,19 +217,27 @@ fn load_from_journal() -> Result<SearchIndex> {
     let data_dir = ensure_data_dir(&config)?;
 
     migrate_legacy_data(&data_dir)?;
+    load_index_from(&data_dir)
+}
 
-    let snapshot_file = layout::snapshot_path(&data_dir);
-    let journal_file = layout::journal_path(&data_dir);
-
-    let mut index: SearchIndex = if snapshot_file.exists() {
-        let bytes = fs::read(&snapshot_file)
-            .context("Failed to read journal snapshot")?;
-        serde_json::from_slice(&bytes)
-            .context("Failed to parse journal snapshot")?
+/// Reads the snapshot and replays the journal on top. If entries changed on
+/// disk without going through the journal, say by a sync tool, or the
+/// snapshot was written in an older format, the tree is rescanned instead
+/// and the snapshot rewritten from the result.
+fn load_index_from(data_dir: &Path) -> Result<SearchIndex> {
+    let snapshot = if snapshot_is_stale(data_dir) {
+        None
     } else {
-        HashMap::new()
+        read_snapshot(data_dir)?
+    };
+    let Some(mut index) = snapshot else {
+        eprintln!("Index snapshot is out of date, rescanning clipboard history...");
+        let (index, _) = scan_objects(data_dir);
+        write_snapshot(data_dir, &index).context("Failed to write rescanned index")?;
+        return Ok(index);
     };
 
+    let journal_file = layout::journal_path(data_dir);
     if journal_file.exists() {
         let file = fs::File::open(&journal_file)
             .context("Failed to open journal")?;
@@ -164,6 +263,56 @@ fn load_from_journal() -> Result<SearchIndex> {
     Ok(index)
 }
 
+/// The snapshot's records, `None` when it has to be rebuilt because it was
+/// written in another format or can't be parsed.
+fn read_snapshot(data_dir: &Path) -> Result<Option<SearchIndex>> {
+    let snapshot_file = layout::snapshot_path(data_dir);
+    if !snapshot_file.exists() {
+        return Ok(Some(HashMap::new()));
+    }
+    let bytes = fs::read(&snapshot_file).context("Failed to read journal snapshot")?;
+    Ok(serde_json::from_slice::<IndexSnapshot<SearchIndex>>(&bytes)
+        .ok()
+        .filter(|snapshot| snapshot.format == INDEX_FORMAT)
+        .map(|snapshot| snapshot.records))
+}
+
+/// Whether `objects/` changed after the snapshot and journal were last
+/// written. Every store write adds or removes an entry directory before
+/// appending to the journal, so only the two shard levels need checking.
+fn snapshot_is_stale(data_dir: &Path) -> bool {
+    let Some(tree) = newest_shard_mtime(&layout::objects_dir(data_dir)) else {
+        return false;
+    };
+    let modified = |path: PathBuf| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let saved =
+        modified(layout::snapshot_path(data_dir)).max(modified(layout::journal_path(data_dir)));
+    saved.is_none_or(|saved| tree > saved)
+}
+
+fn newest_shard_mtime(objects_dir: &Path) -> Option<SystemTime> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let subdirs = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut newest = modified(objects_dir)?;
+    for first in subdirs(objects_dir) {
+        newest = newest.max(modified(&first)?);
+        for second in subdirs(&first) {
+            newest = newest.max(modified(&second)?);
+        }
+    }
+    Some(newest)
+}
+
 fn apply_journal_entry(index: &mut SearchIndex, entry: &JournalEntry) {
     match entry {
         JournalEntry::Add { hash, .. } => {
@@ -178,6 +327,9 @@ fn apply_journal_entry(index: &mut SearchIndex, entry: &JournalEntry) {
 }
 
 fn append_journal(entry: &JournalEntry) {
+    match entry {
+        JournalEntry::Add { hash, .. } | JournalEntry::Delete { hash } => flush::note_write(hash),
+    }
     if let Ok(config) = load_config() {
         if let Ok(data_dir) = ensure_data_dir(&config) {
             let journal_file = layout::journal_path(&data_dir);
@@ -204,20 +356,554 @@ fn append_journal(entry: &JournalEntry) {
 
 fn compact_journal(data_dir: &Path) -> Result<()> {
     let index = with_state(|s| Arc::clone(&s.index));
+    write_snapshot(data_dir, &index)?;
+
+    with_state_mut(|state| {
+        state.journal_len = 0;
+    });
+    Ok(())
+}
+
+/// Replaces the snapshot with `index` and empties the journal.
+fn write_snapshot(data_dir: &Path, index: &SearchIndex) -> Result<()> {
     let snapshot_file = layout::snapshot_path(data_dir);
     let journal_file = layout::journal_path(data_dir);
 
     let tmp_snapshot = snapshot_file.with_extension("snapshot.tmp");
-    let bytes = serde_json::to_vec(&*index)?;
+    let bytes = serde_json::to_vec(&IndexSnapshot {
+        format: INDEX_FORMAT,
+        records: index,
+    })?;
     fs::write(&tmp_snapshot, bytes)?;
     fs::rename(&tmp_snapshot, &snapshot_file)?;
 
     let _ = fs::write(&journal_file, b"");
+    Ok(())
+}
+
+// --- Reindexing ---
 
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReindexReport {
+    pub scanned: usize,
+    pub updated: usize,
+    pub failed: usize,
+}
+
+/// Rebuilds the index from every `metadata.json` under `objects/`, dropping
+/// records whose files are gone and picking up entries the journal missed.
+pub fn rebuild_index() -> Result<ReindexReport> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let (index, report) = scan_objects(&data_dir);
     with_state_mut(|state| {
-        state.journal_len = 0;
+        state.index = Arc::new(index);
+        state.sorted_valid = false;
+        state.generation += 1;
     });
-    Ok(())
+    compact_journal(&data_dir).context("Failed to write rebuilt index")?;
+    Ok(report)
+}
+
+/// Builds an index by reading every entry's `metadata.json`.
+fn scan_objects(data_dir: &Path) -> (SearchIndex, ReindexReport) {
+    let mut report = ReindexReport::default();
+    let mut index = SearchIndex::new();
+    let objects_dir = layout::objects_dir(data_dir);
+    let shard_dirs = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for first in shard_dirs(&objects_dir) {
+        for second in shard_dirs(&first) {
+            for item_dir in shard_dirs(&second) {
+                report.scanned += 1;
+                let Some(hash) = item_dir.file_name().and_then(|name| name.to_str()) else {
+                    report.failed += 1;
+                    continue;
+                };
+                match read_metadata(data_dir, hash) {
+                    Ok(metadata) => {
+                        index.insert(metadata.hash.clone(), index_record(&metadata));
+                        report.updated += 1;
+                    }
+                    Err(_) => report.failed += 1,
+                }
+            }
+        }
+    }
+    (index, report)
+}
+
+/// Regenerates only the summaries of stored entries with the current capture
+/// logic. Content, timestamps and counts are left alone.
+pub fn resummarize_entries() -> Result<ReindexReport> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let index = load_index()?;
+    let mut report = ReindexReport::default();
+    for hash in index.keys() {
+        report.scanned += 1;
+        let Ok(mut metadata) = read_metadata(&data_dir, hash) else {
+            report.failed += 1;
+            continue;
+        };
+        let item_dir = data_dir.join(&metadata.relative_path);
+        match refresh_summary(&mut metadata, &item_dir) {
+            Ok(true) => {
+                commit_metadata(&metadata)?;
+                report.updated += 1;
+            }
+            Ok(false) => {}
+            Err(_) => report.failed += 1,
+        }
+    }
+    Ok(report)
+}
+
+/// Returns whether the regenerated summary differs from the stored one. A
+/// summary set with `rename` is kept as is.
+fn refresh_summary(metadata: &mut EntryMetadata, item_dir: &Path) -> Result<bool> {
+    let summary = match &metadata.summary_override {
+        Some(custom) => custom.clone(),
+        None => match plugins::regenerate_summary(metadata, item_dir)? {
+            Some(summary) => summary,
+            None => return Ok(false),
+        },
+    };
+    if metadata.summary.as_deref() == Some(summary.as_str()) {
+        return Ok(false);
+    }
+    metadata.summary = Some(summary);
+    Ok(true)
+}
+
+// --- Replay ---
+
+/// Re-reads the files behind a stored file entry so its sizes, types and
+/// file list match what is on disk now.
+///
+/// The entry keeps its hash even though a fresh capture of the same files
+/// would now hash differently (sizes are part of it). The hash is the id
+/// that selectors, pins, the mirror queue and API clients hold on to, and
+/// replaying is meant to refresh an item in place, not to turn it into a
+/// new one. Copying the changed files again stores them as a new item, as
+/// it would without a replay.
+pub fn replay_entry(hash: &str) -> Result<(EntryMetadata, plugins::ReplayReport)> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let mut metadata = read_metadata(&data_dir, hash)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let shared_blobs = config.shares_stashed_files().then_some(data_dir.as_path());
+    let report = replay_item_files(&mut metadata, &item_dir, shared_blobs)?;
+    commit_metadata(&metadata)?;
+    Ok((metadata, report))
+}
+
+fn replay_item_files(
+    metadata: &mut EntryMetadata,
+    item_dir: &Path,
+    shared_blobs: Option<&Path>,
+) -> Result<plugins::ReplayReport> {
+    let no_files = || anyhow!("Item {} has no files to replay", metadata.hash);
+    let files_meta = metadata
+        .extra
+        .get("plugins")
+        .and_then(|plugins| plugins.get(plugins::FILES_PLUGIN.id()))
+        .ok_or_else(no_files)?;
+    let (records, report) = plugins::refresh_records(files_meta);
+    if records.is_empty() {
+        return Err(no_files());
+    }
+    let old_files: Vec<String> = files_meta
+        .get("storedFiles")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(String::from)
+        .collect();
+    let mut capture = plugins::FILES_PLUGIN
+        .capture(&ClipboardSnapshot::from_files(records), &Default::default())
+        .ok_or_else(no_files)?;
+    capture.finalize_metadata();
+
+    let mut old_bytes = 0;
+    for filename in &old_files {
+        let path = item_dir.join(filename);
+        old_bytes += fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        if !capture.files.iter().any(|file| &file.filename == filename) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    for file in &capture.files {
+        write_capture_file(item_dir, file, shared_blobs)?;
+    }
+
+    if let Some(stored) = metadata
+        .extra
+        .get_mut("plugins")
+        .and_then(Value::as_object_mut)
+    {
+        stored.insert(plugins::FILES_PLUGIN.id().into(), capture.metadata.clone());
+    }
+    metadata.byte_size = metadata.byte_size.saturating_sub(old_bytes) + capture.byte_size;
+    if metadata.kind == EntryKind::File {
+        let summary = capture.summary.clone().unwrap_or_default();
+        metadata.search_text = search_text_with_override(
+            build_search_text(std::slice::from_ref(&capture), &summary, None),
+            metadata.summary_override.as_deref(),
+        );
+        if metadata.summary_override.is_none() {
+            metadata.summary = Some(summary);
+        }
+    }
+    Ok(report)
+}
+
+// --- Image optimization ---
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeReport {
+    pub scanned: usize,
+    pub optimized: usize,
+    pub failed: usize,
+    pub bytes_saved: u64,
+    pub dry_run: bool,
+}
+
+const OPTIMIZED_IMAGE_FILENAME: &str = "image__full.webp";
+
+/// Transcodes stored PNG images to lossless WebP wherever that saves space.
+/// With `dry_run`, only reports what would be saved.
+pub fn optimize_images(dry_run: bool) -> Result<OptimizeReport> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let index = load_index()?;
+    let mut report = OptimizeReport {
+        dry_run,
+        ..OptimizeReport::default()
+    };
+    for record in index.values().filter(|record| record.kind == EntryKind::Image) {
+        report.scanned += 1;
+        match optimize_entry_image(&data_dir, &record.hash, dry_run) {
+            Ok(0) => {}
+            Ok(saved) => {
+                report.optimized += 1;
+                report.bytes_saved += saved;
+            }
+            Err(err) => {
+                eprintln!("Failed to optimize {}: {err:#}", record.hash);
+                report.failed += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Optimizes one entry's image. The PNG is only removed once the metadata
+/// pointing at the WebP has been committed, so a failure part way leaves a
+/// readable entry.
+fn optimize_entry_image(data_dir: &Path, hash: &str, dry_run: bool) -> Result<u64> {
+    let mut metadata = read_metadata(data_dir, hash)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let png_file = metadata.extra["plugins"]["image"]["storedFiles"][0]
+        .as_str()
+        .map(String::from);
+    let saved = optimize_item_image(&mut metadata, &item_dir, dry_run)?;
+    if saved > 0 && !dry_run {
+        commit_metadata(&metadata)?;
+        if let Some(png_file) = png_file {
+            let png_path = item_dir.join(png_file);
+            fs::remove_file(&png_path)
+                .with_context(|| format!("Failed to remove {}", png_path.display()))?;
+        }
+    }
+    Ok(saved)
+}
+
+/// Writes a smaller WebP next to the entry's stored PNG and points its plugin
+/// metadata at it, leaving the PNG for the caller to remove. Returns the
+/// bytes saved, or 0 when the PNG was kept.
+fn optimize_item_image(
+    metadata: &mut EntryMetadata,
+    item_dir: &Path,
+    dry_run: bool,
+) -> Result<u64> {
+    let Some(image_meta) = metadata
+        .extra
+        .get_mut("plugins")
+        .and_then(|plugins| plugins.get_mut("image"))
+        .and_then(Value::as_object_mut)
+    else {
+        return Ok(0);
+    };
+    if image_meta.get("mime").and_then(Value::as_str) != Some("image/png") {
+        return Ok(0);
+    }
+    let Some(filename) = image_meta
+        .get("storedFiles")
+        .and_then(Value::as_array)
+        .and_then(|files| files.first())
+        .and_then(Value::as_str)
+        .map(String::from)
+    else {
+        return Ok(0);
+    };
+
+    let png_path = item_dir.join(&filename);
+    let png =
+        fs::read(&png_path).with_context(|| format!("Failed to read {}", png_path.display()))?;
+    let Some(webp) = plugins::image::lossless_webp(&png)? else {
+        return Ok(0);
+    };
+    let saved = (png.len() - webp.len()) as u64;
+    if dry_run {
+        return Ok(saved);
+    }
+
+    let webp_path = item_dir.join(OPTIMIZED_IMAGE_FILENAME);
+    fs::write(&webp_path, &webp)
+        .with_context(|| format!("Failed to write {}", webp_path.display()))?;
+
+    image_meta.insert("storedFiles".into(), Value::from(vec![OPTIMIZED_IMAGE_FILENAME]));
+    image_meta.insert("mime".into(), Value::from("image/webp"));
+    image_meta.insert("byteSize".into(), Value::from(webp.len()));
+    if metadata.content_filename == filename {
+        metadata.content_filename = OPTIMIZED_IMAGE_FILENAME.into();
+        if fs::symlink_metadata(item_dir.join(STABLE_CONTENT_FILENAME)).is_ok() {
+            link_stable_content(item_dir, OPTIMIZED_IMAGE_FILENAME)?;
+        }
+    }
+    metadata.byte_size = metadata.byte_size.saturating_sub(saved);
+    Ok(saved)
+}
+
+// --- Image dedup ---
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupReport {
+    pub scanned: usize,
+    pub hashed: usize,
+    pub merged: usize,
+    pub failed: usize,
+    pub dry_run: bool,
+}
+
+/// Default Hamming distance under which two image hashes count as the same
+/// picture.
+pub const DEFAULT_PHASH_THRESHOLD: u32 = 5;
+
+/// Largest threshold `dedup --images` accepts. Beyond a quarter of the 64
+/// bits, unrelated pictures start to count as duplicates.
+pub const MAX_PHASH_THRESHOLD: u32 = 16;
+
+/// Merges images whose perceptual hashes are within `threshold` bits, and
+/// whose shape and average colour agree, into the most recent one. Images
+/// captured before `image_phash` was enabled are hashed and the hash is
+/// stored. With `dry_run`, only reports what would be merged.
+pub fn dedup_images(threshold: u32, dry_run: bool) -> Result<DedupReport> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let index = load_index()?;
+    let mut records: Vec<_> = index
+        .values()
+        .filter(|record| record.kind == EntryKind::Image)
+        .collect();
+    records.sort_by(|a, b| a.cmp_newest_first(b));
+
+    let mut report = DedupReport {
+        dry_run,
+        ..DedupReport::default()
+    };
+    let mut kept: Vec<(EntryMetadata, plugins::image::ImageFingerprint)> = Vec::new();
+    for record in records {
+        report.scanned += 1;
+        let mut metadata = match read_metadata(&data_dir, &record.hash) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                eprintln!("Skipping {}: {err:#}", record.hash);
+                report.failed += 1;
+                continue;
+            }
+        };
+        let fingerprint = match stored_fingerprint(&metadata) {
+            Some(fingerprint) => fingerprint,
+            None => {
+                let item_dir = data_dir.join(&metadata.relative_path);
+                let Some(fingerprint) = store_item_fingerprint(&mut metadata, &item_dir)? else {
+                    continue;
+                };
+                if !dry_run {
+                    commit_metadata(&metadata)?;
+                }
+                report.hashed += 1;
+                fingerprint
+            }
+        };
+        let duplicate_of = kept
+            .iter_mut()
+            .find(|(_, kept_fingerprint)| kept_fingerprint.matches(&fingerprint, threshold));
+        match duplicate_of {
+            Some((keep, _)) => {
+                report.merged += 1;
+                if !dry_run {
+                    merge_duplicate(keep, &metadata);
+                    commit_metadata(keep)?;
+                    delete_entry(&metadata.hash)?;
+                }
+            }
+            None => kept.push((metadata, fingerprint)),
+        }
+    }
+    Ok(report)
+}
+
+/// The fingerprint recorded at capture, or `None` when the entry predates
+/// `image_phash` (or the average colour that came with it).
+fn stored_fingerprint(metadata: &EntryMetadata) -> Option<plugins::image::ImageFingerprint> {
+    let image_meta = metadata.extra.get("plugins")?.get("image")?;
+    let dimension = |key: &str| image_meta.get(key)?.as_u64()?.try_into().ok();
+    Some(plugins::image::ImageFingerprint {
+        phash: plugins::image::parse_phash(image_meta.get("phash")?.as_str()?)?,
+        width: dimension("width")?,
+        height: dimension("height")?,
+        mean_color: plugins::image::parse_mean_color(image_meta.get("meanColor")?.as_str()?)?,
+    })
+}
+
+/// Fingerprints the entry's stored image and records it in the image plugin
+/// metadata. `None` when the entry has no readable image.
+fn store_item_fingerprint(
+    metadata: &mut EntryMetadata,
+    item_dir: &Path,
+) -> Result<Option<plugins::image::ImageFingerprint>> {
+    let Some(image_meta) = metadata
+        .extra
+        .get_mut("plugins")
+        .and_then(|plugins| plugins.get_mut("image"))
+        .and_then(Value::as_object_mut)
+    else {
+        return Ok(None);
+    };
+    let Some(filename) = image_meta
+        .get("storedFiles")
+        .and_then(Value::as_array)
+        .and_then(|files| files.first())
+        .and_then(Value::as_str)
+    else {
+        return Ok(None);
+    };
+    let Ok(bytes) = fs::read(item_dir.join(filename)) else {
+        return Ok(None);
+    };
+    let Ok(fingerprint) = plugins::image::ImageFingerprint::from_bytes(&bytes) else {
+        return Ok(None);
+    };
+    image_meta.insert(
+        "phash".into(),
+        Value::from(plugins::image::format_phash(fingerprint.phash)),
+    );
+    image_meta.insert(
+        "meanColor".into(),
+        Value::from(plugins::image::format_mean_color(fingerprint.mean_color)),
+    );
+    image_meta.insert("width".into(), Value::from(fingerprint.width));
+    image_meta.insert("height".into(), Value::from(fingerprint.height));
+    Ok(Some(fingerprint))
+}
+
+/// Folds a duplicate's history into the entry that is kept.
+fn merge_duplicate(keep: &mut EntryMetadata, duplicate: &EntryMetadata) {
+    keep.copy_count = keep.copy_count.saturating_add(duplicate.copy_count);
+    keep.copy_events = merge_copy_events(&keep.copy_events, &duplicate.copy_events);
+    keep.paste_count = keep.paste_count.saturating_add(duplicate.paste_count);
+    keep.last_pasted = keep.last_pasted.max(duplicate.last_pasted);
+    keep.first_seen = keep.first_seen.min(duplicate.first_seen);
+    keep.last_seen = keep.last_seen.max(duplicate.last_seen);
+    keep.pinned |= duplicate.pinned;
+    for tag in &duplicate.tags {
+        if !keep.tags.contains(tag) {
+            keep.tags.push(tag.clone());
+        }
+    }
+}
+
+// --- Compaction ---
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReport {
+    pub removed_dirs: usize,
+    pub removed_blobs: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// Removes empty directories and unused shared blobs left behind by deletes
+/// and folds the journal into its snapshot.
+pub fn compact_store() -> Result<CompactReport> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let journal_file = layout::journal_path(&data_dir);
+    let snapshot_file = layout::snapshot_path(&data_dir);
+    let file_len = |path: &Path| fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+
+    let mut report = CompactReport::default();
+    let (removed_blobs, blob_bytes) = blobs::prune_blobs(&data_dir)?;
+    report.removed_blobs = removed_blobs;
+    report.reclaimed_bytes += blob_bytes;
+    let objects_dir = layout::objects_dir(&data_dir);
+    for dir in [data_dir.as_path(), objects_dir.as_path()] {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() && path != objects_dir {
+                remove_empty_dirs(&path, &mut report)?;
+            }
+        }
+    }
+
+    // The snapshot is written last so it is newer than the pruned shards.
+    let before = file_len(&journal_file) + file_len(&snapshot_file);
+    compact_journal(&data_dir).context("Failed to compact journal")?;
+    let after = file_len(&journal_file) + file_len(&snapshot_file);
+    report.reclaimed_bytes += before.saturating_sub(after);
+    Ok(report)
+}
+
+/// Depth-first removal of empty directories under and including `dir`.
+/// Returns whether `dir` itself was removed.
+fn remove_empty_dirs(dir: &Path, report: &mut CompactReport) -> Result<bool> {
+    let mut is_empty = true;
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let is_real_dir = fs::symlink_metadata(&path)
+            .map(|meta| meta.is_dir())
+            .unwrap_or(false);
+        if !(is_real_dir && remove_empty_dirs(&path, report)?) {
+            is_empty = false;
+        }
+    }
+    if !is_empty {
+        return Ok(false);
+    }
+    let size = fs::metadata(dir).map(|meta| meta.len()).unwrap_or(0);
+    fs::remove_dir(dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    report.removed_dirs += 1;
+    report.reclaimed_bytes += size;
+    Ok(true)
 }
 
 // --- Legacy migration ---
@@ -342,6 +1028,7 @@ fn scan_legacy_metadata(data_dir: &Path) -> Result<SearchIndex> {
                         };
                         index.insert(meta.hash.clone(), SearchIndexRecord {
                             hash: meta.hash.clone(),
+                            first_seen: meta.first_seen,
                             last_seen: meta.last_seen,
                             kind: meta.kind.clone(),
                             copy_count: meta.copy_count,
@@ -350,6 +1037,8 @@ fn scan_legacy_metadata(data_dir: &Path) -> Result<SearchIndex> {
                             detected_formats: meta.detected_formats.clone(),
                             byte_size: meta.byte_size,
                             relative_path: meta.relative_path.clone(),
+                            pinned: meta.pinned,
+                            source_app: meta.source_app.clone(),
                         });
                     }
                 }
@@ -400,73 +1089,345 @@ fn read_dir_sorted(path: &Path) -> Result<Vec<fs::DirEntry>> {
 
 // --- Core storage operations ---
 
-pub fn store_snapshot(snapshot: ClipboardSnapshot) -> Result<EntryMetadata> {
-    let plugin_captures = plugins::capture_plugins(&snapshot);
+pub fn store_snapshot(mut snapshot: ClipboardSnapshot) -> Result<EntryMetadata> {
+    let config = load_config()?;
+    transform::apply_transforms(&mut snapshot, config.capture_transforms());
+    // HTML is only dropped when the text plugin keeps the plain text instead.
+    if config.drops_redundant_html() && config.is_plugin_enabled("text") {
+        plugins::drop_redundant_html(&mut snapshot);
+    }
+    let dedupe_by_text = config.dedupes_by_text();
+    let hash = snapshot.storage_hash(dedupe_by_text);
+    let timestamp = snapshot.copied_at.unwrap_or_else(time::now);
+
+    let window = config.collapse_window();
+    let index = load_index()?;
+    let stored = index.get(&hash);
+    if snapshot.change_count.is_some() && stored.is_some() {
+        let existing = load_metadata(&hash)?;
+        if is_repeat_capture(&existing, &hash, snapshot.change_count) {
+            return Ok(existing);
+        }
+    }
+    if is_quick_repeat(stored, timestamp, window) {
+        return touch_entry(&hash, timestamp, snapshot.change_count);
+    }
+
+    let capture_ctx = plugins::CaptureContext::from_config(&config);
+    let mut plugin_captures = plugins::capture_plugins(&snapshot, &capture_ctx);
+    plugin_captures.retain(|capture| config.is_plugin_enabled(capture.plugin_id));
+    if let Some(max_formats) = config.max_formats {
+        plugin_captures = plugins::limit_captures(plugin_captures, max_formats);
+    }
     anyhow::ensure!(
         !plugin_captures.is_empty(),
         "No clipboard plugins matched snapshot"
     );
 
-    let hash = snapshot.compute_hash();
-    let timestamp = time::now();
     let sources = snapshot.sources();
     let detected_formats = snapshot.detected_formats.clone();
     let summary_hint = snapshot.summary.clone();
 
-    persist_entry(
+    let origin = CaptureOrigin {
+        source_app: snapshot.source_app,
+        change_count: snapshot.change_count,
+    };
+    let metadata = persist_entry(
         &hash,
         timestamp,
         &plugin_captures,
-        summary_hint,
-        detected_formats,
-        sources,
-        CopyCountMode::Increment,
-        None,
-        None,
-    )
-}
+        PersistOptions {
+            summary_hint,
+            detected_formats,
+            base_sources: sources,
+            copy_mode: CopyCountMode::Increment,
+            copy_events: &[],
+            search_override: None,
+            first_seen_override: None,
+            keep_existing_formats: dedupe_by_text,
+            summary_override: None,
+            origin,
+        },
+    )?;
 
-pub fn store_json_item(item: &plugins::ClipboardJsonFullItem) -> Result<EntryMetadata> {
-    let import = plugins::prepare_import(item)?;
-    let timestamp = match item.date.as_ref() {
-        Some(raw) => crate::util::time::parse_date(raw).unwrap_or_else(|_| time::now()),
-        None => time::now(),
-    };
-    let first_seen = match item.first_date.as_ref() {
-        Some(raw) => crate::util::time::parse_date(raw).ok(),
-        None => None,
-    };
-    let hash = if let Some(existing) = item
-        .id
-        .as_ref()
-        .map(|value| value.trim())
-        .filter(|value| !value.is_empty())
+    if config.prunes_on_capture()
+        && let Err(err) = apply_retention(&config, false)
     {
-        existing.to_string()
-    } else {
-        plugins::compute_json_item_hash(item)?
+        eprintln!("Failed to prune history: {err:#}");
+    }
+    Ok(metadata)
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    pub scanned: usize,
+    pub protected: usize,
+    pub pruned: usize,
+    pub dry_run: bool,
+}
+
+/// Applies the configured pruning policy. With `dry_run`, only reports what
+/// would be deleted.
+pub fn prune_history(dry_run: bool) -> Result<PruneReport> {
+    let config = load_config()?;
+    apply_retention(&config, dry_run)
+}
+
+/// Deletes whatever the configured pruning policy no longer keeps, working
+/// from the index alone. Pinned items and the newest item of each recently
+/// active source app are exempt.
+fn apply_retention(config: &AppConfig, dry_run: bool) -> Result<PruneReport> {
+    let index = load_index()?;
+    let mut report = PruneReport {
+        scanned: index.len(),
+        dry_run,
+        ..PruneReport::default()
     };
-    let detected_formats = if !item.detected_formats.is_empty() {
-        item.detected_formats.clone()
-    } else {
-        inferred_detected_formats(&import)
+    let Some(directive) = config.should_prune(index.len()) else {
+        return Ok(report);
     };
-    let summary = item.summary.clone();
-    let copy_count = item.copy_count.unwrap_or(1);
-    let sources = item.sources.clone();
-    let search_override = item.search_text.clone();
+    let mut ordered: Vec<&SearchIndexRecord> = index.values().collect();
+    ordered.sort_by(|a, b| a.cmp_newest_first(b));
 
-    persist_entry(
-        &hash,
-        timestamp,
-        &import.captures,
-        summary,
-        detected_formats,
-        sources,
-        CopyCountMode::Override(copy_count),
-        search_override,
-        first_seen,
-    )
+    let protected = protected_from_pruning(&ordered, config.keep_latest_per_app());
+    report.protected = protected.len();
+
+    let doomed = prune_candidates(&ordered, directive, &protected);
+    report.pruned = doomed.len();
+    if !dry_run {
+        for hash in &doomed {
+            delete_entry(hash).with_context(|| format!("Failed to prune {hash}"))?;
+        }
+    }
+    Ok(report)
+}
+
+/// Hashes pruning has to keep: pinned items and the newest item of each of
+/// the `keep_per_app` most recently active source apps. `ordered` must be
+/// newest first.
+fn protected_from_pruning(ordered: &[&SearchIndexRecord], keep_per_app: usize) -> HashSet<String> {
+    let mut protected = latest_per_app(
+        ordered
+            .iter()
+            .map(|record| (record.hash.as_str(), record.source_app.as_deref())),
+        keep_per_app,
+    );
+    protected.extend(
+        ordered
+            .iter()
+            .filter(|record| record.pinned)
+            .map(|record| record.hash.clone()),
+    );
+    protected
+}
+
+/// Hashes of the newest item from each of the `limit` most recently active
+/// source apps. `entries` must be ordered newest first.
+fn latest_per_app<'a>(
+    entries: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    limit: usize,
+) -> HashSet<String> {
+    let mut apps = HashSet::new();
+    let mut protected = HashSet::new();
+    for (hash, app) in entries {
+        if apps.len() >= limit {
+            break;
+        }
+        if let Some(app) = app
+            && apps.insert(app)
+        {
+            protected.insert(hash.to_string());
+        }
+    }
+    protected
+}
+
+/// Entries `directive` evicts, oldest first, never including `protected`.
+fn prune_candidates(
+    ordered: &[&SearchIndexRecord],
+    directive: PruneDirective,
+    protected: &HashSet<String>,
+) -> Vec<String> {
+    let evictable = ordered
+        .iter()
+        .rev()
+        .filter(|record| !protected.contains(&record.hash));
+    match directive {
+        PruneDirective::ByCount(excess) => evictable
+            .take(excess)
+            .map(|record| record.hash.clone())
+            .collect(),
+        PruneDirective::ByDate(cutoff) => evictable
+            .filter(|record| record.last_seen < cutoff)
+            .map(|record| record.hash.clone())
+            .collect(),
+    }
+}
+
+/// True when the stored entry for this copy was last seen within `window`,
+/// i.e. the same thing was copied again moments ago.
+fn is_quick_repeat(
+    stored: Option<&SearchIndexRecord>,
+    now: OffsetDateTime,
+    window: ::time::Duration,
+) -> bool {
+    window > ::time::Duration::ZERO && stored.is_some_and(|record| now - record.last_seen <= window)
+}
+
+/// Bumps the copy count and last-seen time of an existing entry without
+/// touching its stored files.
+fn touch_entry(
+    hash: &str,
+    timestamp: OffsetDateTime,
+    change_count: Option<isize>,
+) -> Result<EntryMetadata> {
+    let mut metadata = load_metadata(hash)?;
+    apply_copy_mode(&mut metadata, CopyCountMode::Increment, &[], timestamp, None);
+    if let Some(change_count) = change_count {
+        record_change_count(&mut metadata, change_count);
+    }
+    commit_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+const CHANGE_COUNT_KEY: &str = "changeCount";
+
+/// Pasteboard change count stored in `extra` when the entry was last captured.
+pub fn recorded_change_count(metadata: &EntryMetadata) -> Option<isize> {
+    metadata
+        .extra
+        .get(CHANGE_COUNT_KEY)
+        .and_then(Value::as_i64)
+        .map(|count| count as isize)
+}
+
+/// Stores `change_count` in `extra`, returning whether it differed.
+fn record_change_count(metadata: &mut EntryMetadata, change_count: isize) -> bool {
+    if recorded_change_count(metadata) == Some(change_count) {
+        return false;
+    }
+    if !metadata.extra.is_object() {
+        metadata.extra = Value::Object(Map::new());
+    }
+    if let Some(root) = metadata.extra.as_object_mut() {
+        root.insert(CHANGE_COUNT_KEY.into(), Value::from(change_count as i64));
+    }
+    true
+}
+
+/// True when `existing` was already captured from this very pasteboard
+/// change, e.g. `capture-now` run twice without copying anything new.
+fn is_repeat_capture(existing: &EntryMetadata, hash: &str, change_count: Option<isize>) -> bool {
+    existing.hash == hash
+        && change_count.is_some_and(|count| recorded_change_count(existing) == Some(count))
+}
+
+/// Stores an item posted to `/save`. One that is already stored, typically
+/// sent by a mirror, is merged as in a sync import rather than overwritten.
+pub fn store_json_item(item: &plugins::ClipboardJsonFullItem) -> Result<EntryMetadata> {
+    let hash = json_item_hash(item)?;
+    let dates = json_item_dates(item)?;
+    let exists = load_index()?.contains_key(&hash);
+    let copy_count = item.copy_count.unwrap_or(1);
+    let mode = conflict_copy_mode(ImportConflict::SyncMerge, exists, copy_count)
+        .unwrap_or(CopyCountMode::Override(copy_count));
+    persist_json_item(item, &hash, dates, mode)
+}
+
+/// Stores an imported item, resolving a clash with an existing entry of the
+/// same hash according to `policy`.
+pub fn import_json_item(
+    item: &plugins::ClipboardJsonFullItem,
+    policy: ImportConflict,
+) -> Result<ImportOutcome> {
+    let hash = json_item_hash(item)?;
+    // Checked before a replaced entry is deleted.
+    let dates = json_item_dates(item)?;
+    let exists = load_index()?.contains_key(&hash);
+    let copy_count = item.copy_count.unwrap_or(1);
+    let Some(mode) = conflict_copy_mode(policy, exists, copy_count) else {
+        return Ok(ImportOutcome::Skipped(hash));
+    };
+    if exists && policy == ImportConflict::Replace {
+        delete_entry(&hash)?;
+    }
+    persist_json_item(item, &hash, dates, mode)
+        .map(|metadata| ImportOutcome::Stored(Box::new(metadata)))
+}
+
+/// The item's `date` and `firstDate`, when given. An item with a date that
+/// doesn't parse is rejected rather than stored under a made-up one; `export
+/// verify` applies the same rule.
+pub fn json_item_dates(
+    item: &plugins::ClipboardJsonFullItem,
+) -> Result<(Option<OffsetDateTime>, Option<OffsetDateTime>)> {
+    let parse = |raw: Option<&String>| {
+        raw.map(|raw| time::parse_date(raw).with_context(|| format!("Invalid date {raw}")))
+            .transpose()
+    };
+    Ok((parse(item.date.as_ref())?, parse(item.first_date.as_ref())?))
+}
+
+fn conflict_copy_mode(
+    policy: ImportConflict,
+    exists: bool,
+    copy_count: u64,
+) -> Option<CopyCountMode> {
+    match (policy, exists) {
+        (ImportConflict::Skip, true) => None,
+        (ImportConflict::Merge, true) => Some(CopyCountMode::Merge(copy_count)),
+        (ImportConflict::SyncMerge, true) => Some(CopyCountMode::Sync(copy_count)),
+        _ => Some(CopyCountMode::Override(copy_count)),
+    }
+}
+
+fn json_item_hash(item: &plugins::ClipboardJsonFullItem) -> Result<String> {
+    match item
+        .id
+        .as_ref()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+    {
+        Some(existing) => Ok(existing.to_string()),
+        None => plugins::compute_json_item_hash(item),
+    }
+}
+
+fn persist_json_item(
+    item: &plugins::ClipboardJsonFullItem,
+    hash: &str,
+    (date, first_seen): (Option<OffsetDateTime>, Option<OffsetDateTime>),
+    copy_mode: CopyCountMode,
+) -> Result<EntryMetadata> {
+    let import = plugins::prepare_import(item)?;
+    let timestamp = date.unwrap_or_else(time::now);
+    let detected_formats = if !item.detected_formats.is_empty() {
+        item.detected_formats.clone()
+    } else {
+        inferred_detected_formats(&import)
+    };
+    let summary = item.summary.clone();
+    let sources = item.sources.clone();
+    let search_override = item.search_text.clone();
+
+    persist_entry(
+        hash,
+        timestamp,
+        &import.captures,
+        PersistOptions {
+            summary_hint: summary,
+            detected_formats,
+            base_sources: sources,
+            copy_mode,
+            copy_events: &item.copy_events,
+            search_override,
+            first_seen_override: first_seen,
+            keep_existing_formats: false,
+            summary_override: item.summary_override.clone(),
+            origin: CaptureOrigin::default(),
+        },
+    )
 }
 
 pub fn copy_json_item(item: &plugins::ClipboardJsonFullItem) -> Result<()> {
@@ -475,7 +1436,7 @@ pub fn copy_json_item(item: &plugins::ClipboardJsonFullItem) -> Result<()> {
         !import.clipboard_contents.is_empty(),
         "Clipboard payload included no formats"
     );
-    let ctx = ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+    let ctx = clipboard_context()?;
     ctx.set(import.clipboard_contents)
         .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
     Ok(())
@@ -484,6 +1445,19 @@ pub fn copy_json_item(item: &plugins::ClipboardJsonFullItem) -> Result<()> {
 fn inferred_detected_formats(import: &plugins::ClipboardJsonImport) -> Vec<String> {
     let mut formats = Vec::new();
     for capture in &import.captures {
+        if capture.plugin_id == "passthrough" {
+            let utis = capture.metadata["formats"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|format| format["uti"].as_str());
+            for uti in utis {
+                if !formats.iter().any(|existing| existing == uti) {
+                    formats.push(uti.to_string());
+                }
+            }
+            continue;
+        }
         let label = match capture.plugin_id {
             "text" => "public.utf8-plain-text",
             "html" => "public.html",
@@ -568,40 +1542,87 @@ fn combine_sources(captures: &[PluginCapture], base_sources: Vec<String>) -> Vec
     combined
 }
 
+/// Name of the optional link to an entry's primary file, see
+/// `stable_content_file`.
+pub const STABLE_CONTENT_FILENAME: &str = "content";
+
+/// Points the entry's `content` file at `target`, replacing any earlier one.
+/// It is a relative symlink where supported and a copy elsewhere.
+fn link_stable_content(item_dir: &Path, target: &str) -> Result<()> {
+    let link = item_dir.join(STABLE_CONTENT_FILENAME);
+    match fs::remove_file(&link) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            return Err(err).with_context(|| format!("Failed to remove {}", link.display()));
+        }
+        _ => {}
+    }
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(target, &link);
+    #[cfg(not(unix))]
+    let result = fs::copy(item_dir.join(target), &link).map(|_| ());
+    result.with_context(|| format!("Failed to write {}", link.display()))
+}
+
+/// Writes one plugin output into the entry directory. With `shared_blobs`
+/// set to the data directory, stashed files go through the shared blob store.
+/// Existing files are replaced rather than rewritten in place, since they may
+/// be links to a blob other entries share.
+fn write_capture_file(
+    item_dir: &Path,
+    output: &FileOutput,
+    shared_blobs: Option<&Path>,
+) -> Result<()> {
+    let dest = item_dir.join(&output.filename);
+    if let Some(parent) = dest.parent() {
+        layout::ensure_dir(parent)?;
+    }
+    if let Some(data_dir) = shared_blobs
+        && plugins::is_stashed_file(&output.filename)
+    {
+        return blobs::write_shared(data_dir, &dest, &output.bytes);
+    }
+    let _ = fs::remove_file(&dest);
+    fs::write(&dest, &output.bytes)
+        .with_context(|| format!("Failed to write snapshot content to {}", dest.display()))
+}
+
 fn persist_entry(
     hash: &str,
     timestamp: OffsetDateTime,
     plugin_captures: &[PluginCapture],
-    summary_hint: Option<String>,
-    detected_formats: Vec<String>,
-    base_sources: Vec<String>,
-    copy_mode: CopyCountMode,
-    search_override: Option<String>,
-    first_seen_override: Option<OffsetDateTime>,
+    options: PersistOptions<'_>,
 ) -> Result<EntryMetadata> {
     anyhow::ensure!(!plugin_captures.is_empty(), "No plugin captures available");
+    let PersistOptions {
+        summary_hint,
+        detected_formats,
+        base_sources,
+        copy_mode,
+        copy_events,
+        search_override,
+        first_seen_override,
+        keep_existing_formats,
+        summary_override,
+        origin,
+    } = options;
 
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
     let item_dir = layout::item_dir(&data_dir, hash);
     layout::ensure_dir(&item_dir)?;
 
+    let shared_blobs = config.shares_stashed_files().then_some(data_dir.as_path());
     let mut wrote_file = false;
     for capture in plugin_captures {
         for output in &capture.files {
-            let dest = item_dir.join(&output.filename);
-            if let Some(parent) = dest.parent() {
-                layout::ensure_dir(parent)?;
-            }
-            fs::write(&dest, &output.bytes).with_context(|| {
-                format!("Failed to write snapshot content to {}", dest.display())
-            })?;
+            write_capture_file(&item_dir, output, shared_blobs)?;
             wrote_file = true;
         }
     }
     anyhow::ensure!(wrote_file, "No plugin produced persisted files");
 
-    let prioritized = plugins::prioritized_capture(plugin_captures).unwrap_or(&plugin_captures[0]);
+    let prioritized = plugins::primary_capture(plugin_captures, config.mixed_capture_primary())
+        .unwrap_or(&plugin_captures[0]);
     let primary = prioritized
         .files
         .first()
@@ -635,7 +1656,7 @@ fn persist_entry(
 
     let combined_sources = combine_sources(plugin_captures, base_sources);
 
-    let plugin_order = plugins::plugin_order(plugin_captures);
+    let plugin_order = plugins::plugin_order_led_by(plugin_captures, prioritized.plugin_id);
     let mut plugin_meta_map = Map::new();
     for capture in plugin_captures {
         plugin_meta_map.insert(capture.plugin_id.to_string(), capture.metadata.clone());
@@ -653,32 +1674,49 @@ fn persist_entry(
     let relative_path = layout::relative_path_for_hash(hash);
     let metadata_path = item_dir.join("metadata.json");
 
-    let metadata = if metadata_path.exists() {
+    let mut metadata = if metadata_path.exists() {
         let mut existing: EntryMetadata = serde_json::from_slice(&fs::read(&metadata_path)?)?;
-        existing.last_seen = timestamp;
+        apply_copy_mode(
+            &mut existing,
+            copy_mode,
+            copy_events,
+            timestamp,
+            first_seen_override,
+        );
         existing.byte_size = total_byte_size;
-        existing.summary = Some(summary.clone());
-        existing.search_text = search_text.clone();
-        existing.detected_formats = detected_formats.clone();
+        if existing.summary_override.is_none() {
+            existing.summary = Some(summary.clone());
+        }
+        existing.search_text =
+            search_text_with_override(search_text.clone(), existing.summary_override.as_deref());
         existing.sources = combined_sources.clone();
         existing.files = combined_sources.clone();
         existing.content_filename = primary.clone();
-        existing.extra = extra.clone();
-        existing.kind = entry_kind.clone();
-        existing.relative_path = relative_path;
-        match copy_mode {
-            CopyCountMode::Increment => {
-                existing.copy_count = existing.copy_count.saturating_add(1);
-            }
-            CopyCountMode::Override(value) => {
-                existing.copy_count = value.max(1);
+        if keep_existing_formats {
+            existing.byte_size += carried_over_bytes(&item_dir, &existing.extra, plugin_captures);
+            existing.extra = merge_plugin_extra(&existing.extra, extra.clone());
+            for format in &detected_formats {
+                if !existing.detected_formats.contains(format) {
+                    existing.detected_formats.push(format.clone());
+                }
             }
+        } else {
+            existing.extra = extra.clone();
+            existing.detected_formats = detected_formats.clone();
         }
+        existing.kind = entry_kind.clone();
+        existing.relative_path = relative_path;
         existing
     } else {
         let copy_count = match copy_mode {
             CopyCountMode::Increment => 1,
-            CopyCountMode::Override(value) => value.max(1),
+            CopyCountMode::Override(value)
+            | CopyCountMode::Merge(value)
+            | CopyCountMode::Sync(value) => value.max(1),
+        };
+        let copy_events = match copy_mode {
+            CopyCountMode::Increment => vec![time::unix_millis(timestamp)],
+            _ => merge_copy_events(&[], copy_events),
         };
         EntryMetadata {
             hash: hash.to_string(),
@@ -690,28 +1728,39 @@ fn persist_entry(
             byte_size: total_byte_size,
             sources: combined_sources.clone(),
             summary: Some(summary.clone()),
+            summary_override: None,
+            primary_plugin: None,
             search_text: search_text.clone(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             relative_path,
             content_filename: primary.clone(),
             files: combined_sources.clone(),
             extra: extra.clone(),
+            tags: Vec::new(),
+            pinned: false,
+            paste_count: 0,
+            last_pasted: None,
+            source_app: None,
+            copy_events,
         }
     };
+    if summary_override.is_some() {
+        apply_summary_override(&mut metadata, summary_override.as_deref());
+    }
+    apply_primary_plugin(&mut metadata);
+    if origin.source_app.is_some() {
+        metadata.source_app = origin.source_app;
+    }
+    if let Some(change_count) = origin.change_count {
+        record_change_count(&mut metadata, change_count);
+    }
 
+    if config.writes_stable_content_file() {
+        link_stable_content(&item_dir, &metadata.content_filename)?;
+    }
     fs::write(&metadata_path, serde_json::to_vec_pretty(&metadata)?)?;
 
-    let record = SearchIndexRecord {
-        hash: metadata.hash.clone(),
-        last_seen: metadata.last_seen,
-        kind: metadata.kind.clone(),
-        copy_count: metadata.copy_count,
-        summary: metadata.summary.clone(),
-        search_text: metadata.search_text.clone(),
-        detected_formats: metadata.detected_formats.clone(),
-        byte_size: metadata.byte_size,
-        relative_path: metadata.relative_path.clone(),
-    };
+    let record = index_record(&metadata);
     let journal_entry = JournalEntry::from_record(&record);
     mutate_index(|idx| {
         idx.insert(record.hash.clone(), record);
@@ -721,6 +1770,129 @@ fn persist_entry(
     Ok(metadata)
 }
 
+/// On-disk size of the formats in `extra` that none of `captures` replaces,
+/// which `merge_plugin_extra` keeps alongside the new ones.
+fn carried_over_bytes(item_dir: &Path, extra: &Value, captures: &[PluginCapture]) -> u64 {
+    let Some(stored) = extra.get("plugins").and_then(Value::as_object) else {
+        return 0;
+    };
+    stored
+        .iter()
+        .filter(|(id, _)| {
+            !captures
+                .iter()
+                .any(|capture| capture.plugin_id == id.as_str())
+        })
+        .filter_map(|(_, meta)| meta.get("storedFiles").and_then(Value::as_array))
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|filename| {
+            fs::metadata(item_dir.join(filename))
+                .map(|meta| meta.len())
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Carries over plugin formats from `previous` that `current` lacks, so a
+/// plain-text copy doesn't discard formats an earlier rich copy stored. The
+/// merged order follows plugin priority; `persist_entry` then moves the
+/// entry's `primary_plugin` back to the front.
+fn merge_plugin_extra(previous: &Value, mut current: Value) -> Value {
+    let (Some(prev_plugins), Some(root)) = (
+        previous.get("plugins").and_then(Value::as_object),
+        current.as_object_mut(),
+    ) else {
+        return current;
+    };
+    let mut merged = root
+        .get("plugins")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    for (id, meta) in prev_plugins {
+        merged.entry(id.clone()).or_insert_with(|| meta.clone());
+    }
+    let mut order: Vec<String> = merged.keys().cloned().collect();
+    order.sort_by_key(|id| plugins::plugin_by_id(id).map_or(u8::MAX, |plugin| plugin.priority()));
+    root.insert("plugins".into(), Value::Object(merged));
+    root.insert(
+        "pluginOrder".into(),
+        Value::Array(order.into_iter().map(Value::String).collect()),
+    );
+    current
+}
+
+fn apply_copy_mode(
+    existing: &mut EntryMetadata,
+    copy_mode: CopyCountMode,
+    copy_events: &[i64],
+    timestamp: OffsetDateTime,
+    first_seen: Option<OffsetDateTime>,
+) {
+    match copy_mode {
+        CopyCountMode::Increment => {
+            existing.last_seen = timestamp;
+            existing.copy_count = existing.copy_count.saturating_add(1);
+            existing.copy_events =
+                merge_copy_events(&existing.copy_events, &[time::unix_millis(timestamp)]);
+        }
+        CopyCountMode::Override(value) => {
+            existing.last_seen = timestamp;
+            existing.copy_count = value.max(1);
+            existing.copy_events = merge_copy_events(&[], copy_events);
+        }
+        CopyCountMode::Merge(value) => {
+            existing.last_seen = existing.last_seen.max(timestamp);
+            if let Some(first_seen) = first_seen {
+                existing.first_seen = existing.first_seen.min(first_seen);
+            }
+            existing.copy_count = existing.copy_count.saturating_add(value.max(1));
+            existing.copy_events = merge_copy_events(&existing.copy_events, copy_events);
+        }
+        CopyCountMode::Sync(value) => {
+            existing.last_seen = existing.last_seen.max(timestamp);
+            if let Some(first_seen) = first_seen {
+                existing.first_seen = existing.first_seen.min(first_seen);
+            }
+            // Without event history from the other side the best conflict-free
+            // guess is the larger count, never the sum.
+            let synced = existing
+                .copy_count
+                .saturating_add(unseen_copy_events(&existing.copy_events, copy_events));
+            existing.copy_count = synced.max(value.max(1));
+            existing.copy_events = merge_copy_events(&existing.copy_events, copy_events);
+        }
+    }
+}
+
+/// Number of distinct events in `incoming` that `known` doesn't have. Once
+/// `known` is full, events older than its oldest one may have been trimmed
+/// and are assumed to be counted already.
+fn unseen_copy_events(known: &[i64], incoming: &[i64]) -> u64 {
+    let horizon = (known.len() >= MAX_COPY_EVENTS)
+        .then(|| known.iter().min().copied())
+        .flatten();
+    let known: HashSet<i64> = known.iter().copied().collect();
+    let unseen: HashSet<i64> = incoming
+        .iter()
+        .copied()
+        .filter(|event| !known.contains(event))
+        .filter(|event| horizon.is_none_or(|oldest| *event > oldest))
+        .collect();
+    unseen.len() as u64
+}
+
+/// Sorted union of both event lists, keeping the newest `MAX_COPY_EVENTS`.
+fn merge_copy_events(known: &[i64], incoming: &[i64]) -> Vec<i64> {
+    let mut events: Vec<i64> = known.iter().chain(incoming).copied().collect();
+    events.sort_unstable();
+    events.dedup();
+    let overflow = events.len().saturating_sub(MAX_COPY_EVENTS);
+    events.drain(..overflow);
+    events
+}
+
 fn clip_search_text(input: &str) -> String {
     clip_search_text_to_max(input, MAX_SEARCH_TEXT_CHARS)
 }
@@ -850,61 +2022,238 @@ pub fn history_stream(
     Ok(items.into_iter())
 }
 
+/// Entries kept by the metadata cache.
+const METADATA_CACHE_CAPACITY: usize = 256;
+
+struct CachedMetadata {
+    hash: String,
+    modified: SystemTime,
+    metadata: EntryMetadata,
+}
+
+/// The most recently loaded `metadata.json` files, newest first, so the API
+/// doesn't re-read and re-parse hot items on every request. Everything is
+/// dropped when the index generation moves, and an entry whose file changed
+/// on disk since it was cached (another process wrote it) is a miss.
+struct MetadataCache {
+    generation: u64,
+    entries: VecDeque<CachedMetadata>,
+}
+
+impl MetadataCache {
+    const fn new() -> Self {
+        MetadataCache {
+            generation: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn sync_generation(&mut self, generation: u64) {
+        if generation != self.generation {
+            self.entries.clear();
+            self.generation = generation;
+        }
+    }
+
+    fn get(&mut self, generation: u64, hash: &str, modified: SystemTime) -> Option<EntryMetadata> {
+        self.sync_generation(generation);
+        let position = self.entries.iter().position(|entry| entry.hash == hash)?;
+        let entry = self.entries.remove(position)?;
+        if entry.modified != modified {
+            return None;
+        }
+        let metadata = entry.metadata.clone();
+        self.entries.push_front(entry);
+        Some(metadata)
+    }
+
+    fn insert(&mut self, generation: u64, modified: SystemTime, metadata: EntryMetadata) {
+        self.sync_generation(generation);
+        self.forget(&metadata.hash);
+        self.entries.push_front(CachedMetadata {
+            hash: metadata.hash.clone(),
+            modified,
+            metadata,
+        });
+        self.entries.truncate(METADATA_CACHE_CAPACITY);
+    }
+
+    fn forget(&mut self, hash: &str) {
+        self.entries.retain(|entry| entry.hash != hash);
+    }
+}
+
+static METADATA_CACHE: Mutex<MetadataCache> = Mutex::new(MetadataCache::new());
+
+/// Drops `hash` from the metadata cache after a write that doesn't go
+/// through the index.
+fn forget_cached_metadata(hash: &str) {
+    METADATA_CACHE.lock().forget(hash);
+}
+
 /// O(1) metadata lookup — constructs path directly from hash
 pub fn load_metadata(hash: &str) -> Result<EntryMetadata> {
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
-    let meta_path = layout::metadata_path(&data_dir, hash);
-
-    if meta_path.exists() {
-        let meta: EntryMetadata = serde_json::from_slice(&fs::read(&meta_path)?)
-            .with_context(|| format!("Failed to parse metadata for {hash}"))?;
-        return Ok(meta);
+    let generation = index_generation();
+    let modified = fs::metadata(layout::metadata_path(&data_dir, hash))
+        .and_then(|meta| meta.modified())
+        .ok();
+    if let Some(modified) = modified
+        && let Some(metadata) = METADATA_CACHE.lock().get(generation, hash, modified)
+    {
+        return Ok(metadata);
     }
+    let metadata = read_metadata(&data_dir, hash)?;
+    if let Some(modified) = modified {
+        METADATA_CACHE
+            .lock()
+            .insert(generation, modified, metadata.clone());
+    }
+    Ok(metadata)
+}
 
-    Err(anyhow!("Metadata not found for {hash}"))
+pub fn read_metadata(data_dir: &Path, hash: &str) -> Result<EntryMetadata, ClipboardError> {
+    let meta_path = layout::metadata_path(data_dir, hash);
+    if !meta_path.exists() {
+        return Err(ClipboardError::NotFound(format!("Metadata not found for {hash}")));
+    }
+    let raw = fs::read(&meta_path).map_err(|err| {
+        ClipboardError::io(format!("Failed to read {}", meta_path.display()), err)
+    })?;
+    serde_json::from_slice(&raw)
+        .map_err(|err| ClipboardError::Parse(format!("Failed to parse metadata for {hash}: {err}")))
 }
 
 pub fn resolve_selector(
     index: &SearchIndex,
     selector: &str,
     filter: &SelectionFilter,
-) -> Result<String> {
+) -> Result<String, ClipboardError> {
     if selector.len() >= 6 {
         if let Some(record) = index.get(selector) {
             if !filter.matches(record) {
-                anyhow::bail!("Selector did not match active filters");
+                return Err(ClipboardError::NotFound(
+                    "Selector did not match active filters".into(),
+                ));
             }
             return Ok(record.hash.clone());
         }
     }
     let offset: usize = selector
         .parse()
-        .with_context(|| format!("Invalid selector {selector}"))?;
+        .map_err(|_| ClipboardError::InvalidInput(format!("Invalid selector {selector}")))?;
     let mut records: Vec<_> = index
         .values()
         .filter(|record| filter.matches(record))
         .collect();
-    records.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    records.sort_by(|a, b| a.cmp_newest_first(b));
     if let Some(record) = records.get(offset) {
         Ok(record.hash.clone())
     } else {
-        Err(anyhow!("No entry at offset {offset}"))
+        Err(ClipboardError::NotFound(format!("No entry at offset {offset}")))
     }
 }
 
+/// Resolves the item next to the last one Clippy copied, honouring the same
+/// newest-first ordering and filters as numeric selectors.
+pub fn resolve_relative(
+    index: &SearchIndex,
+    direction: Direction,
+    filter: &SelectionFilter,
+) -> Result<String> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let mut records: Vec<_> = index
+        .values()
+        .filter(|record| filter.matches(record))
+        .collect();
+    records.sort_by(|a, b| a.cmp_newest_first(b));
+    let ordered: Vec<String> = records.iter().map(|record| record.hash.clone()).collect();
+    let current = cursor::load_last_copied(&data_dir);
+    cursor::step(&ordered, current.as_deref(), direction)
+        .ok_or_else(|| anyhow!("No entries to navigate"))
+}
+
 pub fn copy_by_selector(hash: &str) -> Result<EntryMetadata> {
+    copy_formats_by_selector(hash, None)
+}
+
+/// Copies only the listed plugin formats of an entry, or all of them when
+/// `formats` is `None`.
+pub fn copy_formats_by_selector(hash: &str, formats: Option<&[String]>) -> Result<EntryMetadata> {
     let metadata = load_metadata(hash)?;
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
     let item_dir = data_dir.join(&metadata.relative_path);
-    let contents = plugins::rebuild_clipboard_contents(&metadata, &item_dir)?;
-    let ctx = ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+    let contents = plugins::rebuild_clipboard_formats(&metadata, &item_dir, formats)?;
+    // A subset reads back as different content, so the watcher sees it under
+    // another hash than the entry's own.
+    let copied_hash = match formats {
+        Some(_) => ClipboardSnapshot::from_contents(&contents, config.follows_symlinks())
+            .storage_hash(config.dedupes_by_text()),
+        None => metadata.hash.clone(),
+    };
+    let ctx = clipboard_context()?;
     ctx.set(contents)
         .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
+    note_self_copy(&data_dir, &copied_hash, &metadata.hash);
+    Ok(metadata)
+}
+
+/// Puts the entry's full JSON record on the clipboard as text instead of its
+/// content. The watcher is told to ignore the resulting clipboard change.
+pub fn copy_metadata_by_selector(hash: &str) -> Result<EntryMetadata> {
+    let metadata = load_metadata(hash)?;
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let payload = metadata_json(&metadata, &item_dir)?;
+    let ctx = clipboard_context()?;
+    ctx.set_text(payload.clone())
+        .map_err(|e| anyhow!("Failed to set clipboard text: {e}"))?;
+    if let Err(err) =
+        guard::record_self_copy(&data_dir, &ClipboardSnapshot::text_only_hash(&payload))
+    {
+        eprintln!("Failed to record self copy: {err:?}");
+    }
     Ok(metadata)
 }
 
+/// Puts plain `text` on the clipboard without storing it, and tells the
+/// watcher to ignore the change.
+pub fn copy_untracked_text(text: &str) -> Result<()> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let ctx = clipboard_context()?;
+    ctx.set_text(text.to_string())
+        .map_err(|e| anyhow!("Failed to set clipboard text: {e}"))?;
+    if let Err(err) = guard::record_self_copy(&data_dir, &ClipboardSnapshot::text_only_hash(text))
+    {
+        eprintln!("Failed to record self copy: {err:?}");
+    }
+    Ok(())
+}
+
+/// Pretty-printed `build_full_json_item` record for an entry.
+fn metadata_json(metadata: &EntryMetadata, item_dir: &Path) -> Result<String> {
+    let item = plugins::build_full_json_item(metadata, item_dir, None, None)?;
+    serde_json::to_string_pretty(&item).context("Failed to serialize item metadata")
+}
+
+/// Tells the watcher to skip `copied_hash`, the hash the clipboard will read
+/// back as, and moves the `--relative` cursor to the entry itself. The two
+/// differ whenever a copy puts only part of the entry (or derived text) on
+/// the clipboard.
+fn note_self_copy(data_dir: &Path, copied_hash: &str, entry_hash: &str) {
+    if let Err(err) = guard::record_self_copy(data_dir, copied_hash) {
+        eprintln!("Failed to record self copy: {err:?}");
+    }
+    if let Err(err) = cursor::record_last_copied(data_dir, entry_hash) {
+        eprintln!("Failed to record last copied item: {err:?}");
+    }
+}
+
 pub fn copy_plain_by_selector(hash: &str) -> Result<EntryMetadata> {
     let metadata = load_metadata(hash)?;
     let config = load_config()?;
@@ -924,10 +2273,10 @@ pub fn copy_plain_by_selector(hash: &str) -> Result<EntryMetadata> {
 
     if has_image && !has_text && !has_html && !has_rtf && !has_files {
         let contents = plugins::rebuild_clipboard_contents(&metadata, &item_dir)?;
-        let ctx =
-            ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+        let ctx = clipboard_context()?;
         ctx.set(contents)
             .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
+        note_self_copy(&data_dir, &metadata.hash, &metadata.hash);
         return Ok(metadata);
     }
 
@@ -1020,62 +2369,402 @@ pub fn copy_plain_by_selector(hash: &str) -> Result<EntryMetadata> {
     }
 
     if let Some(text) = text_content {
-        let ctx =
-            ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+        let ctx = clipboard_context()?;
+        let text_hash = ClipboardSnapshot::text_only_hash(&text);
         ctx.set_text(text)
             .map_err(|e| anyhow!("Failed to set clipboard text: {e}"))?;
+        note_self_copy(&data_dir, &text_hash, &metadata.hash);
         return Ok(metadata);
     }
 
     let contents = plugins::rebuild_clipboard_contents(&metadata, &item_dir)?;
-    let ctx = ClipboardContext::new().map_err(|e| anyhow!("Failed to access clipboard: {e}"))?;
+    let ctx = clipboard_context()?;
     ctx.set(contents)
         .map_err(|e| anyhow!("Failed to set clipboard: {e}"))?;
+    note_self_copy(&data_dir, &metadata.hash, &metadata.hash);
     Ok(metadata)
 }
 
-pub fn delete_entry(hash: &str) -> Result<()> {
+/// Appends the item's text to whatever text is currently on the clipboard,
+/// building up a scratch buffer across copies.
+pub fn copy_appending_by_selector(hash: &str) -> Result<EntryMetadata> {
+    let metadata = load_metadata(hash)?;
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
-    let item_dir = layout::item_dir(&data_dir, hash);
-    if item_dir.exists() {
-        fs::remove_dir_all(&item_dir)?;
-    }
-    mutate_index(|idx| {
-        idx.remove(hash);
-    });
-    append_journal(&JournalEntry::delete(hash));
-    Ok(())
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let addition = item_text(&metadata, &item_dir)?
+        .ok_or_else(|| anyhow!("Item {hash} has no text to append"))?;
+
+    let ctx = clipboard_context()?;
+    let current = ctx.get_text().unwrap_or_default();
+    let combined = append_text(&current, &addition);
+    let text_hash = ClipboardSnapshot::text_only_hash(&combined);
+    ctx.set_text(combined)
+        .map_err(|e| anyhow!("Failed to set clipboard text: {e}"))?;
+    note_self_copy(&data_dir, &text_hash, &metadata.hash);
+    Ok(metadata)
 }
 
-pub fn increment_copy_count(hash: &str) -> Result<EntryMetadata> {
-    let mut metadata = load_metadata(hash)?;
-    metadata.copy_count = metadata.copy_count.saturating_add(1);
+/// Sets the clipboard text to the entry's image as a base64 `data:` URI, for
+/// pasting into HTML or CSS.
+pub fn copy_data_uri_by_selector(hash: &str) -> Result<EntryMetadata> {
+    let metadata = load_metadata(hash)?;
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
-    let item_dir = layout::item_dir(&data_dir, hash);
-    let metadata_path = item_dir.join("metadata.json");
-    fs::write(&metadata_path, serde_json::to_vec_pretty(&metadata)?)?;
+    let item_dir = data_dir.join(&metadata.relative_path);
+    let uri = item_data_uri(&metadata, &item_dir)?
+        .ok_or_else(|| anyhow!("Item {hash} has no image to copy as a data URI"))?;
 
-    let record = SearchIndexRecord {
-        hash: metadata.hash.clone(),
-        last_seen: metadata.last_seen,
-        kind: metadata.kind.clone(),
-        copy_count: metadata.copy_count,
-        summary: metadata.summary.clone(),
-        search_text: metadata.search_text.clone(),
-        detected_formats: metadata.detected_formats.clone(),
-        byte_size: metadata.byte_size,
-        relative_path: metadata.relative_path.clone(),
-    };
+    let ctx = clipboard_context()?;
+    let text_hash = ClipboardSnapshot::text_only_hash(&uri);
+    ctx.set_text(uri)
+        .map_err(|e| anyhow!("Failed to set clipboard text: {e}"))?;
+    note_self_copy(&data_dir, &text_hash, &metadata.hash);
+    Ok(metadata)
+}
+
+/// Runs `f` with the context of the entry's `plugin_id` capture, or returns
+/// `None` when the entry has no such capture.
+fn with_plugin_context<T>(
+    metadata: &EntryMetadata,
+    item_dir: &Path,
+    plugin_id: &str,
+    f: impl FnOnce(&dyn plugins::ClipboardPlugin, &plugins::PluginContext<'_>) -> Result<Option<T>>,
+) -> Result<Option<T>> {
+    let Some((_, map)) = plugins::extract_plugin_meta(metadata)? else {
+        return Ok(None);
+    };
+    let (Some(plugin_meta), Some(plugin)) = (map.get(plugin_id), plugins::plugin_by_id(plugin_id))
+    else {
+        return Ok(None);
+    };
+    let stored_files = plugins::load_plugin_files(item_dir, plugin_meta)?;
+    let ctx = plugins::PluginContext {
+        metadata,
+        plugin_meta,
+        item_dir,
+        stored_files: &stored_files,
+    };
+    f(plugin, &ctx)
+}
+
+fn item_text(metadata: &EntryMetadata, item_dir: &Path) -> Result<Option<String>> {
+    with_plugin_context(metadata, item_dir, "text", |plugin, ctx| {
+        Ok(plugin
+            .to_clipboard_items(ctx)?
+            .into_iter()
+            .find_map(|item| match item {
+                clipboard_rs::common::ClipboardContent::Text(text) => Some(text),
+                _ => None,
+            }))
+    })
+}
+
+/// The image capture's export form, which is already a `data:` URI.
+fn item_data_uri(metadata: &EntryMetadata, item_dir: &Path) -> Result<Option<String>> {
+    with_plugin_context(metadata, item_dir, "image", |plugin, ctx| {
+        Ok(plugin.export_json(ctx)?.as_str().map(str::to_string))
+    })
+}
+
+/// Joins `addition` onto `current`, starting it on a new line unless the
+/// buffer is empty or already ends with one.
+fn append_text(current: &str, addition: &str) -> String {
+    if current.is_empty() {
+        return addition.to_string();
+    }
+    let separator = if current.ends_with('\n') { "" } else { "\n" };
+    format!("{current}{separator}{addition}")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagAction {
+    Add(String),
+    Remove(String),
+    Pin,
+    Unpin,
+}
+
+/// Applies `action` to the stored metadata of `hash`. Returns whether the
+/// entry changed; unchanged entries are not rewritten.
+pub fn update_tags(hash: &str, action: &TagAction) -> Result<bool> {
+    let mut metadata = load_metadata(hash)?;
+    if !apply_tag_action(&mut metadata, action) {
+        return Ok(false);
+    }
+    if matches!(action, TagAction::Pin | TagAction::Unpin) {
+        // The index mirrors `pinned` so pruning can skip pins without
+        // reading every entry.
+        commit_metadata(&metadata)?;
+        return Ok(true);
+    }
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let metadata_path = layout::item_dir(&data_dir, hash).join("metadata.json");
+    fs::write(&metadata_path, serde_json::to_vec_pretty(&metadata)?)
+        .with_context(|| format!("Failed to write {}", metadata_path.display()))?;
+    forget_cached_metadata(hash);
+    flush::note_write(hash);
+    Ok(true)
+}
+
+fn apply_tag_action(metadata: &mut EntryMetadata, action: &TagAction) -> bool {
+    match action {
+        TagAction::Add(tag) => {
+            if metadata.tags.iter().any(|existing| existing == tag) {
+                return false;
+            }
+            metadata.tags.push(tag.clone());
+            true
+        }
+        TagAction::Remove(tag) => {
+            let before = metadata.tags.len();
+            metadata.tags.retain(|existing| existing != tag);
+            metadata.tags.len() != before
+        }
+        TagAction::Pin => !std::mem::replace(&mut metadata.pinned, true),
+        TagAction::Unpin => std::mem::replace(&mut metadata.pinned, false),
+    }
+}
+
+/// Makes `plugin_id` the entry's primary format, so copying and displaying
+/// the entry default to it, and copying it again keeps it. Returns false when
+/// the entry has no such format.
+pub fn set_primary_plugin(hash: &str, plugin_id: &str) -> Result<bool> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let mut metadata = read_metadata(&data_dir, hash)?;
+    if !promote_plugin(&mut metadata, plugin_id) {
+        return Ok(false);
+    }
+    if config.writes_stable_content_file() {
+        let item_dir = data_dir.join(&metadata.relative_path);
+        link_stable_content(&item_dir, &metadata.content_filename)?;
+    }
+    commit_metadata(&metadata)?;
+    Ok(true)
+}
+
+/// Gives the entry a custom summary that listings show instead of the
+/// generated one and that reindexing leaves alone. `None` or blank text goes
+/// back to the generated summary.
+pub fn set_summary_override(hash: &str, summary: Option<&str>) -> Result<EntryMetadata> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let mut metadata = read_metadata(&data_dir, hash)?;
+    apply_summary_override(&mut metadata, summary);
+    if metadata.summary_override.is_none() {
+        let item_dir = data_dir.join(&metadata.relative_path);
+        refresh_summary(&mut metadata, &item_dir)?;
+    }
+    commit_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+fn apply_summary_override(metadata: &mut EntryMetadata, summary: Option<&str>) {
+    let summary = summary.map(str::trim).filter(|summary| !summary.is_empty());
+    let generated = generated_search_text(metadata);
+    metadata.summary_override = summary.map(String::from);
+    metadata.search_text = search_text_with_override(generated, summary);
+    if let Some(summary) = summary {
+        metadata.summary = Some(summary.to_string());
+    }
+}
+
+/// Leads the search text with the custom summary, so a renamed entry is found
+/// by its new name as well as by its contents.
+fn search_text_with_override(search_text: Option<String>, summary: Option<&str>) -> Option<String> {
+    let Some(summary) = summary else {
+        return search_text;
+    };
+    match search_text {
+        Some(text) if text == summary || text.starts_with(&format!("{summary}\n\n")) => Some(text),
+        Some(text) => Some(clip_search_text(&format!("{summary}\n\n{text}"))),
+        None => Some(clip_search_text(summary)),
+    }
+}
+
+/// The entry's search text without the custom summary that
+/// `search_text_with_override` put in front of it.
+fn generated_search_text(metadata: &EntryMetadata) -> Option<String> {
+    let text = metadata.search_text.clone()?;
+    let Some(summary) = metadata.summary_override.as_deref() else {
+        return Some(text);
+    };
+    match text.strip_prefix(summary) {
+        Some("") => None,
+        Some(rest) => match rest.strip_prefix("\n\n") {
+            Some(generated) => Some(generated.to_string()),
+            None => Some(text),
+        },
+        None => Some(text),
+    }
+}
+
+fn promote_plugin(metadata: &mut EntryMetadata, plugin_id: &str) -> bool {
+    let Ok(Some((_, map))) = plugins::extract_plugin_meta(metadata) else {
+        return false;
+    };
+    if !map.contains_key(plugin_id) {
+        return false;
+    }
+    metadata.primary_plugin = Some(plugin_id.to_string());
+    apply_primary_plugin(metadata);
+    true
+}
+
+/// Moves the entry's `primary_plugin`, when it still holds that format, to
+/// the front of `pluginOrder` and points `content_filename` at its file.
+fn apply_primary_plugin(metadata: &mut EntryMetadata) {
+    let Some(plugin_id) = metadata.primary_plugin.clone() else {
+        return;
+    };
+    let Ok(Some((mut order, map))) = plugins::extract_plugin_meta(metadata) else {
+        return;
+    };
+    let Some(plugin_meta) = map.get(&plugin_id) else {
+        return;
+    };
+    if let Some(file) = plugin_meta
+        .get("storedFiles")
+        .and_then(|files| files.get(0))
+        .and_then(Value::as_str)
+    {
+        metadata.content_filename = file.to_string();
+    }
+    order.retain(|id| *id != plugin_id);
+    order.insert(0, plugin_id);
+    if let Some(root) = metadata.extra.as_object_mut() {
+        root.insert("pluginOrder".into(), Value::from(order));
+    }
+}
+
+#[cfg(not(test))]
+fn clipboard_context() -> Result<ClipboardContext, ClipboardError> {
+    ClipboardContext::new()
+        .map_err(|err| ClipboardError::Clipboard(format!("Failed to access clipboard: {err}")))
+}
+
+#[cfg(test)]
+fn clipboard_context() -> Result<test_clipboard::TestClipboard, ClipboardError> {
+    Ok(test_clipboard::TestClipboard)
+}
+
+/// In-memory stand-in for the pasteboard, so tests can run the copy commands
+/// without touching the real clipboard.
+#[cfg(test)]
+pub(crate) mod test_clipboard {
+    use clipboard_rs::common::ClipboardContent;
+    use parking_lot::Mutex;
+
+    static CONTENTS: Mutex<Vec<ClipboardContent>> = Mutex::new(Vec::new());
+
+    pub(crate) struct TestClipboard;
+
+    impl TestClipboard {
+        pub(crate) fn set(&self, contents: Vec<ClipboardContent>) -> Result<(), String> {
+            *CONTENTS.lock() = contents;
+            Ok(())
+        }
+
+        pub(crate) fn set_text(&self, text: String) -> Result<(), String> {
+            self.set(vec![ClipboardContent::Text(text)])
+        }
+
+        pub(crate) fn get_text(&self) -> Result<String, String> {
+            CONTENTS
+                .lock()
+                .iter()
+                .find_map(|content| match content {
+                    ClipboardContent::Text(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .ok_or_else(|| "no text on the clipboard".to_string())
+        }
+    }
+
+    /// Takes whatever the last copy put on the clipboard.
+    pub(crate) fn take() -> Vec<ClipboardContent> {
+        std::mem::take(&mut *CONTENTS.lock())
+    }
+}
+
+pub fn delete_entry(hash: &str) -> Result<()> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let item_dir = layout::item_dir(&data_dir, hash);
+    if item_dir.exists() {
+        fs::remove_dir_all(&item_dir)?;
+    }
+    let _ = fs::remove_dir_all(layout::thumbnails_dir(&data_dir, hash));
+    mutate_index(|idx| {
+        idx.remove(hash);
+    });
+    append_journal(&JournalEntry::delete(hash));
+    Ok(())
+}
+
+pub fn increment_copy_count(hash: &str) -> Result<EntryMetadata> {
+    let mut metadata = load_metadata(hash)?;
+    metadata.copy_count = metadata.copy_count.saturating_add(1);
+    metadata.copy_events =
+        merge_copy_events(&metadata.copy_events, &[time::unix_millis(time::now())]);
+    commit_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+fn index_record(metadata: &EntryMetadata) -> SearchIndexRecord {
+    SearchIndexRecord {
+        hash: metadata.hash.clone(),
+        first_seen: metadata.first_seen,
+        last_seen: metadata.last_seen,
+        kind: metadata.kind.clone(),
+        copy_count: metadata.copy_count,
+        summary: metadata.summary.clone(),
+        search_text: metadata.search_text.clone(),
+        detected_formats: metadata.detected_formats.clone(),
+        byte_size: metadata.byte_size,
+        relative_path: metadata.relative_path.clone(),
+        pinned: metadata.pinned,
+        source_app: metadata.source_app.clone(),
+    }
+}
+
+/// Writes `metadata` back to disk and mirrors it into the index and journal.
+fn commit_metadata(metadata: &EntryMetadata) -> Result<()> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let metadata_path = layout::metadata_path(&data_dir, &metadata.hash);
+    fs::write(&metadata_path, serde_json::to_vec_pretty(metadata)?)?;
+
+    let record = index_record(metadata);
     let journal_entry = JournalEntry::from_record(&record);
     mutate_index(|idx| {
         idx.insert(record.hash.clone(), record);
     });
     append_journal(&journal_entry);
+    Ok(())
+}
+
+/// Records that the item was actually pasted, as opposed to only copied.
+pub fn record_paste(hash: &str) -> Result<EntryMetadata> {
+    let mut metadata = load_metadata(hash)?;
+    apply_paste(&mut metadata, time::now());
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let metadata_path = layout::metadata_path(&data_dir, hash);
+    fs::write(&metadata_path, serde_json::to_vec_pretty(&metadata)?)
+        .with_context(|| format!("Failed to write {}", metadata_path.display()))?;
+    forget_cached_metadata(hash);
+    flush::note_write(hash);
     Ok(metadata)
 }
 
+fn apply_paste(metadata: &mut EntryMetadata, at: OffsetDateTime) {
+    metadata.paste_count = metadata.paste_count.saturating_add(1);
+    metadata.last_pasted = Some(at);
+}
+
 pub fn load_item_preview(metadata: &EntryMetadata) -> Result<ItemPreview> {
     let config = load_config()?;
     let data_dir = ensure_data_dir(&config)?;
@@ -1088,7 +2777,7 @@ pub fn load_item_preview(metadata: &EntryMetadata) -> Result<ItemPreview> {
             let path = entry.path();
             if path.is_file() {
                 let filename = entry.file_name().to_string_lossy().to_string();
-                if filename == "metadata.json" {
+                if filename == "metadata.json" || filename == STABLE_CONTENT_FILENAME {
                     continue;
                 }
                 let size = entry.metadata()?.len();
@@ -1103,7 +2792,12 @@ pub fn load_item_preview(metadata: &EntryMetadata) -> Result<ItemPreview> {
     files.sort_by(|a, b| a.filename.cmp(&b.filename));
 
     let content_path = item_dir.join(&metadata.content_filename);
-    let text = read_text_preview(&content_path);
+    // A `preferred_plugins` entry picks the previewed format, as it does for
+    // `show` and the API.
+    let text = config
+        .preferred_plugin(&metadata.kind)
+        .and_then(|plugin_id| preferred_preview_text(metadata, &item_dir, plugin_id))
+        .or_else(|| read_text_preview(&content_path));
     let dimensions = if metadata.kind == EntryKind::Image {
         image_dimensions(&content_path)
     } else {
@@ -1203,6 +2897,22 @@ fn truncate_for_preview(text: &str, max_len: usize) -> String {
     }
 }
 
+/// Text shown by the item's `plugin_id` format, or by its usual format when
+/// it doesn't have that one.
+fn preferred_preview_text(
+    metadata: &EntryMetadata,
+    item_dir: &Path,
+    plugin_id: &str,
+) -> Option<String> {
+    let content =
+        plugins::build_display_content_with_preference(metadata, item_dir, Some(plugin_id)).ok()?;
+    match content {
+        plugins::DisplayContent::Text(text) => Some(text),
+        plugins::DisplayContent::Lines(lines) => Some(lines.join("\n")),
+        _ => None,
+    }
+}
+
 fn read_text_preview(path: &Path) -> Option<String> {
     if !path.exists() {
         return None;
@@ -1385,3 +3095,1197 @@ fn fallback_format_labels(metadata: &EntryMetadata) -> Vec<String> {
     }
     labels
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::time::macros::datetime;
+    use serde_json::json;
+
+    fn sample_metadata() -> EntryMetadata {
+        EntryMetadata {
+            hash: "abc123".into(),
+            kind: EntryKind::Text,
+            detected_formats: vec![],
+            copy_count: 3,
+            first_seen: datetime!(2024-01-10 0:00 UTC),
+            last_seen: datetime!(2024-02-10 0:00 UTC),
+            byte_size: 5,
+            sources: vec![],
+            summary: None,
+            summary_override: None,
+            primary_plugin: None,
+            search_text: None,
+            version: "test".into(),
+            relative_path: String::new(),
+            content_filename: String::new(),
+            files: vec![],
+            extra: Value::Null,
+            tags: vec![],
+            pinned: false,
+            paste_count: 0,
+            last_pasted: None,
+            source_app: None,
+            copy_events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_import_conflict_skip() {
+        assert_eq!(conflict_copy_mode(ImportConflict::Skip, true, 2), None);
+        assert_eq!(
+            conflict_copy_mode(ImportConflict::Skip, false, 2),
+            Some(CopyCountMode::Override(2))
+        );
+    }
+
+    #[test]
+    fn test_import_conflict_merge() {
+        let mode = conflict_copy_mode(ImportConflict::Merge, true, 2).unwrap();
+        assert_eq!(mode, CopyCountMode::Merge(2));
+
+        let mut existing = sample_metadata();
+        apply_copy_mode(
+            &mut existing,
+            mode,
+            &[],
+            datetime!(2024-01-20 0:00 UTC),
+            Some(datetime!(2023-12-01 0:00 UTC)),
+        );
+        assert_eq!(existing.copy_count, 5);
+        assert_eq!(existing.last_seen, datetime!(2024-02-10 0:00 UTC));
+        assert_eq!(existing.first_seen, datetime!(2023-12-01 0:00 UTC));
+    }
+
+    #[test]
+    fn test_import_conflict_replace() {
+        let mode = conflict_copy_mode(ImportConflict::Replace, true, 7).unwrap();
+        assert_eq!(mode, CopyCountMode::Override(7));
+
+        let mut existing = sample_metadata();
+        apply_copy_mode(&mut existing, mode, &[], datetime!(2024-01-20 0:00 UTC), None);
+        assert_eq!(existing.copy_count, 7);
+        assert_eq!(existing.last_seen, datetime!(2024-01-20 0:00 UTC));
+    }
+
+    #[test]
+    fn test_sync_merge_counts_each_copy_event_once() {
+        let mode = conflict_copy_mode(ImportConflict::SyncMerge, true, 3).unwrap();
+        assert_eq!(mode, CopyCountMode::Sync(3));
+
+        // Both sides share the copy at 100 and each copied once more since.
+        let mut local = sample_metadata();
+        local.copy_count = 3;
+        local.copy_events = vec![100, 200, 300];
+        let mut remote = sample_metadata();
+        remote.copy_count = 3;
+        remote.copy_events = vec![100, 150, 400];
+        remote.first_seen = datetime!(2024-01-01 0:00 UTC);
+        remote.last_seen = datetime!(2024-03-01 0:00 UTC);
+
+        let merge = |local: &mut EntryMetadata| {
+            apply_copy_mode(
+                local,
+                mode,
+                &remote.copy_events,
+                remote.last_seen,
+                Some(remote.first_seen),
+            )
+        };
+        merge(&mut local);
+        assert_eq!(local.copy_count, 5);
+        assert_eq!(local.copy_events, vec![100, 150, 200, 300, 400]);
+        assert_eq!(local.first_seen, datetime!(2024-01-01 0:00 UTC));
+        assert_eq!(local.last_seen, datetime!(2024-03-01 0:00 UTC));
+
+        // Syncing the same remote state again changes nothing.
+        merge(&mut local);
+        assert_eq!(local.copy_count, 5);
+        assert_eq!(local.copy_events.len(), 5);
+    }
+
+    fn saved_item(copy_count: u64, events: &[i64], date: &str) -> plugins::ClipboardJsonFullItem {
+        serde_json::from_value(json!({
+            "id": "mirrored",
+            "date": date,
+            "firstDate": date,
+            "copyCount": copy_count,
+            "copyEvents": events,
+            "formats": [{ "pluginId": "text", "data": "mirrored text" }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_saving_a_stored_item_merges_counts_and_events() {
+        let _scratch = scratch_store(|_| {});
+        store_json_item(&saved_item(2, &[100, 200], "2024-02-01T00:00:00Z")).unwrap();
+        let merged = store_json_item(&saved_item(2, &[100, 300], "2024-03-01T00:00:00Z")).unwrap();
+
+        assert_eq!(merged.copy_count, 3);
+        assert_eq!(merged.copy_events, vec![100, 200, 300]);
+        assert_eq!(merged.last_seen, datetime!(2024-03-01 0:00 UTC));
+        assert_eq!(merged.first_seen, datetime!(2024-02-01 0:00 UTC));
+
+        // An older copy doesn't move the dates back.
+        let again = store_json_item(&saved_item(1, &[100], "2024-01-01T00:00:00Z")).unwrap();
+        assert_eq!(again.copy_count, 3);
+        assert_eq!(again.last_seen, datetime!(2024-03-01 0:00 UTC));
+        assert_eq!(again.first_seen, datetime!(2024-01-01 0:00 UTC));
+    }
+
+    #[test]
+    fn test_import_rejects_unparseable_dates_before_replacing() {
+        let _scratch = scratch_store(|_| {});
+        store_json_item(&saved_item(1, &[100], "2024-02-01T00:00:00Z")).unwrap();
+
+        let garbled = saved_item(4, &[100], "yesterday-ish");
+        assert!(import_json_item(&garbled, ImportConflict::Replace).is_err());
+        assert!(store_json_item(&garbled).is_err());
+        let kept = load_metadata("mirrored").unwrap();
+        assert_eq!(kept.copy_count, 1);
+        assert_eq!(kept.last_seen, datetime!(2024-02-01 0:00 UTC));
+    }
+
+    #[test]
+    fn test_relative_steps_from_a_plain_copied_item() {
+        let _scratch = scratch_store(|_| {});
+        let stored: Vec<EntryMetadata> = ["01", "02", "03"]
+            .iter()
+            .map(|month| {
+                let item = serde_json::from_value(json!({
+                    "id": format!("item-{month}"),
+                    "date": format!("2024-{month}-01T00:00:00Z"),
+                    "formats": [{ "pluginId": "text", "data": format!("note from {month}") }],
+                }))
+                .unwrap();
+                store_json_item(&item).unwrap()
+            })
+            .collect();
+        let middle = &stored[1];
+
+        copy_plain_by_selector(&middle.hash).unwrap();
+        let on_clipboard = test_clipboard::take();
+        assert!(matches!(
+            on_clipboard.as_slice(),
+            [clipboard_rs::common::ClipboardContent::Text(text)] if text == "note from 02"
+        ));
+
+        // The guard holds the plain-text hash the watcher will read back, but
+        // the cursor points at the entry so `--relative` can find it.
+        let data_dir = ensure_data_dir(&load_config().unwrap()).unwrap();
+        assert!(guard::take_self_copy(
+            &data_dir,
+            &ClipboardSnapshot::text_only_hash("note from 02"),
+            ::time::Duration::seconds(5),
+        ));
+        assert_eq!(
+            cursor::load_last_copied(&data_dir).as_deref(),
+            Some(middle.hash.as_str())
+        );
+        let next = resolve_relative(
+            &load_index().unwrap(),
+            Direction::Prev,
+            &SelectionFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(next, stored[0].hash);
+    }
+
+    #[test]
+    fn test_copy_data_uri_puts_the_image_text_on_the_clipboard() {
+        let _scratch = scratch_store(|_| {});
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 3))
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        let uri = plugins::image::encode_data_url("image/png", &png);
+        let item = serde_json::from_value(json!({
+            "id": "pixel",
+            "date": "2024-01-01T00:00:00Z",
+            "formats": [{ "pluginId": "image", "data": uri }],
+        }))
+        .unwrap();
+        let stored = store_json_item(&item).unwrap();
+
+        copy_data_uri_by_selector(&stored.hash).unwrap();
+        let on_clipboard = test_clipboard::take();
+        assert!(matches!(
+            on_clipboard.as_slice(),
+            [clipboard_rs::common::ClipboardContent::Text(text)] if *text == uri
+        ));
+
+        // The watcher reads back plain text, so the guard holds its hash while
+        // the cursor still points at the image entry.
+        let data_dir = ensure_data_dir(&load_config().unwrap()).unwrap();
+        assert!(guard::take_self_copy(
+            &data_dir,
+            &ClipboardSnapshot::text_only_hash(&uri),
+            ::time::Duration::seconds(5),
+        ));
+        assert_eq!(
+            cursor::load_last_copied(&data_dir).as_deref(),
+            Some(stored.hash.as_str())
+        );
+
+        let text = store_json_item(&saved_item(1, &[100], "2024-02-01T00:00:00Z")).unwrap();
+        assert!(copy_data_uri_by_selector(&text.hash).is_err());
+        assert!(test_clipboard::take().is_empty());
+    }
+
+    #[test]
+    fn test_sync_merge_without_remote_events_keeps_larger_count() {
+        let mut existing = sample_metadata();
+        apply_copy_mode(
+            &mut existing,
+            CopyCountMode::Sync(8),
+            &[],
+            datetime!(2024-01-20 0:00 UTC),
+            None,
+        );
+        assert_eq!(existing.copy_count, 8);
+        assert_eq!(existing.last_seen, datetime!(2024-02-10 0:00 UTC));
+
+        apply_copy_mode(
+            &mut existing,
+            CopyCountMode::Sync(2),
+            &[],
+            datetime!(2024-01-20 0:00 UTC),
+            None,
+        );
+        assert_eq!(existing.copy_count, 8);
+    }
+
+    #[test]
+    fn test_merge_copy_events_keeps_newest() {
+        let known: Vec<i64> = (0..MAX_COPY_EVENTS as i64).collect();
+        let merged = merge_copy_events(&known, &[5, 10_000]);
+        assert_eq!(merged.len(), MAX_COPY_EVENTS);
+        assert_eq!(merged.first(), Some(&1));
+        assert_eq!(merged.last(), Some(&10_000));
+        // Events older than a full history are assumed to be counted already.
+        assert_eq!(unseen_copy_events(&known, &[-1, 10_000]), 1);
+    }
+
+    #[test]
+    fn test_append_text_joins_on_new_line() {
+        assert_eq!(append_text("", "first"), "first");
+        assert_eq!(append_text("first", "second"), "first\nsecond");
+        assert_eq!(append_text("first\n", "second"), "first\nsecond");
+        assert_eq!(append_text("a\nb", ""), "a\nb\n");
+    }
+
+    #[test]
+    fn test_merge_plugin_extra_keeps_richer_formats() {
+        let html_and_text = json!({
+            "plugins": {"text": {"length": 5}, "html": {"storedFiles": ["html.txt"]}},
+            "pluginOrder": ["text", "html"]
+        });
+        let text_only = json!({
+            "plugins": {"text": {"length": 5, "fresh": true}},
+            "pluginOrder": ["text"]
+        });
+
+        let merged = merge_plugin_extra(&html_and_text, text_only);
+        assert_eq!(merged["pluginOrder"], json!(["text", "html"]));
+        assert_eq!(merged["plugins"]["text"]["fresh"], json!(true));
+        assert_eq!(merged["plugins"]["html"]["storedFiles"], json!(["html.txt"]));
+    }
+
+    #[test]
+    fn test_merge_plugin_extra_orders_by_priority() {
+        let text_only = json!({"plugins": {"text": {}}, "pluginOrder": ["text"]});
+        let rich = json!({
+            "plugins": {"rtf": {}, "html": {}, "text": {}},
+            "pluginOrder": ["text", "html", "rtf"]
+        });
+        let merged = merge_plugin_extra(&text_only, rich);
+        assert_eq!(merged["pluginOrder"], json!(["text", "html", "rtf"]));
+    }
+
+    fn record_seen(hash: &str, last_seen: OffsetDateTime) -> SearchIndexRecord {
+        SearchIndexRecord {
+            hash: hash.into(),
+            first_seen: last_seen,
+            last_seen,
+            kind: EntryKind::Text,
+            copy_count: 1,
+            summary: None,
+            search_text: None,
+            detected_formats: vec![],
+            byte_size: 5,
+            relative_path: String::new(),
+            pinned: false,
+            source_app: None,
+        }
+    }
+
+    fn text_snapshot(text: &str, html: Option<&str>) -> ClipboardSnapshot {
+        serde_json::from_value(json!({
+            "kind": "Text",
+            "text": text,
+            "html": html,
+            "rtf": null,
+            "image_bytes": null,
+            "image_mime": null,
+            "files": [],
+            "summary": null,
+            "detected_formats": ["public.utf8-plain-text"],
+            "extra": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_text_dedupe_merge_counts_the_kept_formats() {
+        let _scratch = scratch_store(|cfg| cfg.dedupe_by_text = Some(true));
+        let html = "<p><b>hello</b> world</p>";
+        let rich = store_snapshot(text_snapshot("hello world", Some(html))).unwrap();
+        let merged = store_snapshot(text_snapshot("hello world", None)).unwrap();
+
+        assert_eq!(rich.hash, merged.hash);
+        assert_eq!(merged.byte_size, rich.byte_size);
+        assert!(merged.extra["plugins"].get("html").is_some());
+        assert_eq!(
+            load_index().unwrap()[&merged.hash].byte_size,
+            rich.byte_size
+        );
+    }
+
+    #[test]
+    fn test_quick_repeat_collapses_within_window() {
+        let seen = datetime!(2024-02-10 12:00:00 UTC);
+        let window = ::time::Duration::seconds(5);
+        let stored = record_seen("abc123", seen);
+
+        let soon = seen + ::time::Duration::seconds(3);
+        assert!(is_quick_repeat(Some(&stored), soon, window));
+
+        let later = seen + ::time::Duration::seconds(6);
+        assert!(!is_quick_repeat(Some(&stored), later, window));
+        assert!(!is_quick_repeat(None, soon, window));
+        assert!(!is_quick_repeat(Some(&stored), soon, ::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_apply_paste_leaves_copy_stats_alone() {
+        let mut metadata = sample_metadata();
+        metadata.copy_count = 3;
+        let last_seen = metadata.last_seen;
+        let pasted_at = last_seen + ::time::Duration::minutes(5);
+
+        apply_paste(&mut metadata, pasted_at);
+        apply_paste(&mut metadata, pasted_at);
+
+        assert_eq!(metadata.paste_count, 2);
+        assert_eq!(metadata.last_pasted, Some(pasted_at));
+        assert_eq!(metadata.copy_count, 3);
+        assert_eq!(metadata.last_seen, last_seen);
+
+        let json = serde_json::to_value(&metadata).unwrap();
+        let restored: EntryMetadata = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.paste_count, 2);
+        assert_eq!(restored.last_pasted, Some(pasted_at));
+    }
+
+    #[test]
+    fn test_promote_plugin_changes_display_and_copy_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("html.html"), "<b>rich</b>").unwrap();
+        fs::write(dir.path().join("text.txt"), "plain").unwrap();
+        let mut metadata = sample_metadata();
+        metadata.extra = json!({
+            "plugins": {
+                "html": {"pluginId": "html", "storedFiles": ["html.html"]},
+                "text": {"pluginId": "text", "storedFiles": ["text.txt"]},
+            },
+            "pluginOrder": ["html", "text"],
+        });
+        let first_copied = |metadata: &EntryMetadata| {
+            let contents = plugins::rebuild_clipboard_contents(metadata, dir.path()).unwrap();
+            match contents.into_iter().next() {
+                Some(clipboard_rs::common::ClipboardContent::Text(text)) => text,
+                Some(clipboard_rs::common::ClipboardContent::Html(html)) => html,
+                _ => String::new(),
+            }
+        };
+        assert_eq!(first_copied(&metadata), "<b>rich</b>");
+
+        assert!(promote_plugin(&mut metadata, "text"));
+        assert_eq!(metadata.extra["pluginOrder"], json!(["text", "html"]));
+        assert_eq!(metadata.primary_plugin.as_deref(), Some("text"));
+        assert_eq!(metadata.content_filename, "text.txt");
+        assert_eq!(first_copied(&metadata), "plain");
+        match plugins::build_display_content(&metadata, dir.path()).unwrap() {
+            plugins::DisplayContent::Text(text) => assert_eq!(text, "plain"),
+            _ => panic!("expected the text format first"),
+        }
+
+        assert!(!promote_plugin(&mut metadata, "image"));
+        assert_eq!(metadata.extra["pluginOrder"], json!(["text", "html"]));
+    }
+
+    #[test]
+    fn test_primary_plugin_survives_recapture() {
+        let mut metadata = sample_metadata();
+        metadata.primary_plugin = Some("text".into());
+        let previous = json!({
+            "plugins": {
+                "html": {"storedFiles": ["html__content.html"]},
+                "text": {"storedFiles": ["text__content.txt"]},
+            },
+            "pluginOrder": ["text", "html"],
+        });
+        // A rich copy again: the capture order leads with html.
+        metadata.extra = json!({
+            "plugins": previous["plugins"].clone(),
+            "pluginOrder": ["html", "text"],
+        });
+        metadata.content_filename = "html__content.html".into();
+        apply_primary_plugin(&mut metadata);
+        assert_eq!(metadata.extra["pluginOrder"], json!(["text", "html"]));
+        assert_eq!(metadata.content_filename, "text__content.txt");
+
+        // An html-only copy merged with the stored formats.
+        let html_only = json!({
+            "plugins": {"html": {"storedFiles": ["html__content.html"]}},
+            "pluginOrder": ["html"],
+        });
+        metadata.extra = merge_plugin_extra(&previous, html_only);
+        metadata.content_filename = "html__content.html".into();
+        apply_primary_plugin(&mut metadata);
+        assert_eq!(metadata.extra["pluginOrder"], json!(["text", "html"]));
+        assert_eq!(metadata.content_filename, "text__content.txt");
+
+        // A copy without the chosen format keeps its own order.
+        metadata.extra = json!({
+            "plugins": {"html": {"storedFiles": ["html__content.html"]}},
+            "pluginOrder": ["html"],
+        });
+        metadata.content_filename = "html__content.html".into();
+        apply_primary_plugin(&mut metadata);
+        assert_eq!(metadata.extra["pluginOrder"], json!(["html"]));
+        assert_eq!(metadata.content_filename, "html__content.html");
+    }
+
+    fn cached(hash: &str) -> EntryMetadata {
+        EntryMetadata {
+            hash: hash.into(),
+            ..sample_metadata()
+        }
+    }
+
+    #[test]
+    fn test_metadata_cache_hits_until_generation_moves() {
+        let mut cache = MetadataCache::new();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(100);
+        assert!(cache.get(1, "abc", modified).is_none());
+
+        cache.insert(1, modified, cached("abc"));
+        assert_eq!(cache.get(1, "abc", modified).unwrap().hash, "abc");
+
+        // A write through the index bumps the generation.
+        assert!(cache.get(2, "abc", modified).is_none());
+        assert!(cache.entries.is_empty());
+
+        // The file was rewritten by someone else.
+        cache.insert(2, modified, cached("abc"));
+        let later = modified + std::time::Duration::from_secs(1);
+        assert!(cache.get(2, "abc", later).is_none());
+
+        cache.insert(2, modified, cached("abc"));
+        cache.forget("abc");
+        assert!(cache.get(2, "abc", modified).is_none());
+    }
+
+    #[test]
+    fn test_store_writes_invalidate_cached_metadata() {
+        let _scratch = scratch_store(|_| {});
+        store_json_item(&saved_item(1, &[100], "2024-02-01T00:00:00Z")).unwrap();
+        assert_eq!(load_metadata("mirrored").unwrap().copy_count, 1);
+        assert_eq!(load_metadata("mirrored").unwrap().copy_count, 1);
+
+        // Written through the index.
+        store_json_item(&saved_item(1, &[200], "2024-03-01T00:00:00Z")).unwrap();
+        assert_eq!(load_metadata("mirrored").unwrap().copy_count, 2);
+        assert!(update_tags("mirrored", &TagAction::Pin).unwrap());
+        assert!(load_metadata("mirrored").unwrap().pinned);
+
+        // Written straight to metadata.json, possibly within the same mtime tick.
+        assert!(update_tags("mirrored", &TagAction::Add("work".into())).unwrap());
+        assert_eq!(load_metadata("mirrored").unwrap().tags, vec!["work"]);
+        record_paste("mirrored").unwrap();
+        assert_eq!(load_metadata("mirrored").unwrap().paste_count, 1);
+    }
+
+    #[test]
+    fn test_metadata_cache_evicts_least_recently_used() {
+        let mut cache = MetadataCache::new();
+        let modified = SystemTime::UNIX_EPOCH;
+        for i in 0..METADATA_CACHE_CAPACITY {
+            cache.insert(0, modified, cached(&format!("hash{i}")));
+        }
+        // Touch the oldest entry so it becomes the most recent.
+        assert!(cache.get(0, "hash0", modified).is_some());
+        cache.insert(0, modified, cached("new"));
+
+        assert_eq!(cache.entries.len(), METADATA_CACHE_CAPACITY);
+        assert!(cache.get(0, "hash0", modified).is_some());
+        assert!(cache.get(0, "hash1", modified).is_none());
+        assert!(cache.get(0, "new", modified).is_some());
+    }
+
+    #[test]
+    fn test_apply_tag_action() {
+        let mut metadata = sample_metadata();
+        assert!(apply_tag_action(&mut metadata, &TagAction::Add("web".into())));
+        assert!(!apply_tag_action(&mut metadata, &TagAction::Add("web".into())));
+        assert!(apply_tag_action(&mut metadata, &TagAction::Add("work".into())));
+        assert_eq!(metadata.tags, vec!["web", "work"]);
+        assert!(apply_tag_action(&mut metadata, &TagAction::Remove("web".into())));
+        assert!(!apply_tag_action(&mut metadata, &TagAction::Remove("web".into())));
+        assert_eq!(metadata.tags, vec!["work"]);
+
+        assert!(apply_tag_action(&mut metadata, &TagAction::Pin));
+        assert!(!apply_tag_action(&mut metadata, &TagAction::Pin));
+        assert!(metadata.pinned);
+        assert!(apply_tag_action(&mut metadata, &TagAction::Unpin));
+        assert!(!metadata.pinned);
+    }
+
+    fn write_entry(data_dir: &Path, hash: &str) {
+        let metadata = EntryMetadata {
+            hash: hash.into(),
+            relative_path: layout::relative_path_for_hash(hash),
+            summary: Some(format!("entry {hash}")),
+            ..sample_metadata()
+        };
+        fs::create_dir_all(layout::item_dir(data_dir, hash)).unwrap();
+        fs::write(
+            layout::metadata_path(data_dir, hash),
+            serde_json::to_vec(&metadata).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn backdate(path: &Path, by: std::time::Duration) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - by).unwrap();
+    }
+
+    #[test]
+    fn test_loaded_snapshot_matches_fresh_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        for hash in ["aa11bb22", "cc33dd44", "aa11ee55"] {
+            write_entry(dir.path(), hash);
+        }
+        let (scanned, report) = scan_objects(dir.path());
+        assert_eq!((report.scanned, report.updated), (3, 3));
+        write_snapshot(dir.path(), &scanned).unwrap();
+        assert!(!snapshot_is_stale(dir.path()));
+
+        let loaded = load_index_from(dir.path()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&scanned).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_older_snapshot_format_triggers_rescan() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "aa11bb22");
+        let (mut scanned, _) = scan_objects(dir.path());
+        write_snapshot(dir.path(), &scanned).unwrap();
+
+        // A bare map of records, as written before the format was recorded.
+        scanned.get_mut("aa11bb22").unwrap().summary = Some("stale".into());
+        let legacy = serde_json::to_vec(&scanned).unwrap();
+        fs::write(layout::snapshot_path(dir.path()), legacy).unwrap();
+        assert!(!snapshot_is_stale(dir.path()));
+        assert!(read_snapshot(dir.path()).unwrap().is_none());
+
+        let index = load_index_from(dir.path()).unwrap();
+        assert_eq!(index["aa11bb22"].summary.as_deref(), Some("entry aa11bb22"));
+        assert!(read_snapshot(dir.path()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_stale_or_missing_snapshot_triggers_rescan() {
+        let dir = tempfile::tempdir().unwrap();
+        // No history yet: nothing to rescan.
+        assert!(!snapshot_is_stale(dir.path()));
+
+        write_entry(dir.path(), "aa11bb22");
+        assert!(snapshot_is_stale(dir.path()));
+        assert_eq!(load_index_from(dir.path()).unwrap().len(), 1);
+        assert!(layout::snapshot_path(dir.path()).exists());
+
+        // An entry added behind the journal's back, e.g. by a sync tool.
+        write_entry(dir.path(), "cc33dd44");
+        let hour = std::time::Duration::from_secs(3600);
+        backdate(&layout::snapshot_path(dir.path()), hour);
+        backdate(&layout::journal_path(dir.path()), hour);
+        assert!(snapshot_is_stale(dir.path()));
+        let index = load_index_from(dir.path()).unwrap();
+        assert!(index.contains_key("aa11bb22") && index.contains_key("cc33dd44"));
+        assert!(!snapshot_is_stale(dir.path()));
+    }
+
+    #[test]
+    fn test_shared_stash_files_are_stored_once() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let stashed = FileOutput {
+            filename: "files__stash/0/report.pdf".into(),
+            bytes: b"%PDF same bytes".to_vec(),
+        };
+        let listing = FileOutput {
+            filename: "files__paths.txt".into(),
+            bytes: b"/tmp/report.pdf".to_vec(),
+        };
+        for hash in ["aa11bb22", "cc33dd44"] {
+            let item_dir = layout::item_dir(data_dir.path(), hash);
+            for output in [&stashed, &listing] {
+                write_capture_file(&item_dir, output, Some(data_dir.path())).unwrap();
+            }
+        }
+
+        let blobs: Vec<_> = fs::read_dir(layout::blobs_dir(data_dir.path()))
+            .unwrap()
+            .flatten()
+            .flat_map(|shard| fs::read_dir(shard.path()).unwrap().flatten())
+            .collect();
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(fs::read(blobs[0].path()).unwrap(), stashed.bytes);
+
+        // Replacing one entry's copy leaves the shared blob untouched.
+        let item_dir = layout::item_dir(data_dir.path(), "aa11bb22");
+        let changed = FileOutput {
+            bytes: b"edited".to_vec(),
+            ..stashed
+        };
+        write_capture_file(&item_dir, &changed, None).unwrap();
+        assert_eq!(fs::read(blobs[0].path()).unwrap(), b"%PDF same bytes");
+    }
+
+    #[test]
+    fn test_remove_empty_dirs_keeps_populated_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let objects = root.path().join("objects");
+        fs::create_dir_all(objects.join("ab/cd/abcd1234")).unwrap();
+        fs::write(objects.join("ab/cd/abcd1234/metadata.json"), b"{}").unwrap();
+        fs::create_dir_all(objects.join("ab/ef")).unwrap();
+        fs::create_dir_all(objects.join("12/34/1234abcd")).unwrap();
+
+        let mut report = CompactReport::default();
+        for top in ["ab", "12"] {
+            remove_empty_dirs(&objects.join(top), &mut report).unwrap();
+        }
+
+        assert_eq!(report.removed_dirs, 4);
+        assert!(objects.join("ab/cd/abcd1234/metadata.json").exists());
+        assert!(!objects.join("ab/ef").exists());
+        assert!(!objects.join("12").exists());
+    }
+
+    #[test]
+    fn test_prune_keeps_latest_item_per_app() {
+        let base = datetime!(2024-02-10 12:00:00 UTC);
+        let records: Vec<SearchIndexRecord> = (0..5)
+            .map(|age| record_seen(&format!("h{age}"), base - ::time::Duration::minutes(age)))
+            .collect();
+        let ordered: Vec<&SearchIndexRecord> = records.iter().collect();
+        let apps = [
+            ("h0", Some("com.apple.Safari")),
+            ("h1", Some("com.apple.Safari")),
+            ("h2", None),
+            ("h3", Some("com.apple.Terminal")),
+            ("h4", Some("com.apple.Notes")),
+        ];
+
+        let protected = latest_per_app(apps, 2);
+        assert_eq!(protected, HashSet::from(["h0".to_string(), "h3".to_string()]));
+
+        let doomed = prune_candidates(&ordered, PruneDirective::ByCount(3), &protected);
+        assert_eq!(doomed, vec!["h4", "h2", "h1"]);
+
+        let cutoff = base - ::time::Duration::seconds(30);
+        let doomed = prune_candidates(&ordered, PruneDirective::ByDate(cutoff), &protected);
+        assert_eq!(doomed, vec!["h4", "h2", "h1"]);
+    }
+
+    #[test]
+    fn test_prune_skips_pins_and_reads_apps_from_index() {
+        let base = datetime!(2024-02-10 12:00:00 UTC);
+        let records: Vec<SearchIndexRecord> = (0..5)
+            .map(|age| {
+                let mut record =
+                    record_seen(&format!("h{age}"), base - ::time::Duration::minutes(age));
+                record.pinned = age == 4;
+                record.source_app = (age >= 2).then(|| "com.apple.Notes".to_string());
+                record
+            })
+            .collect();
+        let ordered: Vec<&SearchIndexRecord> = records.iter().collect();
+
+        let protected = protected_from_pruning(&ordered, 1);
+        assert_eq!(protected, HashSet::from(["h2".to_string(), "h4".to_string()]));
+        let doomed = prune_candidates(&ordered, PruneDirective::ByCount(2), &protected);
+        assert_eq!(doomed, vec!["h3", "h1"]);
+
+        let protected = protected_from_pruning(&ordered, 0);
+        assert_eq!(protected, HashSet::from(["h4".to_string()]));
+    }
+
+    #[test]
+    fn test_prune_without_protection_drops_oldest() {
+        let base = datetime!(2024-02-10 12:00:00 UTC);
+        let records: Vec<SearchIndexRecord> = (0..3)
+            .map(|age| record_seen(&format!("h{age}"), base - ::time::Duration::minutes(age)))
+            .collect();
+        let ordered: Vec<&SearchIndexRecord> = records.iter().collect();
+
+        let protected = latest_per_app([("h0", Some("com.apple.Safari"))], 0);
+        assert!(protected.is_empty());
+        let doomed = prune_candidates(&ordered, PruneDirective::ByCount(2), &protected);
+        assert_eq!(doomed, vec!["h2", "h1"]);
+    }
+
+    #[test]
+    fn test_read_metadata_reports_error_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        let hash = "abcdef123456";
+        assert!(matches!(
+            read_metadata(dir.path(), hash),
+            Err(ClipboardError::NotFound(_))
+        ));
+
+        let path = layout::metadata_path(dir.path(), hash);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"{ not json").unwrap();
+        assert!(matches!(
+            read_metadata(dir.path(), hash),
+            Err(ClipboardError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_selector_reports_error_kinds() {
+        let seen = datetime!(2024-02-10 12:00:00 UTC);
+        let index: SearchIndex = [("abc123def".to_string(), record_seen("abc123def", seen))]
+            .into_iter()
+            .collect();
+        let filter = SelectionFilter::default();
+
+        assert_eq!(resolve_selector(&index, "0", &filter).unwrap(), "abc123def");
+        assert!(matches!(
+            resolve_selector(&index, "3", &filter),
+            Err(ClipboardError::NotFound(_))
+        ));
+        assert!(matches!(
+            resolve_selector(&index, "latest", &filter),
+            Err(ClipboardError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_unchanged_change_count_skips_capture() {
+        let mut metadata = sample_metadata();
+        assert_eq!(recorded_change_count(&metadata), None);
+        assert!(!is_repeat_capture(&metadata, &metadata.hash.clone(), Some(41)));
+
+        assert!(record_change_count(&mut metadata, 41));
+        assert!(!record_change_count(&mut metadata, 41));
+        assert_eq!(recorded_change_count(&metadata), Some(41));
+
+        let hash = metadata.hash.clone();
+        assert!(is_repeat_capture(&metadata, &hash, Some(41)));
+        assert!(!is_repeat_capture(&metadata, &hash, Some(42)));
+        assert!(!is_repeat_capture(&metadata, "otherhash", Some(41)));
+        assert!(!is_repeat_capture(&metadata, &hash, None));
+    }
+
+    #[test]
+    fn test_refresh_summary_applies_current_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "word ".repeat(60);
+        fs::write(dir.path().join("text__content.txt"), &text).unwrap();
+
+        let mut metadata = sample_metadata();
+        metadata.summary = Some(text.clone());
+        metadata.content_filename = "text__content.txt".into();
+        metadata.extra = json!({
+            "plugins": {
+                "text": {
+                    "pluginId": "text",
+                    "pluginKind": "text",
+                    "storedFiles": ["text__content.txt"]
+                }
+            },
+            "pluginOrder": ["text"]
+        });
+
+        assert!(refresh_summary(&mut metadata, dir.path()).unwrap());
+        let summary = metadata.summary.clone().unwrap();
+        assert_eq!(summary.chars().count(), 120);
+        assert!(summary.ends_with("..."));
+        assert_eq!(metadata.copy_count, 3);
+
+        assert!(!refresh_summary(&mut metadata, dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_summary_override_survives_reindex() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("text__content.txt"), "generated text").unwrap();
+        let mut metadata = EntryMetadata {
+            summary: Some("generated text".into()),
+            content_filename: "text__content.txt".into(),
+            extra: json!({
+                "plugins": {
+                    "text": {
+                        "pluginId": "text",
+                        "pluginKind": "text",
+                        "storedFiles": ["text__content.txt"]
+                    }
+                },
+                "pluginOrder": ["text"]
+            }),
+            ..sample_metadata()
+        };
+
+        apply_summary_override(&mut metadata, Some("  My snippet "));
+        let stored: EntryMetadata =
+            serde_json::from_slice(&serde_json::to_vec(&metadata).unwrap()).unwrap();
+        assert_eq!(stored.summary_override.as_deref(), Some("My snippet"));
+
+        metadata = stored;
+        assert!(!refresh_summary(&mut metadata, dir.path()).unwrap());
+        assert_eq!(metadata.summary.as_deref(), Some("My snippet"));
+
+        // Blank text goes back to the generated summary.
+        apply_summary_override(&mut metadata, Some("   "));
+        assert_eq!(metadata.summary_override, None);
+        assert!(refresh_summary(&mut metadata, dir.path()).unwrap());
+        assert_eq!(metadata.summary.as_deref(), Some("generated text"));
+    }
+
+    #[test]
+    fn test_summary_override_is_used_in_listings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("text__content.txt"), "generated text").unwrap();
+        let mut metadata = EntryMetadata {
+            content_filename: "text__content.txt".into(),
+            extra: json!({
+                "plugins": {
+                    "text": {
+                        "pluginId": "text",
+                        "pluginKind": "text",
+                        "storedFiles": ["text__content.txt"]
+                    }
+                },
+                "pluginOrder": ["text"]
+            }),
+            ..sample_metadata()
+        };
+        apply_summary_override(&mut metadata, Some("My snippet"));
+
+        assert_eq!(
+            plugins::build_summary(&metadata, dir.path(), false).as_deref(),
+            Some("My snippet")
+        );
+        assert_eq!(
+            index_record(&metadata).summary.as_deref(),
+            Some("My snippet")
+        );
+        let json = metadata_json(&metadata, dir.path()).unwrap();
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["summary"], "My snippet");
+    }
+
+    #[test]
+    fn test_summary_override_is_searchable_and_exported() {
+        let _scratch = scratch_store(|_| {});
+        store_json_item(&saved_item(1, &[100], "2024-02-01T00:00:00Z")).unwrap();
+
+        let renamed = set_summary_override("mirrored", Some("Project notes")).unwrap();
+        assert_eq!(
+            renamed.search_text.as_deref(),
+            Some("Project notes\n\nmirrored text")
+        );
+        let record = load_index().unwrap()["mirrored"].clone();
+        assert_eq!(record.search_text, renamed.search_text);
+
+        // Copying the item again keeps the name in its search text.
+        let recopied = store_json_item(&saved_item(1, &[200], "2024-03-01T00:00:00Z")).unwrap();
+        assert_eq!(recopied.search_text, renamed.search_text);
+
+        let data_dir = ensure_data_dir(&load_config().unwrap()).unwrap();
+        let item_dir = data_dir.join(&recopied.relative_path);
+        let exported = plugins::build_full_json_item(&recopied, &item_dir, None, None).unwrap();
+        assert_eq!(exported.summary_override.as_deref(), Some("Project notes"));
+
+        delete_entry("mirrored").unwrap();
+        let ImportOutcome::Stored(imported) =
+            import_json_item(&exported, ImportConflict::Replace).unwrap()
+        else {
+            panic!("import was skipped");
+        };
+        assert_eq!(imported.summary_override.as_deref(), Some("Project notes"));
+        assert_eq!(imported.summary.as_deref(), Some("Project notes"));
+        assert_eq!(imported.search_text, renamed.search_text);
+
+        let cleared = set_summary_override("mirrored", None).unwrap();
+        assert_eq!(cleared.summary_override, None);
+        assert_eq!(cleared.search_text.as_deref(), Some("mirrored text"));
+    }
+
+    fn image_metadata(item_dir: &Path, png: &[u8]) -> EntryMetadata {
+        fs::write(item_dir.join("image__full.png"), png).unwrap();
+        let mut metadata = sample_metadata();
+        metadata.kind = EntryKind::Image;
+        metadata.byte_size = png.len() as u64;
+        metadata.content_filename = "image__full.png".into();
+        metadata.extra = json!({
+            "plugins": {
+                "image": {
+                    "pluginId": "image",
+                    "mime": "image/png",
+                    "byteSize": png.len(),
+                    "storedFiles": ["image__full.png"]
+                }
+            },
+            "pluginOrder": ["image"]
+        });
+        metadata
+    }
+
+    /// Unfiltered PNG, which compresses a gradient poorly.
+    fn encode_png(img: image::RgbImage) -> Vec<u8> {
+        use image::ImageEncoder;
+        use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+        let mut out = Vec::new();
+        PngEncoder::new_with_quality(&mut out, CompressionType::Fast, FilterType::NoFilter)
+            .write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_optimize_image_replaces_png_when_smaller() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = encode_png(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([x as u8, y as u8, 0])
+        }));
+        let mut metadata = image_metadata(dir.path(), &png);
+
+        let dry = optimize_item_image(&mut metadata.clone(), dir.path(), true).unwrap();
+        assert!(dry > 0);
+        assert!(dir.path().join("image__full.png").exists());
+
+        let saved = optimize_item_image(&mut metadata, dir.path(), false).unwrap();
+        assert_eq!(saved, dry);
+        // Removing the PNG is left until the metadata is committed.
+        assert!(dir.path().join("image__full.png").exists());
+        assert!(dir.path().join(OPTIMIZED_IMAGE_FILENAME).exists());
+        assert_eq!(metadata.extra["plugins"]["image"]["mime"], "image/webp");
+        assert_eq!(metadata.content_filename, OPTIMIZED_IMAGE_FILENAME);
+        assert_eq!(metadata.byte_size, png.len() as u64 - saved);
+
+        assert_eq!(optimize_item_image(&mut metadata, dir.path(), false).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_byte_different_screenshots_hash_as_duplicates() {
+        let screenshot = |cursor: bool| {
+            image::RgbImage::from_fn(256, 160, |x, y| {
+                if cursor && (120..124).contains(&x) && (70..76).contains(&y) {
+                    return image::Rgb([255, 255, 255]);
+                }
+                let shade = if (x / 32 + y / 20) % 2 == 0 { 210 } else { 40 };
+                image::Rgb([shade, x as u8, y as u8])
+            })
+        };
+        let first_dir = tempfile::tempdir().unwrap();
+        let second_dir = tempfile::tempdir().unwrap();
+        let first_png = encode_png(screenshot(false));
+        let second_png = encode_png(screenshot(true));
+        assert_ne!(first_png, second_png);
+        let mut first = image_metadata(first_dir.path(), &first_png);
+        let mut second = image_metadata(second_dir.path(), &second_png);
+
+        let a = store_item_fingerprint(&mut first, first_dir.path()).unwrap();
+        let b = store_item_fingerprint(&mut second, second_dir.path()).unwrap();
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert!(a.matches(&b, DEFAULT_PHASH_THRESHOLD));
+        assert_eq!(stored_fingerprint(&first), Some(a));
+    }
+
+    #[test]
+    fn test_merge_duplicate_keeps_combined_history() {
+        let mut keep = sample_metadata();
+        keep.copy_events = vec![3_000];
+        keep.tags = vec!["work".into()];
+        let duplicate = EntryMetadata {
+            hash: "def456".into(),
+            copy_count: 2,
+            copy_events: vec![1_000, 2_000],
+            first_seen: datetime!(2023-12-01 0:00 UTC),
+            tags: vec!["work".into(), "ui".into()],
+            pinned: true,
+            ..sample_metadata()
+        };
+        merge_duplicate(&mut keep, &duplicate);
+        assert_eq!(keep.hash, "abc123");
+        assert_eq!(keep.copy_count, 5);
+        assert_eq!(keep.copy_events, vec![1_000, 2_000, 3_000]);
+        assert_eq!(keep.first_seen, datetime!(2023-12-01 0:00 UTC));
+        assert_eq!(keep.tags, vec!["work", "ui"]);
+        assert!(keep.pinned);
+    }
+
+    #[test]
+    fn test_stable_content_follows_primary_file() {
+        let _scratch = scratch_store(|cfg| cfg.stable_content_file = Some(true));
+        let item = serde_json::from_value(json!({
+            "id": "rich",
+            "date": "2024-02-01T00:00:00Z",
+            "formats": [
+                { "pluginId": "text", "data": "plain" },
+                { "pluginId": "html", "data": "<b>rich</b>" },
+            ],
+        }))
+        .unwrap();
+        let stored = store_json_item(&item).unwrap();
+        let data_dir = ensure_data_dir(&load_config().unwrap()).unwrap();
+        let item_dir = data_dir.join(&stored.relative_path);
+        let content = item_dir.join(STABLE_CONTENT_FILENAME);
+        assert_eq!(
+            fs::read(&content).unwrap(),
+            fs::read(item_dir.join(&stored.content_filename)).unwrap()
+        );
+
+        // Changing the primary format replaces the link.
+        let other = if fs::read(&content).unwrap() == b"<b>rich</b>" {
+            ("text", b"plain".as_slice())
+        } else {
+            ("html", b"<b>rich</b>".as_slice())
+        };
+        assert!(set_primary_plugin("rich", other.0).unwrap());
+        assert_eq!(fs::read(&content).unwrap(), other.1);
+
+        // So does recapturing with a different primary.
+        let plain = serde_json::from_value(json!({
+            "id": "rich",
+            "date": "2024-03-01T00:00:00Z",
+            "formats": [{ "pluginId": "text", "data": "plain" }],
+        }))
+        .unwrap();
+        store_json_item(&plain).unwrap();
+        assert_eq!(fs::read(&content).unwrap(), b"plain");
+    }
+
+    #[test]
+    fn test_optimize_image_moves_stable_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = encode_png(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([x as u8, y as u8, 0])
+        }));
+        let mut metadata = image_metadata(dir.path(), &png);
+        link_stable_content(dir.path(), &metadata.content_filename).unwrap();
+
+        assert!(optimize_item_image(&mut metadata, dir.path(), false).unwrap() > 0);
+        assert_eq!(
+            fs::read(dir.path().join(STABLE_CONTENT_FILENAME)).unwrap(),
+            fs::read(dir.path().join(OPTIMIZED_IMAGE_FILENAME)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_optimize_image_keeps_file_that_would_not_shrink() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = encode_png(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([x as u8, y as u8, 0])
+        }));
+        // Already as small as the encoder gets it, so re-encoding saves nothing.
+        let webp = plugins::image::lossless_webp(&png).unwrap().unwrap();
+        let mut metadata = image_metadata(dir.path(), &webp);
+        let before = metadata.clone();
+
+        assert_eq!(optimize_item_image(&mut metadata, dir.path(), false).unwrap(), 0);
+        assert!(dir.path().join("image__full.png").exists());
+        assert!(!dir.path().join(OPTIMIZED_IMAGE_FILENAME).exists());
+        assert_eq!(metadata.extra, before.extra);
+        assert_eq!(metadata.byte_size, before.byte_size);
+    }
+
+    #[test]
+    fn test_replay_updates_size_when_file_grows() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = source_dir.path().join("notes.txt");
+        fs::write(&source, b"short").unwrap();
+        let item_dir = tempfile::tempdir().unwrap();
+        fs::write(item_dir.path().join("files__paths.txt"), b"old listing").unwrap();
+        let old_record = crate::clipboard::snapshot::FileRecord::from_path(&source, 5);
+        let mut metadata = EntryMetadata {
+            kind: EntryKind::File,
+            byte_size: 11,
+            extra: json!({
+                "plugins": {
+                    "files": {
+                        "entries": [old_record],
+                        "storedFiles": ["files__paths.txt"],
+                    },
+                },
+                "pluginOrder": ["files"],
+            }),
+            ..sample_metadata()
+        };
+
+        fs::write(&source, b"a good deal longer than before").unwrap();
+        let report = replay_item_files(&mut metadata, item_dir.path(), None).unwrap();
+        assert_eq!(report.files, 1);
+        assert_eq!(report.changed, 1);
+        assert_eq!(report.missing, 0);
+
+        let entry = &metadata.extra["plugins"]["files"]["entries"][0];
+        assert_eq!(entry["size"], json!(30));
+        let listing = fs::read_to_string(item_dir.path().join("files__paths.txt")).unwrap();
+        assert!(listing.contains("notes.txt"));
+        assert_eq!(metadata.byte_size, listing.len() as u64);
+        assert!(metadata.search_text.as_deref().unwrap().contains("notes.txt"));
+
+        // Nothing changed since the last replay.
+        let report = replay_item_files(&mut metadata, item_dir.path(), None).unwrap();
+        assert_eq!(report.changed, 0);
+    }
+
+    #[test]
+    fn test_metadata_json_is_a_valid_item_record() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("text__content.txt"), "hello meta").unwrap();
+        let mut metadata = sample_metadata();
+        metadata.summary = Some("hello meta".into());
+        metadata.extra = json!({
+            "plugins": {
+                "text": {
+                    "pluginId": "text",
+                    "pluginKind": "text",
+                    "storedFiles": ["text__content.txt"]
+                }
+            },
+            "pluginOrder": ["text"]
+        });
+
+        let payload = metadata_json(&metadata, dir.path()).unwrap();
+        let parsed: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(parsed["id"], "abc123");
+        assert_eq!(parsed["summary"], "hello meta");
+        assert_eq!(parsed["formats"][0]["pluginId"], "text");
+        assert_eq!(parsed["formats"][0]["data"], "hello meta");
+
+        let round_trip: plugins::ClipboardJsonFullItem = serde_json::from_str(&payload).unwrap();
+        assert_eq!(round_trip.copy_count, Some(3));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::util::paste::PermissionDenied;
+use std::io;
+use thiserror::Error;
+
+/// Errors the library surfaces at its module boundaries so embedders can
+/// match on the kind of failure. The CLI converts these into `anyhow::Error`
+/// and the API maps them onto HTTP statuses.
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    /// No stored item matches the hash or selector given.
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    /// A selector, filter or query parameter the caller gave that could not
+    /// be understood.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// Stored data that could not be parsed.
+    #[error("{0}")]
+    Parse(String),
+    /// The system clipboard could not be read or written.
+    #[error("{0}")]
+    Clipboard(String),
+    /// macOS refused an operation Clippy lacks permission for.
+    #[error("{0}")]
+    Permission(String),
+}
+
+impl ClipboardError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        ClipboardError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl From<PermissionDenied> for ClipboardError {
+    fn from(denied: PermissionDenied) -> Self {
+        ClipboardError::Permission(denied.to_string())
+    }
+}
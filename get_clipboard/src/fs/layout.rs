@@ -20,6 +20,20 @@ pub fn relative_path_for_hash(hash: &str) -> String {
     format!("objects/{}/{}/{}", &hash[..2], &hash[2..4], hash)
 }
 
+pub fn blobs_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("blobs")
+}
+
+pub fn blob_path(data_dir: &Path, digest: &str) -> PathBuf {
+    blobs_dir(data_dir).join(&digest[..2]).join(digest)
+}
+
+/// Scaled copies of an item's image, kept apart from the item's own files so
+/// exports, sizes and blob sharing never see them.
+pub fn thumbnails_dir(data_dir: &Path, hash: &str) -> PathBuf {
+    data_dir.join("cache").join("thumbnails").join(hash)
+}
+
 pub fn journal_path(data_dir: &Path) -> PathBuf {
     data_dir.join("journal.jsonl")
 }
@@ -28,6 +42,26 @@ pub fn snapshot_path(data_dir: &Path) -> PathBuf {
     data_dir.join("journal.snapshot")
 }
 
+pub fn self_copy_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("self_copy.json")
+}
+
+pub fn last_copied_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("last_copied.json")
+}
+
+pub fn mirror_queue_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("mirror_queue.json")
+}
+
+pub fn crashed_plugin_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("crashed_plugin")
+}
+
+pub fn incognito_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("incognito.json")
+}
+
 pub fn legacy_index_path(data_dir: &Path) -> PathBuf {
     data_dir.join("index.json")
 }
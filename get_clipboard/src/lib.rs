@@ -3,6 +3,7 @@ pub mod cli;
 pub mod clipboard;
 pub mod config;
 pub mod data;
+pub mod error;
 pub mod fs;
 pub mod search;
 pub mod service;
@@ -3,6 +3,7 @@ mod cli;
 mod clipboard;
 mod config;
 mod data;
+mod error;
 mod fs;
 mod search;
 mod service;
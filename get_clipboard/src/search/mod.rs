@@ -1,5 +1,15 @@
 use crate::data::model::{EntryKind, SearchIndex, SearchIndexRecord};
+use crate::error::ClipboardError;
 use crate::util::time::OffsetDateTime;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Minimum trigram overlap for an entry to count as related.
+const RELATED_MIN_SCORE: f64 = 0.3;
+/// Only the start of long entries is compared to keep `related` cheap.
+const RELATED_MAX_CHARS: usize = 4096;
 
 #[derive(Debug, Clone, Default)]
 pub struct SelectionFilter {
@@ -12,6 +22,50 @@ pub struct SelectionFilter {
 }
 
 impl SelectionFilter {
+    /// Builds a filter from the API's comma-separated `formats` and `kinds`
+    /// query parameters. For compatibility `formats` also accepts the kind
+    /// names `text`, `image` and `file(s)`; anything else is matched against
+    /// detected pasteboard formats.
+    pub fn from_query_params(
+        formats: Option<&str>,
+        kinds: Option<&str>,
+    ) -> Result<Self, ClipboardError> {
+        let mut filter = SelectionFilter::default();
+        for format in split_param(formats) {
+            match format.as_str() {
+                "text" => filter.include_text = true,
+                "image" => filter.include_image = true,
+                "file" | "files" => filter.include_file = true,
+                "html" => filter.include_html = true,
+                _ => filter.include_formats.push(format),
+            }
+        }
+        for kind in split_param(kinds) {
+            match kind.as_str() {
+                "text" => filter.include_text = true,
+                "image" => filter.include_image = true,
+                "file" | "files" => filter.include_file = true,
+                "other" => filter.include_other = true,
+                _ => return Err(ClipboardError::InvalidInput(format!("Unknown kind {kind}"))),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Widens this filter to also match whatever `other` matches.
+    pub fn extend(&mut self, other: SelectionFilter) {
+        self.include_text |= other.include_text;
+        self.include_image |= other.include_image;
+        self.include_file |= other.include_file;
+        self.include_other |= other.include_other;
+        self.include_html |= other.include_html;
+        for format in other.include_formats {
+            if !self.include_formats.contains(&format) {
+                self.include_formats.push(format);
+            }
+        }
+    }
+
     pub fn matches(&self, record: &SearchIndexRecord) -> bool {
         let kind_filter_active = self.include_text
             || self.include_image
@@ -55,6 +109,39 @@ pub enum SortOrder {
     Relevance,
 }
 
+/// Which part of an entry a query is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchField {
+    /// Hash, summary and full text.
+    #[default]
+    All,
+    Summary,
+    /// The full text kept for searching, not the summary.
+    Text,
+}
+
+impl SearchField {
+    /// Parses the API's `in` parameter.
+    pub fn parse(value: &str) -> Result<Self, ClipboardError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "all" => Ok(SearchField::All),
+            "summary" => Ok(SearchField::Summary),
+            "text" => Ok(SearchField::Text),
+            other => Err(ClipboardError::InvalidInput(format!(
+                "Unknown search field '{other}', expected summary, text or all"
+            ))),
+        }
+    }
+
+    fn covers(self, field: MatchField) -> bool {
+        match self {
+            SearchField::All => true,
+            SearchField::Summary => field == MatchField::Summary,
+            SearchField::Text => field == MatchField::SearchText,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SortDirection {
     #[default]
@@ -62,6 +149,65 @@ pub enum SortDirection {
     Asc,
 }
 
+/// Regex behavior for `--regex` queries, parsed from flag letters like `im`.
+/// Without explicit flags, matching is case-insensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexFlags {
+    /// `i`: letters match regardless of case.
+    pub case_insensitive: bool,
+    /// `m`: `^` and `$` match at line boundaries.
+    pub multi_line: bool,
+    /// `s`: `.` also matches newlines.
+    pub dot_matches_new_line: bool,
+    /// `x`: whitespace and `#` comments in the pattern are ignored.
+    pub ignore_whitespace: bool,
+}
+
+impl Default for RegexFlags {
+    fn default() -> Self {
+        RegexFlags {
+            case_insensitive: true,
+            multi_line: false,
+            dot_matches_new_line: false,
+            ignore_whitespace: false,
+        }
+    }
+}
+
+impl RegexFlags {
+    /// Only the listed flags are enabled, so `""` or `m` is case-sensitive.
+    pub fn parse(flags: &str) -> Result<Self, ClipboardError> {
+        let mut parsed = RegexFlags {
+            case_insensitive: false,
+            ..RegexFlags::default()
+        };
+        for flag in flags.trim().chars() {
+            match flag {
+                'i' => parsed.case_insensitive = true,
+                'm' => parsed.multi_line = true,
+                's' => parsed.dot_matches_new_line = true,
+                'x' => parsed.ignore_whitespace = true,
+                other => {
+                    return Err(ClipboardError::InvalidInput(format!(
+                        "Unknown regex flag '{other}', expected any of i, m, s, x"
+                    )));
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn build(self, pattern: &str) -> Option<regex::Regex> {
+        regex::RegexBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .ignore_whitespace(self.ignore_whitespace)
+            .build()
+            .ok()
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SearchOptions {
     pub query: Option<String>,
@@ -73,6 +219,10 @@ pub struct SearchOptions {
     pub sort: SortOrder,
     pub order: SortDirection,
     pub regex: bool,
+    /// Ignore diacritics on both sides, so "cafe" matches "café".
+    pub fold: bool,
+    pub regex_flags: RegexFlags,
+    pub field: SearchField,
 }
 
 #[derive(Debug, Clone)]
@@ -97,8 +247,10 @@ pub fn search(index: &SearchIndex, options: &SearchOptions) -> SearchResult {
         let trimmed = query.trim();
         if trimmed.is_empty() {
             None
+        } else if options.regex {
+            Some(trimmed.to_string())
         } else {
-            Some(trimmed.to_lowercase())
+            Some(normalize_text(trimmed, options.fold))
         }
     });
 
@@ -107,7 +259,7 @@ pub fn search(index: &SearchIndex, options: &SearchOptions) -> SearchResult {
 
     let mut all_records: Vec<_> = index.values().collect();
     // Sort by Date first to establish "stable indices"
-    all_records.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    all_records.sort_by(|a, b| a.cmp_newest_first(b));
 
     let mut indexed_records: Vec<(usize, &SearchIndexRecord)> =
         all_records.into_iter().enumerate().collect();
@@ -124,9 +276,10 @@ pub fn search(index: &SearchIndex, options: &SearchOptions) -> SearchResult {
         }),
         SortOrder::Relevance => {
             if let Some(query) = &normalized_query {
+                let query = relevance_query(query, options.regex);
                 indexed_records.sort_by(|(_, a), (_, b)| {
-                    let score_a = calculate_relevance(a, query);
-                    let score_b = calculate_relevance(b, query);
+                    let score_a = calculate_relevance(a, &query, options.fold);
+                    let score_b = calculate_relevance(b, &query, options.fold);
                     score_b.cmp(&score_a)
                 });
             }
@@ -153,7 +306,8 @@ pub fn search(index: &SearchIndex, options: &SearchOptions) -> SearchResult {
     for (global_position, record) in records {
         let record = *record;
         if let Some(query) = normalized_query.as_ref() {
-            if !query_matches(record, query, options.regex) {
+            let regex = options.regex.then_some(options.regex_flags);
+            if !query_matches(record, query, regex, options.fold, options.field) {
                 continue;
             }
         }
@@ -186,6 +340,173 @@ pub fn search(index: &SearchIndex, options: &SearchOptions) -> SearchResult {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct RelatedHit {
+    pub hash: String,
+    pub score: f64,
+}
+
+/// Finds up to `limit` entries whose text shares the most trigrams with the
+/// entry `hash`, best match first. The entry itself is never included.
+pub fn related(index: &SearchIndex, hash: &str, limit: usize) -> Vec<RelatedHit> {
+    let Some(target) = index.get(hash) else {
+        return Vec::new();
+    };
+    let target_grams = trigrams(record_text(target));
+    if target_grams.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<RelatedHit> = index
+        .values()
+        .filter(|record| record.hash != target.hash)
+        .filter_map(|record| {
+            let grams = trigrams(record_text(record));
+            let shared = target_grams.intersection(&grams).count();
+            if shared == 0 {
+                return None;
+            }
+            let union = target_grams.len() + grams.len() - shared;
+            let score = shared as f64 / union as f64;
+            (score >= RELATED_MIN_SCORE).then(|| RelatedHit {
+                hash: record.hash.clone(),
+                score,
+            })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.hash.cmp(&b.hash)));
+    hits.truncate(limit);
+    hits
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzyCandidate {
+    pub hash: String,
+    pub summary: String,
+}
+
+/// Ranks entries whose summary fuzzily matches `query`, best first. Ties go to
+/// the most recently seen entry.
+pub fn fuzzy_candidates(
+    index: &SearchIndex,
+    query: &str,
+    filter: &SelectionFilter,
+    limit: usize,
+) -> Vec<FuzzyCandidate> {
+    let mut scored: Vec<(u32, &SearchIndexRecord)> = index
+        .values()
+        .filter(|record| filter.matches(record))
+        .filter_map(|record| {
+            let summary = record.summary.as_deref()?;
+            fuzzy_score(summary, query).map(|score| (score, record))
+        })
+        .collect();
+    scored.sort_by(|(score_a, a), (score_b, b)| {
+        score_b
+            .cmp(score_a)
+            .then_with(|| a.cmp_newest_first(b))
+    });
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, record)| FuzzyCandidate {
+            hash: record.hash.clone(),
+            summary: record.summary.clone().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Up to `limit` distinct completions for `prefix`, newest entries first:
+/// summaries that start with it, then words from the entry's text that do.
+/// Comparison ignores case and duplicates keep their most recent spelling. An
+/// empty prefix returns the most recent distinct summaries.
+pub fn suggest(index: &SearchIndex, prefix: &str, limit: usize) -> Vec<String> {
+    let prefix = prefix.trim().to_lowercase();
+    let mut records: Vec<&SearchIndexRecord> = index.values().collect();
+    records.sort_by(|a, b| a.cmp_newest_first(b));
+
+    let mut seen = HashSet::new();
+    let mut suggestions = Vec::new();
+    for record in records {
+        let summary = record.summary.as_deref().map(str::trim);
+        let words = record
+            .search_text
+            .as_deref()
+            .filter(|_| !prefix.is_empty())
+            .into_iter()
+            .flat_map(str::split_whitespace)
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()));
+        for candidate in summary.into_iter().chain(words) {
+            if suggestions.len() >= limit {
+                return suggestions;
+            }
+            let lowered = candidate.to_lowercase();
+            if candidate.is_empty() || !lowered.starts_with(&prefix) {
+                continue;
+            }
+            if seen.insert(lowered) {
+                suggestions.push(candidate.to_string());
+            }
+        }
+    }
+    suggestions
+}
+
+/// Scores `query` as a case-insensitive subsequence of `text`. Substring
+/// matches beat scattered ones, and consecutive or word-start characters earn
+/// bonuses. Returns `None` when not every query character appears in order.
+fn fuzzy_score(text: &str, query: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    let lowered = text.to_lowercase();
+    if let Some(position) = lowered.find(&query) {
+        let prefix_bonus = if position == 0 { 100 } else { 0 };
+        let position_penalty = lowered[..position].chars().count().min(100) as u32;
+        return Some(1000 + prefix_bonus - position_penalty);
+    }
+
+    let chars: Vec<char> = lowered.chars().collect();
+    let mut score: i64 = 0;
+    let mut cursor = 0;
+    let mut previous: Option<usize> = None;
+    for needle in query.chars() {
+        let found = (cursor..chars.len()).find(|&i| chars[i] == needle)?;
+        score += 10;
+        if previous.is_some_and(|prev| prev + 1 == found) {
+            score += 15;
+        }
+        if found == 0 || !chars[found - 1].is_alphanumeric() {
+            score += 10;
+        }
+        if let Some(prev) = previous {
+            score -= (found - prev - 1).min(5) as i64;
+        }
+        previous = Some(found);
+        cursor = found + 1;
+    }
+    Some(score.clamp(1, 999) as u32)
+}
+
+fn record_text(record: &SearchIndexRecord) -> &str {
+    record
+        .search_text
+        .as_deref()
+        .or(record.summary.as_deref())
+        .unwrap_or("")
+}
+
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = text
+        .chars()
+        .take(RELATED_MAX_CHARS)
+        .flat_map(char::to_lowercase)
+        .collect();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
 fn in_range(
     record: &SearchIndexRecord,
     from: Option<&OffsetDateTime>,
@@ -199,49 +520,167 @@ fn in_range(
     }
 }
 
-fn query_matches(record: &SearchIndexRecord, query: &str, is_regex: bool) -> bool {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Hash,
+    Summary,
+    SearchText,
+}
+
+impl MatchField {
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchField::Hash => "hash",
+            MatchField::Summary => "summary",
+            MatchField::SearchText => "search_text",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchType {
+    Substring,
+    Regex,
+}
+
+impl MatchType {
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchType::Substring => "substring",
+            MatchType::Regex => "regex",
+        }
+    }
+}
+
+/// Why a record matched a query, for `search --explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchExplanation {
+    pub field: MatchField,
+    pub match_type: MatchType,
+    pub score: u32,
+}
+
+/// Explains how `record` matches `query` using the same rules as [`search`].
+/// Returns `None` when it doesn't match.
+pub fn explain_match(
+    record: &SearchIndexRecord,
+    query: &str,
+    regex: Option<RegexFlags>,
+    fold: bool,
+    field: SearchField,
+) -> Option<MatchExplanation> {
+    let query = if regex.is_some() {
+        query.trim().to_string()
+    } else {
+        normalize_text(query.trim(), fold)
+    };
+    let (field, match_type) = matched_field(record, &query, regex, fold, field)?;
+    Some(MatchExplanation {
+        field,
+        match_type,
+        score: calculate_relevance(record, &relevance_query(&query, regex.is_some()), fold),
+    })
+}
+
+/// Relevance is substring based, so regex patterns are scored lowercased.
+fn relevance_query(query: &str, is_regex: bool) -> Cow<'_, str> {
     if is_regex {
-        if let Ok(re) = regex::RegexBuilder::new(query)
-            .case_insensitive(true)
-            .build()
-        {
-            if re.is_match(&record.hash) {
-                return true;
+        Cow::Owned(query.to_lowercase())
+    } else {
+        Cow::Borrowed(query)
+    }
+}
+
+fn query_matches(
+    record: &SearchIndexRecord,
+    query: &str,
+    regex: Option<RegexFlags>,
+    fold: bool,
+    field: SearchField,
+) -> bool {
+    matched_field(record, query, regex, fold, field).is_some()
+}
+
+/// Lowercases `text` and, with `fold`, strips diacritics by decomposing it
+/// (NFD) and dropping the combining marks.
+fn normalize_text(text: &str, fold: bool) -> String {
+    if !fold || text.is_ascii() {
+        return text.to_lowercase();
+    }
+    text.nfd()
+        .filter(|ch| !is_combining_mark(*ch))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Folded text for regex matching; case is left to the regex itself. The
+/// pattern is folded the same way so its literals can match folded text.
+fn regex_haystack(text: &str, fold: bool) -> Cow<'_, str> {
+    if !fold || text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.nfd().filter(|ch| !is_combining_mark(*ch)).collect())
+}
+
+fn matched_field(
+    record: &SearchIndexRecord,
+    query: &str,
+    regex: Option<RegexFlags>,
+    fold: bool,
+    field: SearchField,
+) -> Option<(MatchField, MatchType)> {
+    if let Some(flags) = regex {
+        if let Some(re) = flags.build(&regex_haystack(query, fold)) {
+            if field.covers(MatchField::Hash) && re.is_match(&record.hash) {
+                return Some((MatchField::Hash, MatchType::Regex));
             }
-            if record
-                .summary
-                .as_ref()
-                .map(|summary| re.is_match(summary))
-                .unwrap_or(false)
+            if field.covers(MatchField::Summary)
+                && record
+                    .summary
+                    .as_ref()
+                    .map(|summary| re.is_match(&regex_haystack(summary, fold)))
+                    .unwrap_or(false)
             {
-                return true;
+                return Some((MatchField::Summary, MatchType::Regex));
             }
             return record
                 .search_text
                 .as_ref()
-                .map(|text| re.is_match(text))
-                .unwrap_or(false);
+                .filter(|_| field.covers(MatchField::SearchText))
+                .filter(|text| re.is_match(&regex_haystack(text, fold)))
+                .map(|_| (MatchField::SearchText, MatchType::Regex));
         }
+        // Invalid patterns fall back to a plain substring match.
+        return matched_field(record, &normalize_text(query, fold), None, fold, field);
     }
 
-    if record.hash.to_lowercase().contains(query) {
-        return true;
+    if field.covers(MatchField::Hash) && record.hash.to_lowercase().contains(query) {
+        return Some((MatchField::Hash, MatchType::Substring));
     }
 
-    if record
-        .summary
-        .as_ref()
-        .map(|summary| summary.to_lowercase().contains(query))
-        .unwrap_or(false)
+    if field.covers(MatchField::Summary)
+        && record
+            .summary
+            .as_ref()
+            .map(|summary| normalize_text(summary, fold).contains(query))
+            .unwrap_or(false)
     {
-        return true;
+        return Some((MatchField::Summary, MatchType::Substring));
     }
 
     record
         .search_text
         .as_ref()
-        .map(|text| text.to_lowercase().contains(query))
-        .unwrap_or(false)
+        .filter(|_| field.covers(MatchField::SearchText))
+        .filter(|text| normalize_text(text, fold).contains(query))
+        .map(|_| (MatchField::SearchText, MatchType::Substring))
+}
+
+fn split_param(raw: Option<&str>) -> impl Iterator<Item = String> + '_ {
+    raw.into_iter()
+        .flat_map(|value| value.split(','))
+        .map(|part| part.trim().to_lowercase())
+        .filter(|part| !part.is_empty())
 }
 
 fn contains_format(formats: &[String], needle: &str) -> bool {
@@ -250,7 +689,7 @@ fn contains_format(formats: &[String], needle: &str) -> bool {
         .any(|format| format.to_ascii_lowercase().contains(needle))
 }
 
-fn calculate_relevance(record: &SearchIndexRecord, query: &str) -> u32 {
+fn calculate_relevance(record: &SearchIndexRecord, query: &str, fold: bool) -> u32 {
     // Note: Regex relevance scoring is simplified to boolean match for now
     // as calculating "how much" it matches is complex and potentially slow.
     // We fall back to standard string matching for relevance if not regex,
@@ -265,7 +704,7 @@ fn calculate_relevance(record: &SearchIndexRecord, query: &str) -> u32 {
     } else if hash.contains(query) {
         80
     } else if let Some(summary) = &record.summary {
-        let summary = summary.to_lowercase();
+        let summary = normalize_text(summary, fold);
         if summary == query {
             90
         } else if summary.starts_with(query) {
@@ -273,7 +712,7 @@ fn calculate_relevance(record: &SearchIndexRecord, query: &str) -> u32 {
         } else if summary.contains(query) {
             60
         } else if let Some(text) = &record.search_text {
-            if text.to_lowercase().contains(query) {
+            if normalize_text(text, fold).contains(query) {
                 40
             } else {
                 0
@@ -282,7 +721,7 @@ fn calculate_relevance(record: &SearchIndexRecord, query: &str) -> u32 {
             0
         }
     } else if let Some(text) = &record.search_text {
-        if text.to_lowercase().contains(query) {
+        if normalize_text(text, fold).contains(query) {
             40
         } else {
             0
@@ -370,9 +809,12 @@ mod tests {
     use crate::data::model::{EntryKind, SearchIndexRecord};
     use time::OffsetDateTime;
 
+    const ALL: SearchField = SearchField::All;
+
     fn create_record(hash: &str, kind: EntryKind, formats: Vec<String>, summary: Option<String>) -> SearchIndexRecord {
         SearchIndexRecord {
             hash: hash.to_string(),
+            first_seen: OffsetDateTime::now_utc(),
             last_seen: OffsetDateTime::now_utc(),
             kind,
             copy_count: 1,
@@ -381,9 +823,232 @@ mod tests {
             detected_formats: formats,
             byte_size: 100,
             relative_path: "".to_string(),
+            pinned: false,
+            source_app: None,
         }
     }
 
+    #[test]
+    fn test_fuzzy_score_prefers_substrings_and_prefixes() {
+        let prefix = fuzzy_score("meeting notes for monday", "meeting").unwrap();
+        let inner = fuzzy_score("weekly meeting notes", "meeting").unwrap();
+        let scattered = fuzzy_score("meet in a new thing", "meeting").unwrap();
+        assert!(prefix > inner);
+        assert!(inner > scattered);
+        assert!(fuzzy_score("unrelated", "meeting").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_score_rewards_word_starts() {
+        let initials = fuzzy_score("quarterly board review", "qbr").unwrap();
+        let buried = fuzzy_score("aqxbyr", "qbr").unwrap();
+        assert!(initials > buried);
+    }
+
+    #[test]
+    fn test_fuzzy_candidates_ranking() {
+        let mut index = SearchIndex::new();
+        for (hash, summary) in [
+            ("aaaaaa", "git push origin main"),
+            ("bbbbbb", "push notifications config"),
+            ("cccccc", "please upload shell"),
+            ("dddddd", "nothing relevant"),
+        ] {
+            index.insert(
+                hash.to_string(),
+                create_record(hash, EntryKind::Text, vec![], Some(summary.to_string())),
+            );
+        }
+        let ranked: Vec<String> = fuzzy_candidates(&index, "push", &SelectionFilter::default(), 10)
+            .into_iter()
+            .map(|candidate| candidate.hash)
+            .collect();
+        assert_eq!(ranked, vec!["bbbbbb", "aaaaaa", "cccccc"]);
+    }
+
+    #[test]
+    fn test_equal_timestamps_keep_a_fixed_order() {
+        let seen = OffsetDateTime::now_utc();
+        let mut index = SearchIndex::new();
+        for hash in ["dddddd", "aaaaaa", "cccccc", "bbbbbb"] {
+            let mut record = create_record(hash, EntryKind::Text, vec![], None);
+            record.last_seen = seen;
+            index.insert(hash.to_string(), record);
+        }
+
+        let order = |index: &SearchIndex| -> Vec<String> {
+            search(index, &SearchOptions::default())
+                .hits
+                .into_iter()
+                .map(|hit| hit.hash)
+                .collect()
+        };
+        let first = order(&index);
+        assert_eq!(first, vec!["aaaaaa", "bbbbbb", "cccccc", "dddddd"]);
+        for _ in 0..5 {
+            let rebuilt: SearchIndex = index.clone().into_iter().collect();
+            assert_eq!(order(&rebuilt), first);
+        }
+    }
+
+    #[test]
+    fn test_explain_match_reports_field_and_type() {
+        let mut record = create_record(
+            "a1b2c3d4",
+            EntryKind::Text,
+            vec![],
+            Some("Meeting notes".to_string()),
+        );
+        record.search_text = Some("Meeting notes\nagenda: budget".to_string());
+
+        let summary = explain_match(&record, "Meeting", None, false, ALL).unwrap();
+        assert_eq!(summary.field, MatchField::Summary);
+        assert_eq!(summary.match_type, MatchType::Substring);
+        assert_eq!(summary.score, calculate_relevance(&record, "meeting", false));
+
+        let body = explain_match(&record, "budget", None, false, ALL).unwrap();
+        assert_eq!(body.field, MatchField::SearchText);
+
+        let regex = Some(RegexFlags::default());
+        let hash = explain_match(&record, "^a1b2", regex, false, ALL).unwrap();
+        assert_eq!(
+            (hash.field, hash.match_type),
+            (MatchField::Hash, MatchType::Regex)
+        );
+
+        assert!(explain_match(&record, "absent", None, false, ALL).is_none());
+    }
+
+    #[test]
+    fn test_fold_matches_accented_and_unaccented_text() {
+        let mut index = SearchIndex::new();
+        let records = [
+            ("accented", "Meet at the café"),
+            ("plain", "Cafe menu"),
+            ("decomposed", "Cafe\u{301} noir"),
+        ];
+        for (hash, summary) in records {
+            index.insert(
+                hash.to_string(),
+                create_record(hash, EntryKind::Text, vec![], Some(summary.to_string())),
+            );
+        }
+        let hashes = |query: &str, fold: bool| {
+            let options = SearchOptions {
+                query: Some(query.to_string()),
+                fold,
+                ..Default::default()
+            };
+            let mut hashes: Vec<String> = search(&index, &options)
+                .hits
+                .into_iter()
+                .map(|hit| hit.hash)
+                .collect();
+            hashes.sort();
+            hashes
+        };
+
+        assert_eq!(
+            hashes("cafe", true),
+            vec!["accented", "decomposed", "plain"]
+        );
+        assert_eq!(
+            hashes("CAFÉ", true),
+            vec!["accented", "decomposed", "plain"]
+        );
+        assert_eq!(hashes("cafe", false), vec!["decomposed", "plain"]);
+        assert_eq!(hashes("café", false), vec!["accented"]);
+    }
+
+    #[test]
+    fn test_fold_applies_to_regex_content_and_explain() {
+        let record = create_record(
+            "h1",
+            EntryKind::Text,
+            vec![],
+            Some("Crème brûlée".to_string()),
+        );
+        let regex = Some(RegexFlags::default());
+        assert!(query_matches(&record, "creme bru", None, true, ALL));
+        assert!(!query_matches(&record, "creme bru", None, false, ALL));
+        assert!(query_matches(&record, "^creme", regex, true, ALL));
+        assert!(!query_matches(&record, "^creme", regex, false, ALL));
+        assert!(query_matches(&record, "^crème b.ûl", regex, true, ALL));
+        assert!(query_matches(&record, "brûl[eé]e$", regex, true, ALL));
+
+        let explained = explain_match(&record, "BRULEE", None, true, ALL).unwrap();
+        assert_eq!(explained.field, MatchField::Summary);
+        assert!(explained.score > 0);
+    }
+
+    #[test]
+    fn test_search_field_restricts_matching() {
+        let mut record = create_record(
+            "a1b2c3d4",
+            EntryKind::Text,
+            vec![],
+            Some("Meeting notes".to_string()),
+        );
+        record.search_text = Some("Meeting notes\nagenda: budget".to_string());
+        let (summary, text) = (SearchField::Summary, SearchField::Text);
+
+        assert!(!query_matches(&record, "budget", None, false, summary));
+        assert!(query_matches(&record, "budget", None, false, text));
+        assert!(query_matches(&record, "budget", None, false, ALL));
+        assert!(query_matches(&record, "meeting", None, false, summary));
+        assert!(!query_matches(&record, "a1b2", None, false, summary));
+        assert!(!query_matches(&record, "a1b2", None, false, text));
+
+        let regex = Some(RegexFlags::default());
+        assert!(!query_matches(&record, "^agenda", regex, false, summary));
+        let multi_line = Some(RegexFlags::parse("m").unwrap());
+        assert!(query_matches(&record, "^agenda", multi_line, false, text));
+
+        let explained = explain_match(&record, "meeting", None, false, text).unwrap();
+        assert_eq!(explained.field, MatchField::SearchText);
+
+        let mut index = SearchIndex::new();
+        index.insert(record.hash.clone(), record);
+        let mut options = SearchOptions {
+            query: Some("budget".to_string()),
+            field: summary,
+            ..SearchOptions::default()
+        };
+        assert!(search(&index, &options).hits.is_empty());
+        options.field = text;
+        assert_eq!(search(&index, &options).hits.len(), 1);
+
+        assert_eq!(SearchField::parse(" Summary ").unwrap(), summary);
+        assert!(SearchField::parse("body").is_err());
+    }
+
+    #[test]
+    fn test_regex_flags_case_sensitive_and_multiline() {
+        let mut record = create_record("h1", EntryKind::Text, vec![], Some("Todo list".into()));
+        record.search_text = Some("Todo list\nFIXME: ship it".to_string());
+
+        let default = Some(RegexFlags::default());
+        let sensitive = Some(RegexFlags::parse("").unwrap());
+        assert!(query_matches(&record, "todo", default, false, ALL));
+        assert!(!query_matches(&record, "todo", sensitive, false, ALL));
+        assert!(query_matches(&record, "Todo", sensitive, false, ALL));
+
+        // `^` only matches at line starts with `m`.
+        assert!(!query_matches(&record, "^FIXME", sensitive, false, ALL));
+        let multi_line = Some(RegexFlags::parse("m").unwrap());
+        assert!(query_matches(&record, "^FIXME", multi_line, false, ALL));
+        assert!(!query_matches(&record, "^fixme", multi_line, false, ALL));
+        let both = Some(RegexFlags::parse("im").unwrap());
+        assert!(query_matches(&record, "^fixme", both, false, ALL));
+
+        // `s` lets `.` cross the newline.
+        assert!(!query_matches(&record, "list.FIXME", sensitive, false, ALL));
+        let dot_all = Some(RegexFlags::parse("s").unwrap());
+        assert!(query_matches(&record, "list.FIXME", dot_all, false, ALL));
+
+        assert!(RegexFlags::parse("q").is_err());
+    }
+
     #[test]
     fn test_parse_search_query_link() {
         let (query, is_regex, _) = parse_search_query("@link", false);
@@ -418,4 +1083,87 @@ mod tests {
         assert!(filter.matches(&record_html));
         assert!(!filter.matches(&record_text));
     }
+
+    #[test]
+    fn test_suggest_matches_prefix_without_duplicates() {
+        let mut index = SearchIndex::new();
+        let mut add = |hash: &str, minutes_ago: i64, summary: &str, text: &str| {
+            let mut record =
+                create_record(hash, EntryKind::Text, vec![], Some(summary.to_string()));
+            record.last_seen = OffsetDateTime::now_utc() - time::Duration::minutes(minutes_ago);
+            record.search_text = Some(text.to_string());
+            index.insert(hash.to_string(), record);
+        };
+        add("new", 1, "Deploy notes", "Deploy notes: deployment window, (deploy) again");
+        add("old", 5, "deploy notes", "deploy notes");
+        add("other", 2, "Lunch order", "Lunch order for the team");
+
+        assert_eq!(
+            suggest(&index, "dep", 10),
+            vec!["Deploy notes", "Deploy", "deployment"]
+        );
+        assert_eq!(suggest(&index, "DEP", 1), vec!["Deploy notes"]);
+        assert!(suggest(&index, "xyz", 10).is_empty());
+        assert_eq!(suggest(&index, "", 10), vec!["Deploy notes", "Lunch order"]);
+    }
+
+    #[test]
+    fn test_related_returns_near_duplicates() {
+        let mut index = SearchIndex::new();
+        let mut add = |hash: &str, text: &str| {
+            let mut record = create_record(hash, EntryKind::Text, vec![], Some(text.to_string()));
+            record.search_text = Some(text.to_string());
+            index.insert(hash.to_string(), record);
+        };
+        add("base", "SELECT id, name FROM users WHERE active = 1");
+        add("variant", "SELECT id, name FROM users WHERE active = 0");
+        add("other", "Remember to buy oat milk on the way home");
+
+        let hits = related(&index, "base", 5);
+        let hashes: Vec<_> = hits.iter().map(|hit| hit.hash.as_str()).collect();
+        assert_eq!(hashes, vec!["variant"]);
+        assert!(related(&index, "missing", 5).is_empty());
+    }
+
+    #[test]
+    fn test_query_params_combine_formats_and_kinds() {
+        let filter =
+            SelectionFilter::from_query_params(Some("html, public.rtf"), Some("Image,file"))
+                .unwrap();
+        assert!(filter.include_html);
+        assert!(filter.include_image);
+        assert!(filter.include_file);
+        assert!(!filter.include_text);
+        assert_eq!(filter.include_formats, vec!["public.rtf".to_string()]);
+
+        let image = create_record("img", EntryKind::Image, vec![], None);
+        let plain = vec!["public.utf8-plain-text".into()];
+        let text = create_record("txt", EntryKind::Text, plain, None);
+        let rtf = create_record("rtf", EntryKind::Text, vec!["public.rtf".into()], None);
+        assert!(filter.matches(&image));
+        assert!(!filter.matches(&text));
+        assert!(filter.matches(&rtf));
+    }
+
+    #[test]
+    fn test_query_params_keep_legacy_kind_formats() {
+        let legacy = SelectionFilter::from_query_params(Some("text,files"), None).unwrap();
+        let explicit = SelectionFilter::from_query_params(None, Some("text,file")).unwrap();
+        assert!(legacy.include_text && legacy.include_file);
+        assert!(explicit.include_text && explicit.include_file);
+        assert!(legacy.include_formats.is_empty());
+
+        let empty = SelectionFilter::from_query_params(Some(" , "), None).unwrap();
+        assert!(empty.include_formats.is_empty());
+        let other = create_record("o", EntryKind::Other, vec![], None);
+        assert!(empty.matches(&other));
+    }
+
+    #[test]
+    fn test_query_params_reject_unknown_kinds() {
+        assert!(matches!(
+            SelectionFilter::from_query_params(None, Some("text,video")),
+            Err(ClipboardError::InvalidInput(_))
+        ));
+    }
 }
@@ -10,7 +10,7 @@ use std::process::Command;
 const SERVICE_NAME: &str = "get_clipboard.service";
 const UNIT_ID: &str = "get_clipboard";
 
-pub fn install_agent() -> Result<()> {
+pub fn install_agent(autostart: bool) -> Result<()> {
     let unit_path = service_unit_path()?;
     let content = build_unit()?;
     if let Some(dir) = unit_path.parent() {
@@ -18,8 +18,16 @@ pub fn install_agent() -> Result<()> {
     }
     fs::write(&unit_path, content)?;
     run_systemctl(&["--user", "daemon-reload"])?;
-    run_systemctl(&["--user", "enable", "--now", UNIT_ID])?;
+    if autostart {
+        run_systemctl(&["--user", "enable", "--now", UNIT_ID])?;
+    } else {
+        // Drop any login hook left by an earlier autostart install.
+        let _ = run_systemctl(&["--user", "disable", UNIT_ID]);
+    }
     println!("Installed systemd unit at {}", unit_path.display());
+    if !autostart {
+        println!("Autostart disabled; run `get_clipboard service start` to launch it");
+    }
     println!("Service logs: {}", log_file_path()?.display());
     Ok(())
 }
@@ -9,9 +9,9 @@ use std::process::Command;
 
 const LABEL: &str = "com.tjs.get_clipboard";
 
-pub fn install_agent() -> Result<()> {
+pub fn install_agent(autostart: bool) -> Result<()> {
     let plist_path = agent_plist_path()?;
-    let content = build_plist()?;
+    let content = build_plist(autostart)?;
     if let Some(dir) = plist_path.parent() {
         fs::create_dir_all(dir)?;
     }
@@ -28,6 +28,9 @@ pub fn install_agent() -> Result<()> {
         LABEL,
         plist_path.display()
     );
+    if !autostart {
+        println!("Autostart disabled; run `get_clipboard service start` to launch it");
+    }
     println!("Service logs: {}", log_file_path()?.display());
     Ok(())
 }
@@ -50,15 +53,16 @@ pub fn start_agent() -> Result<()> {
         bail!("Service is not installed. Run `get_clipboard service install` first.");
     }
     
-    let is_running = Command::new("launchctl")
+    let is_loaded = Command::new("launchctl")
         .args(["list", LABEL])
         .status()
         .map(|status| status.success())
         .unwrap_or(false);
     
-    if is_running {
-        println!("Launch agent {} is already running", LABEL);
-        return Ok(());
+    if is_loaded {
+        // Installed with --no-autostart: the job is loaded but idle until started.
+        println!("Starting launch agent {}", LABEL);
+        return run_launchctl(["start", LABEL]);
     }
     
     println!("Starting launch agent {}", LABEL);
@@ -142,22 +146,36 @@ fn agent_plist_path() -> Result<PathBuf> {
         .join("com.tjs.get_clipboard.plist"))
 }
 
-fn build_plist() -> Result<String> {
+fn build_plist(autostart: bool) -> Result<String> {
     let exe = std::env::current_exe()?;
     let paths = resolve_paths();
     fs::create_dir_all(&paths.config_dir)?;
     let log_path = paths.config_dir.join("service.log");
     let now = time::format_human(time::now());
-    // Using 'watch' command instead of 'api' for the background service
-    Ok(format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n<dict>\n    <key>Label</key>\n    <string>{LABEL}</string>\n    <key>ProgramArguments</key>\n    <array>\n        <string>{}</string>\n        <string>watch</string>\n    </array>\n    <key>RunAtLoad</key>\n    <true/>\n    <key>KeepAlive</key>\n    <true/>\n    <key>StandardErrorPath</key>\n    <string>{}</string>\n    <key>StandardOutPath</key>\n    <string>{}</string>\n    <key>EnvironmentVariables</key>\n    <dict>\n        <key>GET_CLIPBOARD_STARTED</key>\n        <string>{}</string>\n    </dict>\n</dict>\n</plist>\n",
-        exe.to_string_lossy(),
-        log_path.to_string_lossy(),
-        log_path.to_string_lossy(),
-        now
+    Ok(render_plist(
+        &exe.to_string_lossy(),
+        &log_path.to_string_lossy(),
+        &now,
+        autostart,
     ))
 }
 
+/// Without autostart, `RunAtLoad` is off and `KeepAlive` is left out
+/// entirely, since any `KeepAlive` condition can make launchd start the
+/// watcher on load.
+fn render_plist(exe: &str, log_path: &str, started: &str, autostart: bool) -> String {
+    let run_at_load = if autostart { "<true/>" } else { "<false/>" };
+    let keep_alive = if autostart {
+        "\n    <key>KeepAlive</key>\n    <true/>"
+    } else {
+        ""
+    };
+    // Using 'watch' command instead of 'api' for the background service
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n<plist version=\"1.0\">\n<dict>\n    <key>Label</key>\n    <string>{LABEL}</string>\n    <key>ProgramArguments</key>\n    <array>\n        <string>{exe}</string>\n        <string>watch</string>\n    </array>\n    <key>RunAtLoad</key>\n    {run_at_load}{keep_alive}\n    <key>StandardErrorPath</key>\n    <string>{log_path}</string>\n    <key>StandardOutPath</key>\n    <string>{log_path}</string>\n    <key>EnvironmentVariables</key>\n    <dict>\n        <key>GET_CLIPBOARD_STARTED</key>\n        <string>{started}</string>\n    </dict>\n</dict>\n</plist>\n"
+    )
+}
+
 fn log_file_path() -> Result<PathBuf> {
     let paths = resolve_paths();
     Ok(paths.config_dir.join("service.log"))
@@ -171,3 +189,43 @@ fn run_launchctl<const N: usize>(args: [&str; N]) -> Result<()> {
         Err(anyhow!("launchctl exited with status {}", status))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plist(autostart: bool) -> String {
+        render_plist(
+            "/usr/local/bin/get_clipboard",
+            "/tmp/service.log",
+            "now",
+            autostart,
+        )
+    }
+
+    #[test]
+    fn test_plist_runs_at_load_with_autostart() {
+        let content = plist(true);
+        assert!(content.contains("<key>RunAtLoad</key>\n    <true/>"));
+        assert!(content.contains("<key>KeepAlive</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn test_plist_skips_load_without_autostart() {
+        let content = plist(false);
+        assert!(content.contains("<key>RunAtLoad</key>\n    <false/>"));
+        assert!(!content.contains("<key>KeepAlive</key>"));
+    }
+
+    #[test]
+    fn test_plist_keeps_program_and_log_paths() {
+        for autostart in [true, false] {
+            let content = plist(autostart);
+            assert!(content.contains("<string>/usr/local/bin/get_clipboard</string>"));
+            assert_eq!(
+                content.matches("<string>/tmp/service.log</string>").count(),
+                2
+            );
+        }
+    }
+}
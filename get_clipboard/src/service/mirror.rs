@@ -0,0 +1,473 @@
+use crate::clipboard::plugins;
+use crate::data::store::read_metadata;
+use crate::error::ClipboardError;
+use crate::fs::layout;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Pending items beyond this are dropped, oldest first, so an unreachable
+/// remote cannot grow the queue without bound.
+const MAX_QUEUED: usize = 500;
+/// Rejected deliveries before an item is given up on. Only failures the
+/// remote answered count, so an offline mirror never uses them up.
+const MAX_ATTEMPTS: u32 = 20;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the background sender waits for new items before retrying
+/// whatever is still queued.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Why a delivery failed, which decides what happens to the queued item.
+#[derive(Debug)]
+pub enum SendError {
+    /// The item no longer exists locally, so it is dropped right away.
+    Missing(anyhow::Error),
+    /// The remote answered but refused the item, or it could not be built.
+    /// Counts towards [`MAX_ATTEMPTS`].
+    Rejected(anyhow::Error),
+    /// The remote could not be reached. The item waits for the next retry.
+    Unreachable,
+}
+
+/// Delivers one stored item to the mirror.
+pub trait MirrorTransport {
+    fn send(&self, hash: &str) -> Result<(), SendError>;
+}
+
+/// Posts items to another Clippy API's `/save` endpoint.
+pub struct HttpTransport {
+    save_url: String,
+    data_dir: PathBuf,
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    pub fn new(mirror_url: &str, data_dir: &Path) -> Self {
+        HttpTransport {
+            save_url: save_url(mirror_url),
+            data_dir: data_dir.to_path_buf(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+}
+
+impl MirrorTransport for HttpTransport {
+    fn send(&self, hash: &str) -> Result<(), SendError> {
+        let metadata = read_metadata(&self.data_dir, hash).map_err(|err| match err {
+            ClipboardError::NotFound(_) => SendError::Missing(err.into()),
+            err => SendError::Rejected(err.into()),
+        })?;
+        let item_dir = self.data_dir.join(&metadata.relative_path);
+        let item = plugins::build_full_json_item(&metadata, &item_dir, None, None)
+            .map_err(SendError::Rejected)?;
+        self.agent
+            .post(&self.save_url)
+            .send_json(&item)
+            .map_err(|err| match err {
+                ureq::Error::Status(..) => SendError::Rejected(anyhow!(
+                    "Mirror request to {} failed: {err}",
+                    self.save_url
+                )),
+                ureq::Error::Transport(_) => SendError::Unreachable,
+            })?;
+        Ok(())
+    }
+}
+
+/// Accepts either an API base URL or its `/save` endpoint.
+fn save_url(mirror_url: &str) -> String {
+    let base = mirror_url.trim().trim_end_matches('/');
+    if base.ends_with("/save") {
+        base.to_string()
+    } else {
+        format!("{base}/save")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct QueuedItem {
+    hash: String,
+    attempts: u32,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct FlushReport {
+    pub delivered: usize,
+    pub dropped: usize,
+    pub pending: usize,
+}
+
+/// Best-effort forwarding of captured items. Items wait in a queue file in
+/// the data directory until the transport accepts them, so nothing is lost
+/// while the remote is offline.
+pub struct Mirror<T: MirrorTransport> {
+    queue_path: PathBuf,
+    transport: T,
+}
+
+impl<T: MirrorTransport> Mirror<T> {
+    pub fn new(data_dir: &Path, transport: T) -> Self {
+        Mirror {
+            queue_path: layout::mirror_queue_path(data_dir),
+            transport,
+        }
+    }
+
+    /// Queues `hash` and tries to deliver everything pending. Failures are
+    /// logged; the watcher never stops because of the mirror.
+    pub fn forward(&self, hash: &str) {
+        if let Err(err) = self.enqueue(hash) {
+            eprintln!("Failed to queue item for mirroring: {err:#}");
+            return;
+        }
+        self.retry();
+    }
+
+    /// Retries whatever is still queued.
+    pub fn retry(&self) {
+        match self.flush() {
+            Ok(report) if report.pending > 0 => {
+                eprintln!(
+                    "Mirror unavailable, {} item(s) queued for retry",
+                    report.pending
+                );
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Failed to flush mirror queue: {err:#}"),
+        }
+    }
+
+    /// Queues every hash that arrives on `receiver` and delivers them,
+    /// retrying the backlog whenever nothing new arrives for
+    /// `retry_interval`. Returns once every sender is gone.
+    fn run(&self, receiver: &Receiver<String>, retry_interval: Duration) {
+        loop {
+            match receiver.recv_timeout(retry_interval) {
+                Ok(hash) => {
+                    // Queue everything that piled up during the last flush
+                    // so an offline remote costs one timeout, not one each.
+                    for hash in std::iter::once(hash).chain(receiver.try_iter()) {
+                        if let Err(err) = self.enqueue(&hash) {
+                            eprintln!("Failed to queue item for mirroring: {err:#}");
+                        }
+                    }
+                    self.retry();
+                }
+                Err(RecvTimeoutError::Timeout) => self.retry(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    fn enqueue(&self, hash: &str) -> Result<()> {
+        let mut queue = self.load_queue()?;
+        queue.retain(|item| item.hash != hash);
+        queue.push(QueuedItem {
+            hash: hash.to_string(),
+            attempts: 0,
+        });
+        let overflow = queue.len().saturating_sub(MAX_QUEUED);
+        queue.drain(..overflow);
+        self.save_queue(&queue)
+    }
+
+    /// Sends queued items in order, stopping at the first failure so the
+    /// remote receives them in capture order. Items deleted locally are
+    /// dropped instead of holding up the rest.
+    fn flush(&self) -> Result<FlushReport> {
+        let mut queue = self.load_queue()?;
+        if queue.is_empty() {
+            return Ok(FlushReport::default());
+        }
+        let mut report = FlushReport::default();
+        while let Some(item) = queue.first_mut() {
+            match self.transport.send(&item.hash) {
+                Ok(()) => {
+                    queue.remove(0);
+                    report.delivered += 1;
+                }
+                Err(SendError::Unreachable) => break,
+                Err(SendError::Missing(err)) => {
+                    eprintln!("Dropping {} from mirror queue: {err:#}", item.hash);
+                    queue.remove(0);
+                    report.dropped += 1;
+                }
+                Err(SendError::Rejected(err)) => {
+                    item.attempts += 1;
+                    if item.attempts < MAX_ATTEMPTS {
+                        break;
+                    }
+                    eprintln!("Dropping {} from mirror queue: {err:#}", item.hash);
+                    queue.remove(0);
+                    report.dropped += 1;
+                }
+            }
+        }
+        report.pending = queue.len();
+        self.save_queue(&queue)?;
+        Ok(report)
+    }
+
+    fn load_queue(&self) -> Result<Vec<QueuedItem>> {
+        match fs::read(&self.queue_path) {
+            Ok(raw) => serde_json::from_slice(&raw)
+                .with_context(|| format!("Failed to parse {}", self.queue_path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => {
+                Err(err).with_context(|| format!("Failed to read {}", self.queue_path.display()))
+            }
+        }
+    }
+
+    fn save_queue(&self, queue: &[QueuedItem]) -> Result<()> {
+        if queue.is_empty() {
+            return match fs::remove_file(&self.queue_path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)
+                    .with_context(|| format!("Failed to remove {}", self.queue_path.display())),
+                _ => Ok(()),
+            };
+        }
+        // Written beside the queue and renamed over it, so a crash mid-write
+        // never leaves a truncated queue that fails to parse.
+        let payload = serde_json::to_vec(queue)?;
+        let dir = self.queue_path.parent().unwrap_or(Path::new("."));
+        tempfile::NamedTempFile::new_in(dir)
+            .and_then(|mut temp| {
+                temp.write_all(&payload)?;
+                temp.persist(&self.queue_path).map_err(|err| err.error)
+            })
+            .with_context(|| format!("Failed to write {}", self.queue_path.display()))?;
+        Ok(())
+    }
+}
+
+impl<T: MirrorTransport + Send + 'static> Mirror<T> {
+    /// Moves delivery onto its own thread so a slow or unreachable remote
+    /// never holds up clipboard polling.
+    pub fn spawn(self) -> MirrorHandle {
+        let (sender, receiver) = mpsc::channel();
+        let worker = thread::spawn(move || self.run(&receiver, RETRY_INTERVAL));
+        MirrorHandle {
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+}
+
+/// Hands captured items to a [`Mirror`] running in the background.
+/// Dropping it waits for the items already handed over to be queued.
+pub struct MirrorHandle {
+    sender: Option<Sender<String>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl MirrorHandle {
+    pub fn forward(&self, hash: &str) {
+        let sent = self
+            .sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(hash.to_string()).is_ok());
+        if !sent {
+            eprintln!("Mirror thread stopped, not forwarding {hash}");
+        }
+    }
+}
+
+impl Drop for MirrorHandle {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Clone, Copy, Default)]
+    enum Remote {
+        #[default]
+        Offline,
+        Rejecting,
+        Online,
+    }
+
+    #[derive(Default)]
+    struct MockTransport {
+        remote: Cell<Remote>,
+        deleted: RefCell<Vec<String>>,
+        sent: RefCell<Vec<String>>,
+        attempts: Cell<usize>,
+    }
+
+    impl MirrorTransport for &MockTransport {
+        fn send(&self, hash: &str) -> Result<(), SendError> {
+            if self.deleted.borrow().iter().any(|deleted| deleted == hash) {
+                return Err(SendError::Missing(anyhow!("Metadata not found for {hash}")));
+            }
+            self.attempts.set(self.attempts.get() + 1);
+            match self.remote.get() {
+                Remote::Offline => Err(SendError::Unreachable),
+                Remote::Rejecting => Err(SendError::Rejected(anyhow!("status 500"))),
+                Remote::Online => {
+                    self.sent.borrow_mut().push(hash.to_string());
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn queued_hashes<T: MirrorTransport>(mirror: &Mirror<T>) -> Vec<String> {
+        mirror
+            .load_queue()
+            .unwrap()
+            .into_iter()
+            .map(|item| item.hash)
+            .collect()
+    }
+
+    #[test]
+    fn test_offline_items_stay_queued_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+
+        mirror.forward("aaa");
+        mirror.forward("bbb");
+        assert_eq!(queued_hashes(&mirror), vec!["aaa", "bbb"]);
+        assert!(transport.sent.borrow().is_empty());
+        // Each flush stops at the first failure instead of trying every item.
+        assert_eq!(transport.attempts.get(), 2);
+    }
+
+    #[test]
+    fn test_retry_delivers_backlog_once_online() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+        mirror.forward("aaa");
+        mirror.forward("bbb");
+
+        transport.remote.set(Remote::Online);
+        let report = mirror.flush().unwrap();
+        assert_eq!(
+            report,
+            FlushReport {
+                delivered: 2,
+                dropped: 0,
+                pending: 0
+            }
+        );
+        assert_eq!(*transport.sent.borrow(), vec!["aaa", "bbb"]);
+        assert!(!layout::mirror_queue_path(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_requeued_hash_moves_to_the_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+        mirror.forward("aaa");
+        mirror.forward("bbb");
+        mirror.forward("aaa");
+        assert_eq!(queued_hashes(&mirror), vec!["bbb", "aaa"]);
+    }
+
+    #[test]
+    fn test_item_dropped_after_max_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        transport.remote.set(Remote::Rejecting);
+        let mirror = Mirror::new(dir.path(), &transport);
+        mirror.enqueue("aaa").unwrap();
+        for _ in 1..MAX_ATTEMPTS {
+            assert_eq!(mirror.flush().unwrap().pending, 1);
+        }
+        let report = mirror.flush().unwrap();
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.pending, 0);
+        assert!(queued_hashes(&mirror).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_remote_does_not_use_up_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+        mirror.enqueue("aaa").unwrap();
+        for _ in 0..MAX_ATTEMPTS * 2 {
+            assert_eq!(mirror.flush().unwrap().pending, 1);
+        }
+        assert_eq!(mirror.load_queue().unwrap()[0].attempts, 0);
+
+        transport.remote.set(Remote::Online);
+        assert_eq!(mirror.flush().unwrap().delivered, 1);
+    }
+
+    #[test]
+    fn test_locally_deleted_item_is_dropped_without_blocking_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        transport.remote.set(Remote::Online);
+        transport.deleted.borrow_mut().push("aaa".into());
+        let mirror = Mirror::new(dir.path(), &transport);
+        mirror.enqueue("aaa").unwrap();
+        mirror.enqueue("bbb").unwrap();
+
+        let report = mirror.flush().unwrap();
+        assert_eq!(
+            report,
+            FlushReport {
+                delivered: 1,
+                dropped: 1,
+                pending: 0
+            }
+        );
+        assert_eq!(*transport.sent.borrow(), vec!["bbb"]);
+    }
+
+    #[test]
+    fn test_queue_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+        for idx in 0..MAX_QUEUED + 3 {
+            mirror.enqueue(&format!("hash{idx}")).unwrap();
+        }
+        let queued = queued_hashes(&mirror);
+        assert_eq!(queued.len(), MAX_QUEUED);
+        assert_eq!(queued[0], "hash3");
+    }
+
+    #[test]
+    fn test_worker_queues_everything_sent_before_stopping() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::default();
+        let mirror = Mirror::new(dir.path(), &transport);
+        let (sender, receiver) = mpsc::channel();
+        for hash in ["aaa", "bbb", "ccc"] {
+            sender.send(hash.to_string()).unwrap();
+        }
+        drop(sender);
+
+        mirror.run(&receiver, Duration::from_millis(10));
+        assert_eq!(queued_hashes(&mirror), vec!["aaa", "bbb", "ccc"]);
+        // The burst is queued first and flushed once.
+        assert_eq!(transport.attempts.get(), 1);
+    }
+
+    #[test]
+    fn test_save_url_accepts_base_or_endpoint() {
+        assert_eq!(save_url("http://desk:3016"), "http://desk:3016/save");
+        assert_eq!(save_url("http://desk:3016/"), "http://desk:3016/save");
+        assert_eq!(save_url("http://desk:3016/save"), "http://desk:3016/save");
+    }
+}
@@ -1,5 +1,7 @@
 pub mod watch;
+pub mod mirror;
 pub mod permissions;
+pub mod power;
 
 #[cfg(target_os = "linux")]
 mod linux;
@@ -43,8 +45,10 @@ impl ServiceStatus {
     }
 }
 
-pub fn install_agent() -> Result<()> {
-    platform::install_agent()
+/// Installs the watcher as a per-user service. With `autostart` unset the
+/// service is registered but only runs after `service start`.
+pub fn install_agent(autostart: bool) -> Result<()> {
+    platform::install_agent(autostart)
 }
 
 pub fn uninstall_agent() -> Result<()> {
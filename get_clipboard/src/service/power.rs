@@ -0,0 +1,118 @@
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Whether the Mac is running on battery or has Low Power Mode on. Either
+/// one makes image capture follow the `battery_images` policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub low_power: bool,
+}
+
+impl PowerState {
+    pub fn constrained(self) -> bool {
+        self.on_battery || self.low_power
+    }
+}
+
+/// Reads the power state from `pmset`. When it can't be run the Mac is
+/// assumed to be plugged in, so images keep being captured.
+pub fn current_power_state() -> PowerState {
+    PowerState {
+        on_battery: pmset_output(&["-g", "batt"]).is_some_and(|out| on_battery(&out)),
+        low_power: pmset_output(&["-g"]).is_some_and(|out| low_power_enabled(&out)),
+    }
+}
+
+/// Reuses a power reading for `ttl`, so a burst of image copies doesn't run
+/// `pmset` twice for each one.
+pub struct PowerMonitor {
+    ttl: Duration,
+    last: Option<(Instant, PowerState)>,
+}
+
+impl PowerMonitor {
+    pub fn new(ttl: Duration) -> Self {
+        PowerMonitor { ttl, last: None }
+    }
+
+    pub fn current(&mut self) -> PowerState {
+        self.read_at(Instant::now(), current_power_state)
+    }
+
+    fn read_at(&mut self, now: Instant, read: impl FnOnce() -> PowerState) -> PowerState {
+        if let Some((at, state)) = self.last
+            && now.duration_since(at) < self.ttl
+        {
+            return state;
+        }
+        let state = read();
+        self.last = Some((now, state));
+        state
+    }
+}
+
+fn pmset_output(args: &[&str]) -> Option<String> {
+    let output = Command::new("pmset").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `pmset -g batt` starts with "Now drawing from 'Battery Power'" or
+/// "'AC Power'".
+fn on_battery(batt: &str) -> bool {
+    batt.lines()
+        .next()
+        .is_some_and(|line| line.contains("'Battery Power'"))
+}
+
+/// `pmset -g` lists `lowpowermode 1` while Low Power Mode is on.
+fn low_power_enabled(settings: &str) -> bool {
+    settings.lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        parts.next() == Some("lowpowermode") && parts.next() == Some("1")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_pmset_output() {
+        assert!(on_battery(
+            "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1)\t85%; discharging;"
+        ));
+        assert!(!on_battery(
+            "Now drawing from 'AC Power'\n -InternalBattery-0 (id=1)\t100%; charged;"
+        ));
+        assert!(!on_battery(""));
+
+        let settings = "System-wide power settings:\nCurrently in use:\n lowpowermode         1\n sleep                1\n";
+        assert!(low_power_enabled(settings));
+        assert!(!low_power_enabled(
+            &settings.replace("lowpowermode         1", "lowpowermode 0")
+        ));
+        assert!(!low_power_enabled("Currently in use:\n sleep 1\n"));
+    }
+
+    #[test]
+    fn test_power_reading_is_reused_within_ttl() {
+        let mut monitor = PowerMonitor::new(Duration::from_secs(5));
+        let start = Instant::now();
+        let mut reads = 0;
+        let mut read = |at: Duration| {
+            monitor.read_at(start + at, || {
+                reads += 1;
+                PowerState::default()
+            })
+        };
+        read(Duration::ZERO);
+        read(Duration::from_secs(1));
+        read(Duration::from_secs(4));
+        read(Duration::from_secs(6));
+        assert_eq!(reads, 2);
+    }
+}
@@ -5,7 +5,7 @@ fn unsupported() -> Result<()> {
     bail!("Service management is not supported on this platform")
 }
 
-pub fn install_agent() -> Result<()> {
+pub fn install_agent(_autostart: bool) -> Result<()> {
     unsupported()
 }
 
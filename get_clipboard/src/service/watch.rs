@@ -1,61 +1,508 @@
 use crate::clipboard::ClipboardSnapshot;
+use crate::clipboard::noise::{NoiseFilter, NoiseSettings};
+use crate::clipboard::plugins::CaptureContext;
+use crate::clipboard::snapshot::{PasteboardReport, clipboard_has_image};
+use crate::config::model::BatteryImagePolicy;
 use crate::config::{ensure_data_dir, load_config};
+use crate::data::flush;
+use crate::data::guard;
+use crate::data::incognito;
+use crate::data::model::EntryMetadata;
 use crate::data::store::store_snapshot;
-use anyhow::Result;
+use crate::service::mirror::{HttpTransport, Mirror, MirrorHandle};
+use crate::service::power::{PowerMonitor, PowerState};
+use crate::util::time;
+use anyhow::{Context, Result};
 use objc2::rc::autoreleasepool;
 use objc2_app_kit::NSPasteboard;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-pub fn run_watch(max_iterations: Option<u64>) -> Result<()> {
+/// Polls between power checks while an image copy is deferred (about 20s).
+const POWER_RECHECK_POLLS: u64 = 50;
+/// How long a power reading is trusted before `pmset` is run again.
+const POWER_CACHE_TTL: Duration = Duration::from_secs(5);
+/// Pasteboard changes allowed within `BULK_WINDOW` before capture pauses.
+/// Far more than anyone copies by hand, far less than an app stuck in a loop.
+const BULK_LIMIT: usize = 60;
+const BULK_WINDOW: Duration = Duration::from_secs(10);
+/// An empty clipboard is only re-checked this soon after real content.
+const EMPTY_RETRY_WINDOW: Duration = Duration::from_secs(2);
+/// Pause between re-reads of an empty clipboard.
+const EMPTY_RETRY_STEP: Duration = Duration::from_millis(50);
+
+/// Whether the watcher stores clipboard changes as they happen, or leaves
+/// capturing to explicit `capture-now` invocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureMode {
+    Auto,
+    Manual,
+}
+
+/// How a change's images are handled when it is read.
+enum ImageCapture {
+    Include,
+    /// Store the copy without its image, for the `skip` battery policy.
+    Exclude,
+    /// Store a copy deferred earlier, stamped with when and where it was
+    /// actually made rather than when power came back.
+    Deferred(DeferredCopy),
+}
+
+/// What storing a change needs from the config and the watcher, set up once
+/// when the watcher starts.
+struct WatchSettings<'a> {
+    data_dir: &'a Path,
+    dedupe_by_text: bool,
+    self_copy_window: time::Duration,
+    capture_ctx: CaptureContext,
+    mirror: Option<&'a MirrorHandle>,
+}
+
+/// An image copy held back while the Mac is on battery.
+#[derive(Debug, Clone)]
+struct DeferredCopy {
+    copied_at: time::OffsetDateTime,
+    source_app: Option<String>,
+}
+
+/// Records the latest change count and reports whether it should be
+/// captured. Manual mode keeps tracking changes but never captures.
+fn observe_change(mode: CaptureMode, change: isize, last_change: &mut isize) -> bool {
+    let changed = change != *last_change;
+    *last_change = change;
+    changed && mode == CaptureMode::Auto
+}
+
+/// Incognito is checked on every change rather than once at startup so
+/// toggling it through the API takes effect without restarting the watcher.
+fn capture_allowed(data_dir: &Path) -> bool {
+    if incognito::is_incognito(data_dir) {
+        eprintln!("Incognito: dropping clipboard change");
+        return false;
+    }
+    true
+}
+
+/// Whether this poll stores the clipboard: a new change, or the re-read after
+/// a burst ends, as long as capture isn't throttled or incognito.
+fn should_capture(changed: bool, resumed: bool, throttled: bool, data_dir: &Path) -> bool {
+    (changed || resumed) && !throttled && capture_allowed(data_dir)
+}
+
+/// The policy that applies to this change. The pasteboard is only checked
+/// for an image under a non-default policy, and power only for image copies,
+/// so the default setup never pays for either and plain copies never run
+/// `pmset`.
+fn effective_image_policy(
+    policy: BatteryImagePolicy,
+    has_image: impl FnOnce() -> bool,
+    power: impl FnOnce() -> PowerState,
+) -> BatteryImagePolicy {
+    if policy == BatteryImagePolicy::Capture || !has_image() || !power().constrained() {
+        return BatteryImagePolicy::Capture;
+    }
+    policy
+}
+
+/// Lists every format on the pasteboard, for working out why something was
+/// or wasn't captured.
+pub fn inspect_formats() -> Result<PasteboardReport> {
+    crate::clipboard::mac::assert_macos()?;
+    let capture_ctx = CaptureContext::from_config(&load_config()?);
+    autoreleasepool(|_| {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        ClipboardSnapshot::inspect_pasteboard(&pasteboard, &capture_ctx)
+    })
+}
+
+/// Number of pasteboard changes that happened between two polls without being
+/// seen, e.g. several quick copies inside one poll interval.
+fn missed_changes(last_change: isize, change: isize) -> isize {
+    if last_change == 0 {
+        return 0;
+    }
+    (change - last_change - 1).max(0)
+}
+
+/// Counts pasteboard changes over a sliding window so a buggy app or script
+/// copying in a loop can't fill the disk. Capture stays paused while the
+/// window holds more than `limit` changes and resumes once it drains.
+struct BulkGuard {
+    limit: usize,
+    window: Duration,
+    changes: VecDeque<(Instant, usize)>,
+    throttled: bool,
+    /// Set when a change went uncaptured while paused, so whatever the
+    /// clipboard holds once the burst drains is still stored.
+    skipped: bool,
+}
+
+impl BulkGuard {
+    fn new(limit: usize, window: Duration) -> Self {
+        BulkGuard {
+            limit,
+            window,
+            changes: VecDeque::new(),
+            throttled: false,
+            skipped: false,
+        }
+    }
+
+    /// Notes a change that wasn't captured because capture was paused.
+    fn skip(&mut self) {
+        self.skipped = true;
+    }
+
+    /// Reports, once, that capture is running again after skipping changes.
+    fn take_resumed(&mut self) -> bool {
+        let resumed = self.skipped && !self.throttled;
+        if resumed {
+            self.skipped = false;
+        }
+        resumed
+    }
+
+    /// Records `changes` pasteboard updates seen at `now` and reports whether
+    /// capture should be paused.
+    fn record(&mut self, now: Instant, changes: usize) -> bool {
+        if changes > 0 {
+            self.changes.push_back((now, changes));
+        }
+        while let Some(&(at, _)) = self.changes.front() {
+            if now.duration_since(at) < self.window {
+                break;
+            }
+            self.changes.pop_front();
+        }
+        let total: usize = self.changes.iter().map(|(_, count)| count).sum();
+        self.throttled = total > self.limit;
+        self.throttled
+    }
+}
+
+/// Some apps clear the clipboard and set the new contents a moment later. When
+/// a change leaves it empty shortly after real content, the clipboard is read
+/// again for up to `budget` so the real copy isn't missed.
+struct EmptyRetry {
+    budget: Duration,
+    window: Duration,
+    last_content: Option<Instant>,
+}
+
+impl EmptyRetry {
+    fn new(budget: Duration, window: Duration) -> Self {
+        EmptyRetry {
+            budget,
+            window,
+            last_content: None,
+        }
+    }
+
+    /// Runs `read`, repeating it while it comes back empty if the last
+    /// non-empty read was within the window. Returns the snapshot and
+    /// whether it only turned up on a re-read.
+    fn read<T>(
+        &mut self,
+        mut read: impl FnMut() -> Result<Option<T>>,
+        mut sleep: impl FnMut(Duration),
+    ) -> Result<(Option<T>, bool)> {
+        let mut snapshot = read()?;
+        let recent = self
+            .last_content
+            .is_some_and(|at| at.elapsed() <= self.window);
+        let mut waited = Duration::ZERO;
+        let mut retried = false;
+        while snapshot.is_none() && recent && waited < self.budget {
+            sleep(EMPTY_RETRY_STEP);
+            waited += EMPTY_RETRY_STEP;
+            retried = true;
+            snapshot = read()?;
+        }
+        let found = snapshot.is_some();
+        if found {
+            self.last_content = Some(Instant::now());
+        }
+        Ok((snapshot, retried && found))
+    }
+}
+
+/// Pasteboard updates since the last poll, including ones that were missed.
+fn changes_since(last_change: isize, change: isize) -> usize {
+    if last_change == 0 {
+        return 0;
+    }
+    (change - last_change).max(0) as usize
+}
+
+fn configured_mirror(mirror_url: Option<&str>, data_dir: &Path) -> Option<Mirror<HttpTransport>> {
+    mirror_url.map(|url| Mirror::new(data_dir, HttpTransport::new(url, data_dir)))
+}
+
+/// Stores whatever is on the clipboard right now, bypassing the watcher.
+/// This is an explicit request, so it still stores while incognito is on.
+pub fn capture_now() -> Result<EntryMetadata> {
+    crate::clipboard::mac::assert_macos()?;
     let config = load_config()?;
-    ensure_data_dir(&config)?;
+    let capture_ctx = CaptureContext::from_config(&config);
+    let snapshot = autoreleasepool(|_| {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        ClipboardSnapshot::from_pasteboard(&pasteboard, &capture_ctx)
+    })?
+    .context("Clipboard has no supported content")?;
+    let metadata = store_snapshot(snapshot)?;
+    let data_dir = ensure_data_dir(&config)?;
+    if let Some(mirror) = configured_mirror(config.mirror_url(), &data_dir) {
+        mirror.forward(&metadata.hash);
+    }
+    Ok(metadata)
+}
+
+/// The clipboard as it was before a temporary copy, such as the marker
+/// `paste-test` puts there.
+pub struct SavedClipboard {
+    data_dir: PathBuf,
+    types: Vec<(String, Vec<u8>)>,
+    /// What the watcher reads the saved contents as.
+    hash: Option<String>,
+}
+
+impl SavedClipboard {
+    pub fn save() -> Result<Self> {
+        crate::clipboard::mac::assert_macos()?;
+        let config = load_config()?;
+        let capture_ctx = CaptureContext::from_config(&config);
+        let snapshot = autoreleasepool(|_| {
+            let pasteboard = NSPasteboard::generalPasteboard();
+            ClipboardSnapshot::from_pasteboard(&pasteboard, &capture_ctx)
+        })?;
+        Ok(SavedClipboard {
+            data_dir: ensure_data_dir(&config)?,
+            types: crate::clipboard::mac::read_pasteboard_types(),
+            hash: snapshot.map(|snapshot| snapshot.storage_hash(config.dedupes_by_text())),
+        })
+    }
+
+    /// Puts the saved contents back, telling the watcher they aren't a new
+    /// copy.
+    pub fn restore(&self) {
+        if let Some(hash) = &self.hash
+            && let Err(err) = guard::record_self_copy(&self.data_dir, hash)
+        {
+            eprintln!("Failed to record self copy: {err:?}");
+        }
+        crate::clipboard::mac::write_pasteboard_types(&self.types);
+    }
+}
+
+/// Reads the clipboard and stores it unless it is Clippy's own copy. Errors
+/// are logged so the watcher keeps running. When the contents only turned up
+/// on a re-read of an empty clipboard, returns the change count they belong
+/// to so the watcher doesn't store them a second time.
+fn store_current_clipboard(
+    settings: &WatchSettings,
+    noise: Option<&mut NoiseFilter>,
+    empty_retry: Option<&mut EmptyRetry>,
+    images: ImageCapture,
+) -> Option<isize> {
+    autoreleasepool(|_| {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        let read = || {
+            if matches!(images, ImageCapture::Exclude) {
+                ClipboardSnapshot::from_pasteboard_without_images(
+                    &pasteboard,
+                    &settings.capture_ctx,
+                )
+            } else {
+                ClipboardSnapshot::from_pasteboard(&pasteboard, &settings.capture_ctx)
+            }
+        };
+        let mut recovered = None;
+        let snapshot = match empty_retry {
+            Some(retry) => retry.read(read, thread::sleep).map(|(snapshot, retried)| {
+                if retried {
+                    eprintln!("Clipboard was briefly empty, capturing what replaced it");
+                    recovered = Some(pasteboard.changeCount());
+                }
+                snapshot
+            }),
+            None => read(),
+        };
+        match snapshot {
+            Ok(Some(mut snapshot)) => {
+                if let ImageCapture::Deferred(deferred) = images {
+                    snapshot.copied_at = Some(deferred.copied_at);
+                    snapshot.source_app = deferred.source_app;
+                }
+                store_read_snapshot(settings, noise, snapshot);
+            }
+            Ok(None) => {
+                eprintln!("Clipboard change had no supported content");
+            }
+            Err(err) => {
+                eprintln!("Failed to read clipboard snapshot: {err:?}");
+            }
+        }
+        recovered
+    })
+}
+
+/// Stores a snapshot read off the clipboard, unless it is Clippy's own copy
+/// or app noise, and forwards it to the mirror. Returns the stored entry.
+fn store_read_snapshot(
+    settings: &WatchSettings,
+    noise: Option<&mut NoiseFilter>,
+    snapshot: ClipboardSnapshot,
+) -> Option<EntryMetadata> {
+    // Log all detected formats with previews
+    snapshot.log_format_details();
+
+    if guard::take_self_copy(
+        settings.data_dir,
+        &snapshot.storage_hash(settings.dedupe_by_text),
+        settings.self_copy_window,
+    ) {
+        eprintln!("Ignoring clipboard change made by Clippy");
+        return None;
+    }
+    if noise.is_some_and(|noise| noise.check(&snapshot, time::now())) {
+        eprintln!("Ignoring clipboard change that looks like app noise");
+        return None;
+    }
+
+    match store_snapshot(snapshot) {
+        Ok(metadata) => {
+            let summary = metadata
+                .summary
+                .clone()
+                .unwrap_or_else(|| "(no summary)".into());
+            eprintln!(
+                "Stored clipboard item: {} [{} copies]",
+                summary, metadata.copy_count
+            );
+            if let Some(mirror) = settings.mirror {
+                mirror.forward(&metadata.hash);
+            }
+            Some(metadata)
+        }
+        Err(err) => {
+            eprintln!("Failed to persist clipboard item: {err:?}");
+            None
+        }
+    }
+}
+
+pub fn run_watch(max_iterations: Option<u64>, mode: CaptureMode) -> Result<()> {
+    let config = load_config()?;
+    let data_dir = ensure_data_dir(&config)?;
+    let mirror = configured_mirror(config.mirror_url(), &data_dir).map(Mirror::spawn);
     crate::clipboard::mac::assert_macos()?;
+    crate::clipboard::plugins::start_safe_mode(&data_dir);
     let mut last_change: isize = 0;
     let mut iterations = 0;
+    let mut polls: u64 = 0;
+    let battery_images = config.battery_images();
+    let flush_delay = config.idle_flush_delay();
+    // Set while an image copy waits for the Mac to be plugged in.
+    let mut deferred: Option<DeferredCopy> = None;
+    let mut power = PowerMonitor::new(POWER_CACHE_TTL);
+    let mut bulk = BulkGuard::new(BULK_LIMIT, BULK_WINDOW);
+    let mut noise = config
+        .noise_filter
+        .as_ref()
+        .map(|config| NoiseFilter::new(NoiseSettings::from(config)));
+    let mut empty_retry = config
+        .empty_retry_budget()
+        .map(|budget| EmptyRetry::new(budget, EMPTY_RETRY_WINDOW));
+    let settings = WatchSettings {
+        data_dir: &data_dir,
+        dedupe_by_text: config.dedupes_by_text(),
+        self_copy_window: config.self_copy_window(),
+        capture_ctx: CaptureContext::from_config(&config),
+        mirror: mirror.as_ref(),
+    };
+    let mut capture = |images: ImageCapture| {
+        store_current_clipboard(&settings, noise.as_mut(), empty_retry.as_mut(), images)
+    };
 
-    eprintln!("Starting clipboard watch...");
+    match mode {
+        CaptureMode::Auto => eprintln!("Starting clipboard watch..."),
+        CaptureMode::Manual => {
+            eprintln!("Starting clipboard watch in manual mode (run `capture-now` to store)...");
+        }
+    }
 
     loop {
-        let (current_change, should_capture) = autoreleasepool(|_| {
+        let current_change = autoreleasepool(|_| {
             let pasteboard = NSPasteboard::generalPasteboard();
-            let change = pasteboard.changeCount();
-            (change, change != last_change)
+            pasteboard.changeCount()
         });
 
-        if should_capture {
-            last_change = current_change;
-            autoreleasepool(|_| {
-                let pasteboard = NSPasteboard::generalPasteboard();
-                match ClipboardSnapshot::from_pasteboard(&pasteboard) {
-                    Ok(Some(snapshot)) => {
-                        // Log all detected formats with previews
-                        snapshot.log_format_details();
-
-                        match store_snapshot(snapshot) {
-                            Ok(metadata) => {
-                                let summary = metadata
-                                    .summary
-                                    .clone()
-                                    .unwrap_or_else(|| "(no summary)".into());
-                                eprintln!(
-                                    "Stored clipboard item: {} [{} copies]",
-                                    summary, metadata.copy_count
-                                );
-                            }
-                            Err(err) => {
-                                eprintln!("Failed to persist clipboard item: {err:?}");
-                            }
-                        }
-                    }
-                    Ok(None) => {
-                        eprintln!("Clipboard change had no supported content");
-                    }
-                    Err(err) => {
-                        eprintln!("Failed to read clipboard snapshot: {err:?}");
-                    }
+        let missed = missed_changes(last_change, current_change);
+        if missed > 0 {
+            eprintln!("Missed {missed} intermediate clipboard change(s)");
+        }
+        let was_throttled = bulk.throttled;
+        let throttled = bulk.record(Instant::now(), changes_since(last_change, current_change));
+        if throttled && !was_throttled {
+            eprintln!(
+                "Warning: more than {BULK_LIMIT} clipboard changes in {}s, pausing capture",
+                BULK_WINDOW.as_secs()
+            );
+        } else if was_throttled && !throttled {
+            eprintln!("Clipboard activity back to normal, resuming capture");
+        }
+        let changed = observe_change(mode, current_change, &mut last_change);
+        if changed {
+            // A newer copy replaces whatever was waiting.
+            deferred = None;
+            if throttled {
+                bulk.skip();
+            }
+        }
+        // The clipboard may still hold a copy made during the burst.
+        let resumed = bulk.take_resumed();
+        let mut recovered = None;
+        if should_capture(changed, resumed, throttled, &data_dir) {
+            let current_power = || power.current();
+            let policy = effective_image_policy(battery_images, clipboard_has_image, current_power);
+            recovered = match policy {
+                BatteryImagePolicy::Capture => capture(ImageCapture::Include),
+                BatteryImagePolicy::Skip => {
+                    eprintln!("On battery power: storing copy without its image");
+                    capture(ImageCapture::Exclude)
                 }
-            });
+                BatteryImagePolicy::Defer => {
+                    eprintln!("On battery power: deferring image copy until plugged in");
+                    deferred = Some(DeferredCopy {
+                        copied_at: time::now(),
+                        source_app: crate::clipboard::mac::frontmost_app(),
+                    });
+                    None
+                }
+            };
+        } else if deferred.is_some()
+            && polls.is_multiple_of(POWER_RECHECK_POLLS)
+            && !power.current().constrained()
+            && capture_allowed(&data_dir)
+            && let Some(copy) = deferred.take()
+        {
+            eprintln!("Power restored: capturing deferred image copy");
+            recovered = capture(ImageCapture::Deferred(copy));
+        }
+        // A re-read of an empty clipboard already stored the copy that
+        // replaced it.
+        if let Some(change) = recovered {
+            last_change = change;
+        }
+
+        polls += 1;
+        if let Some(delay) = flush_delay
+            && let Err(err) = flush::flush_if_idle(&data_dir, delay)
+        {
+            eprintln!("Failed to flush clipboard history to disk: {err:#}");
         }
 
         thread::sleep(Duration::from_millis(400));
@@ -68,3 +515,256 @@ pub fn run_watch(max_iterations: Option<u64>) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_mode_captures_each_change_once() {
+        let mut last_change = 0;
+        assert!(observe_change(CaptureMode::Auto, 1, &mut last_change));
+        assert!(!observe_change(CaptureMode::Auto, 1, &mut last_change));
+        assert!(observe_change(CaptureMode::Auto, 2, &mut last_change));
+    }
+
+    #[test]
+    fn test_manual_mode_never_auto_stores() {
+        let mut last_change = 0;
+        for change in [1, 2, 2, 5, 9] {
+            assert!(!observe_change(CaptureMode::Manual, change, &mut last_change));
+            assert_eq!(last_change, change);
+        }
+    }
+
+    #[test]
+    fn test_incognito_drops_changes_until_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut last_change = 0;
+        incognito::set_incognito(dir.path(), true).unwrap();
+        for change in [1, 2, 3] {
+            let changed = observe_change(CaptureMode::Auto, change, &mut last_change);
+            assert!(changed);
+            assert!(!should_capture(changed, false, false, dir.path()));
+            assert_eq!(last_change, change);
+        }
+        // The re-read after a burst is dropped too.
+        assert!(!should_capture(false, true, false, dir.path()));
+
+        // Turning it off doesn't resurrect the dropped changes, only new ones
+        // are captured.
+        incognito::set_incognito(dir.path(), false).unwrap();
+        let changed = observe_change(CaptureMode::Auto, 3, &mut last_change);
+        assert!(!should_capture(changed, false, false, dir.path()));
+        let changed = observe_change(CaptureMode::Auto, 4, &mut last_change);
+        assert!(should_capture(changed, false, false, dir.path()));
+        assert!(!should_capture(changed, false, true, dir.path()));
+    }
+
+    #[test]
+    fn test_image_policy_applies_only_on_constrained_power() {
+        let battery = PowerState {
+            on_battery: true,
+            low_power: false,
+        };
+        let low_power = PowerState {
+            on_battery: false,
+            low_power: true,
+        };
+        for policy in [BatteryImagePolicy::Skip, BatteryImagePolicy::Defer] {
+            assert_eq!(effective_image_policy(policy, || true, || battery), policy);
+            assert_eq!(
+                effective_image_policy(policy, || true, || low_power),
+                policy
+            );
+            assert_eq!(
+                effective_image_policy(policy, || true, PowerState::default),
+                BatteryImagePolicy::Capture
+            );
+        }
+        assert_eq!(
+            effective_image_policy(BatteryImagePolicy::Capture, || true, || battery),
+            BatteryImagePolicy::Capture
+        );
+    }
+
+    #[test]
+    fn test_image_policy_skips_power_check_when_irrelevant() {
+        let unreachable = || -> PowerState { panic!("power state should not be read") };
+        assert_eq!(
+            effective_image_policy(BatteryImagePolicy::Skip, || false, unreachable),
+            BatteryImagePolicy::Capture
+        );
+        // The default policy doesn't even look at the pasteboard.
+        let no_pasteboard = || -> bool { panic!("pasteboard should not be read") };
+        assert_eq!(
+            effective_image_policy(BatteryImagePolicy::Capture, no_pasteboard, unreachable),
+            BatteryImagePolicy::Capture
+        );
+    }
+
+    #[test]
+    fn test_missed_changes_counts_skipped_updates() {
+        assert_eq!(missed_changes(0, 57), 0);
+        assert_eq!(missed_changes(5, 5), 0);
+        assert_eq!(missed_changes(5, 6), 0);
+        assert_eq!(missed_changes(5, 9), 3);
+        assert_eq!(missed_changes(9, 2), 0);
+    }
+
+    /// A reader handing out `reads` in order, then empty clipboards.
+    fn reader(reads: Vec<Option<&'static str>>) -> impl FnMut() -> Result<Option<&'static str>> {
+        let mut reads = VecDeque::from(reads);
+        move || Ok(reads.pop_front().flatten())
+    }
+
+    #[test]
+    fn test_empty_read_after_content_is_retried() {
+        let mut retry = EmptyRetry::new(Duration::from_millis(250), EMPTY_RETRY_WINDOW);
+        let mut sleeps = 0;
+        let (first, recovered) = retry
+            .read(reader(vec![Some("old")]), |_| sleeps += 1)
+            .unwrap();
+        assert_eq!((first, recovered), (Some("old"), false));
+        assert_eq!(sleeps, 0);
+
+        // Cleared, then set a moment later: the second re-read finds it.
+        let (snapshot, recovered) = retry
+            .read(reader(vec![None, None, Some("new")]), |_| sleeps += 1)
+            .unwrap();
+        assert_eq!((snapshot, recovered), (Some("new"), true));
+        assert_eq!(sleeps, 2);
+    }
+
+    #[test]
+    fn test_empty_read_retries_are_bounded() {
+        let mut retry = EmptyRetry::new(Duration::from_millis(200), EMPTY_RETRY_WINDOW);
+        let mut sleeps = 0;
+        retry.read(reader(vec![Some("old")]), |_| ()).unwrap();
+        let (snapshot, recovered) = retry.read(reader(vec![]), |_| sleeps += 1).unwrap();
+        assert_eq!((snapshot, recovered), (None, false));
+        assert_eq!(sleeps, 4);
+
+        // Nothing copied recently, so an empty clipboard is taken as is.
+        let mut fresh = EmptyRetry::new(Duration::from_millis(200), EMPTY_RETRY_WINDOW);
+        let (snapshot, _) = fresh
+            .read(reader(vec![None, Some("late")]), |_| {
+                panic!("should not retry")
+            })
+            .unwrap();
+        assert_eq!(snapshot, None);
+
+        let mut stale = EmptyRetry::new(Duration::from_millis(200), Duration::ZERO);
+        stale.read(reader(vec![Some("old")]), |_| ()).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        let (snapshot, _) = stale
+            .read(reader(vec![None, Some("late")]), |_| {
+                panic!("should not retry")
+            })
+            .unwrap();
+        assert_eq!(snapshot, None);
+    }
+
+    #[test]
+    fn test_bulk_guard_throttles_above_limit() {
+        let start = Instant::now();
+        let mut guard = BulkGuard::new(5, Duration::from_secs(10));
+        for secs in 0..5 {
+            assert!(!guard.record(start + Duration::from_secs(secs), 1));
+        }
+        assert!(guard.record(start + Duration::from_secs(5), 1));
+
+        // Hundreds of changes inside one poll trip it straight away.
+        let mut burst = BulkGuard::new(5, Duration::from_secs(10));
+        assert_eq!(changes_since(100, 400), 300);
+        assert!(burst.record(start, changes_since(100, 400)));
+    }
+
+    #[test]
+    fn test_bulk_guard_resumes_once_after_skipped_changes() {
+        let start = Instant::now();
+        let mut guard = BulkGuard::new(5, Duration::from_secs(10));
+        assert!(guard.record(start, 20));
+        guard.skip();
+        assert!(!guard.take_resumed());
+
+        assert!(!guard.record(start + Duration::from_secs(10), 0));
+        assert!(guard.take_resumed());
+        assert!(!guard.take_resumed());
+
+        // Nothing skipped, nothing to catch up on.
+        let mut quiet = BulkGuard::new(5, Duration::from_secs(10));
+        assert!(quiet.record(start, 20));
+        assert!(!quiet.record(start + Duration::from_secs(10), 0));
+        assert!(!quiet.take_resumed());
+    }
+
+    #[test]
+    fn test_bulk_guard_resets_once_rate_drops() {
+        let start = Instant::now();
+        let mut guard = BulkGuard::new(5, Duration::from_secs(10));
+        assert!(guard.record(start, 20));
+        // Quiet polls keep it paused until the burst leaves the window.
+        assert!(guard.record(start + Duration::from_secs(9), 0));
+        assert!(!guard.record(start + Duration::from_secs(10), 0));
+        assert!(!guard.throttled);
+
+        // A steady pace below the limit never trips it.
+        for secs in (11..60).step_by(3) {
+            assert!(!guard.record(start + Duration::from_secs(secs), 1));
+        }
+        assert_eq!(changes_since(0, 57), 0);
+    }
+
+    #[test]
+    fn test_clippy_copy_is_skipped_and_keeps_the_cursor() {
+        use crate::data::cursor::{self, Direction};
+        use crate::data::store::{
+            SelectionFilter, copy_plain_by_selector, load_index, resolve_relative, scratch_store,
+            store_json_item, test_clipboard,
+        };
+        use clipboard_rs::common::ClipboardContent;
+
+        let _scratch = scratch_store(|_| {});
+        let stored: Vec<EntryMetadata> = ["01", "02", "03"]
+            .iter()
+            .map(|month| {
+                let item = serde_json::from_value(serde_json::json!({
+                    "id": format!("item-{month}"),
+                    "date": format!("2024-{month}-01T00:00:00Z"),
+                    "formats": [{ "pluginId": "text", "data": format!("note from {month}") }],
+                }))
+                .unwrap();
+                store_json_item(&item).unwrap()
+            })
+            .collect();
+        let config = load_config().unwrap();
+        let data_dir = ensure_data_dir(&config).unwrap();
+        let settings = WatchSettings {
+            data_dir: &data_dir,
+            dedupe_by_text: config.dedupes_by_text(),
+            self_copy_window: config.self_copy_window(),
+            capture_ctx: CaptureContext::from_config(&config),
+            mirror: None,
+        };
+
+        copy_plain_by_selector(&stored[1].hash).unwrap();
+        let copied = ClipboardSnapshot::from_contents(&test_clipboard::take(), true);
+        assert!(store_read_snapshot(&settings, None, copied).is_none());
+
+        // Nothing was stored, so `--relative` still steps from the copied entry.
+        let index = load_index().unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            cursor::load_last_copied(&data_dir).as_deref(),
+            Some(stored[1].hash.as_str())
+        );
+        let prev = resolve_relative(&index, Direction::Prev, &SelectionFilter::default());
+        assert_eq!(prev.unwrap(), stored[0].hash);
+
+        // The guard is used up: copying the same text by hand is stored.
+        let by_hand = [ClipboardContent::Text("note from 02".into())];
+        let snapshot = ClipboardSnapshot::from_contents(&by_hand, true);
+        assert!(store_read_snapshot(&settings, None, snapshot).is_some());
+    }
+}
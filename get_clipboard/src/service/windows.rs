@@ -7,7 +7,7 @@ use std::process::Command;
 
 const TASK_NAME: &str = "ClippyGetClipboard";
 
-pub fn install_agent() -> Result<()> {
+pub fn install_agent(autostart: bool) -> Result<()> {
     let exe = std::env::current_exe()?;
     let paths = resolve_paths();
     fs::create_dir_all(&paths.config_dir)?;
@@ -19,14 +19,17 @@ pub fn install_agent() -> Result<()> {
         log_path.to_string_lossy()
     );
     let status = Command::new("schtasks")
-        .args([
-            "/Create", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", TASK_NAME, "/F", "/TR", &command,
-        ])
+        .arg("/Create")
+        .args(schedule_args(autostart))
+        .args(["/RL", "LIMITED", "/TN", TASK_NAME, "/F", "/TR", &command])
         .status()?;
     if !status.success() {
         return Err(anyhow!("schtasks exited with status {}", status));
     }
     println!("Installed scheduled task {}", TASK_NAME);
+    if !autostart {
+        println!("Autostart disabled; run `get_clipboard service start` to launch it");
+    }
     println!("Service logs: {}", log_path.display());
     Ok(())
 }
@@ -132,6 +135,16 @@ pub fn print_logs(lines: usize, follow: bool) -> Result<()> {
     }
 }
 
+/// A task needs a trigger, so manual installs get a one-off trigger that has
+/// already passed and only run through `/Run`.
+fn schedule_args(autostart: bool) -> &'static [&'static str] {
+    if autostart {
+        &["/SC", "ONLOGON"]
+    } else {
+        &["/SC", "ONCE", "/ST", "00:00"]
+    }
+}
+
 fn log_file_path() -> Result<PathBuf> {
     let paths = resolve_paths();
     Ok(paths.config_dir.join("service.log"))
@@ -3,8 +3,10 @@ use crate::data::store::{
     HistoryItem, copy_by_selector, delete_entry, load_history_items, load_index, load_item_preview,
     preview_snippet, refresh_index, stream_history_items,
 };
+use crate::config::load_config;
 use crate::search::SearchOptions;
 use crate::tui::state::{AppState, PreviewState};
+use crate::tui::theme::resolve_theme;
 use crate::tui::view::draw_frame;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
@@ -17,10 +19,13 @@ use std::time::Duration;
 const PAGE_SIZE: usize = 100;
 const SEARCH_DEBOUNCE_MS: u64 = 160;
 
-pub fn start(query: Option<String>) -> Result<()> {
+pub fn start(query: Option<String>, theme: Option<String>) -> Result<()> {
+    let config = load_config()?;
+    let theme = resolve_theme(theme.as_deref(), config.tui.as_ref())?;
     let mut stdout = stdout();
     let mut terminal = setup_terminal(&mut stdout)?;
     let mut state = AppState::new(Vec::new());
+    state.theme = theme;
     if let Some(q) = query {
         state.filter = q.clone();
         state.sticky_query = Some(q);
@@ -1,5 +1,6 @@
 pub mod app;
 pub mod state;
+pub mod theme;
 pub mod view;
 
 pub use app::start;
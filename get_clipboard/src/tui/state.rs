@@ -1,4 +1,5 @@
 use crate::data::store::{HistoryItem, ItemPreview};
+use crate::tui::theme::Theme;
 use crossterm::event::KeyCode;
 use std::time::Instant;
 
@@ -14,6 +15,7 @@ pub struct AppState {
     pub loading: bool,
     pub pending_reload: bool,
     pub last_filter_change: Option<Instant>,
+    pub theme: Theme,
 }
 
 pub struct PreviewState {
@@ -35,6 +37,7 @@ impl AppState {
             loading: false,
             pending_reload: false,
             last_filter_change: None,
+            theme: Theme::default(),
         }
     }
 
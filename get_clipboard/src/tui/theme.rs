@@ -0,0 +1,168 @@
+use crate::config::model::TuiConfig;
+use anyhow::{Result, anyhow, bail};
+use ratatui::style::Color;
+use ratatui::widgets::BorderType;
+use std::str::FromStr;
+
+pub const BUILTIN_THEMES: &[&str] = &["dark", "light"];
+const DEFAULT_THEME: &str = "dark";
+
+/// Colors used across the TUI. Built-ins cover dark and light terminals;
+/// the `tui` config section can override individual colors on top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub text: Color,
+    pub muted: Color,
+    pub faint: Color,
+    pub accent: Color,
+    pub selection: Color,
+    pub highlight: Color,
+    pub preview_bg: Color,
+    pub border: BorderType,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            text: Color::White,
+            muted: Color::Gray,
+            faint: Color::DarkGray,
+            accent: Color::Blue,
+            selection: Color::DarkGray,
+            highlight: Color::Yellow,
+            preview_bg: Color::Rgb(24, 24, 24),
+            border: BorderType::Plain,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            text: Color::Black,
+            muted: Color::DarkGray,
+            faint: Color::Gray,
+            accent: Color::Blue,
+            selection: Color::Rgb(210, 220, 235),
+            highlight: Color::Magenta,
+            preview_bg: Color::Rgb(245, 245, 245),
+            border: BorderType::Plain,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            _ => None,
+        }
+    }
+}
+
+/// Picks the `--theme` flag, then the configured theme, then dark, and
+/// applies the color overrides from the `tui` config section.
+pub fn resolve_theme(flag: Option<&str>, config: Option<&TuiConfig>) -> Result<Theme> {
+    let name = flag
+        .or_else(|| config.and_then(|config| config.theme.as_deref()))
+        .unwrap_or(DEFAULT_THEME);
+    let mut theme = Theme::named(name).ok_or_else(|| {
+        anyhow!(
+            "Unknown theme '{name}', expected one of: {}",
+            BUILTIN_THEMES.join(", ")
+        )
+    })?;
+    let Some(config) = config else {
+        return Ok(theme);
+    };
+    if let Some(color) = config.selection_color.as_deref() {
+        theme.selection = parse_color("selection_color", color)?;
+    }
+    if let Some(color) = config.highlight_color.as_deref() {
+        theme.highlight = parse_color("highlight_color", color)?;
+    }
+    if let Some(style) = config.border_style.as_deref() {
+        theme.border = parse_border(style)?;
+    }
+    Ok(theme)
+}
+
+fn parse_color(key: &str, value: &str) -> Result<Color> {
+    Color::from_str(value.trim())
+        .map_err(|_| anyhow!("Invalid tui.{key} '{value}', expected a color name or #rrggbb"))
+}
+
+fn parse_border(value: &str) -> Result<BorderType> {
+    Ok(match value.trim().to_ascii_lowercase().as_str() {
+        "plain" => BorderType::Plain,
+        "rounded" => BorderType::Rounded,
+        "double" => BorderType::Double,
+        "thick" => BorderType::Thick,
+        other => {
+            bail!("Invalid tui.border_style '{other}', expected plain, rounded, double or thick")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(theme: Option<&str>) -> TuiConfig {
+        TuiConfig {
+            theme: theme.map(str::to_string),
+            ..TuiConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_defaults_to_dark() {
+        assert_eq!(resolve_theme(None, None).unwrap(), Theme::dark());
+    }
+
+    #[test]
+    fn test_flag_beats_config_theme() {
+        let config = config(Some("dark"));
+        assert_eq!(
+            resolve_theme(Some("Light"), Some(&config)).unwrap(),
+            Theme::light()
+        );
+        assert_eq!(resolve_theme(None, Some(&config)).unwrap(), Theme::dark());
+    }
+
+    #[test]
+    fn test_config_overrides_apply_on_top_of_theme() {
+        let config = TuiConfig {
+            theme: Some("light".into()),
+            selection_color: Some("#102030".into()),
+            highlight_color: Some("cyan".into()),
+            border_style: Some("rounded".into()),
+        };
+        let theme = resolve_theme(None, Some(&config)).unwrap();
+        assert_eq!(theme.selection, Color::Rgb(16, 32, 48));
+        assert_eq!(theme.highlight, Color::Cyan);
+        assert_eq!(theme.border, BorderType::Rounded);
+        assert_eq!(theme.text, Theme::light().text);
+    }
+
+    #[test]
+    fn test_unknown_names_are_rejected() {
+        let err = resolve_theme(Some("solarized"), None).unwrap_err();
+        assert!(err.to_string().contains("dark, light"));
+
+        let bad_color = TuiConfig {
+            highlight_color: Some("not-a-color".into()),
+            ..TuiConfig::default()
+        };
+        assert!(resolve_theme(None, Some(&bad_color)).is_err());
+
+        let bad_border = TuiConfig {
+            border_style: Some("dotted".into()),
+            ..TuiConfig::default()
+        };
+        assert!(resolve_theme(None, Some(&bad_border)).is_err());
+    }
+}
@@ -2,11 +2,12 @@ use crate::data::store::{
     human_size, narrowest_folder, preview_snippet, resolved_file_paths, saved_format_labels,
 };
 use crate::tui::state::AppState;
+use crate::tui::theme::Theme;
 use crate::util::time::format_human;
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::prelude::Rect;
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 
@@ -20,6 +21,7 @@ pub fn draw_frame(frame: &mut Frame<'_>, state: &AppState) {
             Constraint::Length(1),
         ])
         .split(frame.size());
+    let theme = &state.theme;
 
     let mut title = format!(
         "get_clipboard v{} — {} items",
@@ -30,8 +32,12 @@ pub fn draw_frame(frame: &mut Frame<'_>, state: &AppState) {
         title.push_str(" • searching…");
     }
     let header = Paragraph::new(title)
-        .block(Block::default().borders(Borders::BOTTOM))
-        .style(Style::default().fg(Color::White));
+        .block(
+            Block::default()
+                .borders(Borders::BOTTOM)
+                .border_type(theme.border),
+        )
+        .style(Style::default().fg(theme.text));
     frame.render_widget(header, layout[0]);
 
     let query_display = if state.filter.is_empty() {
@@ -43,10 +49,11 @@ pub fn draw_frame(frame: &mut Frame<'_>, state: &AppState) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::Gray))
+                .border_type(theme.border)
+                .style(Style::default().fg(theme.muted))
                 .title("Search"),
         )
-        .style(Style::default().fg(Color::White));
+        .style(Style::default().fg(theme.text));
     frame.render_widget(query, layout[1]);
 
     let show_preview = frame.size().width > 100;
@@ -70,8 +77,12 @@ pub fn draw_frame(frame: &mut Frame<'_>, state: &AppState) {
 
     let status_text = state.status.clone().unwrap_or_else(default_status);
     let footer = Paragraph::new(status_text)
-        .block(Block::default().borders(Borders::TOP))
-        .style(Style::default().fg(Color::Gray));
+        .block(
+            Block::default()
+                .borders(Borders::TOP)
+                .border_type(theme.border),
+        )
+        .style(Style::default().fg(theme.muted));
     frame.render_widget(footer, layout[3]);
 }
 
@@ -82,6 +93,11 @@ fn list_state(selected: usize) -> ListState {
 }
 
 fn render_list(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
+    let theme = &state.theme;
+    let summary_style = Style::default().fg(theme.text);
+    let match_style = Style::default()
+        .fg(theme.highlight)
+        .add_modifier(Modifier::BOLD);
     let list_width = area.width as usize;
     let mut items = Vec::new();
     for item in &state.items {
@@ -90,22 +106,31 @@ fn render_list(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
         let base_width = offset_text.len() + type_text.len() + 4;
         let available = list_width.saturating_sub(base_width + 2);
         let summary = truncate_display(&item.summary, available);
-        let spans = vec![
-            Span::styled(offset_text, Style::default().fg(Color::DarkGray)),
-            Span::raw(" "),
-            Span::styled(summary, Style::default().fg(Color::White)),
+        let mut spans = vec![
+            Span::styled(offset_text, Style::default().fg(theme.faint)),
             Span::raw(" "),
-            Span::styled(type_text, Style::default().fg(Color::Blue)),
         ];
+        spans.extend(highlight_matches(
+            &summary,
+            &state.filter,
+            summary_style,
+            match_style,
+        ));
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(type_text, Style::default().fg(theme.accent)));
         items.push(ListItem::new(Line::from(spans)));
     }
 
     let mut list_state = list_state(state.selected);
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(theme.border),
+        )
         .highlight_style(
             Style::default()
-                .bg(Color::DarkGray)
+                .bg(theme.selection)
                 .add_modifier(Modifier::BOLD),
         )
         .highlight_symbol("› ");
@@ -113,7 +138,11 @@ fn render_list(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
 }
 
 fn render_preview(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
-    let block = Block::default().borders(Borders::ALL).title("Preview");
+    let theme = &state.theme;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(theme.border)
+        .title("Preview");
     frame.render_widget(block.clone(), area);
     let inner = block.inner(area);
     frame.render_widget(Clear, inner);
@@ -141,16 +170,16 @@ fn render_preview(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
         .split(inner);
 
     let preview_style = Style::default()
-        .fg(Color::White)
-        .bg(Color::Rgb(24, 24, 24))
+        .fg(theme.text)
+        .bg(theme.preview_bg)
         .add_modifier(Modifier::BOLD);
     let text_widget = Paragraph::new(text_content)
         .wrap(Wrap { trim: false })
         .style(preview_style);
     frame.render_widget(text_widget, preview_layout[0]);
 
-    let info_lines = build_info_lines(&selected.metadata, &preview_state.content);
-    let metadata_style = Style::default().fg(Color::Gray).add_modifier(Modifier::DIM);
+    let info_lines = build_info_lines(&selected.metadata, &preview_state.content, theme);
+    let metadata_style = Style::default().fg(theme.muted).add_modifier(Modifier::DIM);
     let info_widget = Paragraph::new(info_lines).style(metadata_style);
     frame.render_widget(info_widget, preview_layout[1]);
 }
@@ -158,13 +187,14 @@ fn render_preview(frame: &mut Frame<'_>, state: &AppState, area: Rect) {
 fn build_info_lines(
     metadata: &crate::data::model::EntryMetadata,
     preview: &crate::data::store::ItemPreview,
+    theme: &Theme,
 ) -> Vec<Line<'static>> {
     let mut lines = Vec::new();
     if let Some(summary) = file_summary_line(metadata, preview) {
         lines.push(Line::from(summary));
     }
 
-    let label_style = Style::default().fg(Color::White);
+    let label_style = Style::default().fg(theme.text);
     let mut info_pairs = Vec::new();
     info_pairs.push(("Type", format!("{:?}", metadata.kind)));
     info_pairs.push(("Copies", metadata.copy_count.to_string()));
@@ -223,6 +253,40 @@ fn file_summary_line(
     ))
 }
 
+/// Splits `text` into spans, styling case-insensitive matches of each search
+/// term with `highlight`.
+fn highlight_matches(text: &str, query: &str, base: Style, highlight: Style) -> Vec<Span<'static>> {
+    let fold = |ch: char| ch.to_lowercase().next().unwrap_or(ch);
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().copied().map(fold).collect();
+    let mut matched = vec![false; chars.len()];
+    for term in query.split_whitespace() {
+        let term: Vec<char> = term.chars().map(fold).collect();
+        if term.len() > folded.len() {
+            continue;
+        }
+        for start in 0..=folded.len() - term.len() {
+            if folded[start..start + term.len()] == term[..] {
+                matched[start..start + term.len()].fill(true);
+            }
+        }
+    }
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for end in 1..=chars.len() {
+        if end == chars.len() || matched[end] != matched[start] {
+            let style = if matched[start] { highlight } else { base };
+            spans.push(Span::styled(
+                chars[start..end].iter().collect::<String>(),
+                style,
+            ));
+            start = end;
+        }
+    }
+    spans
+}
+
 fn truncate_display(input: &str, max_len: usize) -> String {
     if max_len == 0 {
         return String::new();
@@ -250,3 +314,31 @@ fn truncate_display(input: &str, max_len: usize) -> String {
 fn default_status() -> String {
     String::from("Enter copy • Shift+Enter copy+stay • Alt+Delete delete • Ctrl+C exit")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    #[test]
+    fn test_highlight_matches_marks_each_term() {
+        let base = Style::default();
+        let highlight = Style::default().fg(Color::Yellow);
+        let spans = highlight_matches("Hello World hello", "hello w", base, highlight);
+        let parts: Vec<(&str, bool)> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style == highlight))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("Hello", true),
+                (" ", false),
+                ("W", true),
+                ("orld ", false),
+                ("hello", true),
+            ]
+        );
+        assert_eq!(highlight_matches("", "x", base, highlight).len(), 0);
+    }
+}
@@ -1,13 +1,219 @@
-use anyhow::{anyhow, Result};
+use crate::service::permissions;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::fmt;
 use std::thread;
 use std::time::Duration;
 
+/// Returned instead of sending a keystroke when Clippy has not been granted
+/// accessibility permission, which macOS requires for synthetic key events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionDenied;
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pasting needs accessibility permission. Grant it in System Settings > \
+             Privacy & Security > Accessibility, or run `get_clipboard permissions request`"
+        )
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+fn ensure_accessible(accessible: bool) -> Result<()> {
+    if accessible {
+        Ok(())
+    } else {
+        Err(PermissionDenied.into())
+    }
+}
+
+/// True when `err` is (or wraps) a [`PermissionDenied`].
+pub fn is_permission_denied(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<PermissionDenied>().is_some()
+}
+
+/// Upper bound for repeated pastes so a typo can't flood the focused app.
+pub const MAX_PASTE_COUNT: u32 = 100;
+
+/// Pastes `count` times, sleeping `delay` between consecutive pastes.
+pub fn simulate_paste_repeated(count: u32, delay: Duration) -> Result<()> {
+    ensure_accessible(permissions::check_accessibility())?;
+    repeat_paste(count, delay, simulate_paste, thread::sleep)
+}
+
+fn repeat_paste<P, S>(count: u32, delay: Duration, mut paste: P, mut sleep: S) -> Result<()>
+where
+    P: FnMut() -> Result<()>,
+    S: FnMut(Duration),
+{
+    if count == 0 || count > MAX_PASTE_COUNT {
+        bail!("Paste count must be between 1 and {MAX_PASTE_COUNT}");
+    }
+    for i in 0..count {
+        if i > 0 && !delay.is_zero() {
+            sleep(delay);
+        }
+        paste()?;
+    }
+    Ok(())
+}
+
+const PASTE_TEST_PREFIX: &str = "clippy-paste-test-";
+
+/// Unique text put on the clipboard by `paste-test`, so the result is easy to
+/// spot wherever it lands and can't be confused with real clipboard content.
+pub fn paste_test_marker(nonce: i64) -> String {
+    format!("{PASTE_TEST_PREFIX}{nonce}")
+}
+
+/// Outcome of each `paste-test` step. Later steps are skipped once one fails.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PasteTestReport {
+    pub marker: String,
+    pub accessible: bool,
+    pub marker_copied: bool,
+    pub keystroke_sent: bool,
+    pub error: Option<String>,
+}
+
+impl PasteTestReport {
+    pub fn passed(&self) -> bool {
+        self.accessible && self.marker_copied && self.keystroke_sent
+    }
+}
+
+/// Checks permission, copies `marker`, reads it back and sends the paste
+/// keystroke. Once the marker is copied, `restore` puts the previous
+/// clipboard back; it is told whether the keystroke was sent, so it can let
+/// the paste land first. The clipboard and keystroke are passed in so the
+/// steps can be exercised without touching the real pasteboard.
+pub fn run_paste_test<C, R, P, U>(
+    accessible: bool,
+    marker: &str,
+    copy: C,
+    read: R,
+    paste: P,
+    restore: U,
+) -> PasteTestReport
+where
+    C: FnOnce(&str) -> Result<()>,
+    R: FnOnce() -> Result<Option<String>>,
+    P: FnOnce() -> Result<()>,
+    U: FnOnce(bool),
+{
+    let mut report = PasteTestReport {
+        marker: marker.to_string(),
+        accessible,
+        ..PasteTestReport::default()
+    };
+    if !accessible {
+        report.error = Some(PermissionDenied.to_string());
+        return report;
+    }
+    if let Err(err) = copy(marker) {
+        report.error = Some(format!("{err:#}"));
+        return report;
+    }
+    verify_and_paste(&mut report, read, paste);
+    restore(report.keystroke_sent);
+    report
+}
+
+fn verify_and_paste<R, P>(report: &mut PasteTestReport, read: R, paste: P)
+where
+    R: FnOnce() -> Result<Option<String>>,
+    P: FnOnce() -> Result<()>,
+{
+    match read() {
+        Ok(Some(text)) if text == report.marker => report.marker_copied = true,
+        Ok(_) => {
+            report.error = Some("Clipboard did not hold the marker after copying".to_string());
+            return;
+        }
+        Err(err) => {
+            report.error = Some(format!("{err:#}"));
+            return;
+        }
+    }
+    match paste() {
+        Ok(()) => report.keystroke_sent = true,
+        Err(err) => report.error = Some(format!("{err:#}")),
+    }
+}
+
+/// Time given to the target app to come to the front before pasting.
+const ACTIVATION_DELAY: Duration = Duration::from_millis(300);
+
+/// An app to bring to the front before pasting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteTarget {
+    BundleId(String),
+    AppName(String),
+}
+
+impl PasteTarget {
+    /// Reverse-DNS identifiers such as `com.apple.TextEdit` are treated as
+    /// bundle ids; anything else is looked up by application name.
+    pub fn parse(input: &str) -> Result<Self> {
+        let target = input.trim();
+        if target.is_empty() {
+            bail!("Target app must not be empty");
+        }
+        let looks_like_bundle_id = !target.contains(char::is_whitespace)
+            && target.split('.').count() >= 2
+            && target.split('.').all(|part| !part.is_empty());
+        if looks_like_bundle_id {
+            Ok(Self::BundleId(target.to_string()))
+        } else {
+            Ok(Self::AppName(target.to_string()))
+        }
+    }
+
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn open_args(&self) -> [&str; 2] {
+        match self {
+            Self::BundleId(id) => ["-b", id],
+            Self::AppName(name) => ["-a", name],
+        }
+    }
+}
+
+/// Activates `target` and then pastes into it.
+pub fn paste_into(target: &PasteTarget, count: u32, delay: Duration) -> Result<()> {
+    ensure_accessible(permissions::check_accessibility())?;
+    activate_app(target)?;
+    thread::sleep(ACTIVATION_DELAY);
+    simulate_paste_repeated(count, delay)
+}
+
+#[cfg(target_os = "macos")]
+fn activate_app(target: &PasteTarget) -> Result<()> {
+    let status = std::process::Command::new("open")
+        .args(target.open_args())
+        .status()
+        .context("Failed to run open")?;
+    if !status.success() {
+        bail!("Failed to activate {target:?}");
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn activate_app(target: &PasteTarget) -> Result<()> {
+    bail!("Activating {target:?} is only supported on macOS")
+}
+
 #[cfg(target_os = "macos")]
 pub fn simulate_paste() -> Result<()> {
     use objc2_core_graphics::{
         CGEvent, CGEventFlags, CGEventSource, CGEventSourceStateID, CGEventTapLocation,
     };
 
+    ensure_accessible(permissions::check_accessibility())?;
+
     // Create an event source for keyboard events using HID system state
     let event_source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
         .ok_or_else(|| anyhow!("Failed to create CGEventSource"))?;
@@ -56,3 +262,180 @@ pub fn simulate_paste() -> Result<()> {
     println!("Paste simulation not implemented for this platform");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_repeat_paste_schedules_delays_between_pastes() {
+        let events = RefCell::new(Vec::new());
+        repeat_paste(
+            3,
+            Duration::from_millis(200),
+            || {
+                events.borrow_mut().push("paste".to_string());
+                Ok(())
+            },
+            |delay| events.borrow_mut().push(format!("sleep {}", delay.as_millis())),
+        )
+        .unwrap();
+        assert_eq!(
+            events.into_inner(),
+            vec!["paste", "sleep 200", "paste", "sleep 200", "paste"]
+        );
+    }
+
+    #[test]
+    fn test_repeat_paste_rejects_bad_counts() {
+        let mut pastes = 0;
+        let mut paste = || {
+            pastes += 1;
+            Ok(())
+        };
+        assert!(repeat_paste(0, Duration::ZERO, &mut paste, |_| {}).is_err());
+        assert!(repeat_paste(MAX_PASTE_COUNT + 1, Duration::ZERO, &mut paste, |_| {}).is_err());
+        assert_eq!(pastes, 0);
+    }
+
+    #[test]
+    fn test_repeat_paste_stops_on_error() {
+        let mut pastes = 0;
+        let result = repeat_paste(
+            5,
+            Duration::ZERO,
+            || {
+                pastes += 1;
+                if pastes == 2 {
+                    Err(anyhow!("boom"))
+                } else {
+                    Ok(())
+                }
+            },
+            |_| {},
+        );
+        assert!(result.is_err());
+        assert_eq!(pastes, 2);
+    }
+
+    #[test]
+    fn test_missing_permission_is_typed() {
+        assert!(ensure_accessible(true).is_ok());
+        let err = ensure_accessible(false).unwrap_err();
+        assert!(is_permission_denied(&err));
+        assert!(err.to_string().contains("accessibility permission"));
+
+        let wrapped = ensure_accessible(false).context("Paste failed").unwrap_err();
+        assert!(is_permission_denied(&wrapped));
+        assert!(!is_permission_denied(&anyhow!("boom")));
+    }
+
+    fn copy_ok(_: &str) -> Result<()> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_paste_test_stops_without_permission() {
+        let report = run_paste_test(
+            false,
+            "marker",
+            |_| panic!("must not copy without permission"),
+            || panic!("must not read without permission"),
+            || panic!("must not paste without permission"),
+            |_| panic!("must not restore without permission"),
+        );
+        assert!(!report.passed());
+        assert!(!report.marker_copied);
+        assert!(report.error.unwrap().contains("accessibility permission"));
+    }
+
+    #[test]
+    fn test_paste_test_sends_keystroke_after_marker_is_verified() {
+        let marker = paste_test_marker(1764457053000);
+        assert_eq!(marker, "clippy-paste-test-1764457053000");
+        let copied = RefCell::new(None);
+        let mut pasted = false;
+        let mut restored = None;
+        let report = run_paste_test(
+            true,
+            &marker,
+            |text| {
+                *copied.borrow_mut() = Some(text.to_string());
+                Ok(())
+            },
+            || Ok(copied.borrow().clone()),
+            || {
+                pasted = true;
+                Ok(())
+            },
+            |after_paste| restored = Some(after_paste),
+        );
+        assert!(report.passed(), "{report:?}");
+        assert!(pasted);
+        assert_eq!(restored, Some(true));
+        assert_eq!(report.error, None);
+    }
+
+    #[test]
+    fn test_paste_test_skips_paste_when_marker_missing() {
+        let mut restored = None;
+        let report = run_paste_test(
+            true,
+            "marker",
+            copy_ok,
+            || Ok(Some("something else".to_string())),
+            || panic!("must not paste when the marker was not copied"),
+            |after_paste| restored = Some(after_paste),
+        );
+        // The previous clipboard comes back even when the test fails.
+        assert_eq!(restored, Some(false));
+        assert!(report.accessible);
+        assert!(!report.marker_copied);
+        assert!(report.error.unwrap().contains("marker"));
+
+        let report = run_paste_test(
+            true,
+            "marker",
+            copy_ok,
+            || Ok(Some("marker".into())),
+            || Err(anyhow!("Failed to create CGEventSource")),
+            |_| {},
+        );
+        assert!(report.marker_copied);
+        assert!(!report.keystroke_sent);
+        assert_eq!(
+            report.error.as_deref(),
+            Some("Failed to create CGEventSource")
+        );
+    }
+
+    #[test]
+    fn test_paste_target_parse() {
+        assert_eq!(
+            PasteTarget::parse("com.apple.TextEdit").unwrap(),
+            PasteTarget::BundleId("com.apple.TextEdit".to_string())
+        );
+        assert_eq!(
+            PasteTarget::parse(" Visual Studio Code ").unwrap(),
+            PasteTarget::AppName("Visual Studio Code".to_string())
+        );
+        assert_eq!(
+            PasteTarget::parse("Safari").unwrap(),
+            PasteTarget::AppName("Safari".to_string())
+        );
+        assert_eq!(
+            PasteTarget::parse("com..app").unwrap(),
+            PasteTarget::AppName("com..app".to_string())
+        );
+        assert!(PasteTarget::parse("   ").is_err());
+    }
+
+    #[test]
+    fn test_paste_target_open_args() {
+        let bundle = PasteTarget::BundleId("com.apple.Notes".to_string());
+        assert_eq!(bundle.open_args(), ["-b", "com.apple.Notes"]);
+        let app = PasteTarget::AppName("Notes".to_string());
+        assert_eq!(app.open_args(), ["-a", "Notes"]);
+    }
+}
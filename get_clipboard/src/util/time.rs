@@ -1,10 +1,95 @@
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicI64, Ordering};
 use time::format_description::well_known::Iso8601;
+use time::format_description::well_known::iso8601::{Config, EncodedConfig, TimePrecision};
 use time::macros::format_description;
-pub use time::{Date, OffsetDateTime};
+pub use time::{Date, Duration, OffsetDateTime};
 
+const ISO_MILLIS: EncodedConfig = Config::DEFAULT
+    .set_time_precision(TimePrecision::Second {
+        decimal_digits: NonZeroU8::new(3),
+    })
+    .encode();
+
+static LAST_NOW_MILLIS: AtomicI64 = AtomicI64::new(i64::MIN);
+
+/// Current time at millisecond precision. Successive calls within a process
+/// always return strictly increasing values, so rapid copies keep their order.
 pub fn now() -> OffsetDateTime {
-    OffsetDateTime::now_utc()
+    let wall = unix_millis(OffsetDateTime::now_utc());
+    let mut previous = LAST_NOW_MILLIS.load(Ordering::Relaxed);
+    loop {
+        let next = wall.max(previous.saturating_add(1));
+        match LAST_NOW_MILLIS.compare_exchange_weak(
+            previous,
+            next,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return from_unix_millis(next),
+            Err(actual) => previous = actual,
+        }
+    }
+}
+
+pub fn unix_millis(dt: OffsetDateTime) -> i64 {
+    (dt.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn from_unix_millis(millis: i64) -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp_nanos(i128::from(millis) * 1_000_000)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+}
+
+/// Serde adapter storing timestamps as Unix seconds with millisecond
+/// fractions. Whole seconds are written as integers, and integer values
+/// written by older versions still parse.
+pub mod timestamp_millis {
+    use super::{OffsetDateTime, from_unix_millis, unix_millis};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        dt: &OffsetDateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let millis = unix_millis(*dt);
+        if millis % 1000 == 0 {
+            serializer.serialize_i64(millis / 1000)
+        } else {
+            serializer.serialize_f64(millis as f64 / 1000.0)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OffsetDateTime, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        Ok(from_unix_millis((seconds * 1000.0).round() as i64))
+    }
+}
+
+/// [`timestamp_millis`] for optional timestamps.
+pub mod option_timestamp_millis {
+    use super::OffsetDateTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        dt: &Option<OffsetDateTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match dt {
+            Some(dt) => super::timestamp_millis::serialize(dt, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<OffsetDateTime>, D::Error> {
+        let seconds = Option::<f64>::deserialize(deserializer)?;
+        Ok(seconds.map(|seconds| super::from_unix_millis((seconds * 1000.0).round() as i64)))
+    }
 }
 
 pub fn format_human(dt: OffsetDateTime) -> String {
@@ -12,12 +97,30 @@ pub fn format_human(dt: OffsetDateTime) -> String {
     dt.format(&format).unwrap_or_else(|_| dt.to_string())
 }
 
+/// The `YYYY-MM-DD` day a timestamp falls on, used to group history by day.
+pub fn format_day(dt: OffsetDateTime) -> String {
+    let format = format_description!("[year]-[month]-[day]");
+    dt.format(&format).unwrap_or_else(|_| dt.date().to_string())
+}
+
 pub fn format_iso(dt: OffsetDateTime) -> String {
-    dt.format(&Iso8601::DEFAULT)
+    dt.format(&Iso8601::<ISO_MILLIS>)
         .unwrap_or_else(|_| dt.to_string())
 }
 
 pub fn parse_date(input: &str) -> Result<OffsetDateTime> {
+    parse_date_at(input, now())
+}
+
+/// Like [`parse_date`], resolving relative inputs such as `2h`, `3d` or `1w`
+/// as that long before `now`.
+pub fn parse_date_at(input: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let input = input.trim();
+    if let Some(offset) = parse_relative(input) {
+        return offset
+            .and_then(|offset| now.checked_sub(offset))
+            .with_context(|| format!("Relative date out of range: {input}"));
+    }
     if let Ok(dt) = OffsetDateTime::parse(input, &Iso8601::DEFAULT) {
         return Ok(dt);
     }
@@ -31,3 +134,110 @@ pub fn parse_date(input: &str) -> Result<OffsetDateTime> {
     }
     bail!("Unable to parse date: {input}")
 }
+
+/// `None` when the input isn't relative at all, `Some(None)` when it is but
+/// the offset doesn't fit in a [`Duration`].
+fn parse_relative(input: &str) -> Option<Option<Duration>> {
+    let split = input.find(|c: char| !c.is_ascii_digit())?;
+    if split == 0 {
+        return None;
+    }
+    let (amount, unit) = input.split_at(split);
+    let unit_seconds: i64 = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return None,
+    };
+    let seconds = amount
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit_seconds));
+    Some(seconds.map(Duration::seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_parse_date_relative() {
+        let now = datetime!(2024-03-10 12:00:00 UTC);
+        assert_eq!(parse_date_at("2h", now).unwrap(), datetime!(2024-03-10 10:00:00 UTC));
+        assert_eq!(parse_date_at("3d", now).unwrap(), datetime!(2024-03-07 12:00:00 UTC));
+        assert_eq!(parse_date_at("1w", now).unwrap(), datetime!(2024-03-03 12:00:00 UTC));
+        assert_eq!(parse_date_at("30m", now).unwrap(), datetime!(2024-03-10 11:30:00 UTC));
+    }
+
+    #[test]
+    fn test_parse_date_relative_out_of_range_is_an_error() {
+        let now = datetime!(2024-03-10 12:00:00 UTC);
+        for input in ["9999999999d", "99999999999999999w", "99999999999999999999s"] {
+            let err = parse_date_at(input, now).unwrap_err();
+            assert!(err.to_string().contains("out of range"), "{input}: {err}");
+        }
+        let err = parse_date_at("d", now).unwrap_err();
+        assert!(err.to_string().contains("Unable to parse"));
+    }
+
+    #[test]
+    fn test_parse_date_absolute_and_invalid() {
+        let now = datetime!(2024-03-10 12:00:00 UTC);
+        assert_eq!(
+            parse_date_at("2024-01-01", now).unwrap(),
+            datetime!(2024-01-01 00:00:00 UTC)
+        );
+        assert!(parse_date_at("3y", now).is_err());
+        assert!(parse_date_at("d", now).is_err());
+    }
+
+    #[test]
+    fn test_now_is_strictly_increasing_within_a_second() {
+        let first = now();
+        let second = now();
+        assert!(second > first);
+        assert!(second - first < Duration::seconds(1));
+        assert_eq!(second.nanosecond() % 1_000_000, 0);
+    }
+
+    #[test]
+    fn test_timestamp_millis_round_trip() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Stamp {
+            #[serde(with = "timestamp_millis")]
+            at: OffsetDateTime,
+        }
+
+        let earlier = datetime!(2024-03-10 12:00:00.250 UTC);
+        let later = datetime!(2024-03-10 12:00:00.750 UTC);
+        let encoded = serde_json::to_string(&Stamp { at: earlier }).unwrap();
+        assert_eq!(encoded, r#"{"at":1710072000.25}"#);
+        let decoded: Stamp = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded.at, earlier);
+        let decoded_later: Stamp =
+            serde_json::from_str(&serde_json::to_string(&Stamp { at: later }).unwrap()).unwrap();
+        assert!(decoded_later.at > decoded.at);
+
+        let legacy: Stamp = serde_json::from_str(r#"{"at":1710072000}"#).unwrap();
+        assert_eq!(legacy.at, datetime!(2024-03-10 12:00:00 UTC));
+        assert_eq!(serde_json::to_string(&legacy).unwrap(), r#"{"at":1710072000}"#);
+    }
+
+    #[test]
+    fn test_format_day_matches_iso_date() {
+        let dt = datetime!(2024-03-09 23:59:59.999 UTC);
+        assert_eq!(format_day(dt), "2024-03-09");
+        assert!(format_iso(dt).starts_with(&format_day(dt)));
+    }
+
+    #[test]
+    fn test_format_iso_uses_milliseconds() {
+        assert_eq!(
+            format_iso(datetime!(2024-03-10 12:00:00.123456 UTC)),
+            "2024-03-10T12:00:00.123Z"
+        );
+    }
+}
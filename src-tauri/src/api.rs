@@ -1,12 +1,67 @@
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
 pub const API_PORT: u16 = 3016;
 pub const API_BASE: &str = "http://localhost:3016";
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP client shared by every call to the API, kept in Tauri state so
+/// connections are pooled and re-established as needed. The timeouts keep a
+/// hung API from blocking the UI.
+pub struct ApiClient(reqwest::Client);
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self::with_timeouts(CONNECT_TIMEOUT, REQUEST_TIMEOUT)
+    }
+}
+
+impl ApiClient {
+    fn with_timeouts(connect: Duration, request: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(connect)
+            .timeout(request)
+            .build()
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to build API client, falling back to defaults: {}",
+                    e
+                );
+                reqwest::Client::new()
+            });
+        ApiClient(client)
+    }
+}
+
+/// The shared client. `reqwest::Client` is reference counted, so the clone
+/// is cheap and shares the connection pool.
+pub fn client(app: &AppHandle) -> reqwest::Client {
+    app.state::<ApiClient>().0.clone()
+}
+
+/// Turns a failed request into a message for the UI, calling out timeouts
+/// so a stuck API isn't mistaken for an unexpected failure.
+pub fn request_error(err: reqwest::Error) -> String {
+    if err.is_timeout() {
+        format!("API did not respond in time: {}", err)
+    } else {
+        err.to_string()
+    }
+}
+
 pub fn stats_url() -> String {
     format!("{}/stats", API_BASE)
 }
 
-pub fn items_url(count: usize) -> String {
-    format!("{}/items?count={}", API_BASE, count)
+pub fn items_page_url(count: usize, offset: usize) -> String {
+    format!("{}/items?count={}&offset={}", API_BASE, count, offset)
+}
+
+/// Recent items with pinned ones first, up to the CLI's `pinned_first_limit`.
+pub fn tray_items_url(count: usize) -> String {
+    format!("{}/items?count={}&pinned_first=true", API_BASE, count)
 }
 
 pub fn item_preview_url(id: &str, interactive: bool) -> String {
@@ -49,6 +104,17 @@ pub fn dir_url() -> String {
     format!("{}/dir", API_BASE)
 }
 
+pub fn export_ndjson_url(since: Option<&str>) -> String {
+    match since {
+        Some(since) => format!(
+            "{}/export.ndjson?since={}",
+            API_BASE,
+            urlencoding::encode(since)
+        ),
+        None => format!("{}/export.ndjson", API_BASE),
+    }
+}
+
 pub fn save_url() -> String {
     format!("{}/save", API_BASE)
 }
@@ -60,3 +126,25 @@ pub fn item_edit_url(id: &str) -> String {
 pub fn item_text_url(id: &str) -> String {
     format!("{}/item/{}/text", API_BASE, id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    #[test]
+    fn test_hung_api_times_out_promptly() {
+        // Accepts connections (via the backlog) but never answers.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stats", listener.local_addr().unwrap());
+        let client =
+            ApiClient::with_timeouts(Duration::from_millis(200), Duration::from_millis(300));
+
+        let started = Instant::now();
+        let err = tauri::async_runtime::block_on(client.0.get(&url).send()).unwrap_err();
+        assert!(err.is_timeout(), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(request_error(err).starts_with("API did not respond in time"));
+    }
+}
@@ -3,7 +3,7 @@ use tauri::Emitter;
 
 pub fn start_mtime_polling(app_handle: tauri::AppHandle) {
     tauri::async_runtime::spawn(async move {
-        let client = reqwest::Client::new();
+        let client = api::client(&app_handle);
         let mtime_url = api::mtime_url();
         let mut last_known_id: Option<String> = None;
 
@@ -119,15 +119,18 @@ pub fn is_preview_visible(app: tauri::AppHandle) -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub async fn get_preview_content(id: String) -> Result<serde_json::Value, String> {
+pub async fn get_preview_content(
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<serde_json::Value, String> {
     let url = api::item_preview_url(&id, false);
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
 
     let response = client
         .get(&url)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch preview: {}", e))?;
+        .map_err(|e| format!("Failed to fetch preview: {}", api::request_error(e)))?;
 
     if !response.status().is_success() {
         return Err(format!("HTTP error: {}", response.status()));
@@ -142,15 +145,15 @@ pub async fn get_preview_content(id: String) -> Result<serde_json::Value, String
 }
 
 #[tauri::command]
-pub async fn get_item_data(id: String) -> Result<serde_json::Value, String> {
+pub async fn get_item_data(app: tauri::AppHandle, id: String) -> Result<serde_json::Value, String> {
     let url = api::item_data_url(&id);
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
 
     let response = client
         .get(&url)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch item data: {}", e))?;
+        .map_err(|e| format!("Failed to fetch item data: {}", api::request_error(e)))?;
 
     println!("get_item_data response status: {}", response.status());
 
@@ -167,15 +170,15 @@ pub async fn get_item_data(id: String) -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-pub async fn get_item_text(id: String) -> Result<serde_json::Value, String> {
+pub async fn get_item_text(app: tauri::AppHandle, id: String) -> Result<serde_json::Value, String> {
     let url = api::item_text_url(&id);
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
 
     let response = client
         .get(&url)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch item text: {}", e))?;
+        .map_err(|e| format!("Failed to fetch item text: {}", api::request_error(e)))?;
 
     if !response.status().is_success() {
         return Err(format!("HTTP error: {}", response.status()));
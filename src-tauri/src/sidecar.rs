@@ -1,6 +1,8 @@
 use crate::api;
 use crate::paste::simulate_system_paste_internal;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_shell::ShellExt;
 
@@ -113,14 +115,14 @@ pub async fn restart_api(app: AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 pub async fn get_history(
-    _app: AppHandle,
+    app: AppHandle,
     limit: Option<usize>,
     offset: Option<usize>,
     query: Option<String>,
     sort: Option<String>,
     order: Option<String>,
 ) -> Result<String, String> {
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
 
     // Determine base URL based on whether a query is present
     let (base_url, is_search) = if let Some(ref q) = query {
@@ -160,35 +162,35 @@ pub async fn get_history(
         url.push_str(&params.join("&"));
     }
 
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(api::request_error)?;
 
     if response.status().is_success() {
-        Ok(response.text().await.map_err(|e| e.to_string())?)
+        Ok(response.text().await.map_err(api::request_error)?)
     } else {
         Err(format!("API error: {}", response.status()))
     }
 }
 
 #[tauri::command]
-pub async fn get_mtime(_app: AppHandle) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn get_mtime(app: AppHandle) -> Result<String, String> {
+    let client = api::client(&app);
     let url = api::mtime_url();
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(api::request_error)?;
 
     if response.status().is_success() {
         // Just pass the JSON string through to frontend
-        Ok(response.text().await.map_err(|e| e.to_string())?)
+        Ok(response.text().await.map_err(api::request_error)?)
     } else {
         Err(format!("API error: {}", response.status()))
     }
 }
 
 #[tauri::command]
-pub async fn copy_item(_app: AppHandle, selector: String) -> Result<(), String> {
-    let client = reqwest::Client::new();
+pub async fn copy_item(app: AppHandle, selector: String) -> Result<(), String> {
+    let client = api::client(&app);
     let url = api::item_copy_url(&selector);
 
-    let response = client.post(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.post(&url).send().await.map_err(api::request_error)?;
 
     if response.status().is_success() {
         Ok(())
@@ -200,9 +202,9 @@ pub async fn copy_item(_app: AppHandle, selector: String) -> Result<(), String>
 #[tauri::command]
 pub async fn paste_item(app: AppHandle, selector: String) -> Result<(), String> {
     // 1. Copy to system clipboard via API (incrementing copy count)
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
     let url = api::item_copy_url(&selector);
-    let response = client.post(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.post(&url).send().await.map_err(api::request_error)?;
 
     if !response.status().is_success() {
         return Err(format!("API error during copy: {}", response.status()));
@@ -224,9 +226,9 @@ pub async fn paste_item(app: AppHandle, selector: String) -> Result<(), String>
 #[tauri::command]
 pub async fn paste_item_plain_text(app: AppHandle, id: String) -> Result<(), String> {
     // 1. Copy to system clipboard as plain text via API (incrementing copy count)
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
     let url = api::item_copy_plain_url(&id);
-    let response = client.post(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.post(&url).send().await.map_err(api::request_error)?;
 
     if !response.status().is_success() {
         return Err(format!("API error during copy_plain: {}", response.status()));
@@ -246,15 +248,15 @@ pub async fn paste_item_plain_text(app: AppHandle, id: String) -> Result<(), Str
 }
 
 #[tauri::command]
-pub async fn delete_item(_app: AppHandle, selector: String) -> Result<(), String> {
-    let client = reqwest::Client::new();
+pub async fn delete_item(app: AppHandle, selector: String) -> Result<(), String> {
+    let client = api::client(&app);
     let url = api::item_delete_url(&selector);
 
     let response = client
         .delete(&url)
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(api::request_error)?;
 
     if response.status().is_success() {
         Ok(())
@@ -287,14 +289,14 @@ pub async fn configure_data_dir(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn db_get_count(_app: AppHandle) -> Result<usize, String> {
-    let client = reqwest::Client::new();
+pub async fn db_get_count(app: AppHandle) -> Result<usize, String> {
+    let client = api::client(&app);
     let url = api::stats_url();
 
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(api::request_error)?;
 
     if response.status().is_success() {
-        let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        let json: serde_json::Value = response.json().await.map_err(api::request_error)?;
         Ok(json["totalItems"].as_u64().unwrap_or(0) as usize)
     } else {
         Ok(0)
@@ -302,56 +304,154 @@ pub async fn db_get_count(_app: AppHandle) -> Result<usize, String> {
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn db_get_size(_app: AppHandle) -> Result<u64, String> {
-    let client = reqwest::Client::new();
+pub async fn db_get_size(app: AppHandle) -> Result<u64, String> {
+    let client = api::client(&app);
     let url = api::stats_url();
 
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(api::request_error)?;
 
     if response.status().is_success() {
-        let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+        let json: serde_json::Value = response.json().await.map_err(api::request_error)?;
         Ok(json["totalSize"].as_u64().unwrap_or(0))
     } else {
         Ok(0)
     }
 }
 
-#[tauri::command(rename_all = "snake_case")]
-pub async fn db_export_all(_app: AppHandle) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    // Get all items (summary) to get IDs
-    let items_url = api::items_url(1000000);
+/// Items requested per page when walking the whole history.
+const PAGE_SIZE: usize = 500;
+/// Exports stream the whole history, so they get far longer than the shared
+/// client's request timeout.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(600);
+/// Times an interrupted export is resumed before giving up.
+const EXPORT_RETRIES: usize = 3;
+
+fn item_id(item: &serde_json::Value) -> Option<String> {
+    item["hash"]
+        .as_str()
+        .or(item["id"].as_str())
+        .map(str::to_string)
+}
+
+/// Walks the history one page at a time until an empty page shows there is
+/// nothing left, so a large history is never requested in one response. A
+/// short page is not the end: `/items` leaves out entries it fails to load,
+/// which still take up their offsets.
+async fn collect_item_ids<F, Fut>(mut fetch_page: F) -> Result<Vec<String>, String>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<serde_json::Value>, String>>,
+{
+    let mut ids = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = fetch_page(offset).await?;
+        if page.is_empty() {
+            return Ok(ids);
+        }
+        ids.extend(page.iter().filter_map(item_id));
+        offset += PAGE_SIZE;
+    }
+}
+
+async fn fetch_items_page(
+    client: &reqwest::Client,
+    offset: usize,
+) -> Result<Vec<serde_json::Value>, String> {
     let resp = client
-        .get(&items_url)
+        .get(api::items_page_url(PAGE_SIZE, offset))
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(api::request_error)?;
     if !resp.status().is_success() {
         return Err(format!("Failed to list items: {}", resp.status()));
     }
-    let items: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    resp.json().await.map_err(api::request_error)
+}
 
-    // Fetch full data for each item to ensure complete export
+/// Parses every complete line in `buffer`, leaving a trailing partial line
+/// in place for the next chunk.
+fn take_ndjson_lines(buffer: &mut Vec<u8>) -> Result<Vec<serde_json::Value>, String> {
+    let Some(end) = buffer.iter().rposition(|&b| b == b'\n') else {
+        return Ok(Vec::new());
+    };
+    let complete: Vec<u8> = buffer.drain(..=end).collect();
+    complete
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Where to resume an interrupted export: the `date` of the last item
+/// received. The export runs oldest first, so everything after it is new.
+fn resume_since(items: &[serde_json::Value]) -> Option<&str> {
+    items.last().and_then(|item| item["date"].as_str())
+}
+
+/// Keeps the last copy of each item, since one copied again while the export
+/// was interrupted is sent a second time after resuming.
+fn dedupe_by_id(items: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let mut seen = std::collections::HashSet::new();
+    let mut kept: Vec<_> = items
+        .into_iter()
+        .rev()
+        .filter(|item| match item["id"].as_str() {
+            Some(id) => seen.insert(id.to_string()),
+            None => true,
+        })
+        .collect();
+    kept.reverse();
+    kept
+}
+
+async fn stream_export(
+    client: &reqwest::Client,
+    since: Option<String>,
+    items: &mut Vec<serde_json::Value>,
+) -> Result<(), String> {
+    let mut resp = client
+        .get(api::export_ndjson_url(since.as_deref()))
+        .timeout(EXPORT_TIMEOUT)
+        .send()
+        .await
+        .map_err(api::request_error)?;
+    if !resp.status().is_success() {
+        return Err(format!("Failed to export items: {}", resp.status()));
+    }
+    let mut buffer = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(api::request_error)? {
+        buffer.extend_from_slice(&chunk);
+        items.extend(take_ndjson_lines(&mut buffer)?);
+    }
+    Ok(())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn db_export_all(app: AppHandle) -> Result<String, String> {
+    let client = api::client(&app);
+    // Stream full items from /export.ndjson, picking up where we left off if
+    // the connection drops partway through.
     let mut full_items = Vec::new();
-    for item in items {
-        if let Some(id) = item["hash"].as_str().or(item["id"].as_str()) {
-            let data_url = api::item_data_url(id);
-            if let Ok(resp) = client.get(&data_url).send().await {
-                if resp.status().is_success() {
-                    if let Ok(full_item) = resp.json::<serde_json::Value>().await {
-                        full_items.push(full_item);
-                    }
-                }
+    let mut retries = 0;
+    loop {
+        let since = resume_since(&full_items).map(String::from);
+        match stream_export(&client, since, &mut full_items).await {
+            Ok(()) => break,
+            Err(e) if retries < EXPORT_RETRIES => {
+                retries += 1;
+                println!("Export interrupted, resuming: {}", e);
             }
+            Err(e) => return Err(e),
         }
     }
 
-    serde_json::to_string_pretty(&full_items).map_err(|e| e.to_string())
+    serde_json::to_string_pretty(&dedupe_by_id(full_items)).map_err(|e| e.to_string())
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn db_import_all(_app: AppHandle, json_data: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn db_import_all(app: AppHandle, json_data: String) -> Result<String, String> {
+    let client = api::client(&app);
     let items: Vec<serde_json::Value> =
         serde_json::from_str(&json_data).map_err(|e| e.to_string())?;
     let mut success = 0;
@@ -378,24 +478,18 @@ pub async fn db_import_all(_app: AppHandle, json_data: String) -> Result<String,
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn db_delete_all(_app: AppHandle) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let items_url = api::items_url(1000000);
-    let resp = client
-        .get(&items_url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-    let items: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+pub async fn db_delete_all(app: AppHandle) -> Result<String, String> {
+    let client = api::client(&app);
+    // Collect every id before deleting anything, since deleting while paging
+    // would shift the offsets of the items still to come.
+    let ids = collect_item_ids(|offset| fetch_items_page(&client, offset)).await?;
 
     let mut count = 0;
-    for item in items {
-        if let Some(id) = item["hash"].as_str().or(item["id"].as_str()) {
-            let del_url = api::item_delete_url(id);
-            if let Ok(resp) = client.delete(&del_url).send().await {
-                if resp.status().is_success() {
-                    count += 1;
-                }
+    for id in ids {
+        let del_url = api::item_delete_url(&id);
+        if let Ok(resp) = client.delete(&del_url).send().await {
+            if resp.status().is_success() {
+                count += 1;
             }
         }
     }
@@ -403,12 +497,12 @@ pub async fn db_delete_all(_app: AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn get_sidecar_dir(_app: AppHandle) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn get_sidecar_dir(app: AppHandle) -> Result<String, String> {
+    let client = api::client(&app);
     let url = api::dir_url();
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(api::request_error)?;
     if response.status().is_success() {
-        let json: DirResponse = response.json().await.map_err(|e| e.to_string())?;
+        let json: DirResponse = response.json().await.map_err(api::request_error)?;
         Ok(json.path)
     } else {
         Err(format!("API error: {}", response.status()))
@@ -416,12 +510,8 @@ pub async fn get_sidecar_dir(_app: AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn set_sidecar_dir(
-    _app: AppHandle,
-    mode: String,
-    path: String,
-) -> Result<String, String> {
-    let client = reqwest::Client::new();
+pub async fn set_sidecar_dir(app: AppHandle, mode: String, path: String) -> Result<String, String> {
+    let client = api::client(&app);
     let url = api::dir_url();
     let body = serde_json::json!({
         "mode": mode,
@@ -433,10 +523,10 @@ pub async fn set_sidecar_dir(
         .json(&body)
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(api::request_error)?;
 
     if response.status().is_success() {
-        let json: DirResponse = response.json().await.map_err(|e| e.to_string())?;
+        let json: DirResponse = response.json().await.map_err(api::request_error)?;
         Ok(json.path)
     } else {
         Err(format!("API error: {}", response.status()))
@@ -451,11 +541,11 @@ pub async fn get_app_data_dir(app: AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 pub async fn edit_item(
-    _app: AppHandle,
+    app: AppHandle,
     id: String,
     formats: std::collections::HashMap<String, String>,
 ) -> Result<String, String> {
-    let client = reqwest::Client::new();
+    let client = api::client(&app);
     let url = api::item_edit_url(&id);
     let body = serde_json::json!({ "formats": formats });
 
@@ -464,13 +554,108 @@ pub async fn edit_item(
         .json(&body)
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(api::request_error)?;
 
     if response.status().is_success() {
-        Ok(response.text().await.map_err(|e| e.to_string())?)
+        Ok(response.text().await.map_err(api::request_error)?)
     } else {
         let status = response.status();
         let error_text = response.text().await.unwrap_or_default();
         Err(format!("API error {}: {}", status, error_text))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn page(start: usize, len: usize) -> Vec<serde_json::Value> {
+        (start..start + len)
+            .map(|i| json!({ "hash": format!("h{}", i) }))
+            .collect()
+    }
+
+    /// Pages through `total` items, leaving out the ones at `skipped` offsets
+    /// like `/items` does for entries it fails to load.
+    fn collect(total: usize, skipped: &[usize]) -> (Vec<String>, Vec<usize>) {
+        let mut offsets = Vec::new();
+        let ids = tauri::async_runtime::block_on(collect_item_ids(|offset| {
+            offsets.push(offset);
+            let len = total.saturating_sub(offset).min(PAGE_SIZE);
+            let mut items = page(offset, len);
+            items.retain(|item| {
+                let index: usize = item["hash"].as_str().unwrap()[1..].parse().unwrap();
+                !skipped.contains(&index)
+            });
+            std::future::ready(Ok(items))
+        }))
+        .unwrap();
+        (ids, offsets)
+    }
+
+    #[test]
+    fn test_paging_continues_past_short_page() {
+        let (ids, offsets) = collect(PAGE_SIZE + 20, &[3]);
+        assert_eq!(ids.len(), PAGE_SIZE + 19);
+        assert_eq!(ids[PAGE_SIZE - 1], format!("h{}", PAGE_SIZE));
+        assert_eq!(offsets, vec![0, PAGE_SIZE, PAGE_SIZE * 2]);
+    }
+
+    #[test]
+    fn test_paging_stops_at_empty_page() {
+        let (ids, offsets) = collect(PAGE_SIZE * 2, &[]);
+        assert_eq!(ids.len(), PAGE_SIZE * 2);
+        assert_eq!(offsets, vec![0, PAGE_SIZE, PAGE_SIZE * 2]);
+
+        let (ids, offsets) = collect(0, &[]);
+        assert!(ids.is_empty());
+        assert_eq!(offsets, vec![0]);
+    }
+
+    #[test]
+    fn test_paging_stops_on_error() {
+        let mut calls = 0;
+        let result = tauri::async_runtime::block_on(collect_item_ids(|offset| {
+            calls += 1;
+            std::future::ready(if offset == 0 {
+                Ok(page(0, PAGE_SIZE))
+            } else {
+                Err("API error".to_string())
+            })
+        }));
+        assert_eq!(result, Err("API error".to_string()));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_ndjson_lines_split_across_chunks() {
+        let mut buffer = b"{\"index\":0}\n{\"ind".to_vec();
+        let items = take_ndjson_lines(&mut buffer).unwrap();
+        assert_eq!(items, vec![json!({ "index": 0 })]);
+        assert_eq!(buffer, b"{\"ind");
+
+        buffer.extend_from_slice(b"ex\":2}\n");
+        let items = take_ndjson_lines(&mut buffer).unwrap();
+        assert_eq!(items, vec![json!({ "index": 2 })]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_resumed_export_keeps_last_copy_of_each_item() {
+        let items = vec![
+            json!({ "id": "a", "date": "2024-03-10T10:00:00.000Z" }),
+            json!({ "id": "b", "date": "2024-03-10T11:00:00.000Z" }),
+        ];
+        assert_eq!(resume_since(&items), Some("2024-03-10T11:00:00.000Z"));
+        assert_eq!(resume_since(&[]), None);
+
+        let mut resumed = items;
+        resumed.push(json!({ "id": "a", "date": "2024-03-10T12:00:00.000Z" }));
+        let ids: Vec<_> = dedupe_by_id(resumed)
+            .into_iter()
+            .map(|item| item["id"].clone())
+            .collect();
+        assert_eq!(ids, vec![json!("b"), json!("a")]);
+    }
+}
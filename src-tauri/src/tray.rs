@@ -67,6 +67,7 @@ pub fn setup_tray(
         .build()?;
 
     start_tray_stats_updater(
+        api::client(app.handle()),
         stats_item.clone(),
         clip_items.iter().cloned().collect(),
         tray_items.clone(),
@@ -94,14 +95,14 @@ pub fn setup_tray(
 }
 
 fn start_tray_stats_updater(
+    client: reqwest::Client,
     stats_item: tauri::menu::MenuItem<tauri::Wry>,
     clip_items_handles: Vec<tauri::menu::MenuItem<tauri::Wry>>,
     tray_items: TrayClipboardItems,
 ) {
     tauri::async_runtime::spawn(async move {
-        let client = reqwest::Client::new();
         let stats_url = api::stats_url();
-        let items_url = api::items_url(10);
+        let items_url = api::tray_items_url(10);
 
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -145,15 +146,7 @@ fn start_tray_stats_updater(
                                 .to_string();
                             let summary = item["summary"].as_str().unwrap_or("").to_string();
 
-                            let display_summary = {
-                                let char_count = summary.chars().count();
-                                if char_count > 40 {
-                                    let truncated: String = summary.chars().take(37).collect();
-                                    format!("{}...", truncated)
-                                } else {
-                                    summary.clone()
-                                }
-                            };
+                            let display_summary = truncate_summary(&summary, 40);
 
                             let key = if i == 9 {
                                 "0".to_string()
@@ -232,6 +225,16 @@ fn handle_tray_menu_event(
     }
 }
 
+/// Shortens `summary` to at most `max_chars` characters, ending in "..."
+/// when cut. Counts chars rather than bytes so multibyte text never splits.
+fn truncate_summary(summary: &str, max_chars: usize) -> String {
+    if summary.chars().count() <= max_chars {
+        return summary.to_string();
+    }
+    let kept: String = summary.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{kept}...")
+}
+
 fn handle_tray_icon_event(tray: &tauri::tray::TrayIcon, event: TrayIconEvent) {
     if let TrayIconEvent::Click {
         button: MouseButton::Left,
@@ -249,3 +252,26 @@ fn handle_tray_icon_event(tray: &tauri::tray::TrayIcon, event: TrayIconEvent) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_summary_keeps_short_text() {
+        assert_eq!(truncate_summary("hello", 40), "hello");
+        assert_eq!(truncate_summary("", 40), "");
+    }
+
+    #[test]
+    fn test_truncate_summary_handles_multibyte_text() {
+        // Byte-slicing this at 37 would land inside a four-byte emoji.
+        let summary = "🎉".repeat(50);
+        let truncated = truncate_summary(&summary, 40);
+        assert_eq!(truncated.chars().count(), 40);
+        assert_eq!(truncated, format!("{}...", "🎉".repeat(37)));
+
+        let mixed = format!("é{}", "日本語".repeat(20));
+        assert!(truncate_summary(&mixed, 40).ends_with("..."));
+    }
+}